@@ -1,30 +1,247 @@
+use base64::Engine;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
 use rand::prelude::*;
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
+#[cfg(feature = "serde")]
 use serde::{Deserialize, Serialize};
+use std::cmp::Reverse;
+use std::collections::BTreeMap;
+use std::collections::BinaryHeap;
 use std::collections::HashMap;
 use std::collections::HashSet;
 use std::fmt;
 use std::fmt::{Display, Formatter};
 use std::fs::File;
-use std::io::Write;
-use std::sync::LazyLock;
+use std::io::{BufWriter, Write};
+use std::sync::{LazyLock, OnceLock};
 
 #[allow(dead_code)]
-#[derive(clap::ValueEnum, Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum ExitLocation {
     Random,
     Left,
     Right,
     Top,
     Bottom,
+    /// On whichever side the walk from the start is longest, decided once
+    /// the corridors are carved
+    Farthest,
+    /// At the border cell the exit was moved to with `Maze::move_exit`. Only
+    /// mazes with corridors one cell wide can be generated with it.
+    #[cfg_attr(feature = "cli", value(skip))]
+    Custom(Pos),
 }
 
-#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct Pos {
     pub x: usize,
     pub y: usize,
 }
 
-#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+impl Display for Pos {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "({}, {})", self.x, self.y)
+    }
+}
+
+/// Positions are ordered row by row, like the cells are scanned
+impl Ord for Pos {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        (self.y, self.x).cmp(&(other.y, other.x))
+    }
+}
+
+impl PartialOrd for Pos {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+/// One of the four directions to move on the grid. North is up, towards
+/// `y == 0`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum Direction {
+    East,
+    South,
+    West,
+    North,
+}
+
+impl Direction {
+    /// All directions, clockwise starting with east. Searches try neighbors
+    /// in this order, which decides between equally good paths.
+    pub const ALL: [Direction; 4] = [
+        Direction::East,
+        Direction::South,
+        Direction::West,
+        Direction::North,
+    ];
+
+    /// Change of the x and y coordinate when moving in this direction
+    pub fn offset(self) -> (isize, isize) {
+        match self {
+            Direction::East => (1, 0),
+            Direction::South => (0, 1),
+            Direction::West => (-1, 0),
+            Direction::North => (0, -1),
+        }
+    }
+
+    pub fn turn_right(self) -> Direction {
+        Direction::ALL[(self as usize + 1) % 4]
+    }
+
+    pub fn turn_left(self) -> Direction {
+        Direction::ALL[(self as usize + 3) % 4]
+    }
+
+    pub fn reverse(self) -> Direction {
+        Direction::ALL[(self as usize + 2) % 4]
+    }
+}
+
+impl Pos {
+    pub fn new(x: usize, y: usize) -> Self {
+        Pos { x, y }
+    }
+
+    /// The adjacent position in `direction`, or `None` if it lies outside a
+    /// grid of the given `(width, height)`.
+    pub fn step(self, direction: Direction, (width, height): (usize, usize)) -> Option<Pos> {
+        let (dx, dy) = direction.offset();
+        let x = self.x.checked_add_signed(dx)?;
+        let y = self.y.checked_add_signed(dy)?;
+        (x < width && y < height).then_some(Pos { x, y })
+    }
+
+    /// The adjacent positions inside a grid of the given `(width, height)`,
+    /// in the order of `Direction::ALL`.
+    pub fn neighbors(self, bounds: (usize, usize)) -> impl Iterator<Item = Pos> {
+        Direction::ALL
+            .into_iter()
+            .filter_map(move |direction| self.step(direction, bounds))
+    }
+
+    pub fn manhattan(self, other: Pos) -> usize {
+        self.x.abs_diff(other.x) + self.y.abs_diff(other.y)
+    }
+}
+
+/// The open room the start is in, see `Maze::room`
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Room {
+    pub top_left: Pos,
+    pub width: usize,
+    pub height: usize,
+}
+
+impl Room {
+    /// A `size` by `size` room around the middle of a `width` by `height`
+    /// maze. With an even size there is one cell more left of and above the
+    /// middle than right of and below it.
+    pub fn centered(width: usize, height: usize, size: usize) -> Room {
+        let size = size.max(1);
+        Room {
+            top_left: Pos::new(
+                (width / 2).saturating_sub(size / 2),
+                (height / 2).saturating_sub(size / 2),
+            ),
+            width: size,
+            height: size,
+        }
+    }
+
+    pub fn rect(&self) -> Rect {
+        Rect::new(self.top_left.x, self.top_left.y, self.width, self.height)
+    }
+
+    pub fn contains(&self, pos: Pos) -> bool {
+        self.rect().contains(pos)
+    }
+
+    pub fn bottom_right(&self) -> Pos {
+        Pos::new(
+            self.top_left.x + self.width - 1,
+            self.top_left.y + self.height - 1,
+        )
+    }
+}
+
+/// A rectangle of cells, `x` and `y` being its top left corner.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Rect {
+    pub x: usize,
+    pub y: usize,
+    pub width: usize,
+    pub height: usize,
+}
+
+impl Rect {
+    pub fn new(x: usize, y: usize, width: usize, height: usize) -> Self {
+        Rect {
+            x,
+            y,
+            width,
+            height,
+        }
+    }
+
+    pub fn contains(&self, pos: Pos) -> bool {
+        pos.x >= self.x
+            && pos.y >= self.y
+            && pos.x < self.x + self.width
+            && pos.y < self.y + self.height
+    }
+
+    /// Whether the rectangle has no cells
+    pub fn is_empty(&self) -> bool {
+        self.width == 0 || self.height == 0
+    }
+
+    /// The cells in both rectangles, an empty rectangle if they don't
+    /// overlap
+    pub fn intersect(&self, other: Rect) -> Rect {
+        let x = self.x.max(other.x);
+        let y = self.y.max(other.y);
+        let right = (self.x + self.width).min(other.x + other.width);
+        let bottom = (self.y + self.height).min(other.y + other.height);
+        Rect::new(x, y, right.saturating_sub(x), bottom.saturating_sub(y))
+    }
+
+    /// The part of the rectangle inside `bounds`, e.g. `maze.bounds()`
+    pub fn clamp_to(&self, bounds: Rect) -> Rect {
+        self.intersect(bounds)
+    }
+
+    /// All cells of the rectangle in row-major order
+    pub fn positions(self) -> impl Iterator<Item = Pos> {
+        let Rect {
+            x,
+            y,
+            width,
+            height,
+        } = self;
+        (y..y + height).flat_map(move |y| (x..x + width).map(move |x| Pos { x, y }))
+    }
+
+    /// The bottom right cell, `None` for an empty rectangle
+    fn last(&self) -> Option<Pos> {
+        (self.width > 0 && self.height > 0).then(|| Pos {
+            x: self.x + self.width - 1,
+            y: self.y + self.height - 1,
+        })
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum CellType {
     Start,
     Exit,
@@ -46,6 +263,10 @@ pub enum CellType {
     Spider,
     Bat,
     Pumpkin,
+    /// Crossing where a north-south passage bridges over an east-west one
+    BridgeNorthSouth,
+    /// Crossing where an east-west passage bridges over a north-south one
+    BridgeEastWest,
 }
 
 impl Display for CellType {
@@ -71,6 +292,8 @@ impl Display for CellType {
             CellType::Spider => "Spider",
             CellType::Bat => "Bat",
             CellType::Pumpkin => "Pumpkin",
+            CellType::BridgeNorthSouth => "Bridge (north-south)",
+            CellType::BridgeEastWest => "Bridge (east-west)",
         };
         write!(f, "{}", &s)
     }
@@ -99,10 +322,57 @@ impl CellType {
             CellType::Spider => 3,
             CellType::Bat => 1,
             CellType::Pumpkin => 2,
+            CellType::BridgeNorthSouth => 0,
+            CellType::BridgeEastWest => 0,
+        }
+    }
+
+    /// Whether moving through this cell in `direction` passes under a
+    /// bridge, rather than along the passage on top of it.
+    pub fn is_under(&self, direction: Direction) -> bool {
+        match self {
+            CellType::BridgeNorthSouth => matches!(direction, Direction::East | Direction::West),
+            CellType::BridgeEastWest => matches!(direction, Direction::North | Direction::South),
+            _ => false,
         }
     }
 }
 
+/// Weights of cell types, overriding `CellType::weight` for scoring, the
+/// graph and the difficulty of a maze. Types without an entry keep their
+/// default weight.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize), serde(transparent))]
+pub struct WeightTable(HashMap<CellType, i32>);
+
+impl WeightTable {
+    pub fn new() -> Self {
+        WeightTable::default()
+    }
+
+    pub fn get(&self, cell: CellType) -> i32 {
+        self.0.get(&cell).copied().unwrap_or_else(|| cell.weight())
+    }
+
+    pub fn set(&mut self, cell: CellType, weight: i32) {
+        self.0.insert(cell, weight);
+    }
+
+    /// Reads overrides from a JSON object mapping cell types, named like the
+    /// variants of `CellType`, to weights, e.g. `{"Witch": 100}`.
+    #[cfg(feature = "serde")]
+    pub fn load_json(filename: &str) -> Result<WeightTable, MazeError> {
+        let bytes = std::fs::read(filename).map_err(|source| MazeError::Io {
+            path: Some(filename.to_string()),
+            source,
+        })?;
+        serde_json::from_slice(&bytes).map_err(|err| MazeError::Parse {
+            line: err.line(),
+            reason: err.to_string(),
+        })
+    }
+}
+
 pub static REWARDS: LazyLock<Vec<CellType>> = LazyLock::new(|| {
     vec![
         CellType::Marshmallows,
@@ -150,16 +420,23 @@ pub static TRAVERSABLE: LazyLock<HashSet<CellType>> = LazyLock::new(|| {
         CellType::Spider,
         CellType::Bat,
         CellType::Pumpkin,
+        CellType::BridgeNorthSouth,
+        CellType::BridgeEastWest,
     ]
     .into_iter()
     .collect()
 });
 
-#[derive(clap::ValueEnum, Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum SolutionType {
     None,
     ShortestPath,
     MinimumSpanningTree,
+    /// The shortest way over every reward to the exit, see
+    /// `Maze::full_collection_route`
+    FullCollection,
 }
 impl Display for SolutionType {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
@@ -167,864 +444,9058 @@ impl Display for SolutionType {
             SolutionType::None => write!(f, "none"),
             SolutionType::ShortestPath => write!(f, "shortest_path"),
             SolutionType::MinimumSpanningTree => write!(f, "minimum_spanning_tree"),
+            SolutionType::FullCollection => write!(f, "full_collection"),
         }
     }
 }
 
-#[derive(Debug)]
-pub struct MazeError {
-    pub message: String,
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum Hand {
+    Left,
+    Right,
 }
 
-impl Display for MazeError {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "{}", self.message)
-    }
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum Solver {
+    /// Breadth-first search, always yields a shortest path.
+    Bfs,
+    /// A* search guided by the Manhattan distance to the exit.
+    AStar,
+    /// Keep one hand on the wall and walk until the exit is reached.
+    /// The returned path contains every step, including backtracks.
+    WallFollower(Hand),
+    /// Fill dead ends until only the corridors between start and exit remain.
+    DeadEndFill,
 }
 
-impl std::error::Error for MazeError {}
+/// Progress reported by the `*_observed` variants of generation, artifact
+/// placement and solving.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum MazeEvent {
+    /// A wall was turned into a path while carving the maze
+    CarvePath(Pos),
+    /// A wall was removed afterwards to create loops
+    RemoveWall(Pos),
+    PlaceStart(Pos),
+    PlaceExit(Pos),
+    PlaceArtifact(Pos, CellType),
+    /// A corridor was tunneled under an existing one, turning the crossing
+    /// into a bridge
+    PlaceBridge(Pos, CellType),
+    /// A solver added a cell to its frontier
+    Enqueue(Pos),
+    /// A solver expanded or stepped onto a cell
+    Visit(Pos),
+    /// The dead-end filler closed a cell
+    FillDeadEnd(Pos),
+    FoundExit(Pos),
+}
 
-#[derive(Clone)]
-pub struct Maze {
-    width: usize,
-    height: usize,
-    room_size: usize,
-    exit_type: ExitLocation,
-    cells: Vec<CellType>,
+#[derive(Clone, Default)]
+pub struct SolveTrace {
+    pub path: Option<Vec<Pos>>,
+    pub visited: Vec<Pos>,
+    pub expansions: usize,
+    pub order: Vec<Pos>,
 }
 
-#[derive(Clone, Copy, PartialEq, Eq, Hash)]
-pub struct Edge {
-    start_id: usize,
-    end_id: usize,
-    weight: i32,
+/// Generation of a maze one event at a time, see `Maze::generation_steps`
+pub struct GenerationSteps<'a, R: Rng + ?Sized> {
+    maze: &'a mut Maze,
+    rng: &'a mut R,
+    state: GenerationState,
 }
 
-type Edges = HashSet<Edge>;
-type Nodes = HashMap<Pos, usize>; // (position, node_id)
+impl<R: Rng + ?Sized> Iterator for GenerationSteps<'_, R> {
+    type Item = MazeEvent;
 
-macro_rules! constrain_dimension {
-    ($dim:expr) => {
-        if $dim < 7 {
-            7
-        } else {
-            let remainder = ($dim - 7) % 4;
-            if remainder == 0 {
-                $dim
-            } else {
-                $dim + (4 - remainder)
-            }
-        }
-    };
+    fn next(&mut self) -> Option<MazeEvent> {
+        self.state.next_event(self.maze, self.rng)
+    }
 }
 
-impl Maze {
-    pub fn new(width: usize, height: usize, room_size: usize, exit_type: ExitLocation) -> Self {
-        let width = constrain_dimension!(width);
-        let height = constrain_dimension!(height);
-        Maze {
-            width,
-            height,
-            room_size,
-            exit_type,
-            cells: vec![CellType::Wall; width * height],
+/// What `GenerationState` does next, in the order of `generate_within`
+enum GenerationPhase {
+    Room,
+    /// Generating a copy with narrow corridors and widening its events
+    Narrow(Box<Maze>, Box<GenerationState>),
+    Carve {
+        stack: Vec<Pos>,
+        visited: HashSet<Pos>,
+    },
+    RemoveWalls {
+        left: usize,
+    },
+    FarthestExit,
+    Artifacts,
+    Done,
+}
+
+/// The generator as a state machine, advanced a step at a time
+struct GenerationState {
+    phase: GenerationPhase,
+    /// Fill ratio of the artifacts placed at the end, if any
+    artifacts: Option<f32>,
+    /// Events of the last step not handed out yet
+    pending: std::collections::VecDeque<MazeEvent>,
+}
+
+impl GenerationState {
+    fn new(artifacts: Option<f32>) -> Self {
+        GenerationState {
+            phase: GenerationPhase::Room,
+            artifacts,
+            pending: std::collections::VecDeque::new(),
         }
     }
 
-    pub fn get_size(&self) -> (usize, usize) {
-        (self.width, self.height)
+    fn next_event<R: Rng + ?Sized>(&mut self, maze: &mut Maze, rng: &mut R) -> Option<MazeEvent> {
+        loop {
+            if let Some(event) = self.pending.pop_front() {
+                return Some(event);
+            }
+            if matches!(self.phase, GenerationPhase::Done) {
+                return None;
+            }
+            self.advance(maze, rng);
+        }
     }
 
-    pub fn get(&self, x: usize, y: usize) -> CellType {
-        self.cells[y * self.width + x]
+    fn advance<R: Rng + ?Sized>(&mut self, maze: &mut Maze, rng: &mut R) {
+        let GenerationState {
+            phase,
+            artifacts,
+            pending,
+        } = self;
+        let mut observer = |event: MazeEvent| pending.push_back(event);
+        *phase = match std::mem::replace(phase, GenerationPhase::Done) {
+            GenerationPhase::Room if maze.corridor_width > 1 => GenerationPhase::Narrow(
+                Box::new(maze.narrow()),
+                Box::new(GenerationState::new(None)),
+            ),
+            GenerationPhase::Room => {
+                let start = maze.carve_room(None, rng, &mut observer);
+                GenerationPhase::Carve {
+                    stack: vec![start],
+                    visited: maze.uncarvable(start),
+                }
+            }
+            GenerationPhase::Narrow(mut narrow, mut state) => {
+                match state.next_event(&mut narrow, rng) {
+                    Some(event) => {
+                        for event in maze.widen_event(event) {
+                            maze.apply(event);
+                            observer(event);
+                        }
+                        GenerationPhase::Narrow(narrow, state)
+                    }
+                    None => GenerationPhase::Artifacts,
+                }
+            }
+            GenerationPhase::Carve {
+                mut stack,
+                mut visited,
+            } => {
+                if maze.carve_step(&mut stack, &mut visited, None, rng, &mut observer) {
+                    GenerationPhase::Carve { stack, visited }
+                } else {
+                    GenerationPhase::RemoveWalls {
+                        left: maze.wall_removal_count(),
+                    }
+                }
+            }
+            GenerationPhase::RemoveWalls { left: 0 } => GenerationPhase::FarthestExit,
+            GenerationPhase::RemoveWalls { left } => {
+                if let Some(wall) = maze.remove_random_wall(None, rng) {
+                    observer(MazeEvent::RemoveWall(wall));
+                }
+                GenerationPhase::RemoveWalls { left: left - 1 }
+            }
+            GenerationPhase::FarthestExit => {
+                if maze.exit_type == ExitLocation::Farthest
+                    && let Some(exit) = maze.place_farthest_exit(None)
+                {
+                    observer(MazeEvent::PlaceExit(exit));
+                }
+                GenerationPhase::Artifacts
+            }
+            GenerationPhase::Artifacts => {
+                if let Some(fill_ratio) = *artifacts {
+                    maze.place_artifacts_observed(fill_ratio, rng, observer);
+                }
+                GenerationPhase::Done
+            }
+            GenerationPhase::Done => GenerationPhase::Done,
+        };
     }
+}
 
-    pub fn set(&mut self, x: usize, y: usize, value: CellType) {
-        self.cells[y * self.width + x] = value;
+/// A solver run one event at a time, see `Maze::solve_steps`
+pub struct SolveSteps<'a> {
+    maze: &'a Maze,
+    phase: SolvePhase,
+    /// Cells the dead-end filler closed before searching
+    filled: Vec<Pos>,
+    pending: std::collections::VecDeque<MazeEvent>,
+    trace: Option<SolveTrace>,
+}
+
+enum SolvePhase {
+    Search(Box<SearchState>),
+    Walk(WallWalk),
+    Fill(DeadEndFilling),
+    Done,
+}
+
+impl SolveSteps<'_> {
+    /// What the solver found and looked at, once it is done, as
+    /// `Maze::solve_instrumented` returns it
+    pub fn trace(&self) -> Option<&SolveTrace> {
+        self.trace.as_ref()
     }
+}
 
-    pub fn mst_prim(&self) -> (Nodes, Edges) {
-        let (nodes, edges) = self.build_graph();
-        let mut mst_edges = HashSet::new();
-        let mut visited = HashSet::new();
-        let mut total_weight = 0;
+impl Iterator for SolveSteps<'_> {
+    type Item = MazeEvent;
 
-        // Start from the center node
-        let start_node = nodes.get(&Pos {
-            x: self.width / 2,
-            y: self.height / 2,
-        });
-        if start_node.is_none() {
-            return (nodes, mst_edges);
+    fn next(&mut self) -> Option<MazeEvent> {
+        let maze = self.maze;
+        loop {
+            if let Some(event) = self.pending.pop_front() {
+                return Some(event);
+            }
+            let SolveSteps {
+                phase,
+                filled,
+                pending,
+                trace,
+                ..
+            } = &mut *self;
+            let observer = &mut |event: MazeEvent| pending.push_back(event);
+            // The dead-end filler's cells come first, as they were looked at
+            // before the search
+            let with_filled = |mut found: SolveTrace, filled: Vec<Pos>| {
+                found.expansions += filled.len();
+                found.order.splice(0..0, filled.iter().copied());
+                found.visited.splice(0..0, filled);
+                found
+            };
+            *phase = match std::mem::replace(phase, SolvePhase::Done) {
+                SolvePhase::Search(mut search) => {
+                    if search.advance(maze, observer) {
+                        SolvePhase::Search(search)
+                    } else {
+                        *trace = Some(with_filled(search.trace, std::mem::take(filled)));
+                        SolvePhase::Done
+                    }
+                }
+                SolvePhase::Walk(mut walk) => {
+                    if walk.advance(maze, observer) {
+                        SolvePhase::Walk(walk)
+                    } else {
+                        let walked = Maze::with_bridges(walk.path);
+                        let mut seen = HashSet::new();
+                        *trace = Some(SolveTrace {
+                            visited: walked
+                                .iter()
+                                .filter(|pos| seen.insert(**pos))
+                                .copied()
+                                .collect(),
+                            expansions: walked.len(),
+                            path: maze.reached_exit(&walked).then(|| walked.clone()),
+                            order: walked,
+                        });
+                        SolvePhase::Done
+                    }
+                }
+                SolvePhase::Fill(mut filling) => {
+                    if filling.advance(maze, observer) {
+                        SolvePhase::Fill(filling)
+                    } else {
+                        *filled = filling.filled;
+                        match SearchState::new(maze, Some(filling.open), false, observer) {
+                            Some(search) => SolvePhase::Search(Box::new(search)),
+                            None => {
+                                *trace = Some(with_filled(
+                                    SolveTrace::default(),
+                                    std::mem::take(filled),
+                                ));
+                                SolvePhase::Done
+                            }
+                        }
+                    }
+                }
+                SolvePhase::Done => return None,
+            };
         }
-        let start_node_id = *start_node.unwrap();
+    }
+}
 
-        visited.insert(start_node_id);
+/// Best-first search from the start to the exit over the traversable cells.
+/// Without a heuristic this is a plain breadth-first search, with the
+/// distance to the exit as one it's A*.
+struct SearchState {
+    start: Pos,
+    exit: Pos,
+    /// Cells the search may enter, all traversable ones if `None`
+    passable: Option<Vec<bool>>,
+    /// Whether the distance to the exit guides the search, making it A*
+    guided: bool,
+    parents: HashMap<Pos, Pos>,
+    costs: HashMap<Pos, usize>,
+    closed: HashSet<Pos>,
+    counter: usize,
+    open: BinaryHeap<Reverse<(usize, usize, usize, usize)>>,
+    trace: SolveTrace,
+}
 
-        while visited.len() < nodes.len() {
-            let mut min_edge: Option<Edge> = None;
+impl SearchState {
+    /// `None` if the maze has no exit to search for
+    fn new(
+        maze: &Maze,
+        passable: Option<Vec<bool>>,
+        guided: bool,
+        observer: &mut impl FnMut(MazeEvent),
+    ) -> Option<Self> {
+        let start = maze.start_pos();
+        let exit = maze.exit_pos()?;
+        let mut search = SearchState {
+            start,
+            exit,
+            passable,
+            guided,
+            parents: HashMap::from([(start, start)]),
+            costs: HashMap::from([(start, 0)]),
+            closed: HashSet::new(),
+            // Ties are broken by insertion order, which keeps the
+            // zero-heuristic case in breadth-first order
+            counter: 0,
+            open: BinaryHeap::new(),
+            trace: SolveTrace::default(),
+        };
+        search
+            .open
+            .push(Reverse((search.heuristic(start), 0, start.x, start.y)));
+        search.trace.visited.push(start);
+        observer(MazeEvent::Enqueue(start));
+        Some(search)
+    }
 
-            for edge in &edges {
-                // Check if the edge connects a visited node with an unvisited one
-                let connects_visited_and_unvisited = (visited.contains(&edge.start_id)
-                    && !visited.contains(&edge.end_id))
-                    || (visited.contains(&edge.end_id) && !visited.contains(&edge.start_id));
+    fn heuristic(&self, pos: Pos) -> usize {
+        if self.guided {
+            pos.manhattan(self.exit)
+        } else {
+            0
+        }
+    }
 
-                if connects_visited_and_unvisited
-                    && (min_edge.is_none() || edge.weight < min_edge.as_ref().unwrap().weight)
-                {
-                    min_edge = Some(*edge);
+    /// Expands the next cell. Returns false once the exit is found or there
+    /// is nothing left to expand.
+    fn advance(&mut self, maze: &Maze, observer: &mut impl FnMut(MazeEvent)) -> bool {
+        let Some(Reverse((_, _, x, y))) = self.open.pop() else {
+            return false;
+        };
+        let pos = Pos { x, y };
+        if !self.closed.insert(pos) {
+            return true;
+        }
+        self.trace.order.push(pos);
+        self.trace.expansions += 1;
+        observer(MazeEvent::Visit(pos));
+
+        if pos == self.exit {
+            observer(MazeEvent::FoundExit(pos));
+            let mut path = vec![pos];
+            let mut current = pos;
+            while current != self.start {
+                current = self.parents[&current];
+                path.push(current);
+            }
+            path.reverse();
+            self.trace.path = Some(Maze::with_bridges(path));
+            self.open.clear();
+            return false;
+        }
+
+        let cost = self.costs[&pos] + 1;
+        for direction in Direction::ALL {
+            if let Some(next) = maze.step(pos, direction)
+                && self
+                    .passable
+                    .as_ref()
+                    .is_none_or(|open| open[next.y * maze.width + next.x])
+                && self.costs.get(&next).is_none_or(|&known| cost < known)
+            {
+                if !self.costs.contains_key(&next) {
+                    self.trace.visited.push(next);
                 }
+                self.costs.insert(next, cost);
+                self.parents.insert(next, pos);
+                self.counter += 1;
+                self.open.push(Reverse((
+                    cost + self.heuristic(next),
+                    self.counter,
+                    next.x,
+                    next.y,
+                )));
+                observer(MazeEvent::Enqueue(next));
             }
+        }
+        true
+    }
+}
 
-            if let Some(edge) = min_edge {
-                visited.insert(edge.start_id);
-                visited.insert(edge.end_id);
-                mst_edges.insert(edge);
-                total_weight += edge.weight;
+/// The left- or right-hand rule, see `Maze::walk_wall`
+struct WallWalk {
+    hand: Hand,
+    pos: Pos,
+    heading: Direction,
+    touching_wall: bool,
+    path: Vec<Pos>,
+    steps_left: usize,
+}
+
+impl WallWalk {
+    fn new(maze: &Maze, hand: Hand, observer: &mut impl FnMut(MazeEvent)) -> Self {
+        let start = maze.start_pos();
+        let mut walk = WallWalk {
+            hand,
+            pos: start,
+            path: vec![start],
+            heading: Direction::East,
+            touching_wall: false,
+            steps_left: 4 * maze.width * maze.height,
+        };
+        if let Some((path, wall)) = Self::to_room_wall(maze) {
+            walk.pos = path[path.len() - 1];
+            walk.path = path;
+            walk.touching_wall = true;
+            walk.heading = match hand {
+                Hand::Left => wall.turn_right(),
+                Hand::Right => wall.turn_left(),
+            };
+        }
+        for &pos in &walk.path {
+            observer(MazeEvent::Visit(pos));
+        }
+        walk
+    }
+
+    /// The way from the start across the room to its first cell in
+    /// row-major order with a wall next to it, and the direction of that
+    /// wall. The wall goes all around the room and, unless it is an island
+    /// in a loop, out to the border, unlike the first wall straight ahead
+    /// of the start. `None` without such a cell or if the way crosses a
+    /// wall.
+    fn to_room_wall(maze: &Maze) -> Option<(Vec<Pos>, Direction)> {
+        let start = maze.start_pos();
+        if !maze.in_center_room(start) {
+            return None;
+        }
+        let (target, wall) = maze.room.rect().positions().find_map(|pos| {
+            Direction::ALL
+                .into_iter()
+                .find(|&direction| maze.step(pos, direction).is_none())
+                .map(|direction| (pos, direction))
+        })?;
+        // Along the row first, then along the column
+        let mut path = vec![start];
+        let mut pos = start;
+        while pos != target {
+            pos = if pos.x < target.x {
+                Pos::new(pos.x + 1, pos.y)
+            } else if pos.x > target.x {
+                Pos::new(pos.x - 1, pos.y)
+            } else if pos.y < target.y {
+                Pos::new(pos.x, pos.y + 1)
             } else {
-                break;
+                Pos::new(pos.x, pos.y - 1)
+            };
+            if !TRAVERSABLE.contains(&maze.get(pos.x, pos.y)) {
+                return None;
             }
+            path.push(pos);
         }
+        Some((path, wall))
+    }
 
-        println!("Minimum Spanning Tree weight: {}", total_weight);
-        for edge in &mst_edges {
-            println!(
-                "Edge from {} to {} with weight {}",
-                edge.start_id, edge.end_id, edge.weight
-            );
+    /// Headings to try, in order: hand side, straight, other side, back
+    fn turns(&self, heading: Direction) -> [Direction; 4] {
+        match self.hand {
+            Hand::Left => [
+                heading.turn_left(),
+                heading,
+                heading.turn_right(),
+                heading.reverse(),
+            ],
+            Hand::Right => [
+                heading.turn_right(),
+                heading,
+                heading.turn_left(),
+                heading.reverse(),
+            ],
         }
-        (nodes, mst_edges)
     }
 
-    pub fn generate(&mut self) {
-        let center_x = self.width / 2;
-        let center_y = self.height / 2;
-        let start = Pos {
-            x: center_x,
-            y: center_y,
+    /// Takes the next step. Returns false once the exit is reached, the
+    /// walker is stuck or the step cap ran out.
+    fn advance(&mut self, maze: &Maze, observer: &mut impl FnMut(MazeEvent)) -> bool {
+        if self.steps_left == 0 {
+            return false;
+        }
+        self.steps_left -= 1;
+        if maze.get(self.pos.x, self.pos.y) == CellType::Exit {
+            observer(MazeEvent::FoundExit(self.pos));
+            self.steps_left = 0;
+            return false;
+        }
+        if !self.touching_wall {
+            if let Some(next) = maze.step(self.pos, self.heading) {
+                self.pos = next;
+                self.path.push(next);
+                observer(MazeEvent::Visit(next));
+                return true;
+            }
+            // Turn away from the wall ahead so it ends up on our hand
+            self.touching_wall = true;
+            self.heading = self.turns(self.heading)[2];
+        }
+        let Some(direction) = self
+            .turns(self.heading)
+            .into_iter()
+            .find(|&direction| maze.step(self.pos, direction).is_some())
+        else {
+            self.steps_left = 0;
+            return false;
         };
+        self.heading = direction;
+        self.pos = maze.step(self.pos, direction).unwrap();
+        self.path.push(self.pos);
+        observer(MazeEvent::Visit(self.pos));
+        true
+    }
+}
 
-        // Create center room
-        for y in (center_y - self.room_size / 2)..=(center_y + self.room_size / 2) {
-            for x in (center_x - self.room_size / 2)..=(center_x + self.room_size / 2) {
-                self.set(x, y, CellType::Path);
-            }
+/// Filling dead ends until none are left, see `Maze::fill_dead_ends`
+struct DeadEndFilling {
+    start: Pos,
+    exit: Option<Pos>,
+    open: Vec<bool>,
+    filled: Vec<Pos>,
+    stack: Vec<Pos>,
+}
+
+impl DeadEndFilling {
+    fn new(maze: &Maze) -> Self {
+        let open: Vec<bool> = maze.cells.iter().map(|c| TRAVERSABLE.contains(c)).collect();
+        let stack = (0..maze.height)
+            .flat_map(|y| (0..maze.width).map(move |x| Pos { x, y }))
+            .filter(|pos| open[pos.y * maze.width + pos.x])
+            .collect();
+        DeadEndFilling {
+            start: maze.start_pos(),
+            exit: maze.exit_pos(),
+            open,
+            filled: Vec::new(),
+            stack,
         }
+    }
 
-        // Determine exit position based on exit_type
-        let exit_pos = match self.exit_type {
-            ExitLocation::Left => Pos {
-                x: 0,
-                y: self.height / 2,
-            },
-            ExitLocation::Right => Pos {
-                x: self.width - 1,
-                y: self.height / 2,
-            },
+    /// Checks the next cell on the stack. Returns false once it is empty.
+    fn advance(&mut self, maze: &Maze, observer: &mut impl FnMut(MazeEvent)) -> bool {
+        let Some(pos) = self.stack.pop() else {
+            return false;
+        };
+        let idx = pos.y * maze.width + pos.x;
+        if !self.open[idx] || pos == self.start || Some(pos) == self.exit {
+            return true;
+        }
+        let open_neighbors: Vec<Pos> = Direction::ALL
+            .into_iter()
+            .filter_map(|direction| maze.step(pos, direction))
+            .filter(|n| self.open[n.y * maze.width + n.x])
+            .collect();
+        if open_neighbors.len() > 1 {
+            return true;
+        }
+        self.open[idx] = false;
+        self.filled.push(pos);
+        observer(MazeEvent::FillDeadEnd(pos));
+        self.stack.extend(open_neighbors);
+        true
+    }
+}
+
+/// How one solver did in `benchmark_solvers`
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct SolverBenchResult {
+    pub solver: &'static str,
+    /// Whether the solver always finds a shortest path
+    pub optimal: bool,
+    pub time_ms: f64,
+    /// Cells or junctions the solver expanded, if it counts them
+    pub expansions: Option<usize>,
+    /// Number of cells on the path found, `None` if there was none
+    pub path_length: Option<usize>,
+    /// See `Maze::path_cost`
+    pub path_cost: Option<i32>,
+}
+
+/// Runs every solver on `maze` and measures how long each takes. Stepping
+/// from cell to cell costs the same everywhere, so a Dijkstra search over
+/// the cells would be the breadth-first search; Dijkstra is run on the
+/// junction graph instead. Fails if the optimal solvers don't agree on the
+/// length of the shortest path.
+pub fn benchmark_solvers(maze: &Maze) -> Result<Vec<SolverBenchResult>, MazeError> {
+    let timed = |solver: &'static str,
+                 optimal: bool,
+                 run: &dyn Fn() -> (Option<Vec<Pos>>, Option<usize>)| {
+        let started = std::time::Instant::now();
+        let (path, expansions) = run();
+        SolverBenchResult {
+            solver,
+            optimal,
+            time_ms: started.elapsed().as_secs_f64() * 1000.0,
+            expansions,
+            path_length: path.as_ref().map(Vec::len),
+            path_cost: path.as_ref().map(|path| maze.path_cost(path)),
+        }
+    };
+    let traced = |solver: Solver| {
+        let trace = maze.solve_instrumented(solver);
+        (trace.path, Some(trace.expansions))
+    };
+    let results = vec![
+        timed("BFS", true, &|| traced(Solver::Bfs)),
+        timed("A*", true, &|| traced(Solver::AStar)),
+        timed("Dijkstra on junctions", true, &|| {
+            (maze.shortest_path_via_graph(), None)
+        }),
+        timed("Dead-end fill", true, &|| traced(Solver::DeadEndFill)),
+        timed("Wall follower (left)", false, &|| {
+            traced(Solver::WallFollower(Hand::Left))
+        }),
+        timed("Wall follower (right)", false, &|| {
+            traced(Solver::WallFollower(Hand::Right))
+        }),
+    ];
+
+    let mut optimal = results.iter().filter(|result| result.optimal);
+    if let Some(first) = optimal.next()
+        && let Some(other) = optimal.find(|result| result.path_length != first.path_length)
+    {
+        return Err(MazeError::SolverDisagreement {
+            solver: other.solver,
+            length: other.path_length,
+            expected: first.path_length,
+        });
+    }
+    Ok(results)
+}
+
+/// Colors shared by the SVG and terminal renderers
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize), serde(default))]
+pub struct Theme {
+    pub wall_color: [u8; 3],
+    pub path_color: [u8; 3],
+    pub reward_color: [u8; 3],
+    pub danger_color: [u8; 3],
+    pub solution_color: [u8; 3],
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Theme {
+            wall_color: [0x22, 0x22, 0x22],
+            path_color: [0xee, 0xee, 0xee],
+            reward_color: [0x22, 0xdd, 0x11],
+            danger_color: [0xee, 0x44, 0x33],
+            solution_color: [28, 163, 163],
+        }
+    }
+}
+
+/// Summary of a maze's layout, serializable for tools consuming it as JSON
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct MazeStats {
+    pub width: usize,
+    pub height: usize,
+    pub room_size: usize,
+    /// Number of traversable cells
+    pub open_cells: usize,
+    pub dead_ends: usize,
+    pub junctions: usize,
+    /// See `Maze::count_loops`
+    pub loops: usize,
+    /// See `Maze::articulation_points`
+    pub articulation_points: usize,
+    /// See `Maze::cut_corridors`
+    pub cut_corridors: usize,
+    pub rewards: usize,
+    pub dangers: usize,
+    /// Number of cells on the shortest path, `None` if the exit is unreachable
+    pub solution_length: Option<usize>,
+    pub difficulty: f32,
+    /// See `Maze::fingerprint`
+    pub fingerprint: u64,
+    /// Steps from the shortest path to the rewards that can be reached,
+    /// `None` without a path or rewards
+    pub reward_distances: Option<DistanceSummary>,
+}
+
+/// Up to how many rewards `Maze::full_collection_route` finds the shortest
+/// route. Beyond that trying every order takes too long, and it settles for
+/// a good one.
+pub const EXACT_COLLECTION_REWARDS: usize = 15;
+
+/// A way from the start over every reward to the exit, see
+/// `Maze::full_collection_route`
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CollectionRoute {
+    /// The cells walked, from the start to the exit
+    pub path: Vec<Pos>,
+    /// Steps taken, as `Maze::distance_map` counts them
+    pub steps: u32,
+    /// Whether the route is the shortest there is, found with the
+    /// Held-Karp algorithm. Otherwise it heads for the nearest reward left
+    /// each time, shortened by 2-opt afterwards.
+    pub optimal: bool,
+}
+
+/// Order of the points of `distances` that visits them all the fewest steps,
+/// starting at the first and ending at the second. Held-Karp: the shortest
+/// way from the start through each set of the others to each of them, built
+/// up from the smaller sets.
+fn held_karp_order(distances: &[Vec<u32>]) -> Vec<usize> {
+    // The points in between are numbered from 0 here, 2 in `distances`
+    let n = distances.len() - 2;
+    if n == 0 {
+        return vec![0, 1];
+    }
+    let full = (1usize << n) - 1;
+    let mut costs = vec![u32::MAX; (full + 1) * n];
+    let mut parents = vec![0; (full + 1) * n];
+    for first in 0..n {
+        costs[(1 << first) * n + first] = distances[0][first + 2];
+    }
+    for set in 1..=full {
+        for last in (0..n).filter(|last| set & (1 << last) != 0) {
+            let cost = costs[set * n + last];
+            if cost == u32::MAX {
+                continue;
+            }
+            for next in (0..n).filter(|next| set & (1 << next) == 0) {
+                let to = (set | (1 << next)) * n + next;
+                let via = cost.saturating_add(distances[last + 2][next + 2]);
+                if via < costs[to] {
+                    costs[to] = via;
+                    parents[to] = last;
+                }
+            }
+        }
+    }
+    let mut last = (0..n)
+        .min_by_key(|&last| costs[full * n + last].saturating_add(distances[last + 2][1]))
+        .unwrap();
+    let mut set = full;
+    let mut order = vec![1];
+    loop {
+        order.push(last + 2);
+        let parent = parents[set * n + last];
+        set &= !(1 << last);
+        if set == 0 {
+            break;
+        }
+        last = parent;
+    }
+    order.push(0);
+    order.reverse();
+    order
+}
+
+/// Like `held_karp_order`, but only a short order rather than the shortest:
+/// always on to the nearest point left, then reversing stretches of the
+/// order (2-opt) as long as that makes it shorter.
+fn nearest_neighbor_order(distances: &[Vec<u32>]) -> Vec<usize> {
+    let mut order = vec![0];
+    let mut left: Vec<usize> = (2..distances.len()).collect();
+    let mut last = 0;
+    while let Some(i) = (0..left.len()).min_by_key(|&i| distances[last][left[i]]) {
+        last = left.remove(i);
+        order.push(last);
+    }
+    order.push(1);
+    let length = |a: usize, b: usize| u64::from(distances[a][b]);
+    let mut improved = true;
+    while improved {
+        improved = false;
+        for i in 1..order.len() - 2 {
+            for j in i + 1..order.len() - 1 {
+                let (a, b, c, d) = (order[i - 1], order[i], order[j], order[j + 1]);
+                if length(a, c) + length(b, d) < length(a, b) + length(c, d) {
+                    order[i..=j].reverse();
+                    improved = true;
+                }
+            }
+        }
+    }
+    order
+}
+
+/// Smallest, median and largest of a number of distances, in steps. Of an
+/// even number the lower of the two middle ones is the median.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct DistanceSummary {
+    pub min: u32,
+    pub median: u32,
+    pub max: u32,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum SvgOverlay {
+    None,
+    /// Color each traversable cell by its distance from the start
+    #[cfg_attr(feature = "cli", value(name = "heatmap"))]
+    DistanceHeatmap,
+    /// Draw up to `SHORTEST_PATHS_SHOWN` shortest paths, each in its own hue
+    ShortestPaths,
+    /// Shade the corridors from `Maze::cut_corridors` in the danger color
+    CutCorridors,
+}
+
+/// How many of `Maze::all_shortest_paths` the `ShortestPaths` overlay draws
+pub const SHORTEST_PATHS_SHOWN: usize = 8;
+
+/// How the solution is drawn. Dots and arrows give less away than a line,
+/// e.g. for young kids.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum SolutionStyle {
+    /// One line from the start to the exit
+    #[default]
+    Line,
+    /// A dot on every `every`th cell, beginning with the start
+    Dots { every: usize },
+    /// An arrow pointing the way on every `every`th cell, beginning with
+    /// the start
+    Arrows { every: usize },
+}
+
+impl SolutionStyle {
+    /// The cells of `path` that get a dot or an arrow, each with the
+    /// direction of travel there. Empty for a line.
+    pub fn markers(&self, path: &[Pos]) -> Vec<(Pos, Direction)> {
+        let every = match *self {
+            SolutionStyle::Line => return Vec::new(),
+            SolutionStyle::Dots { every } | SolutionStyle::Arrows { every } => every.max(1),
+        };
+        let direction = |from: Pos, to: Pos| {
+            Direction::ALL
+                .into_iter()
+                .find(|&direction| from.step(direction, (usize::MAX, usize::MAX)) == Some(to))
+                .unwrap_or(Direction::East)
+        };
+        (0..path.len())
+            .step_by(every)
+            .map(|i| {
+                // The last cell keeps the direction it was entered in
+                let heading = match (path.get(i + 1), i.checked_sub(1)) {
+                    (Some(&next), _) => direction(path[i], next),
+                    (None, Some(previous)) => direction(path[previous], path[i]),
+                    (None, None) => Direction::East,
+                };
+                (path[i], heading)
+            })
+            .collect()
+    }
+}
+
+/// As written on the command line: `line`, `dots:3` or `arrows:3`
+impl Display for SolutionStyle {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SolutionStyle::Line => write!(f, "line"),
+            SolutionStyle::Dots { every } => write!(f, "dots:{}", every),
+            SolutionStyle::Arrows { every } => write!(f, "arrows:{}", every),
+        }
+    }
+}
+
+/// Parses what `Display` writes. The number of cells between markers is
+/// optional and defaults to 1.
+impl std::str::FromStr for SolutionStyle {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (name, every) = match s.split_once(':') {
+            Some((name, every)) => (
+                name,
+                every
+                    .parse::<usize>()
+                    .ok()
+                    .filter(|&every| every > 0)
+                    .ok_or_else(|| format!("{} isn't a positive number of cells", every))?,
+            ),
+            None => (s, 1),
+        };
+        match name {
+            "line" => Ok(SolutionStyle::Line),
+            "dots" => Ok(SolutionStyle::Dots { every }),
+            "arrows" => Ok(SolutionStyle::Arrows { every }),
+            _ => Err(format!(
+                "unknown solution style {}, expected line, dots or arrows",
+                name
+            )),
+        }
+    }
+}
+
+/// The artifact types a legend lists and the area it takes, in cells
+struct LegendLayout {
+    entries: Vec<CellType>,
+    columns: usize,
+    x: f32,
+    y: f32,
+    width: f32,
+    height: f32,
+}
+
+/// Size and spacing of the coordinate labels and the margin they take up
+/// left of and above the maze, in cells
+struct CoordinateLayout {
+    font_size: f32,
+    left: f32,
+    top: f32,
+    column_step: usize,
+    row_step: usize,
+}
+
+/// Where the legend goes in SVG output, see `SvgOptions::include_legend`
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum LegendPosition {
+    #[default]
+    Bottom,
+    Right,
+}
+
+/// Width of a legend column and height of a row, in cells
+const LEGEND_COLUMN: f32 = 12.0;
+const LEGEND_ROW: f32 = 1.5;
+
+/// Which part of the maze `Maze::write_svg_fogged` shows, the rest is
+/// hidden under `FOG_COLOR`. Radii count steps along the passages, not
+/// cells as the crow flies.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum RevealRule {
+    /// The open cells up to `radius` steps from the start
+    AroundStart { radius: usize },
+    /// The open cells up to `radius` steps from the shortest path, or from
+    /// the start if the exit can't be reached
+    AroundPath { radius: usize },
+    /// Exactly these cells
+    Explicit(HashSet<Pos>),
+}
+
+/// Neutral gray covering the hidden part of a fogged SVG
+const FOG_COLOR: [u8; 3] = [0x99, 0x99, 0x99];
+
+/// Font size of the coordinate labels in cells, and the least it may be in
+/// pixels so the labels stay readable at small scales
+const COORDINATE_FONT: f32 = 0.8;
+const MIN_COORDINATE_FONT_PX: f32 = 9.0;
+
+/// Animation played when an SVG is opened in a browser
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SvgAnimation {
+    None,
+    /// The solution draws itself from the start to the exit
+    SolutionDraw {
+        duration_secs: f32,
+    },
+}
+
+#[derive(Clone)]
+pub struct SvgOptions {
+    pub scale: f32,
+    pub with_solution: SolutionType,
+    pub solver: Solver,
+    /// Shade the cells the solver looked at underneath the solution
+    pub show_visited: bool,
+    pub overlay: SvgOverlay,
+    /// Colors of the heatmap gradient, nearest to farthest from the start
+    pub heatmap_colors: ([u8; 3], [u8; 3]),
+    pub show_artifacts: bool,
+    pub theme: Theme,
+    /// Stroke width of the solution path, relative to the cell size
+    pub solution_width: f32,
+    /// Only a line can be animated
+    pub solution_style: SolutionStyle,
+    /// Icons replacing the built-in ones or the plain circle of a cell
+    /// type: the shapes inside a `<symbol>` with a `0 0 10 10` view box
+    pub icons: HashMap<CellType, String>,
+    /// Colors of the plain circles of single cell types, instead of the
+    /// reward or danger color of the theme
+    pub cell_colors: HashMap<CellType, [u8; 3]>,
+    /// List the artifacts in the maze with their marker, name and weight
+    /// next to it, making the image bigger
+    pub include_legend: bool,
+    pub legend_position: LegendPosition,
+    /// Title of the image, for screen readers and as a tooltip. With a
+    /// title the image is also described by the size, seed and difficulty.
+    pub title: Option<String>,
+    /// How the maze was generated, embedded as JSON so it can be generated
+    /// again, see `Maze::params_from_svg_metadata`
+    #[cfg(feature = "serde")]
+    pub params: Option<MazeBuilder>,
+    /// Draw the characters placed with `Maze::place_labels`
+    pub show_labels: bool,
+    pub animation: SvgAnimation,
+    /// Letter the columns across the top and number the rows down the
+    /// left, as in `Maze::write_text_with_coordinates`, making the image
+    /// bigger by a margin
+    pub coordinates: bool,
+    /// Draw a light grid line every this many cells over the maze
+    pub grid: Option<usize>,
+    /// Sharp edges without hairline seams between walls: the scale is
+    /// rounded to whole pixels per cell and only applied through the width
+    /// and height, the view box is in cells, and the walls are a single
+    /// shape drawn with `shape-rendering="crispEdges"`
+    pub crisp: bool,
+}
+
+impl Default for SvgOptions {
+    fn default() -> Self {
+        SvgOptions {
+            scale: 10.0,
+            with_solution: SolutionType::None,
+            solver: Solver::Bfs,
+            show_visited: false,
+            overlay: SvgOverlay::None,
+            heatmap_colors: ([40, 90, 230], [230, 50, 40]),
+            show_artifacts: true,
+            theme: Theme::default(),
+            solution_width: 0.35,
+            solution_style: SolutionStyle::Line,
+            icons: HashMap::new(),
+            cell_colors: HashMap::new(),
+            include_legend: false,
+            legend_position: LegendPosition::Bottom,
+            title: None,
+            #[cfg(feature = "serde")]
+            params: None,
+            show_labels: true,
+            animation: SvgAnimation::None,
+            coordinates: false,
+            grid: None,
+            crisp: false,
+        }
+    }
+}
+
+fn hex_color([r, g, b]: [u8; 3]) -> String {
+    format!("#{:02x}{:02x}{:02x}", r, g, b)
+}
+
+/// Shapes of the built-in artifact icons, in a 10 by 10 box. Types without
+/// one are drawn as a circle in the reward or danger color.
+fn builtin_icon(cell: CellType) -> Option<&'static str> {
+    let shapes = match cell {
+        CellType::Pumpkin => {
+            r##"<ellipse cx="5" cy="6" rx="4.5" ry="3.5" fill="#f08020" /><rect x="4.5" y="1" width="1" height="2.5" fill="#3a7a20" />"##
+        }
+        CellType::Bat => {
+            r##"<path d="M0 4 Q2 2 3 4 Q4 3 5 5 Q6 3 7 4 Q8 2 10 4 Q8 5 7 7 Q6 6 5 7 Q4 6 3 7 Q2 5 0 4Z" fill="#222" />"##
+        }
+        CellType::Ghost => {
+            r##"<path d="M2 9 V4.5 A3 3 0 0 1 8 4.5 V9 L6.5 8 L5 9 L3.5 8Z" fill="#f4f4f4" stroke="#999" stroke-width="0.3" /><circle cx="4" cy="4.5" r="0.6" fill="#222" /><circle cx="6" cy="4.5" r="0.6" fill="#222" />"##
+        }
+        CellType::Spider => {
+            r##"<path d="M1 2 L5 5 L9 2 M1 5 H9 M1 8 L5 5 L9 8" stroke="#222" stroke-width="0.5" fill="none" /><circle cx="5" cy="5" r="2" fill="#222" />"##
+        }
+        CellType::Witch => {
+            r##"<path d="M5 1 L2.8 8 H7.2Z" fill="#402060" /><rect x="1" y="7.8" width="8" height="1" fill="#402060" />"##
+        }
+        CellType::Candy => {
+            r##"<path d="M2.5 5 L0.5 3 V7Z M7.5 5 L9.5 3 V7Z" fill="#e04090" /><circle cx="5" cy="5" r="2.5" fill="#e04090" />"##
+        }
+        CellType::Cookies => {
+            r##"<circle cx="5" cy="5" r="4" fill="#c8904a" /><circle cx="3.5" cy="4" r="0.6" fill="#4a2a10" /><circle cx="6" cy="3.5" r="0.6" fill="#4a2a10" /><circle cx="5" cy="6.5" r="0.6" fill="#4a2a10" />"##
+        }
+        CellType::Chocolate => {
+            r##"<rect x="2" y="1.5" width="6" height="7" fill="#5a3010" /><path d="M2 4 H8 M2 6.5 H8 M5 1.5 V8.5" stroke="#3a1a05" stroke-width="0.4" />"##
+        }
+        _ => return None,
+    };
+    Some(shapes)
+}
+
+/// The id of the icon of `cell` in the SVG, e.g. `icon-black-cat`
+fn icon_id(cell: CellType) -> String {
+    format!("icon-{}", cell.to_string().to_lowercase().replace(' ', "-"))
+}
+
+/// The icon of `cell`, the one from `options` or the built-in one
+fn icon(cell: CellType, options: &SvgOptions) -> Option<&str> {
+    options
+        .icons
+        .get(&cell)
+        .map(String::as_str)
+        .or_else(|| builtin_icon(cell))
+}
+
+/// The color of the plain circle of an artifact, the one from `options` or
+/// the theme's reward or danger color
+fn artifact_color(cell: CellType, options: &SvgOptions) -> [u8; 3] {
+    match options.cell_colors.get(&cell) {
+        Some(&color) => color,
+        None if REWARDS.contains(&cell) => options.theme.reward_color,
+        None => options.theme.danger_color,
+    }
+}
+
+/// Writes a `<defs>` block with a symbol for each artifact type among
+/// `cells` that has an icon, for the cells to `<use>`.
+fn write_svg_icon_defs<W: Write>(
+    file: &mut W,
+    cells: impl Iterator<Item = CellType>,
+    options: &SvgOptions,
+) -> std::io::Result<()> {
+    if !options.show_artifacts {
+        return Ok(());
+    }
+    let present: HashSet<CellType> = cells.collect();
+    let symbols: Vec<(CellType, &str)> = CELL_TYPES
+        .iter()
+        .filter(|cell| present.contains(cell) && (REWARDS.contains(cell) || DANGERS.contains(cell)))
+        .filter_map(|&cell| Some((cell, icon(cell, options)?)))
+        .collect();
+    if symbols.is_empty() {
+        return Ok(());
+    }
+    writeln!(file, "  <defs>")?;
+    for (cell, shapes) in symbols {
+        writeln!(
+            file,
+            "    <symbol id=\"{}\" viewBox=\"0 0 10 10\">{}</symbol>",
+            icon_id(cell),
+            shapes
+        )?;
+    }
+    writeln!(file, "  </defs>")
+}
+
+/// Color of the lines `write_svg_overlay` draws
+#[derive(Clone, Copy)]
+enum OverlayColor {
+    Solution,
+    /// Hues evenly spread around the color wheel, one per line
+    OneHueEach,
+    Fixed([u8; 3]),
+}
+
+/// Purple, so the route from `Maze::full_collection_route` stands apart
+/// from the solution
+const COLLECTION_ROUTE_COLOR: [u8; 3] = [0x8e, 0x44, 0xad];
+
+/// Writes the solution lines, one per run of `points`, into an overlay group
+/// meant to come after everything else. Rounded corners and ends keep them
+/// clean at small scales.
+///
+/// To animate the solution, each line is a single dash as long as the line,
+/// shifted out of view and moved back in by a CSS animation. The lines take
+/// their share of the duration by length, one after the other.
+fn write_svg_overlay<W: Write>(
+    file: &mut W,
+    lines: &[Vec<(f32, f32)>],
+    color: OverlayColor,
+    options: &SvgOptions,
+) -> std::io::Result<()> {
+    let lengths: Vec<f32> = lines
+        .iter()
+        .map(|points| {
+            points
+                .windows(2)
+                .map(|pair| (pair[1].0 - pair[0].0).hypot(pair[1].1 - pair[0].1))
+                .sum()
+        })
+        .collect();
+    let total: f32 = lengths.iter().sum();
+
+    writeln!(file, "    <g class=\"overlay\">")?;
+    if let SvgAnimation::SolutionDraw { .. } = options.animation {
+        writeln!(
+            file,
+            "      <style>@keyframes draw-solution {{ to {{ stroke-dashoffset: 0; }} }}</style>"
+        )?;
+    }
+    let mut drawn = 0.0;
+    for (i, (points, length)) in lines.iter().zip(lengths).enumerate() {
+        let color = match color {
+            OverlayColor::Solution => hex_color(options.theme.solution_color),
+            OverlayColor::OneHueEach => format!("hsl({}, 80%, 45%)", i * 360 / lines.len()),
+            OverlayColor::Fixed(color) => hex_color(color),
+        };
+        write!(
+            file,
+            "      <polyline fill=\"none\" stroke=\"{}\" stroke-width=\"{}\" stroke-linejoin=\"round\" stroke-linecap=\"round\"",
+            color, options.solution_width
+        )?;
+        if let SvgAnimation::SolutionDraw { duration_secs } = options.animation
+            && total > 0.0
+        {
+            write!(
+                file,
+                " stroke-dasharray=\"{}\" stroke-dashoffset=\"{}\" style=\"animation: draw-solution {}s linear {}s forwards\"",
+                length,
+                length,
+                duration_secs * length / total,
+                duration_secs * drawn / total
+            )?;
+            drawn += length;
+        }
+        write!(file, " points=\"")?;
+        for (x, y) in points {
+            write!(file, "{},{} ", x, y)?;
+        }
+        writeln!(file, "\" />")?;
+    }
+    writeln!(file, "    </g>")
+}
+
+/// Writes the dots or arrows of `options.solution_style` along `path`.
+/// Arrows are drawn pointing east and rotated, the directions going
+/// clockwise in quarter turns.
+fn write_svg_markers<W: Write>(
+    file: &mut W,
+    path: &[Pos],
+    options: &SvgOptions,
+) -> std::io::Result<()> {
+    let color = hex_color(options.theme.solution_color);
+    writeln!(file, "    <g class=\"overlay\">")?;
+    for (pos, direction) in options.solution_style.markers(path) {
+        let (x, y) = (pos.x as f32 + 0.5, pos.y as f32 + 0.5);
+        match options.solution_style {
+            SolutionStyle::Arrows { .. } => writeln!(
+                file,
+                "      <polygon points=\"0.3,0 -0.2,-0.25 -0.2,0.25\" transform=\"translate({} {}) rotate({})\" fill=\"{}\" />",
+                x,
+                y,
+                direction as usize * 90,
+                color
+            )?,
+            _ => writeln!(
+                file,
+                "      <circle cx=\"{}\" cy=\"{}\" r=\"{}\" fill=\"{}\" />",
+                x,
+                y,
+                options.solution_width / 2.0,
+                color
+            )?,
+        }
+    }
+    writeln!(file, "    </g>")
+}
+
+/// Writes what goes on top of an open cell: the artifact, if artifacts are
+/// shown, or the railings of a bridge. Nothing for other cells.
+fn write_svg_cell<W: Write>(
+    file: &mut W,
+    Pos { x, y }: Pos,
+    cell: CellType,
+    options: &SvgOptions,
+) -> std::io::Result<()> {
+    match cell {
+        cell if options.show_artifacts && (REWARDS.contains(&cell) || DANGERS.contains(&cell)) => {
+            if icon(cell, options).is_some() {
+                return writeln!(
+                    file,
+                    "    <use href=\"#{}\" x=\"{}\" y=\"{}\" width=\"1\" height=\"1\"><title>{}</title></use>",
+                    icon_id(cell),
+                    x,
+                    y,
+                    cell
+                );
+            }
+            writeln!(
+                file,
+                "    <circle cx=\"{}\" cy=\"{}\" r=\"0.4\" fill=\"{}\"><title>{}</title></circle>",
+                x as f32 + 0.5,
+                y as f32 + 0.5,
+                hex_color(artifact_color(cell, options)),
+                cell
+            )?;
+        }
+        // Railings along the passage on top, leaving gaps in the one
+        // underneath
+        CellType::BridgeNorthSouth | CellType::BridgeEastWest => {
+            let vertical = cell == CellType::BridgeNorthSouth;
+            for offset in [0.1, 0.8] {
+                let (rx, ry, width, height) = if vertical {
+                    (x as f32 + offset, y as f32, 0.1, 1.0)
+                } else {
+                    (x as f32, y as f32 + offset, 1.0, 0.1)
+                };
+                writeln!(
+                    file,
+                    "    <rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\" fill=\"{}\" />",
+                    rx,
+                    ry,
+                    width,
+                    height,
+                    hex_color(options.theme.wall_color)
+                )?;
+            }
+        }
+        _ => {}
+    }
+    Ok(())
+}
+
+/// Name of the column `x` in coordinate labels: A to Z, then AA, AB and so on
+pub fn column_name(x: usize) -> String {
+    let mut name = Vec::new();
+    let mut n = x + 1;
+    while n > 0 {
+        n -= 1;
+        name.push(b'A' + (n % 26) as u8);
+        n /= 26;
+    }
+    name.iter().rev().map(|&c| c as char).collect()
+}
+
+/// A character as SVG text, escaped if it has a meaning in XML
+fn xml_escape(c: char) -> String {
+    match c {
+        '<' => "&lt;".to_string(),
+        '>' => "&gt;".to_string(),
+        '&' => "&amp;".to_string(),
+        c => c.to_string(),
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum MazeViolation {
+    /// A group of traversable cells that can't be reached from the start
+    Unreachable {
+        pos: Pos,
+        size: usize,
+    },
+    /// A traversable cell other than an exit on the outer border
+    OpenBorder(Pos),
+    MissingStart,
+    MultipleStarts(usize),
+    MissingExit,
+    /// A cell inside the center room that isn't traversable
+    RoomBlocked(Pos),
+}
+
+impl Display for MazeViolation {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MazeViolation::Unreachable { pos, size } => {
+                write!(f, "{} unreachable cell(s) starting at {}", size, pos)
+            }
+            MazeViolation::OpenBorder(pos) => {
+                write!(f, "Border cell {} is not a wall", pos)
+            }
+            MazeViolation::MissingStart => write!(f, "Maze has no start"),
+            MazeViolation::MultipleStarts(count) => write!(f, "Maze has {} starts", count),
+            MazeViolation::MissingExit => write!(f, "Maze has no exit"),
+            MazeViolation::RoomBlocked(pos) => {
+                write!(f, "Center room cell {} is blocked", pos)
+            }
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum MazeError {
+    /// The size doesn't follow the size rule or doesn't match the cells
+    InvalidDimensions {
+        width: usize,
+        height: usize,
+        reason: String,
+    },
+    /// The center room doesn't fit into the maze
+    InvalidRoomSize {
+        room_size: usize,
+        reason: String,
+    },
+    OutOfBounds {
+        pos: Pos,
+        size: (usize, usize),
+    },
+    /// An edit that would break the rules checked by `validate`
+    InvalidEdit {
+        pos: Pos,
+        reason: String,
+    },
+    /// There is no way from the start to the exit
+    Unsolvable,
+    /// Two consecutive positions of a path aren't next to each other
+    NotAdjacent {
+        from: Pos,
+        to: Pos,
+    },
+    /// Reading or writing a file failed. `path` is the file, if known.
+    Io {
+        path: Option<String>,
+        source: std::io::Error,
+    },
+    /// A maze file or share code can't be decoded. Share codes are a single
+    /// line, so `line` is always 1 for them.
+    Parse {
+        line: usize,
+        reason: String,
+    },
+    /// A maze file or share code was written in a newer format
+    UnsupportedVersion {
+        version: u64,
+        supported: u64,
+    },
+    /// A `MazeBuilder` option is out of range
+    InvalidOption {
+        name: &'static str,
+        reason: String,
+    },
+    /// A turn of a `Replay` didn't play out as recorded
+    ReplayDiverged {
+        turn: usize,
+        reason: String,
+    },
+    /// An optimal solver in `benchmark_solvers` found a path of another
+    /// length than the first one
+    SolverDisagreement {
+        solver: &'static str,
+        length: Option<usize>,
+        expected: Option<usize>,
+    },
+}
+
+impl Display for MazeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MazeError::InvalidDimensions {
+                width,
+                height,
+                reason,
+            } => write!(f, "Invalid size {}x{}: {}", width, height, reason),
+            MazeError::InvalidRoomSize { room_size, reason } => {
+                write!(f, "Invalid room size {}: {}", room_size, reason)
+            }
+            MazeError::OutOfBounds { pos, size } => {
+                write!(f, "Cell {} is outside the {}x{} maze", pos, size.0, size.1)
+            }
+            MazeError::InvalidEdit { pos, reason } => {
+                write!(f, "Can't edit cell {}: {}", pos, reason)
+            }
+            MazeError::Unsolvable => write!(f, "There is no way from the start to the exit"),
+            MazeError::NotAdjacent { from, to } => {
+                write!(f, "{} and {} are not adjacent", from, to)
+            }
+            MazeError::Io {
+                path: Some(path),
+                source,
+            } => write!(f, "{}: {}", path, source),
+            MazeError::Io { path: None, source } => write!(f, "{}", source),
+            MazeError::Parse { line, reason } => {
+                write!(f, "Parse error in line {}: {}", line, reason)
+            }
+            MazeError::UnsupportedVersion { version, supported } => write!(
+                f,
+                "Unsupported format version {} (at most {} is supported)",
+                version, supported
+            ),
+            MazeError::InvalidOption { name, reason } => {
+                write!(f, "Invalid {}: {}", name, reason)
+            }
+            MazeError::ReplayDiverged { turn, reason } => {
+                write!(f, "The replay diverged in turn {}: {}", turn, reason)
+            }
+            MazeError::SolverDisagreement {
+                solver,
+                length,
+                expected,
+            } => write!(
+                f,
+                "{} found a path of {:?} cells instead of {:?}",
+                solver, length, expected
+            ),
+        }
+    }
+}
+
+impl std::error::Error for MazeError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            MazeError::Io { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
+
+impl From<std::io::Error> for MazeError {
+    fn from(source: std::io::Error) -> Self {
+        MazeError::Io { path: None, source }
+    }
+}
+
+/// Creates `filename` and lets `write` fill it through a buffer, attaching
+/// the filename to any error.
+fn write_file(
+    filename: &str,
+    write: impl FnOnce(&mut BufWriter<File>) -> std::io::Result<()>,
+) -> Result<(), MazeError> {
+    let io_error = |source| MazeError::Io {
+        path: Some(filename.to_string()),
+        source,
+    };
+    let mut file = BufWriter::new(File::create(filename).map_err(io_error)?);
+    write(&mut file)
+        .and_then(|()| file.flush())
+        .map_err(io_error)
+}
+
+/// Most frames `Maze::export_generation_gif` writes, including the first one
+/// of the blank maze
+#[cfg(feature = "image")]
+pub const MAX_GIF_FRAMES: usize = 200;
+
+/// Mazes `MazeBuilder::min_solution_length` generates before giving up
+pub const MIN_LENGTH_ATTEMPTS: usize = 100;
+
+/// How far the difficulty of a maze built with `MazeBuilder::difficulty` may
+/// be off its target, unless set with `MazeBuilder::difficulty_tolerance`
+pub const DIFFICULTY_TOLERANCE: f32 = 5.0;
+
+/// Mazes `MazeBuilder::difficulty` generates at most to hit its target,
+/// unless set with `MazeBuilder::difficulty_tolerance`
+pub const DIFFICULTY_ATTEMPTS: usize = 100;
+
+/// Random routes `Maze::place_guards` samples to find the busiest corridors
+/// of a maze with loops
+pub const GUARD_SAMPLES: usize = 1000;
+
+/// Distances from the exit `Maze::fair_starts` tries to spread starts out at
+const FAIR_START_BANDS: usize = 8;
+
+/// Format version written as the first byte of a share string. Version 2
+/// adds the corridor width after the exit location, mazes with narrow
+/// corridors are still written as version 1.
+const SHARE_VERSION: u8 = 2;
+
+/// Cell types in declaration order, so a cell's share code is its index
+const CELL_TYPES: [CellType; 22] = [
+    CellType::Start,
+    CellType::Exit,
+    CellType::Wall,
+    CellType::Path,
+    CellType::Marshmallows,
+    CellType::GummyBears,
+    CellType::Cookies,
+    CellType::Candy,
+    CellType::Chocolate,
+    CellType::Zombie,
+    CellType::Ghost,
+    CellType::Witch,
+    CellType::Fog,
+    CellType::Shadows,
+    CellType::Crow,
+    CellType::BlackCat,
+    CellType::Skeleton,
+    CellType::Spider,
+    CellType::Bat,
+    CellType::Pumpkin,
+    CellType::BridgeNorthSouth,
+    CellType::BridgeEastWest,
+];
+
+const EXIT_LOCATIONS: [ExitLocation; 6] = [
+    ExitLocation::Random,
+    ExitLocation::Left,
+    ExitLocation::Right,
+    ExitLocation::Top,
+    ExitLocation::Bottom,
+    ExitLocation::Farthest,
+];
+
+/// The sides an exit can be on, in the order a random one is picked from
+const SIDES: [ExitLocation; 4] = [
+    ExitLocation::Left,
+    ExitLocation::Right,
+    ExitLocation::Top,
+    ExitLocation::Bottom,
+];
+
+/// A move of the player in a `Simulation`
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum PlayerMove {
+    Go(Direction),
+    /// Stay put for a turn, letting the monsters move
+    Wait,
+}
+
+/// What a turn of a `Simulation` came to
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum StepOutcome {
+    /// The game goes on
+    Continue,
+    /// The move ran into a wall, nothing happened and no turn passed
+    Blocked,
+    /// The player reached the exit
+    Win,
+    /// The player ran out of health
+    Lose,
+}
+
+/// How a danger moves in a `Simulation`, one cell per turn at most. Moving
+/// dangers only ever enter plain path cells or the player's, never the
+/// center room.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum MonsterBehavior {
+    /// Stays where it is, like any other artifact
+    #[default]
+    Static,
+    /// Goes to a random neighboring cell
+    Wander,
+    /// Steps towards the player along the shortest way, taking another look
+    /// where the player is every `recompute_every` turns
+    Chase { recompute_every: u32 },
+}
+
+/// Distance maps a `Simulation` keeps for chasing dangers
+const CACHED_DISTANCE_MAPS: usize = 16;
+
+/// Rules of a `Simulation`
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize), serde(default))]
+pub struct SimulationOptions {
+    /// Health the player starts with, `None` for a game that can't be lost
+    pub health: Option<i32>,
+    /// How each type of danger moves, `Static` for those without an entry
+    pub behaviors: HashMap<CellType, MonsterBehavior>,
+    /// Chasing dangers wander until they see the player straight down a
+    /// corridor, only then they give chase
+    pub line_of_sight: bool,
+}
+
+impl SimulationOptions {
+    /// Zombies, bats and spiders wander around, ghosts chase the player and
+    /// witches do too, but only check where the player is every third turn.
+    pub fn monsters(mut self) -> Self {
+        for danger in [CellType::Zombie, CellType::Bat, CellType::Spider] {
+            self.behaviors.insert(danger, MonsterBehavior::Wander);
+        }
+        self.behaviors.insert(
+            CellType::Ghost,
+            MonsterBehavior::Chase { recompute_every: 1 },
+        );
+        self.behaviors.insert(
+            CellType::Witch,
+            MonsterBehavior::Chase { recompute_every: 3 },
+        );
+        self
+    }
+}
+
+/// A turn of a recorded `Simulation` and where everyone ended up after it
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct ReplayTurn {
+    pub turn: usize,
+    pub player_move: PlayerMove,
+    pub player: Pos,
+    /// The moving dangers, in the order they move
+    pub monsters: Vec<Pos>,
+    pub outcome: StepOutcome,
+}
+
+/// A run through a maze as recorded by `Simulation::record`, with all it
+/// takes to play it again with `Simulation::replay`
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Replay {
+    /// Share code of the maze as it was before the first turn
+    pub maze: String,
+    pub weights: WeightTable,
+    pub options: SimulationOptions,
+    pub seed: u64,
+    /// Every turn played, blocked moves left out
+    pub turns: Vec<ReplayTurn>,
+}
+
+#[cfg(feature = "serde")]
+impl Replay {
+    pub fn save(&self, filename: &str) -> Result<(), MazeError> {
+        let json = serde_json::to_string_pretty(self).unwrap();
+        write_file(filename, |file| file.write_all(json.as_bytes()))
+    }
+
+    pub fn load(filename: &str) -> Result<Replay, MazeError> {
+        let bytes = std::fs::read(filename).map_err(|source| MazeError::Io {
+            path: Some(filename.to_string()),
+            source,
+        })?;
+        serde_json::from_slice(&bytes).map_err(|err| MazeError::Parse {
+            line: err.line(),
+            reason: err.to_string(),
+        })
+    }
+}
+
+/// Where a `Simulation` stands, e.g. at the end of a replay
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct StepSummary {
+    pub outcome: StepOutcome,
+    pub turns: usize,
+    pub steps: usize,
+    pub score: i32,
+    pub health: Option<i32>,
+}
+
+/// A danger on the move in a `Simulation`
+struct Monster {
+    pos: Pos,
+    behavior: MonsterBehavior,
+    /// Where a chasing danger is heading, the player's position when it
+    /// last looked. `None` until it has seen the player.
+    target: Option<Pos>,
+}
+
+/// A play-through of a maze, shared by the GUI and the terminal game. Each
+/// turn the player moves a cell, then the moving dangers do. Running into a
+/// danger takes its weight off the score and the health, a reward adds its
+/// weight to both; either is gone afterwards. All randomness comes from the
+/// seed, so the same moves on the same maze always play out the same way.
+pub struct Simulation {
+    /// The maze as it is now, without what was collected and with the
+    /// monsters where they went
+    maze: Maze,
+    rng: StdRng,
+    player: Pos,
+    /// Cells walked, going under a bridge counts as two
+    steps: usize,
+    turn: usize,
+    score: i32,
+    health: Option<i32>,
+    line_of_sight: bool,
+    /// The moving dangers, in the order they move
+    monsters: Vec<Monster>,
+    /// Distances to the cells chasing dangers are heading for, which stay
+    /// valid as collecting artifacts doesn't change the passages
+    distances: HashMap<Pos, Vec<Option<usize>>>,
+    outcome: StepOutcome,
+    optimal_steps: Option<usize>,
+    /// What happened so far, see `record`
+    recording: Replay,
+}
+
+impl Simulation {
+    pub fn new(maze: &Maze, options: SimulationOptions, seed: u64) -> Self {
+        // The start in the center room, not one of the `fair_starts`
+        let player = maze
+            .iter_cells()
+            .find(|&(pos, cell)| cell == CellType::Start && maze.in_center_room(pos))
+            .map_or(maze.start_pos(), |(pos, _)| pos);
+        let monsters = maze
+            .artifacts()
+            .filter_map(|(pos, cell)| {
+                let behavior = options.behaviors.get(&cell).copied().unwrap_or_default();
+                (behavior != MonsterBehavior::Static).then_some(Monster {
+                    pos,
+                    behavior,
+                    target: (!options.line_of_sight).then_some(player),
+                })
+            })
+            .collect();
+        // Mazes too big for a share code can be played, but not replayed:
+        // `replay` fails to decode the empty code
+        let recording = Replay {
+            maze: maze.to_share_string().unwrap_or_default(),
+            weights: maze.weight_table().clone(),
+            options: options.clone(),
+            seed,
+            turns: Vec::new(),
+        };
+        Simulation {
+            maze: maze.clone(),
+            rng: StdRng::seed_from_u64(seed),
+            player,
+            steps: 0,
+            turn: 0,
+            score: 0,
+            health: options.health,
+            line_of_sight: options.line_of_sight,
+            monsters,
+            distances: HashMap::new(),
+            outcome: StepOutcome::Continue,
+            optimal_steps: maze
+                .solve(Solver::AStar)
+                .map(|path| path.len().saturating_sub(1)),
+            recording,
+        }
+    }
+
+    /// Plays `replay` again on the maze it was recorded on, checking that
+    /// every turn ends up exactly as recorded. Fails on the first turn that
+    /// doesn't, including moves into walls and turns after the game was
+    /// over, so a run can't be tampered with unnoticed.
+    pub fn replay(replay: &Replay) -> Result<StepSummary, MazeError> {
+        let mut maze = Maze::from_share_string(&replay.maze)?;
+        maze.set_weight_table(replay.weights.clone());
+        let mut simulation = Simulation::new(&maze, replay.options.clone(), replay.seed);
+        for recorded in &replay.turns {
+            let diverged = |reason: String| MazeError::ReplayDiverged {
+                turn: recorded.turn,
+                reason,
+            };
+            if simulation.is_over() {
+                return Err(diverged("the game was already over".to_string()));
+            }
+            if simulation.step(recorded.player_move) == StepOutcome::Blocked {
+                return Err(diverged(format!(
+                    "{:?} runs into a wall at {}",
+                    recorded.player_move, simulation.player
+                )));
+            }
+            let played = simulation.recording.turns.last().unwrap();
+            if played != recorded {
+                return Err(diverged(format!(
+                    "the player ended up at {} with {:?} instead of {} with {:?}",
+                    played.player, played.outcome, recorded.player, recorded.outcome
+                )));
+            }
+        }
+        Ok(simulation.summary())
+    }
+
+    /// The turns played so far, to be saved and replayed
+    pub fn record(&self) -> Replay {
+        self.recording.clone()
+    }
+
+    pub fn summary(&self) -> StepSummary {
+        StepSummary {
+            outcome: self.outcome,
+            turns: self.turn,
+            steps: self.steps,
+            score: self.score,
+            health: self.health,
+        }
+    }
+
+    pub fn maze(&self) -> &Maze {
+        &self.maze
+    }
+
+    pub fn player(&self) -> Pos {
+        self.player
+    }
+
+    pub fn steps(&self) -> usize {
+        self.steps
+    }
+
+    /// Number of turns played, not counting blocked moves
+    pub fn turn(&self) -> usize {
+        self.turn
+    }
+
+    pub fn score(&self) -> i32 {
+        self.score
+    }
+
+    pub fn health(&self) -> Option<i32> {
+        self.health
+    }
+
+    /// Positions of the moving dangers
+    pub fn monsters(&self) -> impl Iterator<Item = Pos> + '_ {
+        self.monsters.iter().map(|monster| monster.pos)
+    }
+
+    /// Rewards and dangers still in the maze, monsters included
+    pub fn remaining_artifacts(&self) -> usize {
+        self.maze.artifacts().count()
+    }
+
+    /// Number of steps on the shortest way out, for comparison
+    pub fn optimal_steps(&self) -> Option<usize> {
+        self.optimal_steps
+    }
+
+    /// `Continue` until the game is won or lost
+    pub fn outcome(&self) -> StepOutcome {
+        self.outcome
+    }
+
+    pub fn is_over(&self) -> bool {
+        matches!(self.outcome, StepOutcome::Win | StepOutcome::Lose)
+    }
+
+    /// Plays a turn: moves the player unless a wall is in the way, collecting
+    /// whatever is there, then the monsters. Under a bridge the player comes
+    /// out on the far side. Once the game is over nothing changes anymore.
+    pub fn step(&mut self, player_move: PlayerMove) -> StepOutcome {
+        if self.is_over() {
+            return self.outcome;
+        }
+        let outcome = self.play_turn(player_move);
+        if outcome != StepOutcome::Blocked {
+            let turn = ReplayTurn {
+                turn: self.turn,
+                player_move,
+                player: self.player,
+                monsters: self.monsters().collect(),
+                outcome,
+            };
+            self.recording.turns.push(turn);
+        }
+        outcome
+    }
+
+    fn play_turn(&mut self, player_move: PlayerMove) -> StepOutcome {
+        if let PlayerMove::Go(direction) = player_move {
+            let Some(next) = self.maze.step(self.player, direction) else {
+                return StepOutcome::Blocked;
+            };
+            self.steps += self.player.manhattan(next);
+            self.player = next;
+            let cell = self.maze.get(next.x, next.y);
+            if REWARDS.contains(&cell) || DANGERS.contains(&cell) {
+                self.hit(cell);
+                self.maze.set(next.x, next.y, CellType::Path);
+                self.monsters.retain(|monster| monster.pos != next);
+            }
+        }
+        self.turn += 1;
+        if self.maze.get(self.player.x, self.player.y) == CellType::Exit {
+            self.outcome = StepOutcome::Win;
+            return self.outcome;
+        }
+        if !self.is_alive() {
+            self.outcome = StepOutcome::Lose;
+            return self.outcome;
+        }
+        self.move_monsters();
+        if !self.is_alive() {
+            self.outcome = StepOutcome::Lose;
+        }
+        self.outcome
+    }
+
+    fn is_alive(&self) -> bool {
+        self.health.is_none_or(|health| health > 0)
+    }
+
+    /// Takes the weight of the artifact the player ran into off the score
+    /// and the health, so rewards with their negative weights add to both
+    fn hit(&mut self, cell: CellType) {
+        let weight = self.maze.weight_table().get(cell);
+        self.score -= weight;
+        if let Some(health) = &mut self.health {
+            *health -= weight;
+        }
+    }
+
+    /// Moves every monster to a neighboring plain path cell, or onto the
+    /// player, who it then hits. Monsters never enter the center room and
+    /// stay where they are if they have nowhere to go.
+    fn move_monsters(&mut self) {
+        let mut index = 0;
+        while index < self.monsters.len() {
+            let from = self.monsters[index].pos;
+            let ways: Vec<Pos> = Direction::ALL
+                .into_iter()
+                .filter_map(|direction| self.maze.step(from, direction))
+                .filter(|&to| {
+                    (to == self.player || self.maze.get(to.x, to.y) == CellType::Path)
+                        && !self.maze.in_center_room(to)
+                })
+                .collect();
+            let to = match self.monsters[index].behavior {
+                MonsterBehavior::Chase { recompute_every } => {
+                    self.chase(index, recompute_every, &ways)
+                }
+                _ => ways.choose(&mut self.rng).copied(),
+            };
+            let Some(to) = to else {
+                index += 1;
+                continue;
+            };
+            let monster = self.maze.get(from.x, from.y);
+            self.maze.set(from.x, from.y, CellType::Path);
+            if to == self.player {
+                self.hit(monster);
+                self.monsters.remove(index);
+                continue;
+            }
+            self.maze.set(to.x, to.y, monster);
+            self.monsters[index].pos = to;
+            index += 1;
+        }
+    }
+
+    /// The cell among `ways` that brings the chasing monster at `index`
+    /// closer to where it thinks the player is, `None` if none does. Until
+    /// it has seen the player it wanders instead.
+    fn chase(&mut self, index: usize, recompute_every: u32, ways: &[Pos]) -> Option<Pos> {
+        let from = self.monsters[index].pos;
+        if self.monsters[index].target.is_none() && self.line_of_sight && !self.in_sight(from) {
+            return ways.choose(&mut self.rng).copied();
+        }
+        let (player, turn) = (self.player, self.turn);
+        let monster = &mut self.monsters[index];
+        if monster
+            .target
+            .is_none_or(|target| target == from || turn % recompute_every.max(1) as usize == 0)
+        {
+            monster.target = Some(player);
+        }
+        let target = monster.target?;
+        let width = self.maze.width;
+        let distances = self.distances_to(target);
+        let here = distances[from.y * width + from.x]?;
+        ways.iter()
+            .filter_map(|&to| Some((distances[to.y * width + to.x]?, to)))
+            .filter(|&(distance, _)| distance < here)
+            .min_by_key(|&(distance, _)| distance)
+            .map(|(_, to)| to)
+    }
+
+    /// Whether the player can be seen from `from` straight down a corridor
+    fn in_sight(&self, from: Pos) -> bool {
+        Direction::ALL.into_iter().any(|direction| {
+            let mut pos = from;
+            while let Some(next) = self.maze.step(pos, direction) {
+                if next == self.player {
+                    return true;
+                }
+                pos = next;
+            }
+            false
+        })
+    }
+
+    /// Number of steps from every cell to `target`, from the cache if
+    /// possible
+    fn distances_to(&mut self, target: Pos) -> &[Option<usize>] {
+        if self.distances.len() >= CACHED_DISTANCE_MAPS && !self.distances.contains_key(&target) {
+            self.distances.clear();
+        }
+        self.distances
+            .entry(target)
+            .or_insert_with(|| self.maze.distance_map(target))
+    }
+}
+
+/// Format version of maze files written by `to_json`
+#[cfg(feature = "serde")]
+const MAZE_FILE_VERSION: u32 = 1;
+
+/// On-disk representation of a maze
+#[cfg(feature = "serde")]
+#[derive(Serialize, Deserialize)]
+struct MazeFile {
+    version: u32,
+    width: usize,
+    height: usize,
+    room_size: usize,
+    exit_type: ExitLocation,
+    /// Missing in files written before corridors could be widened
+    #[serde(default = "default_corridor_width")]
+    corridor_width: usize,
+    cells: Vec<CellType>,
+    /// Only written if there are any, sorted by position
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    labels: Vec<(Pos, char)>,
+    /// Only written if it isn't where `room_size` puts it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    room: Option<Room>,
+    /// Only written if any were set with `Maze::set_weight_table`, sorted
+    /// by cell type
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    weights: Vec<(CellType, i32)>,
+}
+
+#[cfg(feature = "serde")]
+fn default_corridor_width() -> usize {
+    1
+}
+
+/// The cells a maze may use, see `Maze::generate_masked`. Stored row-major
+/// like the cells of a maze.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MazeMask {
+    width: usize,
+    height: usize,
+    cells: Vec<bool>,
+}
+
+impl MazeMask {
+    /// A mask with the cells for which `inside(x, y)` is true.
+    pub fn from_fn(width: usize, height: usize, inside: impl Fn(usize, usize) -> bool) -> Self {
+        let cells = (0..height)
+            .flat_map(|y| (0..width).map(move |x| (x, y)))
+            .map(|(x, y)| inside(x, y))
+            .collect();
+        MazeMask {
+            width,
+            height,
+            cells,
+        }
+    }
+
+    /// A mask from a black and white image, scaled to `width` x `height`.
+    /// Dark pixels are inside, light and transparent ones outside.
+    #[cfg(feature = "image")]
+    pub fn from_image(filename: &str, width: usize, height: usize) -> Result<Self, MazeError> {
+        let image = image::open(filename)
+            .map_err(|err| match err {
+                image::ImageError::IoError(source) => MazeError::Io {
+                    path: Some(filename.to_string()),
+                    source,
+                },
+                err => MazeError::InvalidOption {
+                    name: "mask image",
+                    reason: err.to_string(),
+                },
+            })?
+            .resize_exact(
+                width as u32,
+                height as u32,
+                image::imageops::FilterType::Nearest,
+            )
+            .into_luma_alpha8();
+        Ok(MazeMask::from_fn(width, height, |x, y| {
+            let [luma, alpha] = image.get_pixel(x as u32, y as u32).0;
+            alpha >= 128 && luma < 128
+        }))
+    }
+
+    pub fn get_size(&self) -> (usize, usize) {
+        (self.width, self.height)
+    }
+
+    /// Whether `pos` is inside, positions beyond the mask never are
+    pub fn contains(&self, pos: Pos) -> bool {
+        pos.x < self.width && pos.y < self.height && self.cells[pos.y * self.width + pos.x]
+    }
+}
+
+/// How `Maze::tile` opens the walls shared by neighboring tiles. The count
+/// is per shared wall; walls with fewer places to open get all of them.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ConnectStrategy {
+    /// Openings at random places
+    Random(usize),
+    /// Openings spread evenly along the wall
+    Evenly(usize),
+}
+
+/// Collects the options of a maze and generates it in one go. Options left
+/// unset keep their defaults: 63x31 with a room of 3, the exit on the right,
+/// a random seed and no artifacts.
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize), serde(default))]
+pub struct MazeBuilder {
+    width: usize,
+    height: usize,
+    room_size: usize,
+    exit: ExitLocation,
+    seed: Option<u64>,
+    artifacts: f32,
+    difficulty: Option<f32>,
+    difficulty_tolerance: f32,
+    difficulty_attempts: usize,
+    min_solution_length: Option<usize>,
+    dead_end_rewards: f32,
+    dead_end_dangers: f32,
+    exact: bool,
+    corridor_width: usize,
+    weave: f32,
+    braid: Option<f32>,
+    weights: WeightTable,
+    room: Option<Room>,
+    #[cfg(feature = "parallel")]
+    chunk_size: Option<usize>,
+}
+
+impl Default for MazeBuilder {
+    fn default() -> Self {
+        MazeBuilder {
+            width: 63,
+            height: 31,
+            room_size: 3,
+            exit: ExitLocation::Right,
+            seed: None,
+            artifacts: 0.0,
+            difficulty: None,
+            difficulty_tolerance: DIFFICULTY_TOLERANCE,
+            difficulty_attempts: DIFFICULTY_ATTEMPTS,
+            min_solution_length: None,
+            dead_end_rewards: 0.0,
+            dead_end_dangers: 0.0,
+            exact: false,
+            corridor_width: 1,
+            weave: 0.0,
+            braid: None,
+            weights: WeightTable::default(),
+            room: None,
+            #[cfg(feature = "parallel")]
+            chunk_size: None,
+        }
+    }
+}
+
+impl MazeBuilder {
+    pub fn new() -> Self {
+        MazeBuilder::default()
+    }
+
+    /// Rounded up to the next valid size like in `Maze::new`
+    pub fn width(mut self, width: usize) -> Self {
+        self.width = width;
+        self
+    }
+
+    /// Rounded up to the next valid size like in `Maze::new`
+    pub fn height(mut self, height: usize) -> Self {
+        self.height = height;
+        self
+    }
+
+    pub fn room_size(mut self, room_size: usize) -> Self {
+        self.room_size = room_size;
+        self
+    }
+
+    /// Puts the room somewhere other than the middle, see `Maze::set_room`
+    pub fn room(mut self, room: Room) -> Self {
+        self.room = Some(room);
+        self
+    }
+
+    pub fn exit(mut self, exit: ExitLocation) -> Self {
+        self.exit = exit;
+        self
+    }
+
+    /// Makes `build` reproducible. Without a seed a random one is used.
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.seed = Some(seed);
+        self
+    }
+
+    /// Share of the path cells outside the room that get a reward or danger,
+    /// between 0 and 1
+    pub fn artifacts(mut self, ratio: f32) -> Self {
+        self.artifacts = ratio;
+        self
+    }
+
+    /// Regenerates until the difficulty (0-100) is close to `target`, see
+    /// `difficulty_tolerance`. Keeps the closest maze if none is close
+    /// enough.
+    pub fn difficulty(mut self, target: f32) -> Self {
+        self.difficulty = Some(target);
+        self
+    }
+
+    /// How far the difficulty may be off the target of `difficulty`, and how
+    /// many mazes are generated at most trying to get that close.
+    /// `DIFFICULTY_TOLERANCE` and `DIFFICULTY_ATTEMPTS` by default.
+    pub fn difficulty_tolerance(mut self, tolerance: f32, max_attempts: usize) -> Self {
+        self.difficulty_tolerance = tolerance;
+        self.difficulty_attempts = max_attempts;
+        self
+    }
+
+    /// Makes sure the shortest path has at least `length` cells, so the exit
+    /// isn't right next to the room. A random exit is moved to another side
+    /// if that is far enough, otherwise the maze is regenerated. Building
+    /// fails after `MIN_LENGTH_ATTEMPTS` mazes that are all too short.
+    pub fn min_solution_length(mut self, length: usize) -> Self {
+        self.min_solution_length = Some(length);
+        self
+    }
+
+    /// Chances of a reward or a danger on every dead end, on top of the
+    /// artifacts spread by `artifacts`, see `Maze::decorate_dead_ends`.
+    /// Together they must not exceed 1.
+    pub fn dead_end_artifacts(mut self, reward_prob: f32, danger_prob: f32) -> Self {
+        self.dead_end_rewards = reward_prob;
+        self.dead_end_dangers = danger_prob;
+        self
+    }
+
+    /// Fails to build instead of rounding up a width or height that isn't a
+    /// valid size, for mazes that have to fit a fixed board.
+    pub fn exact(mut self, exact: bool) -> Self {
+        self.exact = exact;
+        self
+    }
+
+    /// Cells across a corridor, 1 by default. The walls stay a single cell
+    /// thick. The size is rounded up to the next one that fits whole
+    /// corridors, the room size counts corridors and walls.
+    pub fn corridor_width(mut self, corridor_width: usize) -> Self {
+        self.corridor_width = corridor_width;
+        self
+    }
+
+    /// Chance, between 0 and 1, that a corridor running into a dead end
+    /// tunnels under a neighboring corridor instead, making a bridge. 0 by
+    /// default, only possible with corridors one cell wide.
+    pub fn weave(mut self, chance: f32) -> Self {
+        self.weave = chance;
+        self
+    }
+
+    /// Share of the dead ends, between 0 and 1, for which a random wall is
+    /// removed after carving to make loops. 0 gives a perfect maze. Unset,
+    /// a wall is removed for every eight cells of width and height.
+    pub fn braid(mut self, braid: f32) -> Self {
+        self.braid = Some(braid);
+        self
+    }
+
+    /// Weights used for the difficulty target and kept by the built maze,
+    /// see `Maze::set_weight_table`
+    pub fn weights(mut self, weights: WeightTable) -> Self {
+        self.weights = weights;
+        self
+    }
+
+    /// Generates the maze in chunks of about `chunk_size` cells square on
+    /// several threads, see `Maze::generate_parallel_with_rng`.
+    #[cfg(feature = "parallel")]
+    pub fn chunk_size(mut self, chunk_size: usize) -> Self {
+        self.chunk_size = Some(chunk_size);
+        self
+    }
+
+    /// Checks the options and returns the all-wall maze `build` starts from.
+    /// Useful to generate it step by step, e.g. with `generate_observed`.
+    pub fn blank(&self) -> Result<Maze, MazeError> {
+        if !(0.0..=1.0).contains(&self.artifacts) {
+            return Err(MazeError::InvalidOption {
+                name: "artifacts ratio",
+                reason: format!("{} is not between 0 and 1", self.artifacts),
+            });
+        }
+        for (name, prob) in [
+            ("dead end rewards", self.dead_end_rewards),
+            ("dead end dangers", self.dead_end_dangers),
+        ] {
+            if !(0.0..=1.0).contains(&prob) {
+                return Err(MazeError::InvalidOption {
+                    name,
+                    reason: format!("{} is not between 0 and 1", prob),
+                });
+            }
+        }
+        if self.dead_end_rewards + self.dead_end_dangers > 1.0 {
+            return Err(MazeError::InvalidOption {
+                name: "dead end dangers",
+                reason: "the chances of rewards and dangers add up to more than 1".to_string(),
+            });
+        }
+        if let Some(target) = self.difficulty
+            && !(0.0..=100.0).contains(&target)
+        {
+            return Err(MazeError::InvalidOption {
+                name: "difficulty",
+                reason: format!("{} is not between 0 and 100", target),
+            });
+        }
+        if !(0.0..=100.0).contains(&self.difficulty_tolerance) {
+            return Err(MazeError::InvalidOption {
+                name: "difficulty tolerance",
+                reason: format!("{} is not between 0 and 100", self.difficulty_tolerance),
+            });
+        }
+        if self.difficulty_attempts == 0 {
+            return Err(MazeError::InvalidOption {
+                name: "difficulty attempts",
+                reason: "at least one maze has to be generated".to_string(),
+            });
+        }
+        // The width is stored in a single byte in share codes
+        if !(1..=u8::MAX as usize).contains(&self.corridor_width) {
+            return Err(MazeError::InvalidOption {
+                name: "corridor width",
+                reason: format!("{} is not between 1 and {}", self.corridor_width, u8::MAX),
+            });
+        }
+        if !(0.0..=1.0).contains(&self.weave) {
+            return Err(MazeError::InvalidOption {
+                name: "weave",
+                reason: format!("{} is not between 0 and 1", self.weave),
+            });
+        }
+        if let Some(braid) = self.braid
+            && !(0.0..=1.0).contains(&braid)
+        {
+            return Err(MazeError::InvalidOption {
+                name: "braid",
+                reason: format!("{} is not between 0 and 1", braid),
+            });
+        }
+        if self.weave > 0.0 && self.corridor_width > 1 {
+            return Err(MazeError::InvalidOption {
+                name: "weave",
+                reason: "bridges need corridors one cell wide".to_string(),
+            });
+        }
+        if matches!(self.exit, ExitLocation::Custom(_)) && self.corridor_width > 1 {
+            return Err(MazeError::InvalidOption {
+                name: "exit",
+                reason: "only corridors one cell wide can have the exit moved".to_string(),
+            });
+        }
+        let mut maze = Maze::with_corridors(
+            self.width,
+            self.height,
+            self.room_size,
+            self.exit.clone(),
+            self.corridor_width,
+        );
+        if self.exact && maze.get_size() != (self.width, self.height) {
+            return Err(MazeError::InvalidDimensions {
+                width: self.width,
+                height: self.height,
+                reason: format!("the closest valid size is {}x{}", maze.width, maze.height),
+            });
+        }
+        maze.weave = self.weave;
+        maze.braid = self.braid;
+        maze.weights = self.weights.clone();
+        match self.room {
+            Some(room) => maze.set_room(room)?,
+            None => maze.check_room_size()?,
+        }
+        if let ExitLocation::Custom(pos) = self.exit
+            && maze.exit_sides(None).is_empty()
+        {
+            return Err(MazeError::InvalidOption {
+                name: "exit",
+                reason: format!("no corridor can lead to the exit at {}", pos),
+            });
+        }
+        Ok(maze)
+    }
+
+    /// Generates the maze and places the artifacts, seeded if a seed is set.
+    pub fn build(&self) -> Result<Maze, MazeError> {
+        let seed = self.seed.unwrap_or_else(|| rand::rng().random());
+        self.build_with_rng(&mut StdRng::seed_from_u64(seed))
+    }
+
+    /// Same as `build`, but draws all randomness from `rng` and ignores the
+    /// seed.
+    pub fn build_with_rng<R: Rng + ?Sized>(&self, rng: &mut R) -> Result<Maze, MazeError> {
+        let mut maze = match self.min_solution_length {
+            Some(min_length) => self.build_long_enough(min_length, rng)?,
+            None => self.build_once(rng)?,
+        };
+        // Only once the exit has stopped moving, which would change the dead
+        // ends in front of it
+        if self.dead_end_rewards > 0.0 || self.dead_end_dangers > 0.0 {
+            maze.decorate_dead_ends(self.dead_end_rewards, self.dead_end_dangers, rng);
+        }
+        Ok(maze)
+    }
+
+    fn build_long_enough<R: Rng + ?Sized>(
+        &self,
+        min_length: usize,
+        rng: &mut R,
+    ) -> Result<Maze, MazeError> {
+        for _ in 0..MIN_LENGTH_ATTEMPTS {
+            let mut maze = self.build_once(rng)?;
+            if maze.lengthen_solution(min_length) {
+                return Ok(maze);
+            }
+        }
+        Err(MazeError::InvalidOption {
+            name: "minimum solution length",
+            reason: format!(
+                "no maze with a path of {} cells in {} attempts",
+                min_length, MIN_LENGTH_ATTEMPTS
+            ),
+        })
+    }
+
+    fn build_once<R: Rng + ?Sized>(&self, rng: &mut R) -> Result<Maze, MazeError> {
+        let mut maze = self.blank()?;
+        #[cfg(feature = "parallel")]
+        if let Some(chunk_size) = self.chunk_size {
+            if self.difficulty.is_some() {
+                return Err(MazeError::InvalidOption {
+                    name: "chunk size",
+                    reason: "chunks can't be combined with a target difficulty".to_string(),
+                });
+            }
+            maze.generate_parallel_with_rng(chunk_size, rng)?;
+            maze.place_artifacts_with_rng(self.artifacts, rng);
+            return Ok(maze);
+        }
+        match self.difficulty {
+            Some(target) => {
+                maze.generate_with_difficulty_with_rng(
+                    target,
+                    self.difficulty_tolerance,
+                    self.difficulty_attempts,
+                    self.artifacts,
+                    rng,
+                );
+            }
+            None => {
+                maze.generate_with_rng(rng);
+                maze.place_artifacts_with_rng(self.artifacts, rng);
+            }
+        }
+        Ok(maze)
+    }
+}
+
+/// The presets that come with the library, see `MazePreset::by_name`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum Preset {
+    /// Small and bright, with rewards in many dead ends and no dangers
+    KidsEasy,
+    /// Dark and orange, with dangers lurking in the dead ends
+    HalloweenParty,
+    /// Large and black on white without artifacts, for printing
+    PrintLarge,
+    /// Large, bridged and full of dangers, with the exit as far as it gets
+    Brutal,
+}
+
+impl Preset {
+    pub const ALL: [Preset; 4] = [
+        Preset::KidsEasy,
+        Preset::HalloweenParty,
+        Preset::PrintLarge,
+        Preset::Brutal,
+    ];
+
+    /// Name for `MazePreset::by_name`, the same as on the command line
+    pub fn name(self) -> &'static str {
+        match self {
+            Preset::KidsEasy => "kids-easy",
+            Preset::HalloweenParty => "halloween-party",
+            Preset::PrintLarge => "print-large",
+            Preset::Brutal => "brutal",
+        }
+    }
+
+    pub fn preset(self) -> MazePreset {
+        match self {
+            Preset::KidsEasy => MazePreset {
+                width: 23,
+                height: 15,
+                artifacts: 0.1,
+                dead_end_rewards: 0.5,
+                theme: Theme {
+                    wall_color: [0x33, 0x66, 0xcc],
+                    path_color: [0xff, 0xfa, 0xe6],
+                    reward_color: [0xff, 0xc4, 0x00],
+                    danger_color: [0xff, 0x66, 0x99],
+                    solution_color: [0x22, 0xaa, 0x44],
+                },
+                ..MazePreset::default()
+            },
+            Preset::HalloweenParty => MazePreset {
+                width: 47,
+                height: 31,
+                exit: ExitLocation::Random,
+                artifacts: 0.15,
+                dead_end_rewards: 0.2,
+                dead_end_dangers: 0.4,
+                theme: Theme {
+                    wall_color: [0x1a, 0x0f, 0x24],
+                    path_color: [0xff, 0x8c, 0x1a],
+                    reward_color: [0xf5, 0xe0, 0x42],
+                    danger_color: [0x7a, 0x1f, 0xa2],
+                    solution_color: [0x8b, 0xc3, 0x4a],
+                },
+                ..MazePreset::default()
+            },
+            Preset::PrintLarge => MazePreset {
+                width: 119,
+                height: 79,
+                exit: ExitLocation::Farthest,
+                theme: Theme {
+                    wall_color: [0x00, 0x00, 0x00],
+                    path_color: [0xff, 0xff, 0xff],
+                    reward_color: [0x88, 0x88, 0x88],
+                    danger_color: [0x44, 0x44, 0x44],
+                    solution_color: [0x99, 0x99, 0x99],
+                },
+                ..MazePreset::default()
+            },
+            Preset::Brutal => MazePreset {
+                width: 151,
+                height: 91,
+                room_size: 1,
+                exit: ExitLocation::Farthest,
+                weave: 0.3,
+                artifacts: 0.2,
+                dead_end_dangers: 0.6,
+                ..MazePreset::default()
+            },
+        }
+    }
+}
+
+/// Options of a maze bundled with the colors to render it in, so a kind of
+/// maze can be picked by name. Fields left out when deserializing keep the
+/// defaults of `MazeBuilder` and `Theme`.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize), serde(default))]
+pub struct MazePreset {
+    pub width: usize,
+    pub height: usize,
+    pub room_size: usize,
+    pub exit: ExitLocation,
+    pub corridor_width: usize,
+    /// See `MazeBuilder::weave`
+    pub weave: f32,
+    /// See `MazeBuilder::artifacts`
+    pub artifacts: f32,
+    /// See `MazeBuilder::dead_end_artifacts`
+    pub dead_end_rewards: f32,
+    pub dead_end_dangers: f32,
+    /// See `MazeBuilder::difficulty`
+    pub difficulty: Option<f32>,
+    pub theme: Theme,
+}
+
+impl Default for MazePreset {
+    fn default() -> Self {
+        MazePreset {
+            width: 63,
+            height: 31,
+            room_size: 3,
+            exit: ExitLocation::Right,
+            corridor_width: 1,
+            weave: 0.0,
+            artifacts: 0.0,
+            dead_end_rewards: 0.0,
+            dead_end_dangers: 0.0,
+            difficulty: None,
+            theme: Theme::default(),
+        }
+    }
+}
+
+impl MazePreset {
+    /// The built-in preset called `name`, e.g. "kids-easy", see `Preset::name`
+    pub fn by_name(name: &str) -> Option<MazePreset> {
+        Preset::ALL
+            .into_iter()
+            .find(|preset| preset.name() == name)
+            .map(Preset::preset)
+    }
+
+    /// A builder with the options of the preset, to be adjusted further
+    /// before building
+    pub fn builder(&self) -> MazeBuilder {
+        let builder = MazeBuilder::new()
+            .width(self.width)
+            .height(self.height)
+            .room_size(self.room_size)
+            .exit(self.exit.clone())
+            .corridor_width(self.corridor_width)
+            .weave(self.weave)
+            .artifacts(self.artifacts)
+            .dead_end_artifacts(self.dead_end_rewards, self.dead_end_dangers);
+        match self.difficulty {
+            Some(difficulty) => builder.difficulty(difficulty),
+            None => builder,
+        }
+    }
+}
+
+/// A cell changed by an edit, see `MazeJournal`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct CellChange {
+    pub pos: Pos,
+    pub before: CellType,
+    pub after: CellType,
+}
+
+/// Undo and redo history of the edits to a maze. Edits made through `edit`
+/// are recorded with the cell they changed; undoing puts the cell back as it
+/// was. Only the last `depth` edits are kept, and a new edit drops whatever
+/// could be redone.
+#[derive(Clone, Debug)]
+pub struct MazeJournal {
+    undo: std::collections::VecDeque<CellChange>,
+    redo: Vec<CellChange>,
+    depth: usize,
+}
+
+impl Default for MazeJournal {
+    fn default() -> Self {
+        MazeJournal::new(100)
+    }
+}
+
+impl MazeJournal {
+    pub fn new(depth: usize) -> Self {
+        MazeJournal {
+            undo: std::collections::VecDeque::new(),
+            redo: Vec::new(),
+            depth,
+        }
+    }
+
+    /// Keeps at most `depth` edits to undo, forgetting the oldest ones
+    pub fn set_depth(&mut self, depth: usize) {
+        self.depth = depth;
+        while self.undo.len() > depth {
+            self.undo.pop_front();
+        }
+    }
+
+    /// Applies `edit` to `maze`, e.g. `|maze| maze.carve(pos)`, and records
+    /// the change of the cell at `pos` if it succeeds and changes anything.
+    pub fn edit<F>(&mut self, maze: &mut Maze, pos: Pos, edit: F) -> Result<(), MazeError>
+    where
+        F: FnOnce(&mut Maze) -> Result<(), MazeError>,
+    {
+        maze.check_bounds(pos)?;
+        let before = maze.get(pos.x, pos.y);
+        edit(maze)?;
+        self.record(CellChange {
+            pos,
+            before,
+            after: maze.get(pos.x, pos.y),
+        });
+        Ok(())
+    }
+
+    /// Records a change made to the maze some other way. Changes that leave
+    /// the cell as it was are ignored.
+    pub fn record(&mut self, change: CellChange) {
+        if change.before == change.after || self.depth == 0 {
+            return;
+        }
+        self.redo.clear();
+        if self.undo.len() == self.depth {
+            self.undo.pop_front();
+        }
+        self.undo.push_back(change);
+    }
+
+    /// Reverts the last edit and returns it, or `None` if there is nothing
+    /// to undo.
+    pub fn undo(&mut self, maze: &mut Maze) -> Option<CellChange> {
+        let change = self.undo.pop_back()?;
+        maze.set(change.pos.x, change.pos.y, change.before);
+        self.redo.push(change);
+        Some(change)
+    }
+
+    /// Makes the last undone edit again and returns it, or `None` if there
+    /// is nothing to redo.
+    pub fn redo(&mut self, maze: &mut Maze) -> Option<CellChange> {
+        let change = self.redo.pop()?;
+        maze.set(change.pos.x, change.pos.y, change.after);
+        self.undo.push_back(change);
+        Some(change)
+    }
+
+    pub fn can_undo(&self) -> bool {
+        !self.undo.is_empty()
+    }
+
+    pub fn can_redo(&self) -> bool {
+        !self.redo.is_empty()
+    }
+
+    /// Forgets all edits, e.g. once the maze is replaced by another
+    pub fn clear(&mut self) {
+        self.undo.clear();
+        self.redo.clear();
+    }
+}
+
+#[derive(Clone)]
+pub struct Maze {
+    width: usize,
+    height: usize,
+    room_size: usize,
+    /// Where the generator carves the room, see `room`
+    room: Room,
+    exit_type: ExitLocation,
+    /// Cells across a corridor, walls are always a single cell thick
+    corridor_width: usize,
+    /// Chance of tunneling under a corridor when generation runs into a
+    /// dead end, see `MazeBuilder::weave`
+    weave: f32,
+    /// Share of the dead ends opened up into loops, see `MazeBuilder::braid`
+    braid: Option<f32>,
+    cells: Vec<CellType>,
+    /// Characters shown on top of cells, see `place_labels`
+    labels: HashMap<Pos, char>,
+    weights: WeightTable,
+    /// `shortest_path` once it was asked for, until a cell changes
+    solution: OnceLock<Option<Vec<Pos>>>,
+}
+
+/// Mazes are equal if they have the same size and cells, however they were
+/// generated.
+impl PartialEq for Maze {
+    fn eq(&self, other: &Self) -> bool {
+        self.width == other.width && self.height == other.height && self.cells == other.cells
+    }
+}
+
+impl Eq for Maze {}
+
+/// A cell that differs between two mazes, see `Maze::diff`
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct CellDiff {
+    pub pos: Pos,
+    pub left: CellType,
+    pub right: CellType,
+}
+
+impl Display for CellDiff {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {} -> {}", self.pos, self.left, self.right)
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Edge {
+    start_id: usize,
+    end_id: usize,
+    weight: i32,
+}
+
+impl Edge {
+    pub fn start_id(&self) -> usize {
+        self.start_id
+    }
+
+    pub fn end_id(&self) -> usize {
+        self.end_id
+    }
+
+    pub fn weight(&self) -> i32 {
+        self.weight
+    }
+}
+
+type Edges = HashSet<Edge>;
+type Nodes = HashMap<Pos, usize>; // (position, node_id)
+type ModelBox = ([f32; 3], [f32; 3]); // (corner, size) in millimeters
+
+/// A minimum spanning tree of the graph from `Maze::build_graph`, as found
+/// by `Maze::mst_prim` or `Maze::mst_kruskal`. When edges weigh the same
+/// the two may pick different trees of the same total weight.
+#[derive(Clone)]
+pub struct MstResult {
+    pub nodes: Nodes,
+    pub edges: Edges,
+    pub total_weight: i32,
+}
+
+impl Display for MstResult {
+    /// The total weight, then the edges ordered by where they start and end
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "Minimum Spanning Tree weight: {}", self.total_weight)?;
+        let positions: HashMap<usize, Pos> =
+            self.nodes.iter().map(|(&pos, &id)| (id, pos)).collect();
+        let mut sorted_edges: Vec<&Edge> = self.edges.iter().collect();
+        sorted_edges.sort_by_key(|edge| (positions[&edge.start_id], positions[&edge.end_id]));
+        for edge in sorted_edges {
+            writeln!(
+                f,
+                "Edge from {} to {} with weight {}",
+                edge.start_id, edge.end_id, edge.weight
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// The root of `node` in a union-find forest, halving the way there
+fn find_root(parents: &mut [usize], mut node: usize) -> usize {
+    while parents[node] != node {
+        parents[node] = parents[parents[node]];
+        node = parents[node];
+    }
+    node
+}
+
+/// The passages of a maze as junctions and the corridors between them, so
+/// searches don't have to go cell by cell. Junctions are the start, the
+/// exit and every cell with other than two ways to go on.
+struct JunctionGraph {
+    junctions: Vec<Pos>,
+    index: HashMap<Pos, usize>,
+    corridors: Vec<Corridor>,
+    /// The corridors at each junction
+    adjacent: Vec<Vec<usize>>,
+}
+
+/// The `step`s from one junction to another, both ends included
+struct Corridor {
+    ends: (usize, usize),
+    cells: Vec<Pos>,
+}
+
+impl Corridor {
+    fn steps(&self) -> usize {
+        self.cells.len() - 1
+    }
+
+    fn other_end(&self, junction: usize) -> usize {
+        if self.ends.0 == junction {
+            self.ends.1
+        } else {
+            self.ends.0
+        }
+    }
+}
+
+impl JunctionGraph {
+    /// The cheapest corridors from `from` to `to` by number of steps,
+    /// with Dijkstra's algorithm, never entering `avoid_junctions` or taking
+    /// `avoid_corridors`.
+    fn shortest_route(
+        &self,
+        from: usize,
+        to: usize,
+        avoid_junctions: &HashSet<usize>,
+        avoid_corridors: &HashSet<usize>,
+    ) -> Option<(usize, Vec<usize>)> {
+        let mut costs = vec![usize::MAX; self.junctions.len()];
+        let mut taken: Vec<Option<usize>> = vec![None; self.junctions.len()];
+        let mut heap = BinaryHeap::from([Reverse((0, from))]);
+        costs[from] = 0;
+
+        while let Some(Reverse((cost, junction))) = heap.pop() {
+            if junction == to {
+                let mut route = Vec::new();
+                let mut at = to;
+                while let Some(corridor) = taken[at] {
+                    route.push(corridor);
+                    at = self.corridors[corridor].other_end(at);
+                }
+                route.reverse();
+                return Some((cost, route));
+            }
+            if cost > costs[junction] {
+                continue;
+            }
+            for &corridor in &self.adjacent[junction] {
+                let next = self.corridors[corridor].other_end(junction);
+                let next_cost = cost + self.corridors[corridor].steps();
+                if !avoid_corridors.contains(&corridor)
+                    && !avoid_junctions.contains(&next)
+                    && next_cost < costs[next]
+                {
+                    costs[next] = next_cost;
+                    taken[next] = Some(corridor);
+                    heap.push(Reverse((next_cost, next)));
+                }
+            }
+        }
+        None
+    }
+
+    /// The junctions and corridors whose removal would split the passages
+    /// in two, found in one depth-first search tracking how far up each
+    /// subtree reaches (Tarjan's low-link values). Returns whether each
+    /// junction is such an articulation point and the bridge corridors.
+    fn cut_points(&self) -> (Vec<bool>, Vec<usize>) {
+        let count = self.junctions.len();
+        let mut discovered = vec![usize::MAX; count];
+        let mut low = vec![0; count];
+        let mut articulation = vec![false; count];
+        let mut bridges = Vec::new();
+        let mut time = 0;
+
+        for root in 0..count {
+            if discovered[root] != usize::MAX {
+                continue;
+            }
+            discovered[root] = time;
+            low[root] = time;
+            time += 1;
+            let mut root_children = 0;
+            // Junction, corridor it was reached by and next corridor to try;
+            // a stack instead of recursion, as corridors can be very many
+            let mut stack: Vec<(usize, Option<usize>, usize)> = vec![(root, None, 0)];
+            while let Some(&(junction, parent, tried)) = stack.last() {
+                if let Some(&corridor) = self.adjacent[junction].get(tried) {
+                    stack.last_mut().unwrap().2 += 1;
+                    if Some(corridor) == parent {
+                        continue;
+                    }
+                    let next = self.corridors[corridor].other_end(junction);
+                    if discovered[next] == usize::MAX {
+                        discovered[next] = time;
+                        low[next] = time;
+                        time += 1;
+                        if junction == root {
+                            root_children += 1;
+                        }
+                        stack.push((next, Some(corridor), 0));
+                    } else {
+                        low[junction] = low[junction].min(discovered[next]);
+                    }
+                    continue;
+                }
+                stack.pop();
+                if let (Some(&(up, _, _)), Some(corridor)) = (stack.last(), parent) {
+                    low[up] = low[up].min(low[junction]);
+                    if low[junction] > discovered[up] {
+                        bridges.push(corridor);
+                    }
+                    if up != root && low[junction] >= discovered[up] {
+                        articulation[up] = true;
+                    }
+                }
+            }
+            articulation[root] = root_children > 1;
+        }
+        (articulation, bridges)
+    }
+
+    /// The junctions passed along `route` from `from`, both ends included
+    fn stops(&self, from: usize, route: &[usize]) -> Vec<usize> {
+        let mut stops = vec![from];
+        for &corridor in route {
+            stops.push(self.corridors[corridor].other_end(*stops.last().unwrap()));
+        }
+        stops
+    }
+
+    /// The cells along `route` from `from`, as `step`s
+    fn cells(&self, from: usize, route: &[usize]) -> Vec<Pos> {
+        let mut cells = vec![self.junctions[from]];
+        let mut at = from;
+        for &corridor in route {
+            let corridor = &self.corridors[corridor];
+            if corridor.ends.0 == at {
+                cells.extend(&corridor.cells[1..]);
+            } else {
+                cells.extend(corridor.cells[..corridor.steps()].iter().rev());
+            }
+            at = corridor.other_end(at);
+        }
+        cells
+    }
+}
+
+macro_rules! constrain_dimension {
+    ($dim:expr) => {
+        if $dim < 7 {
+            7
+        } else {
+            let remainder = ($dim - 7) % 4;
+            if remainder == 0 {
+                $dim
+            } else {
+                $dim + (4 - remainder)
+            }
+        }
+    };
+}
+
+/// Rounds `dim` up to the size of a maze following the rule of
+/// `constrain_dimension!` once its corridors are widened to
+/// `corridor_width` cells. Such a maze is `1 + corridors * (corridor_width +
+/// 1)` cells across, with an odd number of at least 3 corridors.
+fn scaled_dimension(dim: usize, corridor_width: usize) -> usize {
+    let corridors = dim.saturating_sub(1).div_ceil(corridor_width + 1).max(3) | 1;
+    1 + corridors * (corridor_width + 1)
+}
+
+impl Maze {
+    /// Width and height are rounded up with `constrain_dimension`.
+    pub fn new(width: usize, height: usize, room_size: usize, exit_type: ExitLocation) -> Self {
+        Maze::with_corridors(width, height, room_size, exit_type, 1)
+    }
+
+    /// The width or height `new` makes of `dim`. Mazes have a wall around
+    /// them, an odd number of corridors and a center cell, which makes the
+    /// sizes 7, 11, 15, and so on. Anything in between is rounded up.
+    pub fn constrain_dimension(dim: usize) -> usize {
+        constrain_dimension!(dim)
+    }
+
+    /// Like `new`, with corridors `corridor_width` cells wide. The size is
+    /// rounded up with `scaled_dimension` and the room size counts corridors
+    /// and walls like in a maze with narrow corridors.
+    fn with_corridors(
+        width: usize,
+        height: usize,
+        room_size: usize,
+        exit_type: ExitLocation,
+        corridor_width: usize,
+    ) -> Self {
+        let requested = (width, height);
+        let width = scaled_dimension(width, corridor_width);
+        let height = scaled_dimension(height, corridor_width);
+        if (width, height) != requested {
+            log::debug!(
+                "Adjusted the size from {}x{} to {}x{}",
+                requested.0,
+                requested.1,
+                width,
+                height
+            );
+        }
+        let mut maze = Maze {
+            width,
+            height,
+            room_size,
+            room: Room::centered(width, height, room_size),
+            exit_type,
+            corridor_width,
+            weave: 0.0,
+            braid: None,
+            cells: vec![CellType::Wall; width * height],
+            labels: HashMap::new(),
+            weights: WeightTable::default(),
+            solution: OnceLock::new(),
+        };
+        maze.room = maze.centered_room();
+        maze
+    }
+
+    pub fn get_size(&self) -> (usize, usize) {
+        (self.width, self.height)
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    pub fn room_size(&self) -> usize {
+        self.room_size
+    }
+
+    /// The room around the start. Unless placed with `set_room` it is
+    /// `room_size` cells square in the middle, counting cells like a maze
+    /// with narrow corridors.
+    pub fn room(&self) -> Room {
+        self.room
+    }
+
+    /// Puts the room at `room` instead, for the generator to carve. It has
+    /// to leave the border around it free and hold the start, and only
+    /// mazes with corridors one cell wide can have it moved. Share codes
+    /// only keep the room size, so they bring it back to the middle.
+    pub fn set_room(&mut self, room: Room) -> Result<(), MazeError> {
+        if self.corridor_width > 1 && room != self.centered_room() {
+            return Err(MazeError::InvalidOption {
+                name: "room",
+                reason: "only corridors one cell wide can have the room moved".to_string(),
+            });
+        }
+        let previous = (self.room, self.room_size);
+        self.room = room;
+        self.room_size = room.width.max(room.height);
+        if let Err(err) = self.check_room_size() {
+            (self.room, self.room_size) = previous;
+            return Err(err);
+        }
+        self.solution.take();
+        Ok(())
+    }
+
+    /// The room of `room_size` in the middle, widened with the corridors
+    fn centered_room(&self) -> Room {
+        let (narrow_width, narrow_height) = self.narrow_size();
+        let narrow = Room::centered(narrow_width, narrow_height, self.room_size);
+        let top_left = self.block(narrow.top_left).next().unwrap();
+        let bottom_right = self.block(narrow.bottom_right()).last().unwrap();
+        Room {
+            top_left,
+            width: bottom_right.x + 1 - top_left.x,
+            height: bottom_right.y + 1 - top_left.y,
+        }
+    }
+
+    /// The side the exit was asked for. Transformations keep it up to date,
+    /// so it may differ from what the maze was created with.
+    pub fn exit_location(&self) -> &ExitLocation {
+        &self.exit_type
+    }
+
+    /// Like `exit_location`, but with a custom exit replaced by the side of
+    /// the border it is on, for where only the side can be kept
+    pub fn exit_side(&self) -> ExitLocation {
+        match &self.exit_type {
+            ExitLocation::Custom(pos) => self
+                .border_side(*pos)
+                .map_or(ExitLocation::Random, |(side, _)| side),
+            exit_type => exit_type.clone(),
+        }
+    }
+
+    /// All cells of the maze, with the top left one at the origin
+    pub fn bounds(&self) -> Rect {
+        Rect::new(0, 0, self.width, self.height)
+    }
+
+    pub fn weight_table(&self) -> &WeightTable {
+        &self.weights
+    }
+
+    /// Replaces the weights used by `path_cost`, `least_cost_path`,
+    /// `build_graph`, `difficulty` and the score of a game. They are saved
+    /// with the maze by `to_json`, but not kept in share codes.
+    pub fn set_weight_table(&mut self, weights: WeightTable) {
+        self.weights = weights;
+        self.solution.take();
+    }
+
+    /// Returns the cell at `(x, y)`. Panics if it is outside the maze, use
+    /// `try_get` when that isn't known.
+    pub fn get(&self, x: usize, y: usize) -> CellType {
+        self.cells[y * self.width + x]
+    }
+
+    pub fn set(&mut self, x: usize, y: usize, value: CellType) {
+        self.cells[y * self.width + x] = value;
+        self.solution.take();
+    }
+
+    /// All cells for changing them in place, forgetting the cached solution
+    fn cells_mut(&mut self) -> &mut [CellType] {
+        self.solution.take();
+        &mut self.cells
+    }
+
+    /// Returns the cell at `(x, y)`, or `None` if it is outside the maze.
+    pub fn try_get(&self, x: usize, y: usize) -> Option<CellType> {
+        if x < self.width && y < self.height {
+            Some(self.get(x, y))
+        } else {
+            None
+        }
+    }
+
+    /// Sets the cell at `(x, y)` without any of the checks `carve`, `fill`
+    /// and `place` do, failing only if it is outside the maze.
+    pub fn try_set(&mut self, x: usize, y: usize, value: CellType) -> Result<(), MazeError> {
+        self.check_bounds(Pos { x, y })?;
+        self.set(x, y, value);
+        Ok(())
+    }
+
+    /// All cells in row-major order: the cell at `(x, y)` is at index
+    /// `y * width + x`.
+    pub fn cells(&self) -> &[CellType] {
+        &self.cells
+    }
+
+    /// The rows of the maze from top to bottom, each `width` cells long.
+    pub fn rows(&self) -> impl Iterator<Item = &[CellType]> {
+        self.cells.chunks(self.width)
+    }
+
+    /// All cells with their positions in row-major order.
+    pub fn iter_cells(&self) -> impl Iterator<Item = (Pos, CellType)> + '_ {
+        self.cells.iter().enumerate().map(|(index, &cell)| {
+            (
+                Pos {
+                    x: index % self.width,
+                    y: index / self.width,
+                },
+                cell,
+            )
+        })
+    }
+
+    /// The cells of `rect` that lie inside the maze, in row-major order.
+    pub fn cells_in(&self, rect: Rect) -> impl Iterator<Item = (Pos, CellType)> + '_ {
+        rect.clamp_to(self.bounds())
+            .positions()
+            .map(|pos| (pos, self.get(pos.x, pos.y)))
+    }
+
+    /// The rewards and dangers in the maze with their positions.
+    pub fn artifacts(&self) -> impl Iterator<Item = (Pos, CellType)> + '_ {
+        self.iter_cells()
+            .filter(|(_, cell)| REWARDS.contains(cell) || DANGERS.contains(cell))
+    }
+
+    /// Open cells with exactly one open neighbor, following bridges.
+    pub fn dead_ends(&self) -> Vec<Pos> {
+        self.iter_cells()
+            .filter(|(_, cell)| TRAVERSABLE.contains(cell))
+            .map(|(pos, _)| pos)
+            .filter(|&pos| {
+                Direction::ALL
+                    .into_iter()
+                    .filter(|&direction| self.step(pos, direction).is_some())
+                    .count()
+                    == 1
+            })
+            .collect()
+    }
+
+    /// Rotates the maze a quarter turn clockwise. Width and height swap.
+    pub fn rotated_90(&self) -> Maze {
+        let (width, height) = (self.height, self.width);
+        self.transformed(width, height, |pos| Pos {
+            x: pos.y,
+            y: self.height - 1 - pos.x,
+        })
+    }
+
+    /// Rotates the maze half a turn, which is the same as mirroring it both
+    /// ways. The size stays.
+    pub fn rotated_180(&self) -> Maze {
+        self.transformed(self.width, self.height, |pos| Pos {
+            x: self.width - 1 - pos.x,
+            y: self.height - 1 - pos.y,
+        })
+    }
+
+    /// Mirrors left and right.
+    pub fn mirrored_horizontal(&self) -> Maze {
+        self.transformed(self.width, self.height, |pos| Pos {
+            x: self.width - 1 - pos.x,
+            y: pos.y,
+        })
+    }
+
+    /// Mirrors top and bottom.
+    pub fn mirrored_vertical(&self) -> Maze {
+        self.transformed(self.width, self.height, |pos| Pos {
+            x: pos.x,
+            y: self.height - 1 - pos.y,
+        })
+    }
+
+    /// Mirrors along the diagonal from the top left corner. Width and height
+    /// swap.
+    pub fn transposed(&self) -> Maze {
+        let (width, height) = (self.height, self.width);
+        self.transformed(width, height, |pos| Pos { x: pos.y, y: pos.x })
+    }
+
+    /// Cuts out the cells in `rect`, which has to lie inside the maze and
+    /// follow the size rule of `new`. Its center has to be an open cell,
+    /// which for corridors one cell wide means `rect` starts at even
+    /// coordinates. The cut out part becomes a maze of its own: its border
+    /// is walled up, the start moves to its center and keeps the room only
+    /// if that is where the room was. If the exit was cut away, the border
+    /// cell closest to the start by walking becomes the new exit.
+    pub fn crop(&self, rect: Rect) -> Result<Maze, MazeError> {
+        let Some(last) = rect.last() else {
+            return Err(MazeError::InvalidDimensions {
+                width: rect.width,
+                height: rect.height,
+                reason: "the area is empty".to_string(),
+            });
+        };
+        self.check_bounds(last)?;
+        if constrain_dimension!(rect.width) != rect.width
+            || constrain_dimension!(rect.height) != rect.height
+        {
+            return Err(MazeError::InvalidDimensions {
+                width: rect.width,
+                height: rect.height,
+                reason: "sizes are 7, 11, 15, and so on".to_string(),
+            });
+        }
+        let center = Pos::new(rect.x + rect.width / 2, rect.y + rect.height / 2);
+        if !TRAVERSABLE.contains(&self.get(center.x, center.y)) {
+            return Err(MazeError::InvalidOption {
+                name: "crop",
+                reason: format!("the center {} is not an open cell", center),
+            });
+        }
+
+        let mut maze = Maze::new(rect.width, rect.height, 1, self.exit_type.clone());
+        maze.weights = self.weights.clone();
+        for y in 0..rect.height {
+            for x in 0..rect.width {
+                let on_border = x == 0 || y == 0 || x == rect.width - 1 || y == rect.height - 1;
+                let cell = match self.get(rect.x + x, rect.y + y) {
+                    CellType::Start => CellType::Path,
+                    CellType::Exit if on_border => CellType::Exit,
+                    cell if on_border && TRAVERSABLE.contains(&cell) => CellType::Wall,
+                    cell => cell,
+                };
+                maze.set(x, y, cell);
+                if let Some(&label) = self.labels.get(&Pos::new(rect.x + x, rect.y + y)) {
+                    maze.labels.insert(Pos { x, y }, label);
+                }
+            }
+        }
+        let start = maze.start_pos();
+        if self.start_pos() == Pos::new(rect.x + start.x, rect.y + start.y)
+            && rect.intersect(self.room.rect()) == self.room.rect()
+        {
+            maze.room_size = self.room_size;
+            maze.room = Room {
+                top_left: Pos::new(self.room.top_left.x - rect.x, self.room.top_left.y - rect.y),
+                ..self.room
+            };
+        }
+        maze.set(start.x, start.y, CellType::Start);
+
+        if maze.exit_pos().is_none() {
+            let distances = maze.distance_map(start);
+            let exit = (0..maze.height)
+                .flat_map(|y| (0..maze.width).map(move |x| Pos { x, y }))
+                .filter(|&pos| maze.on_border(pos))
+                .filter_map(|pos| {
+                    maze.open_neighbors(pos)
+                        .filter_map(|next| distances[next.y * maze.width + next.x])
+                        .min()
+                        .map(|distance| (distance, pos))
+                })
+                .min()
+                .map(|(_, pos)| pos)
+                .ok_or(MazeError::Unsolvable)?;
+            maze.set(exit.x, exit.y, CellType::Exit);
+        }
+        let exit = maze.exit_pos().unwrap();
+        maze.exit_type = if exit.x == 0 {
+            ExitLocation::Left
+        } else if exit.x == maze.width - 1 {
+            ExitLocation::Right
+        } else if exit.y == 0 {
+            ExitLocation::Top
+        } else {
+            ExitLocation::Bottom
+        };
+        Ok(maze)
+    }
+
+    /// Stamps `other` into this maze with its top left corner at `at`. Its
+    /// start and exits become plain paths and its open cells are walled up
+    /// where they land on this maze's border. Like with `fill`, the start,
+    /// exits and center room can't be overwritten.
+    ///
+    /// The stamped part isn't necessarily connected to the rest afterwards.
+    /// Returns its open cells along the edge of the stamped area, where it
+    /// can be joined to the surrounding maze, e.g. by `carve` or
+    /// `repair_connectivity`.
+    pub fn blit(&mut self, other: &Maze, at: Pos) -> Result<Vec<Pos>, MazeError> {
+        let area = Rect::new(at.x, at.y, other.width, other.height);
+        let target = |pos: Pos| Pos::new(at.x + pos.x, at.y + pos.y);
+        let Some(last) = area.last() else {
+            return Err(MazeError::InvalidDimensions {
+                width: other.width,
+                height: other.height,
+                reason: "the area is empty".to_string(),
+            });
+        };
+        self.check_bounds(last)?;
+        if let Some(pos) = self.iter_cells().find_map(|(pos, cell)| {
+            let protected =
+                matches!(cell, CellType::Start | CellType::Exit) || self.in_center_room(pos);
+            (protected && area.contains(pos)).then_some(pos)
+        }) {
+            return Err(MazeError::InvalidEdit {
+                pos,
+                reason: "the start, exits and center room can't be overwritten".to_string(),
+            });
+        }
+
+        for (pos, cell) in other.iter_cells() {
+            let target = target(pos);
+            let cell = match cell {
+                CellType::Start | CellType::Exit => CellType::Path,
+                cell => cell,
+            };
+            if self.on_border(target) && TRAVERSABLE.contains(&cell) {
+                self.set(target.x, target.y, CellType::Wall);
+            } else {
+                self.set(target.x, target.y, cell);
+            }
+        }
+
+        Ok(other
+            .iter_cells()
+            .map(|(pos, _)| (pos, target(pos)))
+            .filter(|&(pos, target)| {
+                other.on_border(pos)
+                    && !self.on_border(target)
+                    && TRAVERSABLE.contains(&self.get(target.x, target.y))
+            })
+            .map(|(_, target)| target)
+            .collect())
+    }
+
+    /// Lays out a grid of equally sized mazes, given row by row, as one big
+    /// maze, using the thread-local RNG. See `tile_with_rng`.
+    pub fn tile(grid: &[Vec<Maze>], connect: ConnectStrategy) -> Result<Maze, MazeError> {
+        Maze::tile_with_rng(grid, connect, &mut rand::rng())
+    }
+
+    /// Same as `tile`, but draws all randomness from `rng`.
+    ///
+    /// Neighboring tiles share their border wall, which gets openings as
+    /// `connect` says. The tiles' starts and exits become plain paths. The
+    /// composite gets the center tile's room at its center and a single exit
+    /// on the side of the center tile's exit. With an even number of rows or
+    /// columns a wall is added around to keep the size rule of `new`, and the
+    /// room ends up where the middle tiles meet, joining them. Anything still
+    /// cut off afterwards is joined with `repair_connectivity`.
+    pub fn tile_with_rng<R: Rng + ?Sized>(
+        grid: &[Vec<Maze>],
+        connect: ConnectStrategy,
+        rng: &mut R,
+    ) -> Result<Maze, MazeError> {
+        let rows = grid.len();
+        let columns = grid.first().map_or(0, Vec::len);
+        let Some(first) = grid.first().and_then(|row| row.first()) else {
+            return Err(MazeError::InvalidDimensions {
+                width: columns,
+                height: rows,
+                reason: "no tiles given".to_string(),
+            });
+        };
+        let (tile_width, tile_height) = first.get_size();
+        if grid.iter().any(|row| {
+            row.len() != columns
+                || row
+                    .iter()
+                    .any(|tile| tile.get_size() != (tile_width, tile_height))
+        }) {
+            return Err(MazeError::InvalidDimensions {
+                width: tile_width,
+                height: tile_height,
+                reason: "all rows need as many tiles, all of the same size".to_string(),
+            });
+        }
+
+        let inner_width = columns * (tile_width - 1) + 1;
+        let inner_height = rows * (tile_height - 1) + 1;
+        let center_tile = &grid[rows / 2][columns / 2];
+        let mut maze = Maze::new(
+            inner_width,
+            inner_height,
+            center_tile.room_size,
+            center_tile.exit_side(),
+        );
+        maze.check_room_size()?;
+        let offset_x = (maze.width - inner_width) / 2;
+        let offset_y = (maze.height - inner_height) / 2;
+        let tile_origin = |row: usize, column: usize| {
+            Pos::new(
+                offset_x + column * (tile_width - 1),
+                offset_y + row * (tile_height - 1),
+            )
+        };
+
+        // Only copy open cells, so an opening in a shared wall survives the
+        // wall of the tile on the other side
+        for (row, tiles) in grid.iter().enumerate() {
+            for (column, tile) in tiles.iter().enumerate() {
+                let origin = tile_origin(row, column);
+                for (pos, cell) in tile.iter_cells() {
+                    let target = Pos::new(origin.x + pos.x, origin.y + pos.y);
+                    match cell {
+                        CellType::Wall => {}
+                        _ if maze.on_border(target) => {}
+                        CellType::Start | CellType::Exit => {
+                            maze.set(target.x, target.y, CellType::Path)
+                        }
+                        cell => maze.set(target.x, target.y, cell),
+                    }
+                }
+            }
+        }
+
+        // Shared walls without their ends, with the direction crossing them
+        let mut shared_walls = Vec::new();
+        for row in 0..rows {
+            for column in 0..columns {
+                let origin = tile_origin(row, column);
+                if column > 0 {
+                    let wall: Vec<Pos> = (1..tile_height - 1)
+                        .map(|dy| Pos::new(origin.x, origin.y + dy))
+                        .collect();
+                    shared_walls.push((wall, Direction::East));
+                }
+                if row > 0 {
+                    let wall: Vec<Pos> = (1..tile_width - 1)
+                        .map(|dx| Pos::new(origin.x + dx, origin.y))
+                        .collect();
+                    shared_walls.push((wall, Direction::South));
+                }
+            }
+        }
+        for (wall, across) in shared_walls {
+            let candidates: Vec<Pos> = wall
+                .into_iter()
+                .filter(|&pos| {
+                    maze.get(pos.x, pos.y) == CellType::Wall
+                        && maze.step(pos, across).is_some()
+                        && maze.step(pos, across.reverse()).is_some()
+                })
+                .collect();
+            let openings: Vec<Pos> = match connect {
+                ConnectStrategy::Random(count) => {
+                    candidates.choose_multiple(rng, count).copied().collect()
+                }
+                ConnectStrategy::Evenly(count) => {
+                    let count = count.min(candidates.len());
+                    (0..count)
+                        .map(|i| candidates[(2 * i + 1) * candidates.len() / (2 * count)])
+                        .collect()
+                }
+            };
+            for pos in openings {
+                maze.set(pos.x, pos.y, CellType::Path);
+            }
+        }
+
+        let start = maze.start_pos();
+        for pos in maze.room.rect().positions() {
+            maze.set(pos.x, pos.y, CellType::Path);
+        }
+        maze.set(start.x, start.y, CellType::Start);
+
+        // Tunnel in from the border cell on the exit side that is closest to
+        // an open cell, preferring the middle of the side
+        let side = match &maze.exit_type {
+            ExitLocation::Random | ExitLocation::Farthest => SIDES.choose(rng).unwrap().clone(),
+            side => side.clone(),
+        };
+        let middle = maze.side_middle(&side);
+        let (inward, border): (Direction, Vec<Pos>) = match side {
+            ExitLocation::Left => (
+                Direction::East,
+                (1..maze.height - 1).map(|y| Pos::new(0, y)).collect(),
+            ),
+            ExitLocation::Right => (
+                Direction::West,
+                (1..maze.height - 1)
+                    .map(|y| Pos::new(maze.width - 1, y))
+                    .collect(),
+            ),
+            ExitLocation::Top => (
+                Direction::South,
+                (1..maze.width - 1).map(|x| Pos::new(x, 0)).collect(),
+            ),
+            _ => (
+                Direction::North,
+                (1..maze.width - 1)
+                    .map(|x| Pos::new(x, maze.height - 1))
+                    .collect(),
+            ),
+        };
+        let tunnel = |exit: Pos| -> Option<Vec<Pos>> {
+            let mut tunnel = vec![exit];
+            loop {
+                let next = tunnel.last()?.step(inward, maze.get_size())?;
+                if TRAVERSABLE.contains(&maze.get(next.x, next.y)) {
+                    return Some(tunnel);
+                }
+                tunnel.push(next);
+            }
+        };
+        let tunnel = border
+            .into_iter()
+            .filter_map(|exit| {
+                tunnel(exit).map(|tunnel| (tunnel.len(), exit.manhattan(middle), tunnel))
+            })
+            .min_by_key(|&(length, distance, _)| (length, distance))
+            .map(|(_, _, tunnel)| tunnel)
+            .ok_or(MazeError::Unsolvable)?;
+        for &pos in &tunnel[1..] {
+            maze.set(pos.x, pos.y, CellType::Path);
+        }
+        maze.set(tunnel[0].x, tunnel[0].y, CellType::Exit);
+        if maze.exit_type != ExitLocation::Farthest {
+            maze.exit_type = side;
+        }
+
+        maze.repair_connectivity();
+        if maze.exit_type == ExitLocation::Farthest {
+            maze.place_farthest_exit(None);
+        }
+        if maze.shortest_path().is_none() {
+            return Err(MazeError::Unsolvable);
+        }
+        Ok(maze)
+    }
+
+    fn on_border(&self, pos: Pos) -> bool {
+        pos.x == 0 || pos.y == 0 || pos.x == self.width - 1 || pos.y == self.height - 1
+    }
+
+    /// The side of the border `pos` is on and the direction leading inside,
+    /// `None` if it is not on the border. Corners count as left or right.
+    fn border_side(&self, pos: Pos) -> Option<(ExitLocation, Direction)> {
+        if pos.x >= self.width || pos.y >= self.height {
+            None
+        } else if pos.x == 0 {
+            Some((ExitLocation::Left, Direction::East))
+        } else if pos.x == self.width - 1 {
+            Some((ExitLocation::Right, Direction::West))
+        } else if pos.y == 0 {
+            Some((ExitLocation::Top, Direction::South))
+        } else if pos.y == self.height - 1 {
+            Some((ExitLocation::Bottom, Direction::North))
+        } else {
+            None
+        }
+    }
+
+    /// The cell inside the maze an exit at the border cell `pos` opens to,
+    /// with the direction to it. Corners have none.
+    fn exit_entry(&self, pos: Pos) -> Option<(Direction, Pos)> {
+        let (_, inward) = self.border_side(pos)?;
+        pos.step(inward, self.get_size())
+            .filter(|&inner| !self.on_border(inner))
+            .map(|inner| (inward, inner))
+    }
+
+    /// Builds a `width` x `height` maze whose cell at `pos` is this maze's
+    /// cell at `source(pos)`. The sizes are odd, so the center, and with it
+    /// the start and room, stays in place. The exit side is looked up by
+    /// where the middle of each new side comes from, a custom exit by where
+    /// its cell went.
+    fn transformed(&self, width: usize, height: usize, source: impl Fn(Pos) -> Pos) -> Maze {
+        let mut maze = Maze {
+            width,
+            height,
+            room_size: self.room_size,
+            room: self.room,
+            exit_type: self.exit_type.clone(),
+            corridor_width: self.corridor_width,
+            weave: self.weave,
+            braid: self.braid,
+            cells: Vec::with_capacity(self.cells.len()),
+            labels: HashMap::new(),
+            weights: self.weights.clone(),
+            solution: OnceLock::new(),
+        };
+        // Bridges turn with the maze when rows become columns
+        let swapped = source(Pos { x: 0, y: 0 }).x == source(Pos { x: 1, y: 0 }).x;
+        let (mut room_min, mut room_max) = (Pos::new(width, height), Pos::new(0, 0));
+        for y in 0..height {
+            for x in 0..width {
+                let from = source(Pos { x, y });
+                if self.room.contains(from) {
+                    room_min = Pos::new(room_min.x.min(x), room_min.y.min(y));
+                    room_max = Pos::new(room_max.x.max(x), room_max.y.max(y));
+                }
+                if let Some(&label) = self.labels.get(&from) {
+                    maze.labels.insert(Pos { x, y }, label);
+                }
+                maze.cells.push(match self.get(from.x, from.y) {
+                    CellType::BridgeNorthSouth if swapped => CellType::BridgeEastWest,
+                    CellType::BridgeEastWest if swapped => CellType::BridgeNorthSouth,
+                    cell => cell,
+                });
+            }
+        }
+        maze.room = Room {
+            top_left: room_min,
+            width: room_max.x + 1 - room_min.x,
+            height: room_max.y + 1 - room_min.y,
+        };
+        // With an even corridor width the center room has no middle cell,
+        // so the start can end up next to where it belongs. Extra starts
+        // from `fair_starts` are farther away and just move along.
+        let center = maze.start_pos();
+        let room_start = maze
+            .iter_cells()
+            .filter(|&(_, cell)| cell == CellType::Start)
+            .map(|(pos, _)| pos)
+            .min_by_key(|pos| pos.manhattan(center));
+        if let Some(start) = room_start {
+            maze.cells[start.y * width + start.x] = CellType::Path;
+            maze.set(center.x, center.y, CellType::Start);
+        }
+        let on_exit_side = |pos: Pos| match self.exit_type {
+            ExitLocation::Left => pos.x == 0,
+            ExitLocation::Right => pos.x == self.width - 1,
+            ExitLocation::Top => pos.y == 0,
+            ExitLocation::Bottom => pos.y == self.height - 1,
+            ExitLocation::Random | ExitLocation::Farthest | ExitLocation::Custom(_) => false,
+        };
+        if SIDES.contains(&self.exit_type) {
+            maze.exit_type = SIDES
+                .into_iter()
+                .find(|side| on_exit_side(source(maze.side_middle(side))))
+                .unwrap();
+        }
+        if let ExitLocation::Custom(exit) = self.exit_type {
+            maze.exit_type = (0..height)
+                .flat_map(|y| (0..width).map(move |x| Pos { x, y }))
+                .find(|&pos| source(pos) == exit)
+                .map_or(ExitLocation::Random, ExitLocation::Custom);
+        }
+        maze
+    }
+
+    /// Middle cell of the border on `side`, where generation puts the exit,
+    /// or the cell of a custom exit.
+    fn side_middle(&self, side: &ExitLocation) -> Pos {
+        match side {
+            ExitLocation::Left => Pos {
+                x: 0,
+                y: self.height / 2,
+            },
+            ExitLocation::Right => Pos {
+                x: self.width - 1,
+                y: self.height / 2,
+            },
             ExitLocation::Top => Pos {
                 x: self.width / 2,
                 y: 0,
             },
-            ExitLocation::Bottom => Pos {
-                x: self.width / 2,
-                y: self.height - 1,
+            ExitLocation::Bottom => Pos {
+                x: self.width / 2,
+                y: self.height - 1,
+            },
+            ExitLocation::Custom(pos) => *pos,
+            ExitLocation::Random | ExitLocation::Farthest => {
+                unreachable!("a random or farthest exit has no fixed side")
+            }
+        }
+    }
+
+    fn in_center_room(&self, pos: Pos) -> bool {
+        self.room.contains(pos)
+    }
+
+    /// Size of the maze with one cell wide corridors that this one widens
+    fn narrow_size(&self) -> (usize, usize) {
+        let narrow = |dim: usize| (dim - 1) / (self.corridor_width + 1) * 2 + 1;
+        (narrow(self.width), narrow(self.height))
+    }
+
+    /// The cells that the cell at `pos` in the narrow maze is widened to:
+    /// odd rows and columns are corridors, even ones walls.
+    fn block(&self, pos: Pos) -> impl Iterator<Item = Pos> + use<> {
+        let span = |i: usize| {
+            let first = i.div_ceil(2) + i / 2 * self.corridor_width;
+            let len = if i % 2 == 1 { self.corridor_width } else { 1 };
+            first..first + len
+        };
+        let columns = span(pos.x);
+        span(pos.y).flat_map(move |y| columns.clone().map(move |x| Pos { x, y }))
+    }
+
+    /// An all-wall maze with one cell wide corridors that can be widened to
+    /// this one
+    fn narrow(&self) -> Maze {
+        let (width, height) = self.narrow_size();
+        let mut narrow = Maze::new(width, height, self.room_size, self.exit_type.clone());
+        narrow.braid = self.braid;
+        narrow
+    }
+
+    /// Translates an event of the narrow maze to this one.
+    fn widen_event(&self, event: MazeEvent) -> Vec<MazeEvent> {
+        match event {
+            MazeEvent::CarvePath(pos) => self.block(pos).map(MazeEvent::CarvePath).collect(),
+            // A corner where walls meet stays a single cell, opening it would
+            // leave a gap too narrow for the corridors
+            MazeEvent::RemoveWall(pos) if pos.x % 2 == 0 && pos.y % 2 == 0 => Vec::new(),
+            MazeEvent::RemoveWall(pos) => self.block(pos).map(MazeEvent::RemoveWall).collect(),
+            MazeEvent::PlaceExit(pos) => self.block(pos).map(MazeEvent::PlaceExit).collect(),
+            // Only a single cell of the widened start is the start
+            MazeEvent::PlaceStart(pos) => self
+                .block(pos)
+                .map(MazeEvent::CarvePath)
+                .chain([MazeEvent::PlaceStart(self.start_pos())])
+                .collect(),
+            MazeEvent::PlaceArtifact(pos, cell) => self
+                .block(pos)
+                .map(|pos| MazeEvent::PlaceArtifact(pos, cell))
+                .collect(),
+            event => vec![event],
+        }
+    }
+
+    /// Applies a recorded generation or placement event to this maze.
+    /// Solver events don't change any cells and are ignored.
+    pub fn apply(&mut self, event: MazeEvent) {
+        match event {
+            MazeEvent::CarvePath(pos) | MazeEvent::RemoveWall(pos) => {
+                self.set(pos.x, pos.y, CellType::Path)
+            }
+            MazeEvent::PlaceStart(pos) => self.set(pos.x, pos.y, CellType::Start),
+            MazeEvent::PlaceExit(pos) => self.set(pos.x, pos.y, CellType::Exit),
+            MazeEvent::PlaceArtifact(pos, cell) | MazeEvent::PlaceBridge(pos, cell) => {
+                self.set(pos.x, pos.y, cell)
+            }
+            MazeEvent::Enqueue(_)
+            | MazeEvent::Visit(_)
+            | MazeEvent::FillDeadEnd(_)
+            | MazeEvent::FoundExit(_) => {}
+        }
+    }
+
+    /// The center cell, where the start is
+    pub fn start_pos(&self) -> Pos {
+        Pos {
+            x: self.width / 2,
+            y: self.height / 2,
+        }
+    }
+
+    /// The first exit in row-major order, `None` before generation
+    pub fn exit_pos(&self) -> Option<Pos> {
+        self.cells
+            .iter()
+            .position(|&c| c == CellType::Exit)
+            .map(|i| Pos {
+                x: i % self.width,
+                y: i / self.width,
+            })
+    }
+
+    /// Returns the traversable neighbor of `pos` in the given direction. On
+    /// a bridge only the passage on top can be followed, and going under a
+    /// bridge leads straight to the cell on its far side.
+    fn step(&self, pos: Pos, direction: Direction) -> Option<Pos> {
+        if self.get(pos.x, pos.y).is_under(direction) {
+            return None;
+        }
+        let mut next = pos.step(direction, self.get_size())?;
+        if self.get(next.x, next.y).is_under(direction) {
+            next = next.step(direction, self.get_size())?;
+        }
+        TRAVERSABLE
+            .contains(&self.get(next.x, next.y))
+            .then_some(next)
+    }
+
+    /// The cells one `step` away from `pos`, in the order of
+    /// `Direction::ALL`. For a wall these are the cells it would join once
+    /// carved.
+    fn open_neighbors(&self, pos: Pos) -> impl Iterator<Item = Pos> + '_ {
+        Direction::ALL
+            .into_iter()
+            .filter_map(move |direction| self.step(pos, direction))
+    }
+
+    /// Puts the bridges passed under back into a path made of `step`s, so
+    /// consecutive positions are adjacent again.
+    fn with_bridges(path: Vec<Pos>) -> Vec<Pos> {
+        let mut full: Vec<Pos> = Vec::with_capacity(path.len());
+        for pos in path {
+            if let Some(&last) = full.last()
+                && last.manhattan(pos) == 2
+            {
+                full.push(Pos {
+                    x: (last.x + pos.x) / 2,
+                    y: (last.y + pos.y) / 2,
+                });
+            }
+            full.push(pos);
+        }
+        full
+    }
+
+    pub fn mst_prim(&self) -> MstResult {
+        let (nodes, edges) = self.build_graph();
+        let mut mst_edges = HashSet::new();
+        let mut visited = HashSet::new();
+        let mut total_weight = 0;
+
+        // Start from the center node
+        let start_node = nodes.get(&Pos {
+            x: self.width / 2,
+            y: self.height / 2,
+        });
+        if start_node.is_none() {
+            return MstResult {
+                nodes,
+                edges: mst_edges,
+                total_weight,
+            };
+        }
+        let start_node_id = *start_node.unwrap();
+
+        visited.insert(start_node_id);
+
+        while visited.len() < nodes.len() {
+            let mut min_edge: Option<Edge> = None;
+
+            for edge in &edges {
+                // Check if the edge connects a visited node with an unvisited one
+                let connects_visited_and_unvisited = (visited.contains(&edge.start_id)
+                    && !visited.contains(&edge.end_id))
+                    || (visited.contains(&edge.end_id) && !visited.contains(&edge.start_id));
+
+                // Ties are broken by node ids, as the edges come in no particular order
+                let key = |edge: &Edge| (edge.weight, edge.start_id, edge.end_id);
+                if connects_visited_and_unvisited
+                    && min_edge.is_none_or(|min_edge| key(edge) < key(&min_edge))
+                {
+                    min_edge = Some(*edge);
+                }
+            }
+
+            if let Some(edge) = min_edge {
+                visited.insert(edge.start_id);
+                visited.insert(edge.end_id);
+                mst_edges.insert(edge);
+                total_weight += edge.weight;
+            } else {
+                break;
+            }
+        }
+
+        MstResult {
+            nodes,
+            edges: mst_edges,
+            total_weight,
+        }
+    }
+
+    /// Like `mst_prim`, but adds the lightest edges that don't close a
+    /// loop, keeping track of the joined parts with a union-find. Faster on
+    /// graphs with many edges, as it sorts them once instead of scanning
+    /// them all for every node.
+    pub fn mst_kruskal(&self) -> MstResult {
+        let (nodes, edges) = self.build_graph();
+        let mut sorted_edges: Vec<Edge> = edges.into_iter().collect();
+        // Ties are broken by node ids, like in `mst_prim`
+        sorted_edges.sort_by_key(|edge| (edge.weight, edge.start_id, edge.end_id));
+
+        let mut parents: Vec<usize> = (0..nodes.len()).collect();
+        let mut mst_edges = HashSet::new();
+        let mut total_weight = 0;
+        for edge in sorted_edges {
+            let start_root = find_root(&mut parents, edge.start_id);
+            let end_root = find_root(&mut parents, edge.end_id);
+            if start_root != end_root {
+                parents[start_root] = end_root;
+                mst_edges.insert(edge);
+                total_weight += edge.weight;
+            }
+        }
+
+        MstResult {
+            nodes,
+            edges: mst_edges,
+            total_weight,
+        }
+    }
+
+    /// The cells along each edge of `mst`, from its start node to its end
+    /// node, following the passages like `build_graph` does. Bridges passed
+    /// under are part of the path, so consecutive cells are adjacent.
+    pub fn mst_paths(&self, mst: &MstResult) -> Vec<Vec<Pos>> {
+        let positions: HashMap<usize, Pos> =
+            mst.nodes.iter().map(|(&pos, &id)| (id, pos)).collect();
+        let mut sorted_edges: Vec<&Edge> = mst.edges.iter().collect();
+        sorted_edges.sort_by_key(|edge| (edge.start_id, edge.end_id, edge.weight));
+
+        let mut paths = Vec::new();
+        for edge in sorted_edges {
+            let start_pos = positions[&edge.start_id];
+            for first in self.open_neighbors(start_pos) {
+                let mut weight = self.weights.get(self.get(first.x, first.y));
+                let mut path = vec![start_pos, first];
+                let mut current_pos = first;
+                while !mst.nodes.contains_key(&current_pos) {
+                    let Some(next_pos) = self
+                        .open_neighbors(current_pos)
+                        .find(|next| !path.contains(next))
+                    else {
+                        break;
+                    };
+                    weight += self.weights.get(self.get(next_pos.x, next_pos.y));
+                    path.push(next_pos);
+                    current_pos = next_pos;
+                }
+                if mst.nodes.get(&current_pos) == Some(&edge.end_id) && weight == edge.weight {
+                    paths.push(Maze::with_bridges(path));
+                    break;
+                }
+            }
+        }
+        paths
+    }
+
+    /// Checks the structural rules every maze should follow: all traversable
+    /// cells are reachable from the start, the border is wall except for
+    /// exits, there is exactly one start and at least one exit, and the
+    /// center room is open.
+    pub fn validate(&self) -> Vec<MazeViolation> {
+        let mut violations = Vec::new();
+
+        for component in self.components().iter().skip(1) {
+            violations.push(MazeViolation::Unreachable {
+                pos: component[0],
+                size: component.len(),
+            });
+        }
+
+        for y in 0..self.height {
+            for x in 0..self.width {
+                let on_border = x == 0 || y == 0 || x == self.width - 1 || y == self.height - 1;
+                let cell = self.get(x, y);
+                if on_border && cell != CellType::Exit && TRAVERSABLE.contains(&cell) {
+                    violations.push(MazeViolation::OpenBorder(Pos { x, y }));
+                }
+            }
+        }
+
+        // Extra starts from `fair_starts` are fine next to the one in the
+        // center room
+        let start = self.start_pos();
+        match self.cells.iter().filter(|&&c| c == CellType::Start).count() {
+            0 => violations.push(MazeViolation::MissingStart),
+            1 => {}
+            _ if self.get(start.x, start.y) == CellType::Start => {}
+            count => violations.push(MazeViolation::MultipleStarts(count)),
+        }
+        if self.exit_pos().is_none() {
+            violations.push(MazeViolation::MissingExit);
+        }
+
+        for y in 0..self.height {
+            for x in 0..self.width {
+                let pos = Pos { x, y };
+                if self.in_center_room(pos) && !TRAVERSABLE.contains(&self.get(x, y)) {
+                    violations.push(MazeViolation::RoomBlocked(pos));
+                }
+            }
+        }
+
+        violations
+    }
+
+    /// Joins every group of traversable cells that can't be reached from the
+    /// start to the rest of the maze by carving the fewest interior walls
+    /// needed. Returns the number of carved cells.
+    pub fn repair_connectivity(&mut self) -> usize {
+        let mut carved = 0;
+        loop {
+            let components = self.components();
+            if components.len() < 2 {
+                break;
+            }
+            let stray: HashSet<Pos> = components.last().unwrap().iter().copied().collect();
+
+            // Tunnel through interior walls until one would join another
+            // traversable cell, which can be beyond a bridge
+            let mut parents: HashMap<Pos, Pos> = stray.iter().map(|&pos| (pos, pos)).collect();
+            let mut queue: std::collections::VecDeque<Pos> = stray.iter().copied().collect();
+            let mut tunnel_end = None;
+            while let Some(pos) = queue.pop_front() {
+                if self.open_neighbors(pos).any(|next| !stray.contains(&next)) {
+                    tunnel_end = Some(pos);
+                    break;
+                }
+                for next in pos.neighbors(self.get_size()) {
+                    let interior = next.x > 0
+                        && next.y > 0
+                        && next.x < self.width - 1
+                        && next.y < self.height - 1;
+                    if interior
+                        && self.get(next.x, next.y) == CellType::Wall
+                        && !parents.contains_key(&next)
+                    {
+                        parents.insert(next, pos);
+                        queue.push_back(next);
+                    }
+                }
+            }
+
+            let Some(mut pos) = tunnel_end else {
+                break;
+            };
+            while !stray.contains(&pos) {
+                self.set(pos.x, pos.y, CellType::Path);
+                carved += 1;
+                pos = parents[&pos];
+            }
+        }
+        carved
+    }
+
+    /// Turns a wall into a path. Border cells can only be carved to create
+    /// an additional exit, which requires an open cell right next to them.
+    pub fn carve(&mut self, pos: Pos) -> Result<(), MazeError> {
+        self.check_bounds(pos)?;
+        if self.get(pos.x, pos.y) != CellType::Wall {
+            return Err(MazeError::InvalidEdit {
+                pos,
+                reason: "it is not a wall".to_string(),
+            });
+        }
+        let on_border =
+            pos.x == 0 || pos.y == 0 || pos.x == self.width - 1 || pos.y == self.height - 1;
+        if !on_border {
+            self.set(pos.x, pos.y, CellType::Path);
+            return Ok(());
+        }
+        let leads_inside = Direction::ALL
+            .into_iter()
+            .filter_map(|direction| self.step(pos, direction))
+            .any(|next| {
+                next.x > 0 && next.y > 0 && next.x < self.width - 1 && next.y < self.height - 1
+            });
+        if !leads_inside {
+            return Err(MazeError::InvalidEdit {
+                pos,
+                reason: "border cells can only be carved as an exit next to an open cell"
+                    .to_string(),
+            });
+        }
+        self.set(pos.x, pos.y, CellType::Exit);
+        Ok(())
+    }
+
+    /// Moves the exit to the border cell `pos` and walls up where it was.
+    /// Corners can't hold it, and the cell inside of `pos` has to be a
+    /// corridor the generator carves, not just an opened wall, so the maze
+    /// can be generated with the exit there again: `exit_location` becomes
+    /// `ExitLocation::Custom`. Of several exits only the first one moves.
+    pub fn move_exit(&mut self, pos: Pos) -> Result<(), MazeError> {
+        self.check_bounds(pos)?;
+        let invalid = |reason: &str| MazeError::InvalidEdit {
+            pos,
+            reason: reason.to_string(),
+        };
+        let Some((inward, inner)) = self.exit_entry(pos) else {
+            return Err(invalid("the exit has to be on the border, off the corners"));
+        };
+        let current = self.exit_pos();
+        if current != Some(pos) {
+            if self.get(pos.x, pos.y) != CellType::Wall {
+                return Err(invalid("it is not a wall"));
+            }
+            // Wider corridors can't be generated with a moved exit anyway
+            let carved = self.corridor_width > 1
+                || self
+                    .corridor_reach(&MazeMask::from_fn(self.width, self.height, |_, _| true))
+                    .contains(&inner);
+            if self.step(pos, inward) != Some(inner) || !carved {
+                return Err(invalid("no corridor leads to it"));
+            }
+            if let Some(current) = current {
+                self.set(current.x, current.y, CellType::Wall);
+            }
+            self.set(pos.x, pos.y, CellType::Exit);
+        }
+        self.exit_type = ExitLocation::Custom(pos);
+        Ok(())
+    }
+
+    /// Turns a traversable cell into a wall, unless that would split the
+    /// traversable region in two or cut off the exit. The start, the exits
+    /// and the center room can't be filled.
+    pub fn fill(&mut self, pos: Pos) -> Result<(), MazeError> {
+        self.check_bounds(pos)?;
+        let cell = self.get(pos.x, pos.y);
+        if cell == CellType::Wall {
+            return Ok(());
+        }
+        if cell == CellType::Start || cell == CellType::Exit || self.in_center_room(pos) {
+            return Err(MazeError::InvalidEdit {
+                pos,
+                reason: "the start, exits and center room can't be filled".to_string(),
+            });
+        }
+
+        // All open neighbors must still reach each other without going through `pos`
+        let open_neighbors: Vec<Pos> = Direction::ALL
+            .into_iter()
+            .filter_map(|direction| self.step(pos, direction))
+            .collect();
+        if let Some((&first, rest)) = open_neighbors.split_first() {
+            let mut pending: HashSet<Pos> = rest.iter().copied().collect();
+            let mut seen = HashSet::from([pos, first]);
+            let mut stack = vec![first];
+            while let Some(current) = stack.pop() {
+                if pending.is_empty() {
+                    break;
+                }
+                for direction in Direction::ALL {
+                    if let Some(next) = self.step(current, direction)
+                        && seen.insert(next)
+                    {
+                        pending.remove(&next);
+                        stack.push(next);
+                    }
+                }
+            }
+            if !pending.is_empty() {
+                return Err(MazeError::InvalidEdit {
+                    pos,
+                    reason: "filling it would disconnect the maze".to_string(),
+                });
+            }
+        }
+
+        self.set(pos.x, pos.y, CellType::Wall);
+        Ok(())
+    }
+
+    /// Sets a single cell. Walls and paths go through `fill` and `carve`,
+    /// artifacts can only replace paths or other artifacts, and the start and
+    /// exits can't be placed this way.
+    pub fn place(&mut self, pos: Pos, cell_type: CellType) -> Result<(), MazeError> {
+        self.check_bounds(pos)?;
+        let current = self.get(pos.x, pos.y);
+        let is_artifact = |cell: &CellType| REWARDS.contains(cell) || DANGERS.contains(cell);
+        match cell_type {
+            CellType::Wall => self.fill(pos),
+            CellType::Path if current == CellType::Wall => self.carve(pos),
+            CellType::Start | CellType::Exit => Err(MazeError::InvalidEdit {
+                pos,
+                reason: format!("{} can't be placed", cell_type),
+            }),
+            _ if current == CellType::Path || is_artifact(&current) => {
+                self.set(pos.x, pos.y, cell_type);
+                Ok(())
+            }
+            _ => Err(MazeError::InvalidEdit {
+                pos,
+                reason: format!(
+                    "{} can only be placed on a path, not on {}",
+                    cell_type, current
+                ),
+            }),
+        }
+    }
+
+    fn check_bounds(&self, pos: Pos) -> Result<(), MazeError> {
+        if pos.x < self.width && pos.y < self.height {
+            Ok(())
+        } else {
+            Err(MazeError::OutOfBounds {
+                pos,
+                size: self.get_size(),
+            })
+        }
+    }
+
+    /// Groups all traversable cells into connected components. The component
+    /// containing the start comes first, the others follow in scan order.
+    fn components(&self) -> Vec<Vec<Pos>> {
+        let mut seen = vec![false; self.cells.len()];
+        let mut components = Vec::new();
+        let start = self.start_pos();
+        let scan = std::iter::once(start)
+            .chain((0..self.height).flat_map(|y| (0..self.width).map(move |x| Pos { x, y })));
+
+        for origin in scan {
+            if seen[origin.y * self.width + origin.x]
+                || !TRAVERSABLE.contains(&self.get(origin.x, origin.y))
+            {
+                continue;
+            }
+            seen[origin.y * self.width + origin.x] = true;
+            let mut component = vec![origin];
+            let mut stack = vec![origin];
+            while let Some(pos) = stack.pop() {
+                for direction in Direction::ALL {
+                    if let Some(next) = self.step(pos, direction)
+                        && !seen[next.y * self.width + next.x]
+                    {
+                        seen[next.y * self.width + next.x] = true;
+                        component.push(next);
+                        stack.push(next);
+                    }
+                }
+            }
+            components.push(component);
+        }
+
+        components
+    }
+
+    /// Generates the maze using the thread-local RNG. Use
+    /// `generate_with_rng` with a seeded RNG for reproducible mazes.
+    pub fn generate(&mut self) {
+        self.generate_with_rng(&mut rand::rng());
+    }
+
+    /// Same as `generate`, but draws all randomness from `rng`, so a seeded
+    /// RNG always produces the same maze.
+    pub fn generate_with_rng<R: Rng + ?Sized>(&mut self, rng: &mut R) {
+        self.generate_observed(rng, |_| {});
+    }
+
+    /// Same as `generate_with_rng`, but reports every cell change to
+    /// `observer` in the order it happens. Applying the events to an all-wall
+    /// maze of the same size with `apply` reproduces the generated maze.
+    pub fn generate_observed<R: Rng + ?Sized>(
+        &mut self,
+        rng: &mut R,
+        observer: impl FnMut(MazeEvent),
+    ) {
+        self.generate_within(None, rng, observer);
+    }
+
+    /// Generates the maze only within the cells of `mask`, using the
+    /// thread-local RNG. See `generate_masked_with_rng`.
+    pub fn generate_masked(&mut self, mask: &MazeMask) -> Result<usize, MazeError> {
+        self.generate_masked_with_rng(mask, &mut rand::rng())
+    }
+
+    /// Same as `generate_with_rng`, but only carves cells inside `mask`,
+    /// everything outside stays wall. The center room and the exit, with the
+    /// cell inside of it, have to be in the mask. A random exit is put on one
+    /// of the sides where that holds.
+    ///
+    /// Parts of the mask not connected to the room are left as walls.
+    /// Returns how many cells the corridors could have run through there.
+    pub fn generate_masked_with_rng<R: Rng + ?Sized>(
+        &mut self,
+        mask: &MazeMask,
+        rng: &mut R,
+    ) -> Result<usize, MazeError> {
+        if mask.get_size() != self.get_size() {
+            return Err(MazeError::InvalidDimensions {
+                width: mask.width,
+                height: mask.height,
+                reason: format!(
+                    "the mask doesn't match the {}x{} maze",
+                    self.width, self.height
+                ),
+            });
+        }
+        if self.corridor_width > 1 {
+            // A widened cell is inside if all of its cells are
+            let narrow = self.narrow();
+            let narrow_mask = self.narrow_mask(mask);
+            narrow.check_mask(&narrow_mask)?;
+            self.generate_within(Some(mask), rng, |_| {});
+            return Ok(narrow.cut_off_cells(&narrow_mask));
+        }
+        self.check_mask(mask)?;
+        self.generate_within(Some(mask), rng, |_| {});
+        Ok(self.cut_off_cells(mask))
+    }
+
+    /// Carves the inside of `rect` anew using the thread-local RNG. See
+    /// `regenerate_region_with_rng`.
+    pub fn regenerate_region(&mut self, rect: Rect) -> Result<(), MazeError> {
+        self.regenerate_region_with_rng(rect, &mut rand::rng())
+    }
+
+    /// Walls up the cells inside `rect` and carves new corridors there,
+    /// leaving everything else, including the border of `rect`, as it is.
+    /// `rect` has to lie on wall lines: its top left corner has even
+    /// coordinates and its sides are odd. The inside can't hold the center
+    /// room or the exit.
+    ///
+    /// Every corridor that led into the area still does afterwards, so the
+    /// rest of the maze stays connected. If none did, one is opened towards
+    /// a random open cell next to it.
+    pub fn regenerate_region_with_rng<R: Rng + ?Sized>(
+        &mut self,
+        rect: Rect,
+        rng: &mut R,
+    ) -> Result<(), MazeError> {
+        if self.corridor_width > 1 {
+            return Err(MazeError::InvalidOption {
+                name: "region",
+                reason: "only mazes with corridors one cell wide can be regenerated in part"
+                    .to_string(),
+            });
+        }
+        let Some(last) = rect.last() else {
+            return Err(MazeError::InvalidDimensions {
+                width: rect.width,
+                height: rect.height,
+                reason: "the area is empty".to_string(),
+            });
+        };
+        self.check_bounds(last)?;
+        if !rect.x.is_multiple_of(2)
+            || !rect.y.is_multiple_of(2)
+            || rect.width < 3
+            || rect.height < 3
+            || rect.width.is_multiple_of(2)
+            || rect.height.is_multiple_of(2)
+        {
+            return Err(MazeError::InvalidDimensions {
+                width: rect.width,
+                height: rect.height,
+                reason:
+                    "the area has to start at even coordinates and have odd sides of at least 3"
+                        .to_string(),
+            });
+        }
+        let inner = Rect::new(rect.x + 1, rect.y + 1, rect.width - 2, rect.height - 2);
+        for (pos, cell) in self.iter_cells() {
+            if !inner.contains(pos) {
+                continue;
+            }
+            if self.in_center_room(pos) {
+                return Err(MazeError::InvalidOption {
+                    name: "region",
+                    reason: format!("the center room cell {} is inside of it", pos),
+                });
+            }
+            if cell == CellType::Exit {
+                return Err(MazeError::InvalidOption {
+                    name: "region",
+                    reason: format!("the exit at {} is inside of it", pos),
+                });
+            }
+        }
+
+        // The border of the area, with the direction leading inside
+        let edge: Vec<(Pos, Direction)> = (inner.y..inner.y + inner.height)
+            .flat_map(|y| {
+                [
+                    (Pos::new(rect.x, y), Direction::East),
+                    (Pos::new(last.x, y), Direction::West),
+                ]
+            })
+            .chain((inner.x..inner.x + inner.width).flat_map(|x| {
+                [
+                    (Pos::new(x, rect.y), Direction::South),
+                    (Pos::new(x, last.y), Direction::North),
+                ]
+            }))
+            .collect();
+        let is_open = |pos: Pos| TRAVERSABLE.contains(&self.get(pos.x, pos.y));
+        let mut openings: Vec<(Pos, Direction)> = edge
+            .iter()
+            .copied()
+            .filter(|&(pos, _)| is_open(pos))
+            .collect();
+        if openings.is_empty() {
+            let candidates: Vec<(Pos, Direction)> = edge
+                .into_iter()
+                .filter(|&(pos, inward)| {
+                    !self.on_border(pos)
+                        && pos
+                            .step(inward.reverse(), self.get_size())
+                            .is_some_and(is_open)
+                })
+                .collect();
+            let &opening = candidates
+                .choose(rng)
+                .ok_or_else(|| MazeError::InvalidOption {
+                    name: "region",
+                    reason: "no corridor runs along it to connect to".to_string(),
+                })?;
+            openings.push(opening);
+        }
+
+        for y in inner.y..inner.y + inner.height {
+            for x in inner.x..inner.x + inner.width {
+                self.set(x, y, CellType::Wall);
+                self.labels.remove(&Pos { x, y });
+            }
+        }
+        let mask = MazeMask::from_fn(self.width, self.height, |x, y| inner.contains(Pos { x, y }));
+        let start = Pos::new(inner.x, inner.y);
+        self.set(start.x, start.y, CellType::Path);
+        self.generate_from(start, Some(&mask), rng, &mut |_| {});
+
+        // Corridors end across from a corridor cell, which is always carved;
+        // anything else gets the cell behind it opened
+        for (pos, inward) in openings {
+            if self.get(pos.x, pos.y) == CellType::Wall {
+                self.set(pos.x, pos.y, CellType::Path);
+            }
+            let inside = pos.step(inward, self.get_size()).unwrap();
+            if self.get(inside.x, inside.y) == CellType::Wall {
+                self.set(inside.x, inside.y, CellType::Path);
+            }
+        }
+        Ok(())
+    }
+
+    /// Makes sure the room and a way to the exit are inside `mask`.
+    fn check_mask(&self, mask: &MazeMask) -> Result<(), MazeError> {
+        self.check_room_size()?;
+        if let Some(pos) = self
+            .room
+            .rect()
+            .positions()
+            .find(|&pos| !mask.contains(pos))
+        {
+            return Err(MazeError::InvalidOption {
+                name: "mask",
+                reason: format!("the center room cell {} is outside of it", pos),
+            });
+        }
+        if self.exit_sides(Some(mask)).is_empty() {
+            return Err(MazeError::InvalidOption {
+                name: "mask",
+                reason: match self.exit_type {
+                    ExitLocation::Random | ExitLocation::Farthest => {
+                        "no side has room for the exit".to_string()
+                    }
+                    ref side => format!(
+                        "the exit at {} is outside of it or cut off from the room",
+                        self.side_middle(side)
+                    ),
+                },
+            });
+        }
+        Ok(())
+    }
+
+    /// Number of cells inside `mask` corridors could run through, but that
+    /// are cut off from the room
+    fn cut_off_cells(&self, mask: &MazeMask) -> usize {
+        let reachable = self.corridor_reach(mask);
+        (1..self.height - 1)
+            .step_by(2)
+            .flat_map(|y| (1..self.width - 1).step_by(2).map(move |x| Pos { x, y }))
+            .filter(|pos| mask.contains(*pos) && !reachable.contains(pos))
+            .count()
+    }
+
+    /// The narrow maze's cells that are completely inside `mask`
+    fn narrow_mask(&self, mask: &MazeMask) -> MazeMask {
+        let (width, height) = self.narrow_size();
+        MazeMask::from_fn(width, height, |x, y| {
+            self.block(Pos { x, y }).all(|pos| mask.contains(pos))
+        })
+    }
+
+    /// The cells corridors can reach from the start within `mask`
+    fn corridor_reach(&self, mask: &MazeMask) -> HashSet<Pos> {
+        let mut reached = HashSet::from([self.start_pos()]);
+        let mut stack = vec![self.start_pos()];
+        while let Some(pos) = stack.pop() {
+            for (next, _) in self.corridor_steps(pos, Some(mask)) {
+                if reached.insert(next) {
+                    stack.push(next);
+                }
+            }
+        }
+        reached
+    }
+
+    /// The sides the exit can go on: the one of `exit_type`, or any for a
+    /// random exit, as long as the exit is within `mask` and corridors can
+    /// reach the cell inside of it.
+    fn exit_sides(&self, mask: Option<&MazeMask>) -> Vec<ExitLocation> {
+        let reachable = match (mask, &self.exit_type) {
+            (Some(mask), _) => Some(self.corridor_reach(mask)),
+            // The middle of a side is always across from a corridor, any
+            // other cell only every other one
+            (None, ExitLocation::Custom(_)) => {
+                Some(self.corridor_reach(&MazeMask::from_fn(self.width, self.height, |_, _| true)))
+            }
+            (None, _) => None,
+        };
+        let sides = match &self.exit_type {
+            ExitLocation::Random | ExitLocation::Farthest => SIDES.to_vec(),
+            side => vec![side.clone()],
+        };
+        sides
+            .into_iter()
+            .filter(|side| {
+                let exit = self.side_middle(side);
+                let Some((_, inner)) = self.exit_entry(exit) else {
+                    return false;
+                };
+                mask.is_none_or(|mask| mask.contains(exit))
+                    && reachable
+                        .as_ref()
+                        .is_none_or(|reachable| reachable.contains(&inner))
+            })
+            .collect()
+    }
+
+    fn generate_within<R: Rng + ?Sized>(
+        &mut self,
+        mask: Option<&MazeMask>,
+        rng: &mut R,
+        mut observer: impl FnMut(MazeEvent),
+    ) {
+        if self.corridor_width > 1 {
+            // Generate with narrow corridors, then widen them
+            let mut narrow = self.narrow();
+            let narrow_mask = mask.map(|mask| self.narrow_mask(mask));
+            let mut events = Vec::new();
+            let record: &mut dyn FnMut(MazeEvent) = &mut |event| events.push(event);
+            narrow.generate_within(narrow_mask.as_ref(), rng, record);
+            for event in events {
+                for event in self.widen_event(event) {
+                    self.apply(event);
+                    observer(event);
+                }
+            }
+            return;
+        }
+        let start = self.carve_room(mask, rng, &mut observer);
+        self.generate_from(start, mask, rng, &mut observer);
+
+        // After maze generation, remove some walls to create multiple paths
+        let wall_removal_count = self.wall_removal_count();
+        log::info!("Removing {} walls", wall_removal_count);
+        for _ in 0..wall_removal_count {
+            if let Some(wall) = self.remove_random_wall(mask, rng) {
+                observer(MazeEvent::RemoveWall(wall));
+            }
+        }
+
+        if self.exit_type == ExitLocation::Farthest
+            && let Some(exit_pos) = self.place_farthest_exit(mask)
+        {
+            observer(MazeEvent::PlaceExit(exit_pos));
+        }
+    }
+
+    /// Generates the maze like `generate_observed` followed by
+    /// `place_artifacts_observed` with `artifacts` as the fill ratio, but
+    /// one event per call to `next`, e.g. a few for every frame of an
+    /// animation. The maze changes a step of the generator at a time, which
+    /// can be several events, so an iterator dropped early leaves it at most
+    /// one step ahead of the events handed out: partly carved, but with all
+    /// of its carved cells connected to the room.
+    pub fn generation_steps<'a, R: Rng + ?Sized>(
+        &'a mut self,
+        artifacts: f32,
+        rng: &'a mut R,
+    ) -> GenerationSteps<'a, R> {
+        GenerationSteps {
+            maze: self,
+            rng,
+            state: GenerationState::new(Some(artifacts)),
+        }
+    }
+
+    /// Carves the center room and puts the start in its middle and, unless
+    /// it goes farthest from the start, the exit on its side. Returns the
+    /// start.
+    fn carve_room<R: Rng + ?Sized>(
+        &mut self,
+        mask: Option<&MazeMask>,
+        rng: &mut R,
+        observer: &mut impl FnMut(MazeEvent),
+    ) -> Pos {
+        let center_x = self.width / 2;
+        let center_y = self.height / 2;
+        let start = Pos {
+            x: center_x,
+            y: center_y,
+        };
+
+        // Create center room
+        for pos in self.room.rect().positions() {
+            self.set(pos.x, pos.y, CellType::Path);
+            observer(MazeEvent::CarvePath(pos));
+        }
+        // Marked in the cells, so saved mazes keep it and `validate` can
+        // check there is exactly one. It only changes this one cell, no
+        // randomness is drawn for it.
+        self.set(start.x, start.y, CellType::Start);
+        observer(MazeEvent::PlaceStart(start));
+
+        // Determine exit position based on exit_type. The farthest exit is
+        // only known once the corridors are carved.
+        if self.exit_type != ExitLocation::Farthest {
+            let sides = self.exit_sides(mask);
+            let exit_pos = match &self.exit_type {
+                ExitLocation::Random => {
+                    // Random exit if none specified
+                    self.side_middle(&sides[rng.random_range(0..sides.len())])
+                }
+                side => self.side_middle(side),
+            };
+            self.set(exit_pos.x, exit_pos.y, CellType::Exit);
+            observer(MazeEvent::PlaceExit(exit_pos));
+        }
+        start
+    }
+
+    /// How many walls are removed after carving to create multiple paths
+    fn wall_removal_count(&self) -> usize {
+        match self.braid {
+            Some(braid) => (braid * self.dead_ends().len() as f32).round() as usize,
+            // Adjust this value to control how many walls to remove
+            None => (self.width + self.height) / 8,
+        }
+    }
+
+    /// Opens a random wall inside `mask` between two path cells across from
+    /// each other, if there is one, and returns it.
+    fn remove_random_wall<R: Rng + ?Sized>(
+        &mut self,
+        mask: Option<&MazeMask>,
+        rng: &mut R,
+    ) -> Option<Pos> {
+        let inside = |pos: Pos| mask.is_none_or(|mask| mask.contains(pos));
+        // Find walls that are not on the edge and are surrounded by exactly two path cells
+        let candidate_walls = self.collect_rows(1..self.height - 1, |y| {
+            let mut candidate_walls = Vec::new();
+            for x in 1..self.width - 1 {
+                if self.get(x, y) != CellType::Wall || !inside(Pos { x, y }) {
+                    continue;
+                }
+                let adjacent_paths = [(x + 1, y), (x - 1, y), (x, y + 1), (x, y - 1)]
+                    .iter()
+                    .filter(|&&(ax, ay)| self.get(ax, ay) == CellType::Path)
+                    .count();
+
+                // If exactly two adjacent cells are paths and they're not diagonally opposite
+                if adjacent_paths != 2 {
+                    continue;
+                }
+                // Check that the paths aren't diagonally opposite
+                let has_horizontal_pair =
+                    self.get(x + 1, y) == CellType::Path && self.get(x - 1, y) == CellType::Path;
+                let has_vertical_pair =
+                    self.get(x, y + 1) == CellType::Path && self.get(x, y - 1) == CellType::Path;
+                // Only add wall if the paths are either both horizontal or both vertical
+                if has_horizontal_pair || has_vertical_pair {
+                    candidate_walls.push((x, y));
+                }
+            }
+            candidate_walls
+        });
+        // Remove a random wall from candidates
+        let &(x, y) = candidate_walls.choose(rng)?;
+        self.set(x, y, CellType::Path);
+        Some(Pos { x, y })
+    }
+
+    /// Whether the shortest path has at least `min_length` cells, after
+    /// moving a random exit to the middle of another side if it is too
+    /// short where it is.
+    fn lengthen_solution(&mut self, min_length: usize) -> bool {
+        let long_enough = |maze: &Maze| {
+            maze.shortest_path()
+                .is_some_and(|path| path.len() >= min_length)
+        };
+        if long_enough(self) {
+            return true;
+        }
+        let Some(current) = self.exit_pos() else {
+            return false;
+        };
+        if self.exit_type != ExitLocation::Random || self.corridor_width > 1 {
+            return false;
+        }
+        self.set(current.x, current.y, CellType::Wall);
+        for side in &SIDES {
+            let exit = self.side_middle(side);
+            if exit == current {
+                continue;
+            }
+            self.set(exit.x, exit.y, CellType::Exit);
+            if long_enough(self) {
+                return true;
+            }
+            self.set(exit.x, exit.y, CellType::Wall);
+        }
+        self.set(current.x, current.y, CellType::Exit);
+        false
+    }
+
+    /// Puts the exit on the border cell within `mask` that is the longest
+    /// walk from the start, replacing the current exit. With narrow
+    /// corridors only cells across from a corridor are considered. Ties go
+    /// to the first cell in row-major order.
+    fn place_farthest_exit(&mut self, mask: Option<&MazeMask>) -> Option<Pos> {
+        if let Some(exit) = self.exit_pos() {
+            self.set(exit.x, exit.y, CellType::Wall);
+        }
+        let distances = self.distance_map(self.start_pos());
+        let exit = (0..self.height)
+            .flat_map(|y| (0..self.width).map(move |x| Pos { x, y }))
+            .filter(|&pos| self.on_border(pos) && mask.is_none_or(|mask| mask.contains(pos)))
+            .filter(|pos| self.corridor_width > 1 || (pos.x % 2 == 1) != (pos.y % 2 == 1))
+            .filter_map(|pos| {
+                self.open_neighbors(pos)
+                    .filter_map(|next| distances[next.y * self.width + next.x])
+                    .max()
+                    .map(|distance| (distance, Reverse(pos)))
+            })
+            .max()
+            .map(|(_, Reverse(pos))| pos)?;
+        self.set(exit.x, exit.y, CellType::Exit);
+        Some(exit)
+    }
+
+    /// The cells two steps away from `pos` inside the border and `mask`,
+    /// with the wall in between, that the generator can carve to.
+    fn corridor_steps<'a>(
+        &'a self,
+        pos: Pos,
+        mask: Option<&'a MazeMask>,
+    ) -> impl Iterator<Item = (Pos, Pos)> + 'a {
+        // Right, left, down, up: the order mazes from existing seeds were
+        // generated with
+        [
+            Direction::East,
+            Direction::West,
+            Direction::South,
+            Direction::North,
+        ]
+        .into_iter()
+        .filter_map(move |direction| {
+            let wall = pos.step(direction, self.get_size())?;
+            let next = wall.step(direction, self.get_size())?;
+            Some((next, wall))
+        })
+        .filter(|(next, _)| {
+            next.x > 0 && next.x < self.width - 1 && next.y > 0 && next.y < self.height - 1
+        })
+        .filter(move |(next, wall)| {
+            mask.is_none_or(|mask| mask.contains(*next) && mask.contains(*wall))
+        })
+    }
+
+    /// This code implements a Randomized Depth-First Search (DFS)
+    /// maze generation algorithm a.k.a. backtracking algorithm.
+    fn generate_from<R: Rng + ?Sized>(
+        &mut self,
+        start: Pos,
+        mask: Option<&MazeMask>,
+        rng: &mut R,
+        observer: &mut impl FnMut(MazeEvent),
+    ) {
+        let mut stack = vec![start];
+        let mut visited = self.uncarvable(start);
+
+        while self.carve_step(&mut stack, &mut visited, mask, rng, observer) {}
+    }
+
+    /// The cells a search from `start` begins with as visited: just `start`,
+    /// or if it is in the center room the room and the ring of cells around
+    /// it. Corridors then only reach the room where `open_room` carves
+    /// through the ring, which keeps them from running along its sides and
+    /// looping through it.
+    fn uncarvable(&self, start: Pos) -> HashSet<Pos> {
+        if !self.in_center_room(start) {
+            return HashSet::from([start]);
+        }
+        let size = self.get_size();
+        self.room
+            .rect()
+            .positions()
+            .flat_map(|pos| {
+                Direction::ALL
+                    .into_iter()
+                    .filter_map(move |direction| pos.step(direction, size))
+                    .chain([pos])
+            })
+            .collect()
+    }
+
+    /// The ways out of the center room that the generator can carve, each
+    /// being the cells from the ring around the room to the first corridor
+    /// cell. Where the ring runs along a corridor that is a single cell of
+    /// the ring, otherwise the ring cell is the wall in front of it.
+    fn room_openings<'a>(
+        &'a self,
+        mask: Option<&'a MazeMask>,
+    ) -> impl Iterator<Item = Vec<Pos>> + 'a {
+        let size = self.get_size();
+        self.room
+            .rect()
+            .positions()
+            .flat_map(|pos| {
+                // Same order as `corridor_steps`
+                [
+                    Direction::East,
+                    Direction::West,
+                    Direction::South,
+                    Direction::North,
+                ]
+                .map(|direction| (pos, direction))
+            })
+            .filter_map(move |(pos, direction)| {
+                let ring = pos
+                    .step(direction, size)
+                    .filter(|&ring| !self.in_center_room(ring))?;
+                let across = match direction {
+                    Direction::East | Direction::West => ring.y,
+                    Direction::North | Direction::South => ring.x,
+                };
+                if across % 2 == 0 {
+                    return None;
+                }
+                if ring.x % 2 == 1 && ring.y % 2 == 1 {
+                    Some(vec![ring])
+                } else {
+                    Some(vec![ring, ring.step(direction, size)?])
+                }
+            })
+            .filter(move |cells| {
+                let next = cells[cells.len() - 1];
+                next.x > 0
+                    && next.x < self.width - 1
+                    && next.y > 0
+                    && next.y < self.height - 1
+                    && self.get(next.x, next.y) == CellType::Wall
+                    && mask.is_none_or(|mask| cells.iter().all(|pos| mask.contains(*pos)))
+            })
+    }
+
+    /// The step of `carve_step` from the center room: carves a random one of
+    /// its openings and continues from the corridor cell behind it, or
+    /// leaves the room once all of them are taken.
+    fn open_room<R: Rng + ?Sized>(
+        &mut self,
+        room: Pos,
+        stack: &mut Vec<Pos>,
+        visited: &mut HashSet<Pos>,
+        mask: Option<&MazeMask>,
+        rng: &mut R,
+        observer: &mut impl FnMut(MazeEvent),
+    ) {
+        let openings = self.room_openings(mask).collect::<Vec<_>>();
+        let Some(cells) = openings.choose(rng) else {
+            return;
+        };
+        for &pos in cells {
+            self.set(pos.x, pos.y, CellType::Path);
+            observer(MazeEvent::CarvePath(pos));
+        }
+        let next = cells[cells.len() - 1];
+        visited.insert(next);
+        stack.push(room);
+        stack.push(next);
+    }
+
+    /// One step of `generate_from`: carves from the cell on top of `stack`
+    /// to a random unvisited cell next to it, tunnels from it or backs up.
+    /// Returns false once the stack is empty and the maze is carved.
+    fn carve_step<R: Rng + ?Sized>(
+        &mut self,
+        stack: &mut Vec<Pos>,
+        visited: &mut HashSet<Pos>,
+        mask: Option<&MazeMask>,
+        rng: &mut R,
+        observer: &mut impl FnMut(MazeEvent),
+    ) -> bool {
+        let Some(pos) = stack.pop() else {
+            return false;
+        };
+        if self.in_center_room(pos) {
+            self.open_room(pos, stack, visited, mask, rng, observer);
+            return true;
+        }
+        let valid_directions = self
+            .corridor_steps(pos, mask)
+            .filter(|(next, wall)| !visited.contains(next) && !visited.contains(wall))
+            .collect::<Vec<_>>();
+
+        if valid_directions.is_empty() {
+            if self.weave > 0.0
+                && let Some(landing) = self.tunnel(pos, visited, mask, rng, observer)
+            {
+                visited.insert(landing);
+                stack.push(landing);
+            }
+        } else {
+            stack.push(pos);
+
+            let (next, wall) = valid_directions.choose(rng).unwrap();
+
+            // Carve a path through the wall
+            self.set(wall.x, wall.y, CellType::Path);
+            self.set(next.x, next.y, CellType::Path);
+            observer(MazeEvent::CarvePath(*wall));
+            observer(MazeEvent::CarvePath(*next));
+
+            visited.insert(*next);
+            stack.push(*next);
+        }
+        true
+    }
+
+    /// Concatenates `row(y)` for all `rows`, in order. With the `parallel`
+    /// feature the rows are scanned on the rayon thread pool, which gives the
+    /// same result.
+    fn collect_rows<T: Send>(
+        &self,
+        rows: std::ops::Range<usize>,
+        row: impl Fn(usize) -> Vec<T> + Sync + Send,
+    ) -> Vec<T> {
+        #[cfg(feature = "parallel")]
+        {
+            rows.into_par_iter().flat_map_iter(row).collect()
+        }
+        #[cfg(not(feature = "parallel"))]
+        rows.flat_map(row).collect()
+    }
+
+    /// Generates the maze in chunks of about `chunk_size` cells square on
+    /// rayon workers and stitches them together with `tile_with_rng`,
+    /// opening a few random passages in each shared wall. Each chunk gets its
+    /// own seed drawn from `rng` up front, so the result only depends on
+    /// `rng`, not on how the threads are scheduled.
+    ///
+    /// The maze grows to fit whole chunks, and the chunks have to be big
+    /// enough for the room. Only works with corridors one cell wide.
+    #[cfg(feature = "parallel")]
+    pub fn generate_parallel_with_rng<R: Rng + ?Sized>(
+        &mut self,
+        chunk_size: usize,
+        rng: &mut R,
+    ) -> Result<(), MazeError> {
+        if self.corridor_width > 1 {
+            return Err(MazeError::InvalidOption {
+                name: "chunk size",
+                reason: "chunks need corridors one cell wide".to_string(),
+            });
+        }
+        let chunk_size = constrain_dimension!(chunk_size);
+        let columns = (self.width - 1).div_ceil(chunk_size - 1);
+        let rows = (self.height - 1).div_ceil(chunk_size - 1);
+        let mut chunk = Maze::new(chunk_size, chunk_size, self.room_size, self.exit_side());
+        chunk.weave = self.weave;
+        chunk.braid = self.braid;
+        chunk.check_room_size()?;
+        let seeds: Vec<u64> = (0..rows * columns).map(|_| rng.random()).collect();
+
+        let started = std::time::Instant::now();
+        let chunks: Vec<Maze> = seeds
+            .par_iter()
+            .map(|&seed| {
+                let mut chunk = chunk.clone();
+                chunk.generate_with_rng(&mut StdRng::seed_from_u64(seed));
+                chunk
+            })
+            .collect();
+        log::info!(
+            "Generated {} chunks on {} threads in {:.2?}",
+            chunks.len(),
+            rayon::current_num_threads(),
+            started.elapsed()
+        );
+
+        let grid: Vec<Vec<Maze>> = chunks.chunks(columns).map(<[Maze]>::to_vec).collect();
+        let connect = ConnectStrategy::Random((chunk_size / 32).max(1));
+        *self = Maze::tile_with_rng(&grid, connect, rng)?;
+        log::info!("Stitched the chunks in {:.2?}", started.elapsed());
+        Ok(())
+    }
+
+    /// With a chance of `weave`, carves a corridor from the dead end at `pos`
+    /// under a straight perpendicular corridor next to it, to an unvisited
+    /// cell on the far side, and turns the crossing into a bridge. Returns
+    /// the cell the corridor ends in.
+    fn tunnel<R: Rng + ?Sized>(
+        &mut self,
+        pos: Pos,
+        visited: &HashSet<Pos>,
+        mask: Option<&MazeMask>,
+        rng: &mut R,
+        observer: &mut impl FnMut(MazeEvent),
+    ) -> Option<Pos> {
+        let candidates: Vec<(Direction, [Pos; 4])> = Direction::ALL
+            .into_iter()
+            .filter_map(|direction| {
+                let mut cells = [pos; 4];
+                let mut current = pos;
+                for cell in cells.iter_mut() {
+                    current = current.step(direction, self.get_size())?;
+                    *cell = current;
+                }
+                Some((direction, cells))
+            })
+            .filter(|(direction, [wall, crossing, far_wall, landing])| {
+                let across = [direction.turn_left(), direction.turn_right()];
+                landing.x > 0
+                    && landing.x < self.width - 1
+                    && landing.y > 0
+                    && landing.y < self.height - 1
+                    && ![wall, far_wall, landing]
+                        .iter()
+                        .any(|pos| visited.contains(*pos))
+                    && mask.is_none_or(|mask| {
+                        [wall, crossing, far_wall, landing]
+                            .iter()
+                            .all(|pos| mask.contains(**pos))
+                    })
+                    && self.get(wall.x, wall.y) == CellType::Wall
+                    && self.get(far_wall.x, far_wall.y) == CellType::Wall
+                    && self.get(crossing.x, crossing.y) == CellType::Path
+                    && !self.in_center_room(*crossing)
+                    && across.iter().all(|&side| {
+                        crossing
+                            .step(side, self.get_size())
+                            .is_some_and(|side| self.get(side.x, side.y) == CellType::Path)
+                    })
+            })
+            .collect();
+        if candidates.is_empty() || !rng.random_bool(self.weave as f64) {
+            return None;
+        }
+        let (direction, [wall, crossing, far_wall, landing]) = *candidates.choose(rng).unwrap();
+        let bridge = match direction {
+            Direction::East | Direction::West => CellType::BridgeNorthSouth,
+            Direction::North | Direction::South => CellType::BridgeEastWest,
+        };
+        self.set(wall.x, wall.y, CellType::Path);
+        observer(MazeEvent::CarvePath(wall));
+        self.set(crossing.x, crossing.y, bridge);
+        observer(MazeEvent::PlaceBridge(crossing, bridge));
+        for pos in [far_wall, landing] {
+            self.set(pos.x, pos.y, CellType::Path);
+            observer(MazeEvent::CarvePath(pos));
+        }
+        Some(landing)
+    }
+
+    /// Places rewards and dangers on up to `fill_ratio` of the path cells
+    /// outside the center room, using the thread-local RNG.
+    pub fn place_artifacts(&mut self, fill_ratio: f32) {
+        self.place_artifacts_with_rng(fill_ratio, &mut rand::rng());
+    }
+
+    /// Same as `place_artifacts`, but draws all randomness from `rng`.
+    pub fn place_artifacts_with_rng<R: Rng + ?Sized>(&mut self, fill_ratio: f32, rng: &mut R) {
+        self.place_artifacts_observed(fill_ratio, rng, |_| {});
+    }
+
+    /// Same as `place_artifacts_with_rng`, but reports every placed artifact
+    /// to `observer`.
+    pub fn place_artifacts_observed<R: Rng + ?Sized>(
+        &mut self,
+        fill_ratio: f32,
+        rng: &mut R,
+        mut observer: impl FnMut(MazeEvent),
+    ) {
+        // Calculate how many cells should have artifacts
+        let path_cells = self.cells.iter().filter(|&&c| c == CellType::Path).count();
+        let artifacts_count = (path_cells as f32 * fill_ratio) as usize;
+
+        // Collect all valid positions
+        let mut valid_positions: Vec<Pos> = self.collect_rows(0..self.height, |y| {
+            (0..self.width)
+                .map(|x| Pos { x, y })
+                .filter(|&pos| {
+                    self.get(pos.x, pos.y) == CellType::Path && !self.in_center_room(pos)
+                })
+                .collect()
+        });
+
+        // Shuffle positions
+        valid_positions.shuffle(rng);
+
+        // Place artifacts
+        let reward_ratio = 0.4; // 40% rewards, 60% dangers
+        let reward_count = (artifacts_count as f32 * reward_ratio) as usize;
+        let danger_count = artifacts_count - reward_count;
+
+        // Track occupied positions and their adjacent cells
+        let mut occupied_and_adjacent = HashSet::new();
+
+        // Place rewards first
+        let mut reward_placed = 0;
+        for pos in &valid_positions {
+            if reward_placed >= reward_count {
+                break;
+            }
+
+            if !occupied_and_adjacent.contains(pos) {
+                // Place the reward
+                let reward = *REWARDS.choose(rng).unwrap();
+                self.set(pos.x, pos.y, reward);
+                observer(MazeEvent::PlaceArtifact(*pos, reward));
+                reward_placed += 1;
+
+                // Mark this position and adjacent positions as occupied
+                occupied_and_adjacent.insert(*pos);
+
+                // Mark adjacent cells as unavailable
+                occupied_and_adjacent.extend(pos.neighbors(self.get_size()));
+            }
+        }
+
+        // Place dangers
+        let mut danger_placed = 0;
+        for pos in &valid_positions {
+            if danger_placed >= danger_count {
+                break;
+            }
+
+            if !occupied_and_adjacent.contains(pos) {
+                // Place the danger
+                let danger = *DANGERS.choose(rng).unwrap();
+                self.set(pos.x, pos.y, danger);
+                observer(MazeEvent::PlaceArtifact(*pos, danger));
+                danger_placed += 1;
+
+                // Mark this position and adjacent positions as occupied
+                occupied_and_adjacent.insert(*pos);
+
+                // Mark adjacent cells as unavailable
+                occupied_and_adjacent.extend(pos.neighbors(self.get_size()));
+            }
+        }
+    }
+
+    /// Puts a reward on each dead end with probability `reward_prob`, else
+    /// a danger with probability `danger_prob`, regardless of how many
+    /// artifacts `place_artifacts` put elsewhere. Only plain path cells are
+    /// decorated, so nothing that already holds an artifact, and dead ends
+    /// in the center room or right in front of the exit are left alone.
+    /// Returns the decorated positions.
+    pub fn decorate_dead_ends<R: Rng + ?Sized>(
+        &mut self,
+        reward_prob: f32,
+        danger_prob: f32,
+        rng: &mut R,
+    ) -> Vec<Pos> {
+        let exit = self.exit_pos();
+        let candidates: Vec<Pos> = self
+            .dead_ends()
+            .into_iter()
+            .filter(|&pos| {
+                self.get(pos.x, pos.y) == CellType::Path
+                    && !self.in_center_room(pos)
+                    && exit.is_none_or(|exit| pos.manhattan(exit) > 1)
+            })
+            .collect();
+        let mut decorated = Vec::new();
+        for pos in candidates {
+            let roll: f32 = rng.random();
+            let artifact = if roll < reward_prob {
+                *REWARDS.choose(rng).unwrap()
+            } else if roll < reward_prob + danger_prob {
+                *DANGERS.choose(rng).unwrap()
+            } else {
+                continue;
+            };
+            self.set(pos.x, pos.y, artifact);
+            decorated.push(pos);
+        }
+        decorated
+    }
+
+    /// Marks `n` extra starts for players racing each other to the exit:
+    /// corridor cells or dead ends whose distances from the exit differ by
+    /// at most `tolerance` steps, spread out as far from each other as
+    /// possible. The start in the center room stays the one solvers use.
+    /// Fails if there aren't `n` such cells.
+    pub fn fair_starts(&mut self, n: usize, tolerance: usize) -> Result<Vec<Pos>, MazeError> {
+        if n == 0 {
+            return Ok(Vec::new());
+        }
+        let exit = self.exit_pos().ok_or(MazeError::Unsolvable)?;
+        let from_exit = self.distance_map(exit);
+        let ways = |pos: Pos| {
+            Direction::ALL
+                .into_iter()
+                .filter(|&direction| self.step(pos, direction).is_some())
+                .count()
+        };
+        let mut candidates: Vec<(usize, Pos)> = self
+            .iter_cells()
+            .filter(|&(pos, cell)| {
+                cell == CellType::Path && !self.in_center_room(pos) && ways(pos) <= 2
+            })
+            .filter_map(|(pos, _)| Some((from_exit[pos.y * self.width + pos.x]?, pos)))
+            .collect();
+        candidates.sort();
+
+        // The candidates at most `tolerance` farther from the exit than each
+        // distance there is, if they are enough
+        let mut bands = Vec::new();
+        let mut end = 0;
+        for start in 0..candidates.len() {
+            if start > 0 && candidates[start].0 == candidates[start - 1].0 {
+                continue;
+            }
+            while end < candidates.len() && candidates[end].0 <= candidates[start].0 + tolerance {
+                end += 1;
+            }
+            if end - start >= n {
+                bands.push(&candidates[start..end]);
+            }
+        }
+        if bands.is_empty() {
+            return Err(MazeError::InvalidOption {
+                name: "number of starts",
+                reason: format!(
+                    "there aren't {} corridor cells within {} steps of the same distance from the exit",
+                    n, tolerance
+                ),
+            });
+        }
+        // Farther bands win ties, they make for longer races
+        let mut best: Option<(usize, Vec<Pos>)> = None;
+        for band in bands.iter().step_by(bands.len().div_ceil(FAIR_START_BANDS)) {
+            let (spread, starts) = self.spread_out(band, n);
+            if best
+                .as_ref()
+                .is_none_or(|(best_spread, _)| spread >= *best_spread)
+            {
+                best = Some((spread, starts));
+            }
+        }
+        let (_, starts) = best.unwrap();
+        for &pos in &starts {
+            self.set(pos.x, pos.y, CellType::Start);
+        }
+        Ok(starts)
+    }
+
+    /// Picks `n` of `cells` far apart from each other: the one farthest from
+    /// the exit first, then over and over the one farthest from all picked
+    /// so far. Returns the smallest number of steps between two picked cells
+    /// along with them.
+    fn spread_out(&self, cells: &[(usize, Pos)], n: usize) -> (usize, Vec<Pos>) {
+        let mut picked = vec![cells[cells.len() - 1].1];
+        let mut nearest = vec![usize::MAX; cells.len()];
+        let mut spread = usize::MAX;
+        while picked.len() < n {
+            let distances = self.distance_map(*picked.last().unwrap());
+            for (nearest, &(_, pos)) in nearest.iter_mut().zip(cells) {
+                let distance = distances[pos.y * self.width + pos.x].unwrap_or(usize::MAX);
+                *nearest = (*nearest).min(distance);
+            }
+            let (index, &distance) = nearest
+                .iter()
+                .enumerate()
+                .max_by_key(|&(_, &distance)| distance)
+                .unwrap();
+            spread = spread.min(distance);
+            picked.push(cells[index].1);
+        }
+        (spread, picked)
+    }
+
+    /// Puts up to `count` dangers where they can't be avoided: in the
+    /// corridors every way from the start to the exit has to take. A maze
+    /// with loops may have none, then the corridors the most shortest routes
+    /// go through are guarded instead, see `corridor_importance`. Guards are
+    /// spread over the corridors, only on plain path cells and never next to
+    /// each other. Returns their positions.
+    pub fn place_guards<R: Rng + ?Sized>(&mut self, count: usize, rng: &mut R) -> Vec<Pos> {
+        let graph = self.junction_graph();
+        let route = match (
+            graph.index.get(&self.start_pos()),
+            self.exit_pos().and_then(|exit| graph.index.get(&exit)),
+        ) {
+            (Some(&start), Some(&exit)) => graph
+                .shortest_route(start, exit, &HashSet::new(), &HashSet::new())
+                .map(|(_, route)| route)
+                .unwrap_or_default(),
+            _ => Vec::new(),
+        };
+        // Cells between the two junctions a corridor connects
+        let inside = |cells: &[Pos]| -> Vec<Pos> {
+            cells[1..cells.len() - 1]
+                .iter()
+                .copied()
+                .filter(|&pos| {
+                    self.get(pos.x, pos.y) == CellType::Path && !self.in_center_room(pos)
+                })
+                .collect()
+        };
+        let (_, bridges) = graph.cut_points();
+        let mut groups: Vec<Vec<Pos>> = route
+            .iter()
+            .filter(|&corridor| bridges.contains(corridor))
+            .map(|&corridor| inside(&graph.corridors[corridor].cells))
+            .filter(|cells| !cells.is_empty())
+            .collect();
+        groups.shuffle(rng);
+        if groups.is_empty() {
+            groups = self
+                .corridor_importance_with_rng(GUARD_SAMPLES, rng)
+                .into_iter()
+                .filter(|&(_, importance)| importance > 0.0)
+                .map(|(cells, _)| inside(&cells))
+                .filter(|cells| !cells.is_empty())
+                .collect();
+        }
+        for cells in &mut groups {
+            cells.shuffle(rng);
+        }
+
+        // One guard per corridor in turn, busiest corridors first when
+        // there are no unavoidable ones
+        let mut guards = Vec::new();
+        let mut occupied_and_adjacent = HashSet::new();
+        while guards.len() < count {
+            let placed = guards.len();
+            for cells in &mut groups {
+                if guards.len() == count {
+                    break;
+                }
+                while let Some(pos) = cells.pop() {
+                    if occupied_and_adjacent.insert(pos) {
+                        occupied_and_adjacent.extend(pos.neighbors(self.get_size()));
+                        guards.push(pos);
+                        break;
+                    }
+                }
+            }
+            if guards.len() == placed {
+                break;
+            }
+        }
+        for &pos in &guards {
+            let danger = *DANGERS.choose(rng).unwrap();
+            self.set(pos.x, pos.y, danger);
+        }
+        guards
+    }
+
+    /// Puts characters from `alphabet`, picked at random by their weights,
+    /// on up to `fill_ratio` of the corridor cells: plain paths with exactly
+    /// two open neighbors, so no junctions, dead ends or artifacts. Replaces
+    /// any labels placed before and returns the new ones. Nothing is placed
+    /// if all weights are zero.
+    pub fn place_labels<R: Rng + ?Sized>(
+        &mut self,
+        alphabet: &[(char, u32)],
+        fill_ratio: f32,
+        rng: &mut R,
+    ) -> HashMap<Pos, char> {
+        let mut corridors: Vec<Pos> = self
+            .iter_cells()
+            .filter(|&(pos, cell)| {
+                cell == CellType::Path
+                    && Direction::ALL
+                        .into_iter()
+                        .filter(|&direction| self.step(pos, direction).is_some())
+                        .count()
+                        == 2
+            })
+            .map(|(pos, _)| pos)
+            .collect();
+        let count = (corridors.len() as f32 * fill_ratio) as usize;
+        corridors.shuffle(rng);
+
+        self.labels.clear();
+        for pos in corridors.into_iter().take(count) {
+            let Ok(&(label, _)) = alphabet.choose_weighted(rng, |&(_, weight)| weight) else {
+                break;
+            };
+            self.labels.insert(pos, label);
+        }
+        self.labels.clone()
+    }
+
+    /// Hides `word` along the shortest path: its letters are spread evenly
+    /// over the cells between start and exit, in order, so walking the way
+    /// out spells it. Other labels on the path are removed, the ones
+    /// elsewhere kept, so filler letters can be placed with `place_labels`
+    /// first. Returns the cells of the letters.
+    pub fn embed_word_on_solution(&mut self, word: &str) -> Result<HashMap<Pos, char>, MazeError> {
+        let path = self.shortest_path().ok_or(MazeError::Unsolvable)?;
+        let inner = &path[1..path.len() - 1];
+        let letters: Vec<char> = word.chars().collect();
+        if letters.len() > inner.len() {
+            return Err(MazeError::InvalidOption {
+                name: "word",
+                reason: format!(
+                    "{} letters don't fit on a way out of {} cells",
+                    letters.len(),
+                    inner.len()
+                ),
+            });
+        }
+        for pos in &path {
+            self.labels.remove(pos);
+        }
+        let placed: HashMap<Pos, char> = letters
+            .iter()
+            .enumerate()
+            .map(|(i, &letter)| {
+                let index = (2 * i + 1) * inner.len() / (2 * letters.len());
+                (inner[index], letter)
+            })
+            .collect();
+        self.labels.extend(&placed);
+        Ok(placed)
+    }
+
+    /// The characters placed with `place_labels`, by cell
+    pub fn labels(&self) -> &HashMap<Pos, char> {
+        &self.labels
+    }
+
+    /// Scores how hard the maze is on a 0–100 scale, where 0 means there is
+    /// no way out. The score is a weighted sum of four components, each
+    /// clamped to 0..=1:
+    ///
+    /// - 55 points: solution length relative to the square root of the
+    ///   number of traversable cells, so larger mazes score higher (ten times
+    ///   the square root scores full points)
+    /// - 25 points: dead-end density (one dead end per four traversable
+    ///   cells scores full points)
+    /// - 5 points: junctions along the solution, i.e. decisions to make
+    ///   (one junction every other step scores full points)
+    /// - 15 points: total weight of the dangers reachable from the start
+    ///   (an average of half a weight point per traversable cell scores
+    ///   full points)
+    ///
+    /// The first two dominate, so loops, which shorten the solution and
+    /// close dead ends, make a maze easier even though they add junctions.
+    pub fn difficulty(&self) -> f32 {
+        let Some(path) = self.solve_instrumented(Solver::Bfs).path else {
+            return 0.0;
+        };
+        let open_neighbors = |pos: Pos| {
+            Direction::ALL
+                .into_iter()
+                .filter(|&direction| self.step(pos, direction).is_some())
+                .count()
+        };
+        let area = self
+            .cells
+            .iter()
+            .filter(|cell| TRAVERSABLE.contains(cell))
+            .count() as f32;
+
+        let length = path.len() as f32 / (10.0 * area.sqrt());
+        let junctions = path.iter().filter(|&&pos| open_neighbors(pos) > 2).count();
+        let decisions = 2.0 * junctions as f32 / path.len() as f32;
+        let dead_end_density = 4.0 * self.dead_ends().len() as f32 / area;
+        let danger: i32 = self
+            .distance_map(self.start_pos())
+            .iter()
+            .enumerate()
+            .filter(|(_, distance)| distance.is_some())
+            .map(|(idx, _)| self.cells[idx])
+            .filter(|cell| DANGERS.contains(cell))
+            .map(|cell| self.weights.get(cell))
+            .sum();
+        let danger = 2.0 * danger as f32 / area;
+
+        55.0 * length.min(1.0)
+            + 25.0 * dead_end_density.min(1.0)
+            + 5.0 * decisions.min(1.0)
+            + 15.0 * danger.min(1.0)
+    }
+
+    /// Regenerates the maze, placing artifacts with `fill_ratio`, until its
+    /// difficulty is within `tolerance` of `target` or `max_attempts` are
+    /// used up. Keeps the candidate closest to the target and returns its
+    /// difficulty.
+    pub fn generate_with_difficulty(
+        &mut self,
+        target: f32,
+        tolerance: f32,
+        max_attempts: usize,
+        fill_ratio: f32,
+    ) -> f32 {
+        self.generate_with_difficulty_with_rng(
+            target,
+            tolerance,
+            max_attempts,
+            fill_ratio,
+            &mut rand::rng(),
+        )
+    }
+
+    /// Same as `generate_with_difficulty`, but draws all randomness from
+    /// `rng`.
+    pub fn generate_with_difficulty_with_rng<R: Rng + ?Sized>(
+        &mut self,
+        target: f32,
+        tolerance: f32,
+        max_attempts: usize,
+        fill_ratio: f32,
+        rng: &mut R,
+    ) -> f32 {
+        let mut best: Option<(Maze, f32)> = None;
+        for _ in 0..max_attempts.max(1) {
+            let mut candidate = Maze::with_corridors(
+                self.width,
+                self.height,
+                self.room_size,
+                self.exit_type.clone(),
+                self.corridor_width,
+            );
+            candidate.weave = self.weave;
+            candidate.braid = self.braid;
+            candidate.generate_with_rng(rng);
+            candidate.place_artifacts_with_rng(fill_ratio, rng);
+            let score = candidate.difficulty();
+            let is_better = best
+                .as_ref()
+                .is_none_or(|(_, best_score)| (score - target).abs() < (best_score - target).abs());
+            if is_better {
+                best = Some((candidate, score));
+            }
+            if (score - target).abs() <= tolerance {
+                break;
+            }
+        }
+        let (maze, score) = best.unwrap();
+        *self = maze;
+        score
+    }
+
+    /// The two open cells farthest apart by walking, and the number of steps
+    /// between them, `None` if there are no open cells. In a perfect maze
+    /// with narrow corridors two breadth-first searches find them: the cell
+    /// farthest from any open cell is one end of a longest walk, and the
+    /// cell farthest from it the other. Otherwise walks are measured from
+    /// every dead end, which can come up short in a maze with loops if
+    /// neither end of the longest walk is a dead end.
+    pub fn longest_shortest_path(&self) -> Option<(Pos, Pos, u32)> {
+        // The steps to the farthest cell from `from`, the first one of them
+        // in row-major order
+        let farthest = |from: Pos| {
+            self.distance_map(from)
+                .into_iter()
+                .enumerate()
+                .filter_map(|(idx, distance)| Some((distance?, Reverse(idx))))
+                .max()
+                .map(|(distance, Reverse(idx))| {
+                    let to = Pos {
+                        x: idx % self.width,
+                        y: idx / self.width,
+                    };
+                    (distance, to)
+                })
+        };
+        let traversable: Vec<Pos> = self
+            .iter_cells()
+            .filter(|(_, cell)| TRAVERSABLE.contains(cell))
+            .map(|(pos, _)| pos)
+            .collect();
+        if self.corridor_width == 1 && self.is_perfect() {
+            let (_, from) = farthest(*traversable.first()?)?;
+            let (distance, to) = farthest(from)?;
+            return Some((from, to, distance as u32));
+        }
+        let dead_ends = self.dead_ends();
+        let sources = if dead_ends.is_empty() {
+            traversable
+        } else {
+            dead_ends
+        };
+        sources
+            .into_iter()
+            .filter_map(|from| farthest(from).map(|(distance, to)| (distance, Reverse(from), to)))
+            .max()
+            .map(|(distance, Reverse(from), to)| (from, to, distance as u32))
+    }
+
+    /// Breadth-first distances from `from` to every cell, indexed like the
+    /// cells (`y * width + x`). Walls and unreachable cells are `None`.
+    pub fn distance_map(&self, from: Pos) -> Vec<Option<usize>> {
+        self.distance_map_from([from])
+    }
+
+    /// Like `distance_map`, but the steps to the nearest of `sources`, all
+    /// searched at once
+    fn distance_map_from(&self, sources: impl IntoIterator<Item = Pos>) -> Vec<Option<usize>> {
+        let mut distances = vec![None; self.cells.len()];
+        let mut queue = std::collections::VecDeque::new();
+        for from in sources {
+            if TRAVERSABLE.contains(&self.get(from.x, from.y))
+                && distances[from.y * self.width + from.x].is_none()
+            {
+                distances[from.y * self.width + from.x] = Some(0);
+                queue.push_back((from, 0));
+            }
+        }
+
+        while let Some((pos, distance)) = queue.pop_front() {
+            for direction in Direction::ALL {
+                if let Some(next) = self.step(pos, direction)
+                    && distances[next.y * self.width + next.x].is_none()
+                {
+                    distances[next.y * self.width + next.x] = Some(distance + 1);
+                    queue.push_back((next, distance + 1));
+                }
+            }
+        }
+
+        distances
+    }
+
+    /// The reward fewest steps away from `from` and how many steps that is,
+    /// `None` if no reward can be reached. Ties go to the one found first.
+    pub fn nearest_reward(&self, from: Pos) -> Option<(Pos, u32)> {
+        self.nearest(from, &REWARDS)
+    }
+
+    /// Like `nearest_reward`, but the nearest danger
+    pub fn nearest_danger(&self, from: Pos) -> Option<(Pos, u32)> {
+        self.nearest(from, &DANGERS)
+    }
+
+    /// Breadth-first search from `from` that stops at the first cell of one
+    /// of the `wanted` types
+    fn nearest(&self, from: Pos, wanted: &[CellType]) -> Option<(Pos, u32)> {
+        if !TRAVERSABLE.contains(&self.get(from.x, from.y)) {
+            return None;
+        }
+        let mut seen = HashSet::from([from]);
+        let mut queue = std::collections::VecDeque::from([(from, 0)]);
+        while let Some((pos, distance)) = queue.pop_front() {
+            if wanted.contains(&self.get(pos.x, pos.y)) {
+                return Some((pos, distance));
+            }
+            for direction in Direction::ALL {
+                if let Some(next) = self.step(pos, direction)
+                    && seen.insert(next)
+                {
+                    queue.push_back((next, distance + 1));
+                }
+            }
+        }
+        None
+    }
+
+    /// Steps between every two of the start, the exit if there is one, and
+    /// the artifacts in the order of `artifacts`, in that order, e.g.
+    /// `matrix[0][1]` from the start to the exit. `u32::MAX` marks points
+    /// that can't reach each other. Takes one search per point, each finding
+    /// its way to all the others at once, rather than one per pair.
+    pub fn artifact_distance_matrix(&self) -> Vec<Vec<u32>> {
+        let points: Vec<Pos> = [self.start_pos()]
+            .into_iter()
+            .chain(self.exit_pos())
+            .chain(self.artifacts().map(|(pos, _)| pos))
+            .collect();
+        self.distance_matrix(&points)
+    }
+
+    /// Steps between every two of `points`, `u32::MAX` if there is no way
+    fn distance_matrix(&self, points: &[Pos]) -> Vec<Vec<u32>> {
+        points
+            .iter()
+            .map(|&from| {
+                let distances = self.distance_map(from);
+                points
+                    .iter()
+                    .map(|to| distances[to.y * self.width + to.x].map_or(u32::MAX, |d| d as u32))
+                    .collect()
+            })
+            .collect()
+    }
+
+    /// The shortest walk from the start that picks up every reward on the
+    /// way to the exit, `None` if there is no exit or some reward can't be
+    /// reached. With more than `EXACT_COLLECTION_REWARDS` rewards it is only
+    /// a short one, which `CollectionRoute::optimal` tells.
+    pub fn full_collection_route(&self) -> Option<CollectionRoute> {
+        let start = self.start_pos();
+        let points: Vec<Pos> = [start, self.exit_pos()?]
+            .into_iter()
+            .chain(
+                self.artifacts()
+                    .filter(|(_, cell)| REWARDS.contains(cell))
+                    .map(|(pos, _)| pos),
+            )
+            .collect();
+        let distances = self.distance_matrix(&points);
+        if distances[0].contains(&u32::MAX) {
+            return None;
+        }
+        let optimal = points.len() - 2 <= EXACT_COLLECTION_REWARDS;
+        let order = if optimal {
+            held_karp_order(&distances)
+        } else {
+            nearest_neighbor_order(&distances)
+        };
+        let mut path = vec![start];
+        let mut steps = 0;
+        for leg in order.windows(2) {
+            steps += distances[leg[0]][leg[1]];
+            let leg_distances = self.distance_map(points[leg[0]]);
+            path.extend(self.walk_back(&leg_distances, points[leg[1]]).skip(1));
+        }
+        Some(CollectionRoute {
+            path: Maze::with_bridges(path),
+            steps,
+            optimal,
+        })
+    }
+
+    /// A shortest way to `to` from where `distances` were measured from,
+    /// found by stepping down the distances from `to`
+    fn walk_back(&self, distances: &[Option<usize>], to: Pos) -> impl Iterator<Item = Pos> {
+        let mut path = vec![to];
+        let mut pos = to;
+        while let Some(distance) = distances[pos.y * self.width + pos.x]
+            && distance > 0
+            && let Some(previous) = Direction::ALL
+                .into_iter()
+                .filter_map(|direction| self.step(pos, direction))
+                .find(|next| distances[next.y * self.width + next.x] == Some(distance - 1))
+        {
+            path.push(previous);
+            pos = previous;
+        }
+        path.into_iter().rev()
+    }
+
+    /// How far the reachable rewards are off the shortest path, `None` if
+    /// there is no path or no reward can be reached
+    fn reward_distances(&self) -> Option<DistanceSummary> {
+        let path = self.shortest_path()?;
+        let distances = self.distance_map_from(path);
+        let mut reward_distances: Vec<u32> = self
+            .artifacts()
+            .filter(|(_, cell)| REWARDS.contains(cell))
+            .filter_map(|(pos, _)| distances[pos.y * self.width + pos.x])
+            .map(|distance| distance as u32)
+            .collect();
+        reward_distances.sort_unstable();
+        let (&min, &max) = (reward_distances.first()?, reward_distances.last()?);
+        Some(DistanceSummary {
+            min,
+            median: reward_distances[(reward_distances.len() - 1) / 2],
+            max,
+        })
+    }
+
+    pub fn solve(&self, solver: Solver) -> Option<Vec<Pos>> {
+        match solver {
+            Solver::Bfs => self.shortest_path(),
+            _ => self.solve_instrumented(solver).path,
+        }
+    }
+
+    /// Runs `solver` and records which cells it looked at. `order` holds the
+    /// cells in the sequence they were expanded, so it can drive an animation
+    /// or a heatmap; `visited` holds every cell the solver touched, in the
+    /// order they were first seen.
+    pub fn solve_instrumented(&self, solver: Solver) -> SolveTrace {
+        self.solve_observed(solver, |_| {})
+    }
+
+    /// Same as `solve_instrumented`, but also reports each step of the solver
+    /// to `observer` as it happens.
+    pub fn solve_observed(
+        &self,
+        solver: Solver,
+        mut observer: impl FnMut(MazeEvent),
+    ) -> SolveTrace {
+        let mut steps = self.solve_steps(solver);
+        steps.by_ref().for_each(&mut observer);
+        steps.trace.unwrap_or_default()
+    }
+
+    /// Runs `solver` one event at a time, the events `solve_observed`
+    /// reports. Once the iterator is used up, its `trace` is what
+    /// `solve_instrumented` returns.
+    pub fn solve_steps(&self, solver: Solver) -> SolveSteps<'_> {
+        let mut pending = std::collections::VecDeque::new();
+        let observer = &mut |event: MazeEvent| pending.push_back(event);
+        let phase = match solver {
+            Solver::Bfs => SearchState::new(self, None, false, observer)
+                .map(|search| SolvePhase::Search(Box::new(search))),
+            Solver::AStar => SearchState::new(self, None, true, observer)
+                .map(|search| SolvePhase::Search(Box::new(search))),
+            Solver::WallFollower(hand) => {
+                Some(SolvePhase::Walk(WallWalk::new(self, hand, observer)))
+            }
+            Solver::DeadEndFill => Some(SolvePhase::Fill(DeadEndFilling::new(self))),
+        };
+        SolveSteps {
+            maze: self,
+            // Without an exit there is nothing to search for
+            trace: phase.is_none().then(SolveTrace::default),
+            phase: phase.unwrap_or(SolvePhase::Done),
+            filled: Vec::new(),
+            pending,
+        }
+    }
+
+    /// Simulates the left- or right-hand rule starting at the center.
+    /// Crosses the room to a cell next to its wall, or without a room walks
+    /// straight until a wall is hit, then keeps that wall on the chosen
+    /// hand. Returns `None` if the exit isn't reached within a step cap,
+    /// which happens when the followed wall is an island inside a loop.
+    pub fn solve_wall_follower(&self, hand: Hand) -> Option<Vec<Pos>> {
+        let walked = Maze::with_bridges(self.walk_wall(hand, &mut |_| {}));
+        self.reached_exit(&walked).then_some(walked)
+    }
+
+    fn reached_exit(&self, walked: &[Pos]) -> bool {
+        walked
+            .last()
+            .is_some_and(|pos| self.get(pos.x, pos.y) == CellType::Exit)
+    }
+
+    /// Returns every step taken by the wall follower, ending either at the
+    /// exit or wherever the step cap ran out.
+    fn walk_wall(&self, hand: Hand, observer: &mut impl FnMut(MazeEvent)) -> Vec<Pos> {
+        let mut walk = WallWalk::new(self, hand, observer);
+        while walk.advance(self, observer) {}
+        walk.path
+    }
+
+    /// Repeatedly fills dead ends (traversable cells with at most one open
+    /// neighbor) until none are left. Returns the cells that remain open,
+    /// i.e. the corridors connecting start and exit plus any loops.
+    pub fn solve_dead_end_fill(&self) -> Vec<Pos> {
+        let (open, _) = self.fill_dead_ends(&mut |_| {});
+        (0..self.height)
+            .flat_map(|y| (0..self.width).map(move |x| Pos { x, y }))
+            .filter(|pos| open[pos.y * self.width + pos.x])
+            .collect()
+    }
+
+    /// Returns the per-cell open flags after filling, and the filled cells
+    /// in the order they were filled.
+    fn fill_dead_ends(&self, observer: &mut impl FnMut(MazeEvent)) -> (Vec<bool>, Vec<Pos>) {
+        let mut filling = DeadEndFilling::new(self);
+        while filling.advance(self, observer) {}
+        (filling.open, filling.filled)
+    }
+
+    /// A shortest way from the start to the exit, found with a breadth-first
+    /// search. The result is kept until a cell changes, so asking again,
+    /// e.g. on every frame of the GUI, is cheap.
+    pub fn shortest_path(&self) -> Option<Vec<Pos>> {
+        self.solution
+            .get_or_init(|| {
+                let started = std::time::Instant::now();
+                let path = self.find_shortest_path();
+                log::debug!("Searched the cells in {:.2?}", started.elapsed());
+                path
+            })
+            .clone()
+    }
+
+    /// A shortest way from the start to the exit, found with Dijkstra's
+    /// algorithm on the junctions and the corridors between them rather
+    /// than cell by cell. It is as long as `shortest_path`, but much faster
+    /// on big mazes with long corridors. When there are several shortest
+    /// ways the two may pick different ones.
+    pub fn shortest_path_via_graph(&self) -> Option<Vec<Pos>> {
+        let started = std::time::Instant::now();
+        let graph = self.junction_graph();
+        let start = *graph.index.get(&self.start_pos())?;
+        let exit = *graph.index.get(&self.exit_pos()?)?;
+        let (_, route) = graph.shortest_route(start, exit, &HashSet::new(), &HashSet::new())?;
+        log::debug!(
+            "Searched {} junctions in {:.2?}",
+            graph.junctions.len(),
+            started.elapsed()
+        );
+        Some(Maze::with_bridges(graph.cells(start, &route)))
+    }
+
+    fn find_shortest_path(&self) -> Option<Vec<Pos>> {
+        let start = self.start_pos();
+        let index = |pos: Pos| pos.y * self.width + pos.x;
+        // The cell each one was reached from, the start from itself
+        let mut previous: Vec<Option<Pos>> = vec![None; self.cells.len()];
+        previous[index(start)] = Some(start);
+        let mut queue = std::collections::VecDeque::from([start]);
+
+        while let Some(pos) = queue.pop_front() {
+            if self.get(pos.x, pos.y) == CellType::Exit {
+                let mut path = vec![pos];
+                while let Some(&last) = path.last()
+                    && last != start
+                {
+                    path.push(previous[index(last)].unwrap());
+                }
+                path.reverse();
+                return Some(Maze::with_bridges(path));
+            }
+            for direction in Direction::ALL {
+                if let Some(next) = self.step(pos, direction)
+                    && previous[index(next)].is_none()
+                {
+                    previous[index(next)] = Some(pos);
+                    queue.push_back(next);
+                }
+            }
+        }
+
+        None
+    }
+
+    /// Distinct shortest ways from the start to the exit, at most `limit`
+    /// of them. A braided maze can have a great many, so they are found by
+    /// walking back from the exit to any cell one step closer to the start,
+    /// stopping once there are enough. The room counts as a single place:
+    /// ways only differing inside it are the same way.
+    pub fn all_shortest_paths(&self, limit: usize) -> Vec<Vec<Pos>> {
+        let Some(exit) = self.exit_pos() else {
+            return Vec::new();
+        };
+        let distances = self.distance_map(self.start_pos());
+        let distance = |pos: Pos| distances[pos.y * self.width + pos.x];
+
+        let mut paths = Vec::new();
+        let mut stack = match distance(exit) {
+            Some(_) => vec![vec![exit]],
+            None => Vec::new(),
+        };
+        while paths.len() < limit
+            && let Some(path) = stack.pop()
+        {
+            let pos = *path.last().unwrap();
+            match distance(pos) {
+                Some(0) => {
+                    let mut path = path;
+                    path.reverse();
+                    paths.push(Maze::with_bridges(path));
+                }
+                Some(steps) => {
+                    let previous = Direction::ALL.into_iter().filter_map(|direction| {
+                        self.step(pos, direction)
+                            .filter(|&previous| distance(previous) == Some(steps - 1))
+                    });
+                    // Once in the room the first way to the start will do
+                    let previous: Vec<Pos> = if self.in_center_room(pos) {
+                        previous.take(1).collect()
+                    } else {
+                        previous.collect()
+                    };
+                    // Pushed in reverse, so the first direction is tried first
+                    for &previous in previous.iter().rev() {
+                        let mut longer = path.clone();
+                        longer.push(previous);
+                        stack.push(longer);
+                    }
+                }
+                None => {}
+            }
+        }
+        paths
+    }
+
+    /// Up to `k` ways from the start to the exit without going anywhere
+    /// twice, shortest first, with Yen's algorithm on the junctions and
+    /// corridors. Unlike `all_shortest_paths` these include detours, so
+    /// there are alternatives even when there is only one shortest way.
+    pub fn k_shortest_paths(&self, k: usize) -> Vec<Vec<Pos>> {
+        let graph = self.junction_graph();
+        let (Some(&start), Some(&exit)) = (
+            graph.index.get(&self.start_pos()),
+            self.exit_pos().and_then(|exit| graph.index.get(&exit)),
+        ) else {
+            return Vec::new();
+        };
+        let mut found: Vec<(usize, Vec<usize>)> = Vec::new();
+        if k > 0
+            && let Some(shortest) =
+                graph.shortest_route(start, exit, &HashSet::new(), &HashSet::new())
+        {
+            found.push(shortest);
+        }
+        let mut candidates: BinaryHeap<Reverse<(usize, Vec<usize>)>> = BinaryHeap::new();
+
+        while let Some((_, last)) = found.last()
+            && found.len() < k
+        {
+            let stops = graph.stops(start, last);
+            // Branch off the last route at each of its junctions, without
+            // going back to the junctions before or repeating a known route
+            for (branch, &junction) in stops[..last.len()].iter().enumerate() {
+                let root = &last[..branch];
+                let avoid_corridors = found
+                    .iter()
+                    .filter(|(_, route)| route.len() > branch && route[..branch] == *root)
+                    .map(|(_, route)| route[branch])
+                    .collect();
+                let avoid_junctions = stops[..branch].iter().copied().collect();
+                if let Some((cost, spur)) =
+                    graph.shortest_route(junction, exit, &avoid_junctions, &avoid_corridors)
+                {
+                    let root_cost: usize = root.iter().map(|&c| graph.corridors[c].steps()).sum();
+                    let route = [root, &spur[..]].concat();
+                    if !found.iter().any(|(_, known)| *known == route)
+                        && !candidates.iter().any(|Reverse((_, known))| *known == route)
+                    {
+                        candidates.push(Reverse((root_cost + cost, route)));
+                    }
+                }
+            }
+            match candidates.pop() {
+                Some(Reverse(next)) => found.push(next),
+                None => break,
+            }
+        }
+
+        found
+            .into_iter()
+            .map(|(_, route)| Maze::with_bridges(graph.cells(start, &route)))
+            .collect()
+    }
+
+    /// Junctions whose removal would cut some passages off from the others,
+    /// so every way between them goes through the junction.
+    pub fn articulation_points(&self) -> Vec<Pos> {
+        let graph = self.junction_graph();
+        let (articulation, _) = graph.cut_points();
+        graph
+            .junctions
+            .iter()
+            .zip(articulation)
+            .filter(|&(_, cut)| cut)
+            .map(|(&pos, _)| pos)
+            .collect()
+    }
+
+    /// Corridors whose removal would cut some passages off from the others
+    /// (bridges of the graph, not to be confused with the cells where
+    /// passages cross), each as the cells from one junction to the other.
+    /// In a perfect maze every corridor leaving the room is one.
+    pub fn cut_corridors(&self) -> Vec<Vec<Pos>> {
+        let graph = self.junction_graph();
+        let (_, bridges) = graph.cut_points();
+        bridges
+            .into_iter()
+            .map(|corridor| Maze::with_bridges(graph.corridors[corridor].cells.clone()))
+            .collect()
+    }
+
+    /// How important each corridor is for getting around: the share of
+    /// shortest routes between `samples` random pairs of junctions that go
+    /// through it, an approximation of its betweenness centrality. The
+    /// corridors are returned most important first.
+    pub fn corridor_importance(&self, samples: usize) -> Vec<(Vec<Pos>, f32)> {
+        self.corridor_importance_with_rng(samples, &mut rand::rng())
+    }
+
+    pub fn corridor_importance_with_rng<R: Rng + ?Sized>(
+        &self,
+        samples: usize,
+        rng: &mut R,
+    ) -> Vec<(Vec<Pos>, f32)> {
+        let graph = self.junction_graph();
+        if graph.junctions.is_empty() {
+            return Vec::new();
+        }
+        let mut uses = vec![0; graph.corridors.len()];
+        for _ in 0..samples {
+            let from = rng.random_range(0..graph.junctions.len());
+            let to = rng.random_range(0..graph.junctions.len());
+            if let Some((_, route)) =
+                graph.shortest_route(from, to, &HashSet::new(), &HashSet::new())
+            {
+                for corridor in route {
+                    uses[corridor] += 1;
+                }
+            }
+        }
+
+        let mut importance: Vec<(Vec<Pos>, f32)> = graph
+            .corridors
+            .into_iter()
+            .zip(uses)
+            .map(|(corridor, uses)| {
+                (
+                    Maze::with_bridges(corridor.cells),
+                    uses as f32 / samples.max(1) as f32,
+                )
+            })
+            .collect();
+        importance.sort_by(|a, b| b.1.total_cmp(&a.1));
+        importance
+    }
+
+    fn junction_graph(&self) -> JunctionGraph {
+        let ways = |pos: Pos| {
+            Direction::ALL
+                .into_iter()
+                .filter(|&direction| self.step(pos, direction).is_some())
+                .count()
+        };
+        let start = self.start_pos();
+        let mut junctions = Vec::new();
+        let mut index = HashMap::new();
+        for (pos, cell) in self.iter_cells() {
+            if TRAVERSABLE.contains(&cell)
+                && (pos == start || cell == CellType::Exit || ways(pos) != 2)
+            {
+                index.insert(pos, junctions.len());
+                junctions.push(pos);
+            }
+        }
+
+        let mut corridors = Vec::new();
+        let mut adjacent = vec![Vec::new(); junctions.len()];
+        // Junctions and the direction a corridor leaves them in, so each
+        // corridor is only followed from one of its ends
+        let mut followed = HashSet::new();
+        for (from, &junction) in junctions.iter().enumerate() {
+            for direction in Direction::ALL {
+                if followed.contains(&(junction, direction)) {
+                    continue;
+                }
+                let Some(mut pos) = self.step(junction, direction) else {
+                    continue;
+                };
+                let mut cells = vec![junction, pos];
+                let mut heading = direction;
+                while !index.contains_key(&pos) {
+                    // A corridor cell has one way on besides the way back
+                    let (turn, next) = Direction::ALL
+                        .into_iter()
+                        .filter(|&turn| turn != heading.reverse())
+                        .find_map(|turn| Some((turn, self.step(pos, turn)?)))
+                        .unwrap();
+                    heading = turn;
+                    pos = next;
+                    cells.push(pos);
+                }
+                followed.insert((junction, direction));
+                followed.insert((pos, heading.reverse()));
+                let to = index[&pos];
+                adjacent[from].push(corridors.len());
+                if to != from {
+                    adjacent[to].push(corridors.len());
+                }
+                corridors.push(Corridor {
+                    ends: (from, to),
+                    cells,
+                });
+            }
+        }
+
+        JunctionGraph {
+            junctions,
+            index,
+            corridors,
+            adjacent,
+        }
+    }
+
+    pub fn export_to_svg(&self, filename: &str, options: &SvgOptions) -> Result<(), MazeError> {
+        let started = std::time::Instant::now();
+        write_file(filename, |file| self.write_svg(file, options))?;
+        log::debug!("Exported {} in {:.2?}", filename, started.elapsed());
+        Ok(())
+    }
+
+    pub fn write_svg<W: Write>(&self, file: &mut W, options: &SvgOptions) -> std::io::Result<()> {
+        let maze = self;
+        let scale = if options.crisp {
+            options.scale.round().max(1.0)
+        } else {
+            options.scale
+        };
+        let legend = maze.legend_layout(options);
+        let (width, height) = match &legend {
+            Some(legend) => (
+                (maze.width as f32).max(legend.x + legend.width),
+                (maze.height as f32).max(legend.y + legend.height),
+            ),
+            None => (maze.width as f32, maze.height as f32),
+        };
+        let coordinates = options.coordinates.then(|| {
+            let mut layout = maze.coordinate_layout(scale);
+            if options.crisp {
+                layout.left = layout.left.ceil();
+                layout.top = layout.top.ceil();
+            }
+            layout
+        });
+        let (width, height) = match &coordinates {
+            Some(layout) => (width + layout.left, height + layout.top),
+            None => (width, height),
+        };
+
+        // Write SVG header with scaled dimensions, crisp output leaves the
+        // view box in cells
+        let (view_width, view_height) = if options.crisp {
+            (width, height)
+        } else {
+            (width * scale, height * scale)
+        };
+        write!(
+            file,
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{}\" height=\"{}\" viewBox=\"0 0 {} {}\"",
+            width * scale,
+            height * scale,
+            view_width,
+            view_height
+        )?;
+        match &options.title {
+            Some(title) => {
+                #[cfg(feature = "serde")]
+                let seed = options.params.as_ref().and_then(|params| params.seed);
+                #[cfg(not(feature = "serde"))]
+                let seed = None;
+                let description = maze.svg_description(seed);
+                writeln!(file, " role=\"img\" aria-label=\"{}\">", description)?;
+                let title: String = title.chars().map(xml_escape).collect();
+                writeln!(file, "<title>{}</title>", title)?;
+                writeln!(file, "<desc>{}</desc>", description)?;
+            }
+            None => writeln!(file, ">")?,
+        }
+        #[cfg(feature = "serde")]
+        if let Some(params) = &options.params {
+            writeln!(
+                file,
+                "<metadata><![CDATA[{}]]></metadata>",
+                serde_json::to_string(params).map_err(std::io::Error::other)?
+            )?;
+        }
+
+        writeln!(
+            file,
+            "<rect width=\"100%\" height=\"100%\" fill=\"{}\" />",
+            hex_color(options.theme.path_color)
+        )?;
+        write_svg_icon_defs(file, self.cells.iter().copied(), options)?;
+        match (&coordinates, options.crisp) {
+            (Some(layout), true) => writeln!(
+                file,
+                "  <g shape-rendering=\"crispEdges\" transform=\"translate({} {})\" >",
+                layout.left, layout.top
+            )?,
+            (Some(layout), false) => writeln!(
+                file,
+                "  <g transform=\"scale({}) translate({} {})\" >",
+                scale, layout.left, layout.top
+            )?,
+            (None, true) => writeln!(file, "  <g shape-rendering=\"crispEdges\" >")?,
+            (None, false) => writeln!(file, "  <g transform=\"scale({})\" >", scale)?,
+        }
+        self.write_svg_cells(file, options)?;
+        if let Some(every) = options.grid.filter(|&every| every > 0) {
+            self.write_svg_grid(file, every)?;
+        }
+        if let Some(layout) = &coordinates {
+            self.write_svg_coordinates(file, layout, options)?;
+        }
+        if let Some(legend) = legend {
+            self.write_svg_legend(file, &legend, options)?;
+        }
+        writeln!(file, "  </g>")?;
+        writeln!(file, "</svg>")?;
+        Ok(())
+    }
+
+    pub fn export_to_svg_fogged(
+        &self,
+        filename: &str,
+        scale: f32,
+        reveal: &RevealRule,
+    ) -> Result<(), MazeError> {
+        write_file(filename, |file| self.write_svg_fogged(file, scale, reveal))
+    }
+
+    /// Writes the maze as a teaser with only the cells `reveal` picks drawn,
+    /// everything else a flat gray. Hidden artifacts aren't written at all,
+    /// so they can't be found in the SVG source either.
+    pub fn write_svg_fogged<W: Write>(
+        &self,
+        file: &mut W,
+        scale: f32,
+        reveal: &RevealRule,
+    ) -> std::io::Result<()> {
+        let options = SvgOptions {
+            scale,
+            ..Default::default()
+        };
+        let revealed = self.revealed_cells(reveal);
+        writeln!(
+            file,
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{}\" height=\"{}\" viewBox=\"0 0 {} {}\">",
+            self.width as f32 * scale,
+            self.height as f32 * scale,
+            self.width as f32 * scale,
+            self.height as f32 * scale
+        )?;
+        writeln!(
+            file,
+            "<rect width=\"100%\" height=\"100%\" fill=\"{}\" />",
+            hex_color(FOG_COLOR)
+        )?;
+        let shown: Vec<(Pos, CellType)> = self
+            .iter_cells()
+            .filter(|(pos, _)| revealed.contains(pos))
+            .collect();
+        write_svg_icon_defs(file, shown.iter().map(|&(_, cell)| cell), &options)?;
+        writeln!(file, "  <g transform=\"scale({})\" >", scale)?;
+        // Walls and open cells, one rectangle per run of either along a row
+        for run in shown.chunk_by(|&(a, a_cell), &(b, b_cell)| {
+            a.y == b.y && a.x + 1 == b.x && (a_cell == CellType::Wall) == (b_cell == CellType::Wall)
+        }) {
+            let (Pos { x, y }, cell) = run[0];
+            let color = if cell == CellType::Wall {
+                options.theme.wall_color
+            } else {
+                options.theme.path_color
+            };
+            writeln!(
+                file,
+                "    <rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"1\" fill=\"{}\" />",
+                x,
+                y,
+                run.len(),
+                hex_color(color)
+            )?;
+        }
+        for &(pos, cell) in &shown {
+            write_svg_cell(file, pos, cell, &options)?;
+        }
+        writeln!(file, "  </g>")?;
+        writeln!(file, "</svg>")?;
+        Ok(())
+    }
+
+    /// The cells `reveal` leaves visible in `write_svg_fogged`: the open
+    /// cells it picks and every wall or bridge touching them, diagonally
+    /// too, so the visible part still reads as a maze.
+    pub fn revealed_cells(&self, reveal: &RevealRule) -> HashSet<Pos> {
+        let (sources, radius) = match reveal {
+            RevealRule::AroundStart { radius } => (vec![self.start_pos()], *radius),
+            RevealRule::AroundPath { radius } => (
+                self.shortest_path()
+                    .unwrap_or_else(|| vec![self.start_pos()]),
+                *radius,
+            ),
+            RevealRule::Explicit(cells) => (cells.iter().copied().collect(), 0),
+        };
+        let bounds = self.bounds();
+        let mut open: HashSet<Pos> = sources
+            .into_iter()
+            .filter(|&pos| bounds.contains(pos))
+            .collect();
+        // Breadth first from all sources at once, one ring per step
+        let mut ring: Vec<Pos> = open.iter().copied().collect();
+        for _ in 0..radius {
+            ring = ring
+                .into_iter()
+                .flat_map(|pos| {
+                    Direction::ALL
+                        .into_iter()
+                        .filter_map(move |direction| self.step(pos, direction))
+                })
+                .filter(|&next| open.insert(next))
+                .collect();
+        }
+
+        let mut revealed = open.clone();
+        for pos in open {
+            let (x, y) = (pos.x.saturating_sub(1), pos.y.saturating_sub(1));
+            let around = Rect::new(x, y, pos.x + 2 - x, pos.y + 2 - y);
+            revealed.extend(
+                self.cells_in(around)
+                    .filter(|&(_, cell)| {
+                        !TRAVERSABLE.contains(&cell)
+                            || matches!(cell, CellType::BridgeNorthSouth | CellType::BridgeEastWest)
+                    })
+                    .map(|(pos, _)| pos),
+            );
+        }
+        revealed
+    }
+
+    /// Room the coordinate labels take up left of and above the maze, and
+    /// how many columns and rows apart they are so they don't overlap when
+    /// the font is bigger than a cell
+    fn coordinate_layout(&self, scale: f32) -> CoordinateLayout {
+        let font_size = COORDINATE_FONT.max(MIN_COORDINATE_FONT_PX / scale);
+        // Glyphs are about 0.6 em wide
+        let glyph = 0.6 * font_size;
+        let letters = column_name(self.width.saturating_sub(1)).len();
+        let digits = self.height.to_string().len();
+        CoordinateLayout {
+            font_size,
+            left: glyph * (digits as f32 + 1.0),
+            top: 1.5 * font_size,
+            column_step: ((glyph * (letters as f32 + 0.5)).ceil() as usize).max(1),
+            row_step: ((1.1 * font_size).ceil() as usize).max(1),
+        }
+    }
+
+    /// Thin lines along the cell borders every `every` cells, across the
+    /// maze only
+    fn write_svg_grid<W: Write>(&self, file: &mut W, every: usize) -> std::io::Result<()> {
+        let mut path = String::new();
+        for x in (0..=self.width).step_by(every) {
+            path.push_str(&format!("M{} 0V{}", x, self.height));
+        }
+        for y in (0..=self.height).step_by(every) {
+            path.push_str(&format!("M0 {}H{}", y, self.width));
+        }
+        writeln!(
+            file,
+            "    <path class=\"grid\" d=\"{}\" stroke=\"#888\" stroke-opacity=\"0.4\" stroke-width=\"0.05\" fill=\"none\" />",
+            path
+        )
+    }
+
+    /// Column letters above the maze and row numbers left of it, in the
+    /// margin `layout` makes room for
+    fn write_svg_coordinates<W: Write>(
+        &self,
+        file: &mut W,
+        layout: &CoordinateLayout,
+        options: &SvgOptions,
+    ) -> std::io::Result<()> {
+        writeln!(
+            file,
+            "    <g class=\"coordinates\" font-family=\"sans-serif\" font-size=\"{}\" fill=\"{}\">",
+            layout.font_size,
+            hex_color(options.theme.wall_color)
+        )?;
+        for x in (0..self.width).step_by(layout.column_step) {
+            writeln!(
+                file,
+                "      <text x=\"{}\" y=\"{}\" text-anchor=\"middle\">{}</text>",
+                x as f32 + 0.5,
+                -0.4 * layout.font_size,
+                column_name(x)
+            )?;
+        }
+        for y in (0..self.height).step_by(layout.row_step) {
+            writeln!(
+                file,
+                "      <text x=\"{}\" y=\"{}\" text-anchor=\"end\" dominant-baseline=\"central\">{}</text>",
+                -0.3 * layout.font_size,
+                y as f32 + 0.5,
+                y + 1
+            )?;
+        }
+        writeln!(file, "    </g>")
+    }
+
+    /// E.g. "63×31 maze, seed 42, difficulty 63", the seed only if known
+    fn svg_description(&self, seed: Option<u64>) -> String {
+        match seed {
+            Some(seed) => format!(
+                "{}×{} maze, seed {}, difficulty {:.0}",
+                self.width,
+                self.height,
+                seed,
+                self.difficulty()
+            ),
+            None => format!(
+                "{}×{} maze, difficulty {:.0}",
+                self.width,
+                self.height,
+                self.difficulty()
+            ),
+        }
+    }
+
+    /// Reads the generation parameters `write_svg` embeds with
+    /// `SvgOptions::params` back from an SVG. Building them gives the same
+    /// maze again, as long as they include the seed.
+    #[cfg(feature = "serde")]
+    pub fn params_from_svg_metadata<R: std::io::Read>(
+        mut reader: R,
+    ) -> Result<MazeBuilder, MazeError> {
+        let mut svg = String::new();
+        reader.read_to_string(&mut svg)?;
+        let start_tag = "<metadata><![CDATA[";
+        let Some(start) = svg.find(start_tag).map(|start| start + start_tag.len()) else {
+            return Err(MazeError::Parse {
+                line: 1,
+                reason: "no maze parameters in the SVG".to_string(),
+            });
+        };
+        let line = svg[..start].lines().count().max(1);
+        let end = svg[start..]
+            .find("]]></metadata>")
+            .ok_or_else(|| MazeError::Parse {
+                line,
+                reason: "the maze parameters aren't closed".to_string(),
+            })?;
+        serde_json::from_str(&svg[start..start + end]).map_err(|err| MazeError::Parse {
+            line,
+            reason: err.to_string(),
+        })
+    }
+
+    /// Where the legend goes and what it lists, `None` if there is nothing
+    /// to explain or no legend was asked for. In cells, like the maze.
+    fn legend_layout(&self, options: &SvgOptions) -> Option<LegendLayout> {
+        if !options.include_legend || !options.show_artifacts {
+            return None;
+        }
+        let present: HashSet<CellType> = self.cells.iter().copied().collect();
+        let entries: Vec<CellType> = CELL_TYPES
+            .iter()
+            .copied()
+            .filter(|cell| {
+                present.contains(cell) && (REWARDS.contains(cell) || DANGERS.contains(cell))
+            })
+            .collect();
+        if entries.is_empty() {
+            return None;
+        }
+        // Below the maze as many columns fit as its width allows, to the
+        // right of it a single one
+        let columns = match options.legend_position {
+            LegendPosition::Bottom => ((self.width as f32 / LEGEND_COLUMN) as usize).max(1),
+            LegendPosition::Right => 1,
+        };
+        let rows = entries.len().div_ceil(columns);
+        let (x, y) = match options.legend_position {
+            LegendPosition::Bottom => (0.0, self.height as f32),
+            LegendPosition::Right => (self.width as f32, 0.0),
+        };
+        Some(LegendLayout {
+            entries,
+            columns,
+            x,
+            y,
+            width: columns as f32 * LEGEND_COLUMN,
+            height: rows as f32 * LEGEND_ROW + 1.0,
+        })
+    }
+
+    fn write_svg_legend<W: Write>(
+        &self,
+        file: &mut W,
+        legend: &LegendLayout,
+        options: &SvgOptions,
+    ) -> std::io::Result<()> {
+        writeln!(file, "    <g class=\"legend\">")?;
+        for (i, &cell) in legend.entries.iter().enumerate() {
+            let x = legend.x + (i % legend.columns) as f32 * LEGEND_COLUMN + 0.5;
+            let y = legend.y + (i / legend.columns) as f32 * LEGEND_ROW + 0.75;
+            if icon(cell, options).is_some() {
+                writeln!(
+                    file,
+                    "      <use href=\"#{}\" x=\"{}\" y=\"{}\" width=\"1\" height=\"1\" />",
+                    icon_id(cell),
+                    x,
+                    y
+                )?;
+            } else {
+                writeln!(
+                    file,
+                    "      <circle cx=\"{}\" cy=\"{}\" r=\"0.4\" fill=\"{}\" />",
+                    x + 0.5,
+                    y + 0.5,
+                    hex_color(artifact_color(cell, options))
+                )?;
+            }
+            writeln!(
+                file,
+                "      <text x=\"{}\" y=\"{}\" font-size=\"0.9\" font-family=\"sans-serif\" dominant-baseline=\"central\" fill=\"{}\">{} ({:+})</text>",
+                x + 1.5,
+                y + 0.5,
+                hex_color(options.theme.wall_color),
+                cell,
+                self.weights.get(cell)
+            )?;
+        }
+        writeln!(file, "    </g>")
+    }
+
+    /// Writes the heatmap, the cells of the maze and then the solution in
+    /// unscaled coordinates, one unit per cell.
+    fn write_svg_cells<W: Write>(&self, file: &mut W, options: &SvgOptions) -> std::io::Result<()> {
+        let maze = self;
+
+        if options.overlay == SvgOverlay::DistanceHeatmap {
+            let distances = maze.distance_map(maze.start_pos());
+            let max_distance = distances
+                .iter()
+                .flatten()
+                .max()
+                .copied()
+                .unwrap_or(0)
+                .max(1);
+            let (near, far) = options.heatmap_colors;
+            for (idx, distance) in distances.iter().enumerate() {
+                let Some(distance) = distance else {
+                    continue;
+                };
+                let t = *distance as f32 / max_distance as f32;
+                let [r, g, b] = [0, 1, 2]
+                    .map(|i| (near[i] as f32 + (far[i] as f32 - near[i] as f32) * t).round() as u8);
+                let (x, y) = (idx % maze.width, idx / maze.width);
+                write!(
+                    file,
+                    "    <rect x=\"{}\" y=\"{}\" width=\"1\" height=\"1\" fill=\"rgb({}, {}, {})\"",
+                    x, y, r, g, b
+                )?;
+                if maze.get(x, y) == CellType::Exit {
+                    writeln!(file, "><title>Distance: {}</title></rect>", distance)?;
+                } else {
+                    writeln!(file, " />")?;
+                }
+            }
+        }
+
+        if options.overlay == SvgOverlay::CutCorridors {
+            let cells: HashSet<Pos> = maze.cut_corridors().into_iter().flatten().collect();
+            let mut cells: Vec<Pos> = cells.into_iter().collect();
+            cells.sort();
+            for Pos { x, y } in cells {
+                writeln!(
+                    file,
+                    "    <rect x=\"{}\" y=\"{}\" width=\"1\" height=\"1\" fill=\"{}\" fill-opacity=\"0.5\" />",
+                    x,
+                    y,
+                    hex_color(options.theme.danger_color)
+                )?;
+            }
+        }
+
+        let solution = match options.with_solution {
+            SolutionType::ShortestPath if options.show_visited => {
+                let trace = maze.solve_instrumented(options.solver);
+                for pos in &trace.visited {
+                    writeln!(
+                        file,
+                        "    <rect x=\"{}\" y=\"{}\" width=\"1\" height=\"1\" fill=\"#cde\" />",
+                        pos.x, pos.y
+                    )?;
+                }
+                trace.path
+            }
+            SolutionType::ShortestPath => maze.solve(options.solver),
+            SolutionType::MinimumSpanningTree | SolutionType::FullCollection => None,
+            SolutionType::None => None,
+        };
+        let tree = match options.with_solution {
+            SolutionType::MinimumSpanningTree => maze.mst_paths(&maze.mst_kruskal()),
+            _ => Vec::new(),
+        };
+
+        // Walls, one rectangle per run of them along a row. Crisp output
+        // makes them all one path, which leaves no seams between the rows.
+        let mut walls = String::new();
+        for (y, row) in maze.cells.chunks(maze.width).enumerate() {
+            let mut x = 0;
+            for run in row.chunk_by(|a, b| a == b) {
+                if run[0] == CellType::Wall && options.crisp {
+                    walls.push_str(&format!("M{} {}h{}v1h-{}z", x, y, run.len(), run.len()));
+                } else if run[0] == CellType::Wall {
+                    writeln!(
+                        file,
+                        "    <rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"1\" fill=\"{}\" />",
+                        x,
+                        y,
+                        run.len(),
+                        hex_color(options.theme.wall_color)
+                    )?;
+                }
+                x += run.len();
+            }
+        }
+        if !walls.is_empty() {
+            writeln!(
+                file,
+                "    <path d=\"{}\" fill=\"{}\" />",
+                walls,
+                hex_color(options.theme.wall_color)
+            )?;
+        }
+
+        for (pos, cell) in maze.iter_cells() {
+            write_svg_cell(file, pos, cell, options)?;
+        }
+
+        if options.show_labels {
+            let mut labels: Vec<(&Pos, &char)> = maze.labels.iter().collect();
+            labels.sort();
+            for (pos, label) in labels {
+                writeln!(
+                    file,
+                    "    <text x=\"{}\" y=\"{}\" font-size=\"0.8\" font-family=\"sans-serif\" text-anchor=\"middle\" dominant-baseline=\"central\" fill=\"{}\">{}</text>",
+                    pos.x as f32 + 0.5,
+                    pos.y as f32 + 0.5,
+                    hex_color(options.theme.wall_color),
+                    xml_escape(*label)
+                )?;
+            }
+        }
+
+        // The paths go on top of everything, so walls, artifacts and labels
+        // don't cover them
+        if options.overlay == SvgOverlay::ShortestPaths {
+            let paths = maze.all_shortest_paths(SHORTEST_PATHS_SHOWN);
+            // Shifted a little apart, so shared stretches show every hue
+            let spread = 0.5 / paths.len().max(1) as f32;
+            let lines: Vec<Vec<(f32, f32)>> = paths
+                .iter()
+                .enumerate()
+                .map(|(i, path)| {
+                    let shift = 0.5 + (i as f32 - (paths.len() - 1) as f32 / 2.0) * spread;
+                    path.iter()
+                        .map(|pos| (pos.x as f32 + shift, pos.y as f32 + shift))
+                        .collect()
+                })
+                .collect();
+            write_svg_overlay(file, &lines, OverlayColor::OneHueEach, options)?;
+        }
+        if !tree.is_empty() {
+            let lines: Vec<Vec<(f32, f32)>> = tree
+                .iter()
+                .map(|path| {
+                    path.iter()
+                        .map(|pos| (pos.x as f32 + 0.5, pos.y as f32 + 0.5))
+                        .collect()
+                })
+                .collect();
+            write_svg_overlay(file, &lines, OverlayColor::Solution, options)?;
+        }
+        if options.with_solution == SolutionType::FullCollection
+            && let Some(route) = maze.full_collection_route()
+        {
+            let points = route
+                .path
+                .iter()
+                .map(|pos| (pos.x as f32 + 0.5, pos.y as f32 + 0.5))
+                .collect();
+            write_svg_overlay(
+                file,
+                &[points],
+                OverlayColor::Fixed(COLLECTION_ROUTE_COLOR),
+                options,
+            )?;
+        }
+        if let Some(solution) = solution {
+            if options.solution_style == SolutionStyle::Line {
+                let points = solution
+                    .iter()
+                    .map(|pos| (pos.x as f32 + 0.5, pos.y as f32 + 0.5))
+                    .collect();
+                write_svg_overlay(file, &[points], OverlayColor::Solution, options)?;
+            } else {
+                write_svg_markers(file, &solution, options)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Writes the maze as plain text, one character per cell: `#` for
+    /// walls, `S` and `E` for start and exit, the label for labeled cells,
+    /// `+` for rewards, `!` for dangers and `.` for cells on `solution`.
+    pub fn write_text<W: Write>(&self, out: &mut W, solution: &[Pos]) -> std::io::Result<()> {
+        let on_solution: HashSet<&Pos> = solution.iter().collect();
+        for y in 0..self.height {
+            writeln!(out, "{}", self.text_row(y, &on_solution))?;
+        }
+        Ok(())
+    }
+
+    /// Like `write_text`, with the column letters in a header above the
+    /// maze, one line per letter from the top so every column gets its own
+    /// character, and each row prefixed with its number, counting from 1.
+    pub fn write_text_with_coordinates<W: Write>(
+        &self,
+        out: &mut W,
+        solution: &[Pos],
+    ) -> std::io::Result<()> {
+        let on_solution: HashSet<&Pos> = solution.iter().collect();
+        let digits = self.height.to_string().len();
+        let names: Vec<String> = (0..self.width).map(column_name).collect();
+        let letters = names.iter().map(String::len).max().unwrap_or(0);
+        for line in 0..letters {
+            // Shorter names are aligned to the bottom line
+            let header: String = names
+                .iter()
+                .map(|name| {
+                    let skip = letters - name.len();
+                    match line.checked_sub(skip) {
+                        Some(i) => name.as_bytes()[i] as char,
+                        None => ' ',
+                    }
+                })
+                .collect();
+            writeln!(out, "{:digits$} {}", "", header)?;
+        }
+        for y in 0..self.height {
+            writeln!(out, "{:>digits$} {}", y + 1, self.text_row(y, &on_solution))?;
+        }
+        Ok(())
+    }
+
+    fn text_row(&self, y: usize, on_solution: &HashSet<&Pos>) -> String {
+        (0..self.width)
+            .map(|x| match self.get(x, y) {
+                CellType::Wall => '#',
+                CellType::Start => 'S',
+                CellType::Exit => 'E',
+                _ if self.labels.contains_key(&Pos { x, y }) => self.labels[&Pos { x, y }],
+                cell if REWARDS.contains(&cell) => '+',
+                cell if DANGERS.contains(&cell) => '!',
+                _ if on_solution.contains(&Pos { x, y }) => '.',
+                _ => ' ',
+            })
+            .collect()
+    }
+
+    /// Writes the maze with 24-bit ANSI background colors from `theme`, two
+    /// columns per cell so the cells come out roughly square. Cells on
+    /// `solution` are drawn in the solution color unless they hold an
+    /// artifact.
+    pub fn write_ansi<W: Write>(
+        &self,
+        out: &mut W,
+        theme: &Theme,
+        solution: &[Pos],
+    ) -> std::io::Result<()> {
+        let on_solution: HashSet<&Pos> = solution.iter().collect();
+        for y in 0..self.height {
+            for x in 0..self.width {
+                let cell = self.get(x, y);
+                let color = match cell {
+                    CellType::Wall => theme.wall_color,
+                    cell if REWARDS.contains(&cell) => theme.reward_color,
+                    cell if DANGERS.contains(&cell) => theme.danger_color,
+                    _ if on_solution.contains(&Pos { x, y }) => theme.solution_color,
+                    _ => theme.path_color,
+                };
+                let label = match cell {
+                    CellType::Start => "S ".to_string(),
+                    CellType::Exit => "E ".to_string(),
+                    _ => match self.labels.get(&Pos { x, y }) {
+                        Some(label) => format!("{} ", label),
+                        None => "  ".to_string(),
+                    },
+                };
+                let [r, g, b] = color;
+                let [fr, fg, fb] = theme.wall_color;
+                write!(
+                    out,
+                    "\x1b[48;2;{};{};{}m\x1b[38;2;{};{};{}m{}",
+                    r, g, b, fr, fg, fb, label
+                )?;
+            }
+            writeln!(out, "\x1b[0m")?;
+        }
+        Ok(())
+    }
+
+    pub fn build_graph(&self) -> (Nodes, Edges) {
+        let mut nodes: Nodes = HashMap::new();
+        let mut edges: Edges = HashSet::new();
+        let mut node_id = 0;
+
+        // Special nodes: center (start) and exit
+        let center_x: usize = self.width / 2;
+        let center_y: usize = self.height / 2;
+        let center_pos: Pos = Pos {
+            x: center_x,
+            y: center_y,
+        };
+        nodes.insert(center_pos, node_id);
+        node_id += 1;
+
+        // Find exit node
+        let mut exit_pos: Option<Pos> = None;
+        for x in [0, self.width - 1].iter() {
+            for y in 0..self.height {
+                if self.get(*x, y) == CellType::Exit {
+                    exit_pos = Some(Pos { x: *x, y });
+                    break;
+                }
+            }
+        }
+        if exit_pos.is_none() {
+            return (nodes, edges);
+        }
+
+        if let Some(pos) = exit_pos {
+            nodes.insert(pos, node_id);
+            node_id += 1;
+        }
+
+        // Scan the maze to find all intersections and dead ends
+        for y in 1..self.height - 1 {
+            for x in 1..self.width - 1 {
+                let cell_type = self.get(x, y);
+                // Check if the cell is a path, reward or danger (traversable)
+                if TRAVERSABLE.contains(&cell_type) {
+                    let current_pos = Pos { x, y };
+                    let neighbors = self.open_neighbors(current_pos).count();
+
+                    // Create a node if this is an intersection (>2 neighbors) or dead end (1 neighbor)
+                    if neighbors != 2 && current_pos != center_pos && Some(current_pos) != exit_pos
+                    {
+                        nodes.insert(current_pos, node_id);
+                        node_id += 1;
+                    }
+                }
+            }
+        }
+
+        // Create edges between nodes by following paths
+        for (&start_pos, &start_id) in &nodes {
+            // For each direction, follow the path until another node is found
+            for first in self.open_neighbors(start_pos) {
+                let mut weight = self.weights.get(self.get(first.x, first.y)); // Start with the weight of the first cell
+                let mut visited = HashSet::new();
+                visited.insert(start_pos);
+
+                // Follow the path
+                let mut current_pos = first;
+                loop {
+                    // If we've found another node, create an edge
+                    if let Some(&end_id) = nodes.get(&current_pos) {
+                        if start_id < end_id {
+                            // Only add each edge once
+                            edges.insert(Edge {
+                                start_id,
+                                end_id,
+                                weight,
+                            });
+                        }
+                        break;
+                    }
+
+                    // If not a node, check neighboring cells to continue the path
+                    visited.insert(current_pos);
+
+                    let Some(next_pos) = self
+                        .open_neighbors(current_pos)
+                        .find(|next| !visited.contains(next))
+                    else {
+                        break;
+                    };
+                    weight += self.weights.get(self.get(next_pos.x, next_pos.y));
+                    current_pos = next_pos;
+                }
+            }
+        }
+
+        (nodes, edges)
+    }
+
+    /// Renders the maze with `theme`, each cell a square of `cell_px`
+    /// pixels. The start and exit are drawn in the solution color.
+    #[cfg(feature = "image")]
+    pub fn to_image(&self, cell_px: u32, theme: &Theme) -> image::RgbaImage {
+        image::RgbaImage::from_fn(
+            self.width as u32 * cell_px,
+            self.height as u32 * cell_px,
+            |px, py| {
+                let [r, g, b] = match self.get((px / cell_px) as usize, (py / cell_px) as usize) {
+                    CellType::Wall => theme.wall_color,
+                    CellType::Start | CellType::Exit => theme.solution_color,
+                    cell if REWARDS.contains(&cell) => theme.reward_color,
+                    cell if DANGERS.contains(&cell) => theme.danger_color,
+                    _ => theme.path_color,
+                };
+                image::Rgba([r, g, b, 255])
+            },
+        )
+    }
+
+    /// Writes the image `to_image` renders to a PNG file
+    #[cfg(feature = "image")]
+    pub fn export_to_png(
+        &self,
+        filename: &str,
+        cell_px: u32,
+        theme: &Theme,
+    ) -> Result<(), MazeError> {
+        use image::ImageEncoder;
+        use image::codecs::png::PngEncoder;
+
+        if cell_px == 0 {
+            return Err(MazeError::InvalidOption {
+                name: "cell size",
+                reason: "it has to be at least 1 pixel".to_string(),
+            });
+        }
+        let image = self.to_image(cell_px, theme);
+        write_file(filename, |file| {
+            PngEncoder::new(file)
+                .write_image(
+                    image.as_raw(),
+                    image.width(),
+                    image.height(),
+                    image::ExtendedColorType::Rgba8,
+                )
+                .map_err(std::io::Error::other)
+        })
+    }
+
+    /// Writes an animated GIF of `events`, recorded with the `*_observed`
+    /// variants of generation and artifact placement, being applied to this
+    /// maze, usually the blank one they started from. There is a frame
+    /// every `frame_every` events, or more events per frame for long
+    /// recordings, since there are at most `MAX_GIF_FRAMES` frames. The
+    /// last frame shows the finished maze and is held for two seconds.
+    #[cfg(feature = "image")]
+    pub fn export_generation_gif(
+        &self,
+        filename: &str,
+        events: &[MazeEvent],
+        frame_every: usize,
+        cell_px: u32,
+    ) -> Result<(), MazeError> {
+        use image::codecs::gif::{GifEncoder, Repeat};
+        use image::{Delay, Frame};
+
+        if cell_px == 0 {
+            return Err(MazeError::InvalidOption {
+                name: "cell size",
+                reason: "it has to be at least 1 pixel".to_string(),
+            });
+        }
+        let frame_every = frame_every
+            .max(1)
+            .max(events.len().div_ceil(MAX_GIF_FRAMES - 1));
+        let theme = Theme::default();
+        let mut maze = self.clone();
+        // The blank maze, then one frame per chunk, each encoded as soon as
+        // it's drawn
+        let last = events.chunks(frame_every).len();
+        let chunks = std::iter::once(&[][..]).chain(events.chunks(frame_every));
+        write_file(filename, |file| {
+            let mut encoder = GifEncoder::new(file);
+            encoder
+                .set_repeat(Repeat::Infinite)
+                .map_err(std::io::Error::other)?;
+            for (index, chunk) in chunks.enumerate() {
+                for &event in chunk {
+                    maze.apply(event);
+                }
+                let delay = if index == last { 2000 } else { 40 };
+                let frame = Frame::from_parts(
+                    maze.to_image(cell_px, &theme),
+                    0,
+                    0,
+                    Delay::from_numer_denom_ms(delay, 1),
+                );
+                encoder.encode_frame(frame).map_err(std::io::Error::other)?;
+            }
+            Ok(())
+        })
+    }
+
+    /// The boxes a printable model of the maze is made of, as corner and
+    /// size in millimeters: the base plate first, then one box per run of
+    /// walls along a row, standing on the plate. Rows go up the y axis from
+    /// the bottom of the maze, so it isn't mirrored when viewed from above.
+    fn model_boxes(
+        &self,
+        wall_height_mm: f32,
+        cell_mm: f32,
+        base_mm: f32,
+    ) -> Result<Vec<ModelBox>, MazeError> {
+        for (name, value) in [
+            ("wall height", wall_height_mm),
+            ("cell size", cell_mm),
+            ("base thickness", base_mm),
+        ] {
+            if value.is_nan() || value <= 0.0 {
+                return Err(MazeError::InvalidOption {
+                    name,
+                    reason: format!("{} mm isn't a positive size", value),
+                });
+            }
+        }
+        let mut boxes = vec![(
+            [0.0; 3],
+            [
+                self.width as f32 * cell_mm,
+                self.height as f32 * cell_mm,
+                base_mm,
+            ],
+        )];
+        for (y, row) in self.rows().enumerate() {
+            let mut x = 0;
+            for run in row.chunk_by(|a, b| a == b) {
+                if run[0] == CellType::Wall {
+                    boxes.push((
+                        [
+                            x as f32 * cell_mm,
+                            (self.height - 1 - y) as f32 * cell_mm,
+                            base_mm,
+                        ],
+                        [run.len() as f32 * cell_mm, cell_mm, wall_height_mm],
+                    ));
+                }
+                x += run.len();
+            }
+        }
+        Ok(boxes)
+    }
+
+    pub fn export_to_scad(
+        &self,
+        filename: &str,
+        wall_height_mm: f32,
+        cell_mm: f32,
+        base_mm: f32,
+    ) -> Result<(), MazeError> {
+        let boxes = self.model_boxes(wall_height_mm, cell_mm, base_mm)?;
+        write_file(filename, |file| Maze::write_scad_boxes(file, &boxes))
+    }
+
+    /// Writes an OpenSCAD script of the maze for 3D printing: a base plate
+    /// `base_mm` thick with the walls `wall_height_mm` high on top, each cell
+    /// `cell_mm` square. Walls next to each other in a row are a single
+    /// cube, and the exit is a gap in the rim like any other open cell.
+    pub fn write_scad<W: Write>(
+        &self,
+        out: &mut W,
+        wall_height_mm: f32,
+        cell_mm: f32,
+        base_mm: f32,
+    ) -> Result<(), MazeError> {
+        let boxes = self.model_boxes(wall_height_mm, cell_mm, base_mm)?;
+        Ok(Maze::write_scad_boxes(out, &boxes)?)
+    }
+
+    fn write_scad_boxes<W: Write>(
+        out: &mut W,
+        boxes: &[([f32; 3], [f32; 3])],
+    ) -> std::io::Result<()> {
+        writeln!(out, "union() {{")?;
+        for ([x, y, z], [width, depth, height]) in boxes {
+            writeln!(
+                out,
+                "  translate([{}, {}, {}]) cube([{}, {}, {}]);",
+                x, y, z, width, depth, height
+            )?;
+        }
+        writeln!(out, "}}")
+    }
+
+    /// Saves the same model as `write_scad` as a binary STL file, twelve
+    /// triangles per box. The boxes touch but aren't merged, slicers join
+    /// them.
+    pub fn export_to_stl(
+        &self,
+        filename: &str,
+        wall_height_mm: f32,
+        cell_mm: f32,
+        base_mm: f32,
+    ) -> Result<(), MazeError> {
+        let boxes = self.model_boxes(wall_height_mm, cell_mm, base_mm)?;
+        write_file(filename, |file| Maze::write_stl_boxes(file, &boxes))
+    }
+
+    fn write_stl_boxes<W: Write>(
+        out: &mut W,
+        boxes: &[([f32; 3], [f32; 3])],
+    ) -> std::io::Result<()> {
+        // Corners of each face by index, bit 0 for x, 1 for y and 2 for z
+        // being at the far side, counterclockwise seen from outside
+        const FACES: [([f32; 3], [usize; 4]); 6] = [
+            ([-1.0, 0.0, 0.0], [0, 4, 6, 2]),
+            ([1.0, 0.0, 0.0], [1, 3, 7, 5]),
+            ([0.0, -1.0, 0.0], [0, 1, 5, 4]),
+            ([0.0, 1.0, 0.0], [2, 6, 7, 3]),
+            ([0.0, 0.0, -1.0], [0, 2, 3, 1]),
+            ([0.0, 0.0, 1.0], [4, 5, 7, 6]),
+        ];
+        out.write_all(&[0; 80])?;
+        out.write_all(&(boxes.len() as u32 * 12).to_le_bytes())?;
+        for &(origin, size) in boxes {
+            let corner = |index: usize| {
+                [0, 1, 2].map(|axis| origin[axis] + size[axis] * ((index >> axis) & 1) as f32)
+            };
+            for (normal, [a, b, c, d]) in FACES {
+                for triangle in [[a, b, c], [a, c, d]] {
+                    for value in normal
+                        .into_iter()
+                        .chain(triangle.into_iter().flat_map(corner))
+                    {
+                        out.write_all(&value.to_le_bytes())?;
+                    }
+                    out.write_all(&0u16.to_le_bytes())?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// The outlines of the walls, each a closed loop of grid points with
+    /// the walls on its right when going from point to point (y pointing
+    /// down). Beyond the border counts as open, so the outermost loop is the
+    /// frame of the maze with a notch at the exit. Points are only where the
+    /// outline turns, the loop doesn't repeat its first point.
+    pub fn wall_outlines(&self) -> Vec<Vec<(usize, usize)>> {
+        let is_wall = |x: isize, y: isize| {
+            x >= 0
+                && y >= 0
+                && (x as usize) < self.width
+                && (y as usize) < self.height
+                && self.get(x as usize, y as usize) == CellType::Wall
+        };
+        // Sides of wall cells facing an open cell, clockwise around the cell
+        let mut edges: HashMap<(usize, usize), Vec<(usize, usize)>> = HashMap::new();
+        let mut count = 0;
+        for (Pos { x, y }, cell) in self.iter_cells() {
+            if cell != CellType::Wall {
+                continue;
+            }
+            let (cx, cy) = (x as isize, y as isize);
+            for (open, from, to) in [
+                (!is_wall(cx, cy - 1), (x, y), (x + 1, y)),
+                (!is_wall(cx + 1, cy), (x + 1, y), (x + 1, y + 1)),
+                (!is_wall(cx, cy + 1), (x + 1, y + 1), (x, y + 1)),
+                (!is_wall(cx - 1, cy), (x, y + 1), (x, y)),
+            ] {
+                if open {
+                    edges.entry(from).or_default().push(to);
+                    count += 1;
+                }
+            }
+        }
+
+        let mut starts: Vec<(usize, usize)> = edges.keys().copied().collect();
+        starts.sort_by_key(|&(x, y)| (y, x));
+        let mut outlines = Vec::new();
+        while count > 0 {
+            let first = *starts
+                .iter()
+                .find(|&start| edges.get(start).is_some_and(|ends| !ends.is_empty()))
+                .unwrap();
+            let mut points = vec![first];
+            let mut current = first;
+            loop {
+                let next = edges.get_mut(&current).unwrap().pop().unwrap();
+                count -= 1;
+                if next == first {
+                    break;
+                }
+                points.push(next);
+                current = next;
+            }
+            // Drop the points in the middle of straight stretches
+            let len = points.len();
+            let corners = (0..len)
+                .filter(|&i| {
+                    let (prev, point, next) = (
+                        points[(i + len - 1) % len],
+                        points[i],
+                        points[(i + 1) % len],
+                    );
+                    let vertical = prev.0 == point.0 && point.0 == next.0;
+                    let horizontal = prev.1 == point.1 && point.1 == next.1;
+                    !vertical && !horizontal
+                })
+                .map(|i| points[i])
+                .collect();
+            outlines.push(corners);
+        }
+        outlines
+    }
+
+    pub fn export_to_laser_svg(&self, filename: &str, cell_mm: f32) -> Result<(), MazeError> {
+        if cell_mm.is_nan() || cell_mm <= 0.0 {
+            return Err(MazeError::InvalidOption {
+                name: "cell size",
+                reason: format!("{} mm isn't a positive size", cell_mm),
+            });
+        }
+        write_file(filename, |file| self.write_laser_svg(file, cell_mm))
+    }
+
+    /// Writes the outlines of the walls as cut lines for a laser cutter,
+    /// one closed path each, sized in millimeters with `cell_mm` per cell.
+    pub fn write_laser_svg<W: Write>(&self, out: &mut W, cell_mm: f32) -> std::io::Result<()> {
+        let (width, height) = (self.width as f32 * cell_mm, self.height as f32 * cell_mm);
+        writeln!(
+            out,
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{}mm\" height=\"{}mm\" viewBox=\"0 0 {} {}\">",
+            width, height, width, height
+        )?;
+        // The view box is in millimeters, so this is a hairline of 0.01 mm
+        for outline in self.wall_outlines() {
+            write!(
+                out,
+                "  <path fill=\"none\" stroke=\"red\" stroke-width=\"0.01\" d=\""
+            )?;
+            for (i, &(x, y)) in outline.iter().enumerate() {
+                let command = if i == 0 { "M" } else { "L" };
+                write!(
+                    out,
+                    "{} {} {} ",
+                    command,
+                    x as f32 * cell_mm,
+                    y as f32 * cell_mm
+                )?;
+            }
+            writeln!(out, "Z\" />")?;
+        }
+        writeln!(out, "</svg>")
+    }
+
+    pub fn export_to_dot(&self, filename: &str) -> Result<(), MazeError> {
+        write_file(filename, |file| self.write_dot(file))
+    }
+
+    pub fn write_dot<W: Write>(&self, file: &mut W) -> std::io::Result<()> {
+        self.write_dot_with_mst(file, None)
+    }
+
+    pub fn export_to_dot_with_mst(&self, filename: &str, mst: &MstResult) -> Result<(), MazeError> {
+        write_file(filename, |file| self.write_dot_with_mst(file, Some(mst)))
+    }
+
+    /// Like `write_dot`, drawing the edges of `mst` bold
+    pub fn write_dot_with_mst<W: Write>(
+        &self,
+        file: &mut W,
+        mst: Option<&MstResult>,
+    ) -> std::io::Result<()> {
+        let (nodes, edges) = self.build_graph();
+
+        // Write DOT file header
+        writeln!(file, "graph Maze {{")?;
+        writeln!(file, "    node [shape=point];")?;
+        writeln!(file, "    edge [len=1.0];")?;
+
+        // Write nodes
+        let center_pos = Pos {
+            x: self.width / 2,
+            y: self.height / 2,
+        };
+
+        let exit_pos = self.exit_pos();
+
+        // Nodes and edges are written in a fixed order, so the same maze
+        // always gives the same file
+        let mut sorted_nodes: Vec<(Pos, usize)> = nodes.into_iter().collect();
+        sorted_nodes.sort_by_key(|&(_, node_id)| node_id);
+        for (pos, node_id) in sorted_nodes {
+            if pos == center_pos {
+                writeln!(
+                    file,
+                    "    n{} [color=green, shape=circle, label=\"Start\"];",
+                    node_id
+                )?;
+            } else if Some(pos) == exit_pos {
+                writeln!(
+                    file,
+                    "    n{} [color=red, shape=box, label=\"Exit\"];",
+                    node_id
+                )?;
+            } else {
+                // Determine if node is a dead end or junction
+                let neighbors = pos
+                    .neighbors(self.get_size())
+                    .filter(|p| self.get(p.x, p.y) == CellType::Path)
+                    .count();
+
+                let label = if neighbors == 1 {
+                    "Dead End"
+                } else {
+                    "Junction"
+                };
+                writeln!(file, "    n{} [label=\"{}\"];", node_id, label)?;
+            }
+        }
+
+        // Write edges
+        let mut sorted_edges: Vec<Edge> = edges.into_iter().collect();
+        sorted_edges.sort_by_key(|edge| (edge.start_id, edge.end_id, edge.weight));
+        for edge in sorted_edges {
+            let style = match mst {
+                Some(mst) if mst.edges.contains(&edge) => ", penwidth=3",
+                _ => "",
+            };
+            writeln!(
+                file,
+                "    n{} -- n{} [len={:.1}, label=\"{}\"{}];",
+                edge.start_id, edge.end_id, edge.weight, edge.weight, style
+            )?;
+        }
+
+        writeln!(file, "}}")?;
+        Ok(())
+    }
+
+    /// Encodes the maze as a compact string that can be pasted into a chat
+    /// or a URL fragment. The string is the URL-safe base64 encoding of a
+    /// version byte, the dimensions, the room size and exit location, the
+    /// corridor width if it isn't 1, followed by the cells as (cell type,
+    /// run length) pairs. Mazes wider or higher than 65535 cells, or with a
+    /// room or corridors of more than 255 cells, don't fit into the header.
+    pub fn to_share_string(&self) -> Result<String, MazeError> {
+        let too_big = |reason: &str| MazeError::InvalidDimensions {
+            width: self.width,
+            height: self.height,
+            reason: format!("{} for a share code", reason),
+        };
+        let width = u16::try_from(self.width).map_err(|_| too_big("too wide"))?;
+        let height = u16::try_from(self.height).map_err(|_| too_big("too high"))?;
+        let room_size = u8::try_from(self.room_size).map_err(|_| MazeError::InvalidRoomSize {
+            room_size: self.room_size,
+            reason: "too big for a share code".to_string(),
+        })?;
+        let corridor_width =
+            u8::try_from(self.corridor_width).map_err(|_| MazeError::InvalidOption {
+                name: "corridor width",
+                reason: format!("{} is too wide for a share code", self.corridor_width),
+            })?;
+        let version = if corridor_width == 1 {
+            1
+        } else {
+            SHARE_VERSION
+        };
+        let mut bytes = vec![version];
+        bytes.extend(width.to_be_bytes());
+        bytes.extend(height.to_be_bytes());
+        bytes.push(room_size);
+        bytes.push(
+            EXIT_LOCATIONS
+                .iter()
+                .position(|exit_type| *exit_type == self.exit_side())
+                .unwrap() as u8,
+        );
+        if version >= 2 {
+            bytes.push(corridor_width);
+        }
+        for run in self.cells.chunk_by(|a, b| a == b) {
+            for chunk in run.chunks(u8::MAX as usize) {
+                bytes.push(chunk[0] as u8);
+                bytes.push(chunk.len() as u8);
+            }
+        }
+        Ok(URL_SAFE_NO_PAD.encode(bytes))
+    }
+
+    /// Decodes a maze from a string produced by `to_share_string()`.
+    pub fn from_share_string(code: &str) -> Result<Maze, MazeError> {
+        let error = |reason: String| MazeError::Parse { line: 1, reason };
+        let bytes = URL_SAFE_NO_PAD
+            .decode(code.trim())
+            .map_err(|err| error(err.to_string()))?;
+        let Some((&version, rest)) = bytes.split_first() else {
+            return Err(error("empty share code".to_string()));
+        };
+        if version == 0 || version > SHARE_VERSION {
+            return Err(MazeError::UnsupportedVersion {
+                version: version as u64,
+                supported: SHARE_VERSION as u64,
+            });
+        }
+        let Some((header, mut runs)) = rest.split_first_chunk::<6>() else {
+            return Err(error("share code header too short".to_string()));
+        };
+        let mut corridor_width = 1;
+        if version >= 2 {
+            let Some((&width, rest)) = runs.split_first() else {
+                return Err(error("share code header too short".to_string()));
+            };
+            corridor_width = width as usize;
+            runs = rest;
+        }
+        let width = u16::from_be_bytes([header[0], header[1]]) as usize;
+        let height = u16::from_be_bytes([header[2], header[3]]) as usize;
+        let room_size = header[4] as usize;
+        let exit_type = EXIT_LOCATIONS
+            .get(header[5] as usize)
+            .ok_or_else(|| error(format!("unknown exit location {}", header[5])))?
+            .clone();
+        if runs.len() % 2 != 0 {
+            return Err(error("truncated cell data".to_string()));
+        }
+        let cell_count: usize = runs.chunks(2).map(|run| run[1] as usize).sum();
+        let mut maze = Maze::checked_new(
+            width,
+            height,
+            room_size,
+            exit_type,
+            corridor_width,
+            cell_count,
+        )?;
+        maze.cells.clear();
+        for run in runs.chunks(2) {
+            let cell_type = *CELL_TYPES
+                .get(run[0] as usize)
+                .ok_or_else(|| error(format!("unknown cell type {}", run[0])))?;
+            maze.cells
+                .extend(std::iter::repeat_n(cell_type, run[1] as usize));
+        }
+        Ok(maze)
+    }
+
+    /// Creates an all-wall maze for decoding, making sure the stored size
+    /// is one `new` would produce, the room fits and `cell_count` cells fill
+    /// it exactly. The cells are counted before anything is allocated, so a
+    /// header claiming a huge size fails right away.
+    fn checked_new(
+        width: usize,
+        height: usize,
+        room_size: usize,
+        exit_type: ExitLocation,
+        corridor_width: usize,
+        cell_count: usize,
+    ) -> Result<Maze, MazeError> {
+        if corridor_width == 0 {
+            return Err(MazeError::InvalidOption {
+                name: "corridor width",
+                reason: "it has to be at least 1".to_string(),
+            });
+        }
+        if width.checked_mul(height) != Some(cell_count) {
+            return Err(MazeError::InvalidDimensions {
+                width,
+                height,
+                reason: format!("{} cells given", cell_count),
+            });
+        }
+        if corridor_width >= width.min(height) {
+            return Err(MazeError::InvalidOption {
+                name: "corridor width",
+                reason: format!("{} cells don't fit into the maze", corridor_width),
+            });
+        }
+        let maze = Maze::with_corridors(width, height, room_size, exit_type, corridor_width);
+        if maze.get_size() != (width, height) {
+            return Err(MazeError::InvalidDimensions {
+                width,
+                height,
+                reason: if corridor_width == 1 {
+                    "sizes are 7, 11, 15, and so on".to_string()
+                } else {
+                    format!("not a size for corridors {} cells wide", corridor_width)
+                },
+            });
+        }
+        maze.check_room_size()?;
+        Ok(maze)
+    }
+
+    /// The room has to leave at least the border around it and hold the
+    /// start.
+    fn check_room_size(&self) -> Result<(), MazeError> {
+        let Room {
+            top_left,
+            width,
+            height,
+        } = self.room;
+        if top_left.x == 0
+            || top_left.y == 0
+            || top_left.x + width >= self.width
+            || top_left.y + height >= self.height
+        {
+            return Err(MazeError::InvalidRoomSize {
+                room_size: self.room_size,
+                reason: format!(
+                    "the room doesn't fit into a {}x{} maze",
+                    self.width, self.height
+                ),
+            });
+        }
+        if !self.room.contains(self.start_pos()) {
+            return Err(MazeError::InvalidRoomSize {
+                room_size: self.room_size,
+                reason: format!("the room has to hold the start at {}", self.start_pos()),
+            });
+        }
+        Ok(())
+    }
+
+    #[cfg(feature = "serde")]
+    /// Serializes the maze to JSON, tagged with the file format version.
+    pub fn to_json(&self) -> String {
+        let file = MazeFile {
+            version: MAZE_FILE_VERSION,
+            width: self.width,
+            height: self.height,
+            room_size: self.room_size,
+            exit_type: self.exit_type.clone(),
+            corridor_width: self.corridor_width,
+            cells: self.cells.clone(),
+            labels: {
+                let mut labels: Vec<(Pos, char)> = self.labels.clone().into_iter().collect();
+                labels.sort();
+                labels
+            },
+            room: (self.room != self.centered_room()).then_some(self.room),
+            weights: {
+                let mut weights: Vec<(CellType, i32)> = self
+                    .weights
+                    .0
+                    .iter()
+                    .map(|(&cell, &weight)| (cell, weight))
+                    .collect();
+                weights.sort_by_key(|&(cell, _)| cell as u8);
+                weights
             },
-            ExitLocation::Random => {
-                // Random exit if none specified
-                let exit_positions = [
-                    Pos {
-                        x: 0,
-                        y: self.height / 2,
-                    }, // Left
-                    Pos {
-                        x: self.width - 1,
-                        y: self.height / 2,
-                    }, // Right
-                    Pos {
-                        x: self.width / 2,
-                        y: 0,
-                    }, // Top
-                    Pos {
-                        x: self.width / 2,
-                        y: self.height - 1,
-                    }, // Bottom
-                ];
-                exit_positions[rand::rng().random_range(0..4)]
-            }
-        };
-        self.set(exit_pos.x, exit_pos.y, CellType::Exit);
-        self.generate_from(start);
+        };
+        serde_json::to_string(&file).unwrap()
+    }
 
-        // After maze generation, remove some walls to create multiple paths
-        let mut rng = rand::rng();
-        let wall_removal_count = (self.width + self.height) / 8; // Adjust this value to control how many walls to remove
-        log::info!("Removing {} walls", wall_removal_count);
+    #[cfg(feature = "serde")]
+    pub fn save_json(&self, filename: &str) -> Result<(), MazeError> {
+        write_file(filename, |file| file.write_all(self.to_json().as_bytes()))
+    }
 
-        for _ in 0..wall_removal_count {
-            // Find walls that are not on the edge and are surrounded by exactly two path cells
-            let mut candidate_walls = Vec::new();
+    #[cfg(feature = "serde")]
+    /// Reads a maze written by `to_json`.
+    pub fn from_json(json: &str) -> Result<Maze, MazeError> {
+        Maze::from_bytes(json.as_bytes())
+    }
 
-            for y in 1..self.height - 1 {
-                for x in 1..self.width - 1 {
-                    if self.get(x, y) != CellType::Wall {
-                        continue;
-                    }
-                    let adjacent_paths = [(x + 1, y), (x - 1, y), (x, y + 1), (x, y - 1)]
-                        .iter()
-                        .filter(|&&(ax, ay)| self.get(ax, ay) == CellType::Path)
-                        .count();
+    #[cfg(feature = "serde")]
+    /// Reads a maze file from its raw contents. The version is checked
+    /// before anything else, so files from newer releases are reported as
+    /// such instead of failing on fields this release doesn't know.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Maze, MazeError> {
+        let invalid = |err: serde_json::Error| MazeError::Parse {
+            line: err.line(),
+            reason: err.to_string(),
+        };
+        let value: serde_json::Value = serde_json::from_slice(bytes).map_err(invalid)?;
+        let file: MazeFile = match value.get("version").and_then(|version| version.as_u64()) {
+            Some(version) if version == MAZE_FILE_VERSION as u64 => {
+                serde_json::from_value(value).map_err(invalid)?
+            }
+            Some(version) => {
+                return Err(MazeError::UnsupportedVersion {
+                    version,
+                    supported: MAZE_FILE_VERSION as u64,
+                });
+            }
+            None => {
+                return Err(MazeError::Parse {
+                    line: 1,
+                    reason: "missing version".to_string(),
+                });
+            }
+        };
+        let mut maze = Maze::checked_new(
+            file.width,
+            file.height,
+            file.room_size,
+            file.exit_type,
+            file.corridor_width,
+            file.cells.len(),
+        )?;
+        maze.cells = file.cells;
+        maze.labels = file
+            .labels
+            .into_iter()
+            .filter(|&(pos, _)| maze.check_bounds(pos).is_ok())
+            .collect();
+        maze.weights = WeightTable(file.weights.into_iter().collect());
+        // Rooms of mazes with wide corridors may be off the middle after a
+        // transformation, which `set_room` doesn't allow
+        if let Some(room) = file.room {
+            maze.room = room;
+            maze.check_room_size()?;
+        }
+        Ok(maze)
+    }
 
-                    // If exactly two adjacent cells are paths and they're not diagonally opposite
-                    if adjacent_paths != 2 {
-                        continue;
+    #[cfg(feature = "serde")]
+    pub fn load_json(filename: &str) -> Result<Maze, MazeError> {
+        let bytes = std::fs::read(filename).map_err(|source| MazeError::Io {
+            path: Some(filename.to_string()),
+            source,
+        })?;
+        Maze::from_bytes(&bytes)
+    }
+
+    pub fn stats(&self) -> MazeStats {
+        let traversable: Vec<Pos> = self
+            .iter_cells()
+            .filter(|(_, cell)| TRAVERSABLE.contains(cell))
+            .map(|(pos, _)| pos)
+            .collect();
+        let open_neighbors = |pos: Pos| {
+            Direction::ALL
+                .into_iter()
+                .filter(|&direction| self.step(pos, direction).is_some())
+                .count()
+        };
+        let count = |cell_types: &[CellType]| {
+            self.cells
+                .iter()
+                .filter(|cell| cell_types.contains(cell))
+                .count()
+        };
+        MazeStats {
+            width: self.width,
+            height: self.height,
+            room_size: self.room_size,
+            open_cells: traversable.len(),
+            dead_ends: self.dead_ends().len(),
+            junctions: traversable
+                .iter()
+                .filter(|&&pos| open_neighbors(pos) > 2)
+                .count(),
+            loops: self.count_loops(),
+            articulation_points: self.articulation_points().len(),
+            cut_corridors: self.cut_corridors().len(),
+            rewards: count(&REWARDS),
+            dangers: count(&DANGERS),
+            solution_length: self
+                .solve_instrumented(Solver::Bfs)
+                .path
+                .map(|path| path.len()),
+            difficulty: self.difficulty(),
+            fingerprint: self.fingerprint(),
+            reward_distances: self.reward_distances(),
+        }
+    }
+
+    /// Whether there is exactly one way between any two open cells: the
+    /// passages are connected and have no loops.
+    pub fn is_perfect(&self) -> bool {
+        let (loops, parts) = self.passage_rank();
+        loops == 0 && parts == 1
+    }
+
+    /// Number of independent loops in the passages, i.e. how many walls
+    /// could be put back without cutting off any cell. The center room and
+    /// the cells across a wide corridor count as one, so only the layout of
+    /// the passages makes loops.
+    pub fn count_loops(&self) -> usize {
+        self.passage_rank().0
+    }
+
+    /// Cycle rank and number of connected parts of the graph of passages,
+    /// with a node per cell of the narrow maze and one for the whole room
+    fn passage_rank(&self) -> (usize, usize) {
+        let (narrow_width, narrow_height) = self.narrow_size();
+        let room = Pos {
+            x: narrow_width / 2,
+            y: narrow_height / 2,
+        };
+        let period = self.corridor_width + 1;
+        let narrow = |i: usize| 2 * (i / period) + usize::from(!i.is_multiple_of(period));
+        let node = |pos: Pos| {
+            if self.in_center_room(pos) {
+                room
+            } else {
+                Pos {
+                    x: narrow(pos.x),
+                    y: narrow(pos.y),
+                }
+            }
+        };
+
+        let mut nodes = HashSet::new();
+        let mut edges = HashSet::new();
+        for (pos, cell) in self.iter_cells() {
+            if !TRAVERSABLE.contains(&cell) {
+                continue;
+            }
+            let from = node(pos);
+            nodes.insert(from);
+            for direction in Direction::ALL {
+                if let Some(next) = self.step(pos, direction) {
+                    let to = node(next);
+                    if from != to {
+                        edges.insert((from.min(to), from.max(to)));
                     }
-                    // Check that the paths aren't diagonally opposite
-                    let has_horizontal_pair = self.get(x + 1, y) == CellType::Path
-                        && self.get(x - 1, y) == CellType::Path;
-                    let has_vertical_pair = self.get(x, y + 1) == CellType::Path
-                        && self.get(x, y - 1) == CellType::Path;
-                    // Only add wall if the paths are either both horizontal or both vertical
-                    if has_horizontal_pair || has_vertical_pair {
-                        candidate_walls.push((x, y));
+                }
+            }
+        }
+
+        let mut adjacent: HashMap<Pos, Vec<Pos>> = HashMap::new();
+        for &(a, b) in &edges {
+            adjacent.entry(a).or_default().push(b);
+            adjacent.entry(b).or_default().push(a);
+        }
+        let mut seen = HashSet::new();
+        let mut parts = 0;
+        for &first in &nodes {
+            if !seen.insert(first) {
+                continue;
+            }
+            parts += 1;
+            let mut stack = vec![first];
+            while let Some(pos) = stack.pop() {
+                for &next in adjacent.get(&pos).into_iter().flatten() {
+                    if seen.insert(next) {
+                        stack.push(next);
                     }
                 }
             }
-            // Remove a random wall from candidates
-            if !candidate_walls.is_empty() {
-                let (wx, wy) = candidate_walls.choose(&mut rng).unwrap();
-                self.set(*wx, *wy, CellType::Path);
+        }
+        ((edges.len() + parts).saturating_sub(nodes.len()), parts)
+    }
+
+    /// A hash of the size and the cells that is the same for equal grids, no
+    /// matter how they were made, and stays the same across releases and
+    /// platforms. It is 64-bit FNV-1a over the width and height as
+    /// little-endian u64s, followed by one byte per cell in row-major order,
+    /// the cell's share code.
+    pub fn fingerprint(&self) -> u64 {
+        const OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+        const PRIME: u64 = 0x0100_0000_01b3;
+        (self.width as u64)
+            .to_le_bytes()
+            .into_iter()
+            .chain((self.height as u64).to_le_bytes())
+            .chain(self.cells.iter().map(|&cell| cell as u8))
+            .fold(OFFSET_BASIS, |hash, byte| {
+                (hash ^ byte as u64).wrapping_mul(PRIME)
+            })
+    }
+
+    /// Every cell that differs from `other`, in row-major order, with this
+    /// maze's cell on the left. Both mazes have to be the same size.
+    pub fn diff(&self, other: &Maze) -> Result<Vec<CellDiff>, MazeError> {
+        if self.get_size() != other.get_size() {
+            return Err(MazeError::InvalidDimensions {
+                width: other.width,
+                height: other.height,
+                reason: format!("can't compare with a {}x{} maze", self.width, self.height),
+            });
+        }
+        Ok(self
+            .iter_cells()
+            .zip(other.cells.iter())
+            .filter(|&((_, left), &right)| left != right)
+            .map(|((pos, left), &right)| CellDiff { pos, left, right })
+            .collect())
+    }
+
+    /// The shortest way to the exit whose dangers add up to at most
+    /// `max_danger` by their weights, and what they add up to. Rewards cost
+    /// nothing rather than making up for dangers. `None` if the exit can't
+    /// be reached within the budget.
+    ///
+    /// Searches the states of a cell and the danger spent to get there
+    /// breadth first. A state is dropped if its cell was already reached in
+    /// as few steps with no more danger, which bounds the states per cell by
+    /// the distinct amounts of danger and keeps the search finite.
+    pub fn path_with_danger_budget(&self, max_danger: i32) -> Option<(Vec<Pos>, i32)> {
+        let start = self.start_pos();
+        let exit = self.exit_pos()?;
+        let danger = |pos: Pos| self.weights.get(self.get(pos.x, pos.y)).max(0);
+        if danger(start) > max_danger {
+            return None;
+        }
+        // The least danger each cell was reached with so far
+        let mut least = vec![i32::MAX; self.cells.len()];
+        least[start.y * self.width + start.x] = danger(start);
+        // Cell, danger spent and the label it was reached from
+        let mut labels = vec![(start, danger(start), usize::MAX)];
+        let mut queue = std::collections::VecDeque::from([0]);
+        while let Some(label) = queue.pop_front() {
+            let (pos, spent, _) = labels[label];
+            if pos == exit {
+                let mut path = Vec::new();
+                let mut current = label;
+                while current != usize::MAX {
+                    path.push(labels[current].0);
+                    current = labels[current].2;
+                }
+                path.reverse();
+                return Some((Maze::with_bridges(path), spent));
+            }
+            for direction in Direction::ALL {
+                let Some(next) = self.step(pos, direction) else {
+                    continue;
+                };
+                let spent = spent + danger(next);
+                let idx = next.y * self.width + next.x;
+                if spent <= max_danger && spent < least[idx] {
+                    least[idx] = spent;
+                    labels.push((next, spent, label));
+                    queue.push_back(labels.len() - 1);
+                }
             }
         }
+        None
     }
 
-    /// This code implements a Randomized Depth-First Search (DFS)
-    /// maze generation algorithm a.k.a. backtracking algorithm.
-    fn generate_from(&mut self, start: Pos) {
-        let mut rng = rand::rng();
-        let mut stack = vec![start];
+    /// The cheapest way to the exit by the weights of the cells, found with
+    /// Dijkstra's algorithm. A step costs one plus the weight of the cell it
+    /// enters, so a danger is worth as many steps of detour as it weighs.
+    /// Rewards cost nothing rather than making up for dangers, which keeps
+    /// the costs from going negative.
+    pub fn least_cost_path(&self) -> Option<Vec<Pos>> {
+        let start = self.start_pos();
+        let exit = self.exit_pos()?;
+        let danger = |pos: Pos| self.weights.get(self.get(pos.x, pos.y)).max(0) as usize;
+        let mut costs = vec![usize::MAX; self.cells.len()];
+        let mut came_from: Vec<Option<Pos>> = vec![None; self.cells.len()];
+        let mut heap = BinaryHeap::from([Reverse((0, start))]);
+        costs[start.y * self.width + start.x] = 0;
+        while let Some(Reverse((cost, pos))) = heap.pop() {
+            if pos == exit {
+                let mut path = vec![exit];
+                let mut at = exit;
+                while let Some(previous) = came_from[at.y * self.width + at.x] {
+                    path.push(previous);
+                    at = previous;
+                }
+                path.reverse();
+                return Some(Maze::with_bridges(path));
+            }
+            if cost > costs[pos.y * self.width + pos.x] {
+                continue;
+            }
+            for next in self.open_neighbors(pos) {
+                // Passing under a bridge takes two steps
+                let next_cost = cost + pos.manhattan(next) + danger(next);
+                let idx = next.y * self.width + next.x;
+                if next_cost < costs[idx] {
+                    costs[idx] = next_cost;
+                    came_from[idx] = Some(pos);
+                    heap.push(Reverse((next_cost, next)));
+                }
+            }
+        }
+        None
+    }
 
-        let mut visited = HashSet::new();
-        visited.insert(start);
+    /// Sum of the cell weights along `path`: rewards lower it, dangers raise it.
+    pub fn path_cost(&self, path: &[Pos]) -> i32 {
+        path.iter()
+            .map(|pos| self.weights.get(self.get(pos.x, pos.y)))
+            .sum()
+    }
 
-        while let Some(pos) = stack.pop() {
-            let directions = [
-                (
-                    Pos {
-                        x: pos.x + 2,
-                        y: pos.y,
-                    },
-                    Pos {
-                        x: pos.x + 1,
-                        y: pos.y,
-                    },
-                ), // Right
-                (
-                    Pos {
-                        x: pos.x.saturating_sub(2),
-                        y: pos.y,
-                    },
-                    Pos {
-                        x: pos.x.saturating_sub(1),
-                        y: pos.y,
-                    },
-                ), // Left
-                (
-                    Pos {
-                        x: pos.x,
-                        y: pos.y + 2,
-                    },
-                    Pos {
-                        x: pos.x,
-                        y: pos.y + 1,
-                    },
-                ), // Down
-                (
-                    Pos {
-                        x: pos.x,
-                        y: pos.y.saturating_sub(2),
-                    },
-                    Pos {
-                        x: pos.x,
-                        y: pos.y.saturating_sub(1),
-                    },
-                ), // Up
-            ];
+    /// Converts a path into a string of moves, one of `R`, `D`, `L` and `U`
+    /// per step. Consecutive positions have to be adjacent.
+    pub fn path_to_moves(path: &[Pos]) -> Result<String, MazeError> {
+        path.windows(2)
+            .map(|step| {
+                let (from, to) = (step[0], step[1]);
+                match (
+                    to.x as isize - from.x as isize,
+                    to.y as isize - from.y as isize,
+                ) {
+                    (1, 0) => Ok('R'),
+                    (0, 1) => Ok('D'),
+                    (-1, 0) => Ok('L'),
+                    (0, -1) => Ok('U'),
+                    _ => Err(MazeError::NotAdjacent { from, to }),
+                }
+            })
+            .collect()
+    }
+}
 
-            let valid_directions = directions
-                .iter()
-                .filter(|(next, _)| {
-                    next.x > 0
-                        && next.x < self.width - 1
-                        && next.y > 0
-                        && next.y < self.height - 1
-                        && !visited.contains(next)
+/// Stairs connecting each pair of adjacent levels of a `Maze3D`, as long as
+/// there are enough cells both levels have a path on
+const STAIRS_PER_LEVEL: usize = 2;
+
+/// Mazes stacked on top of each other. Each entry of `stairs` is a cell
+/// with a staircase from level `z` up to the same cell on level `z + 1`.
+/// The start is the center of the middle level and the one exit is on the
+/// border of any level.
+#[derive(Clone)]
+pub struct Maze3D {
+    pub levels: Vec<Maze>,
+    pub stairs: Vec<(usize, Pos)>,
+}
+
+impl Maze3D {
+    /// Builds `levels` mazes with `builder` and connects them, using the
+    /// thread-local RNG.
+    pub fn generate(builder: &MazeBuilder, levels: usize) -> Result<Self, MazeError> {
+        Maze3D::generate_with_rng(builder, levels, &mut rand::rng())
+    }
+
+    /// Same as `generate`, but draws all randomness from `rng`. The seed of
+    /// `builder` is ignored.
+    pub fn generate_with_rng<R: Rng + ?Sized>(
+        builder: &MazeBuilder,
+        levels: usize,
+        rng: &mut R,
+    ) -> Result<Self, MazeError> {
+        if levels == 0 {
+            return Err(MazeError::InvalidOption {
+                name: "levels",
+                reason: "there has to be at least one level".to_string(),
+            });
+        }
+        let mut mazes = (0..levels)
+            .map(|_| builder.build_with_rng(rng))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        // Only the middle level keeps its start and one level its exit
+        let start_level = levels / 2;
+        let exit_level = rng.random_range(0..levels);
+        for (z, maze) in mazes.iter_mut().enumerate() {
+            for cell in maze.cells_mut() {
+                match cell {
+                    CellType::Start if z != start_level => *cell = CellType::Path,
+                    CellType::Exit if z != exit_level => *cell = CellType::Wall,
+                    _ => {}
+                }
+            }
+        }
+
+        let mut stairs = Vec::new();
+        for z in 0..levels - 1 {
+            let (lower, upper) = (&mazes[z], &mazes[z + 1]);
+            let mut candidates: Vec<Pos> = lower
+                .iter_cells()
+                .filter(|&(pos, cell)| {
+                    cell == CellType::Path
+                        && upper.get(pos.x, pos.y) == CellType::Path
+                        && !lower.in_center_room(pos)
                 })
-                .collect::<Vec<_>>();
+                .map(|(pos, _)| pos)
+                .collect();
+            if candidates.is_empty() {
+                return Err(MazeError::Unsolvable);
+            }
+            candidates.shuffle(rng);
+            stairs.extend(
+                candidates
+                    .into_iter()
+                    .take(STAIRS_PER_LEVEL)
+                    .map(|pos| (z, pos)),
+            );
+        }
+        Ok(Maze3D {
+            levels: mazes,
+            stairs,
+        })
+    }
 
-            if !valid_directions.is_empty() {
-                stack.push(pos);
+    pub fn start_pos(&self) -> (usize, Pos) {
+        let z = self.levels.len() / 2;
+        (z, self.levels[z].start_pos())
+    }
 
-                let (next, wall) = valid_directions.choose(&mut rng).unwrap();
+    /// The level and cell of the exit
+    pub fn exit_pos(&self) -> Option<(usize, Pos)> {
+        self.levels.iter().enumerate().find_map(|(z, maze)| {
+            maze.iter_cells()
+                .find(|&(_, cell)| cell == CellType::Exit)
+                .map(|(pos, _)| (z, pos))
+        })
+    }
 
-                // Carve a path through the wall
-                self.set(wall.x, wall.y, CellType::Path);
-                self.set(next.x, next.y, CellType::Path);
+    /// The cells one step away from `pos` on level `z`, including the other
+    /// end of a staircase.
+    pub fn neighbors(&self, (z, pos): (usize, Pos)) -> Vec<(usize, Pos)> {
+        let maze = &self.levels[z];
+        let mut neighbors: Vec<(usize, Pos)> = pos
+            .neighbors(maze.get_size())
+            .filter(|next| TRAVERSABLE.contains(&maze.get(next.x, next.y)))
+            .map(|next| (z, next))
+            .collect();
+        for &(stair_z, stair_pos) in &self.stairs {
+            if stair_pos != pos {
+                continue;
+            }
+            if stair_z == z {
+                neighbors.push((z + 1, pos));
+            } else if stair_z + 1 == z {
+                neighbors.push((stair_z, pos));
+            }
+        }
+        neighbors
+    }
 
-                visited.insert(*next);
-                stack.push(*next);
+    /// The shortest way from the start to the exit across all levels, found
+    /// with a breadth-first search. Taking the stairs counts as one step.
+    pub fn solve(&self) -> Option<Vec<(usize, Pos)>> {
+        let start = self.start_pos();
+        let exit = self.exit_pos()?;
+        let mut came_from = HashMap::from([(start, start)]);
+        let mut queue = std::collections::VecDeque::from([start]);
+        while let Some(current) = queue.pop_front() {
+            if current == exit {
+                let mut path = vec![exit];
+                let mut step = exit;
+                while step != start {
+                    step = came_from[&step];
+                    path.push(step);
+                }
+                path.reverse();
+                return Some(path);
+            }
+            for next in self.neighbors(current) {
+                if let std::collections::hash_map::Entry::Vacant(entry) = came_from.entry(next) {
+                    entry.insert(current);
+                    queue.push_back(next);
+                }
             }
         }
+        None
     }
 
-    pub fn place_artifacts(&mut self, fill_ratio: f32) {
-        let mut rng = rand::rng();
+    pub fn export_to_svg(&self, filename: &str, options: &SvgOptions) -> Result<(), MazeError> {
+        write_file(filename, |file| self.write_svg(file, options))
+    }
 
-        // Calculate how many cells should have artifacts
-        let path_cells = self.cells.iter().filter(|&&c| c == CellType::Path).count();
-        let artifacts_count = (path_cells as f32 * fill_ratio) as usize;
+    /// Writes the levels side by side, from the lowest on the left, one cell
+    /// apart. Stairs are marked with a triangle pointing up on the lower
+    /// level and down on the upper one, titled with the level they lead to.
+    /// The solution, if requested, is the one across all levels.
+    pub fn write_svg<W: Write>(&self, file: &mut W, options: &SvgOptions) -> std::io::Result<()> {
+        let scale = options.scale;
+        let (width, height) = self.levels[0].get_size();
+        let total_width = self.levels.len() * (width + 1) - 1;
+        writeln!(
+            file,
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{}\" height=\"{}\" viewBox=\"0 0 {} {}\">",
+            total_width as f32 * scale,
+            height as f32 * scale,
+            total_width as f32 * scale,
+            height as f32 * scale
+        )?;
+        writeln!(
+            file,
+            "<rect width=\"100%\" height=\"100%\" fill=\"{}\" />",
+            hex_color(options.theme.path_color)
+        )?;
+        write_svg_icon_defs(
+            file,
+            self.levels
+                .iter()
+                .flat_map(|level| level.cells.iter().copied()),
+            options,
+        )?;
+        writeln!(file, "  <g transform=\"scale({})\" >", scale)?;
 
-        let center_x = self.width / 2;
-        let center_y = self.height / 2;
+        let level_options = SvgOptions {
+            with_solution: SolutionType::None,
+            show_visited: false,
+            ..options.clone()
+        };
+        let offset = |z: usize| (z * (width + 1)) as f32;
+        for (z, maze) in self.levels.iter().enumerate() {
+            writeln!(file, "  <g transform=\"translate({} 0)\" >", offset(z))?;
+            maze.write_svg_cells(file, &level_options)?;
+            writeln!(file, "  </g>")?;
+        }
 
-        // Collect all valid positions
-        let mut valid_positions: Vec<Pos> = (0..self.height)
-            .flat_map(|y| (0..self.width).map(move |x| Pos { x, y }))
-            .filter(|pos| {
-                let in_center_room = pos.x >= center_x - self.room_size / 2
-                    && pos.x <= center_x + self.room_size / 2
-                    && pos.y >= center_y - self.room_size / 2
-                    && pos.y <= center_y + self.room_size / 2;
+        for &(z, pos) in &self.stairs {
+            let (x, y) = (pos.x as f32, pos.y as f32);
+            for (level, points, destination) in [
+                (z, [(0.5, 0.15), (0.85, 0.8), (0.15, 0.8)], z + 1),
+                (z + 1, [(0.5, 0.85), (0.85, 0.2), (0.15, 0.2)], z),
+            ] {
+                write!(file, "    <polygon points=\"")?;
+                for (dx, dy) in points {
+                    write!(file, "{},{} ", offset(level) + x + dx, y + dy)?;
+                }
+                writeln!(
+                    file,
+                    "\" fill=\"{}\"><title>Stairs to level {}</title></polygon>",
+                    hex_color(options.theme.solution_color),
+                    destination + 1
+                )?;
+            }
+        }
+
+        if options.with_solution == SolutionType::ShortestPath
+            && let Some(solution) = self.solve()
+        {
+            // One line per stretch on a single level
+            let lines: Vec<Vec<(f32, f32)>> = solution
+                .chunk_by(|a, b| a.0 == b.0)
+                .map(|stretch| {
+                    stretch
+                        .iter()
+                        .map(|(z, pos)| (offset(*z) + pos.x as f32 + 0.5, pos.y as f32 + 0.5))
+                        .collect()
+                })
+                .collect();
+            write_svg_overlay(file, &lines, OverlayColor::Solution, options)?;
+        }
+
+        writeln!(file, "  </g>")?;
+        writeln!(file, "</svg>")?;
+        Ok(())
+    }
+}
+
+/// A cell of a `CircularMaze`. Ring 0 is the single cell at the center,
+/// `index` counts the cells of a ring clockwise from the top.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct PolarPos {
+    pub ring: usize,
+    pub index: usize,
+}
+
+impl Display for PolarPos {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "(ring {}, {})", self.ring, self.index)
+    }
+}
+
+/// A maze of concentric rings around a center cell. The first ring has six
+/// cells, after that a ring has twice as many cells as the one inside of it
+/// whenever they would otherwise get too wide, so each cell has one cell
+/// inwards and one or two outwards. The start is the center and the exit is
+/// on the outermost ring.
+#[derive(Clone)]
+pub struct CircularMaze {
+    /// Number of cells in each ring, starting with the center
+    ring_sizes: Vec<usize>,
+    /// Index in `cells` of the first cell of each ring
+    offsets: Vec<usize>,
+    cells: Vec<CellType>,
+    /// Whether the wall towards the inner ring is open, per cell
+    inward_open: Vec<bool>,
+    /// Whether the wall towards the next cell clockwise is open, per cell
+    clockwise_open: Vec<bool>,
+}
 
-                self.get(pos.x, pos.y) == CellType::Path && !in_center_room
+impl CircularMaze {
+    /// A maze with `rings` rings around the center cell and all walls up.
+    pub fn new(rings: usize) -> Result<Self, MazeError> {
+        if rings < 2 {
+            return Err(MazeError::InvalidOption {
+                name: "rings",
+                reason: "there have to be at least 2 rings around the center".to_string(),
+            });
+        }
+        let mut ring_sizes = vec![1, 6];
+        for ring in 2..=rings {
+            let previous = ring_sizes[ring - 1];
+            // Split the cells when they'd be 1.5 times as wide as they're deep
+            let width = 2.0 * std::f32::consts::PI * ring as f32 / previous as f32;
+            ring_sizes.push(if width >= 1.5 { previous * 2 } else { previous });
+        }
+        let offsets: Vec<usize> = ring_sizes
+            .iter()
+            .scan(0, |offset, size| {
+                *offset += size;
+                Some(*offset - size)
             })
             .collect();
+        let count = ring_sizes.iter().sum();
+        Ok(CircularMaze {
+            ring_sizes,
+            offsets,
+            cells: vec![CellType::Path; count],
+            inward_open: vec![false; count],
+            clockwise_open: vec![false; count],
+        })
+    }
 
-        // Shuffle positions
-        valid_positions.shuffle(&mut rng);
+    /// Number of rings around the center cell
+    pub fn rings(&self) -> usize {
+        self.ring_sizes.len() - 1
+    }
 
-        // Place artifacts
-        let reward_ratio = 0.4; // 40% rewards, 60% dangers
-        let reward_count = (artifacts_count as f32 * reward_ratio) as usize;
-        let danger_count = artifacts_count - reward_count;
+    /// Number of cells in `ring`, 1 for the center
+    pub fn ring_size(&self, ring: usize) -> usize {
+        self.ring_sizes[ring]
+    }
 
-        // Track occupied positions and their adjacent cells
-        let mut occupied_and_adjacent = HashSet::new();
+    pub fn get(&self, pos: PolarPos) -> CellType {
+        self.cells[self.id(pos)]
+    }
 
-        // Place rewards first
-        let mut reward_placed = 0;
-        for pos in &valid_positions {
-            if reward_placed >= reward_count {
-                break;
-            }
+    pub fn set(&mut self, pos: PolarPos, value: CellType) {
+        let id = self.id(pos);
+        self.cells[id] = value;
+    }
 
-            if !occupied_and_adjacent.contains(pos) {
-                // Place the reward
-                let reward = *REWARDS.choose(&mut rng).unwrap();
-                self.set(pos.x, pos.y, reward);
-                reward_placed += 1;
+    pub fn start_pos(&self) -> PolarPos {
+        PolarPos { ring: 0, index: 0 }
+    }
 
-                // Mark this position and adjacent positions as occupied
-                occupied_and_adjacent.insert(*pos);
+    /// The exit cell, if the maze has been generated
+    pub fn exit_pos(&self) -> Option<PolarPos> {
+        let ring = self.rings();
+        (0..self.ring_size(ring))
+            .map(|index| PolarPos { ring, index })
+            .find(|&pos| self.get(pos) == CellType::Exit)
+    }
 
-                // Mark adjacent cells as unavailable
-                let adjacent = [
-                    Pos {
-                        x: pos.x + 1,
-                        y: pos.y,
-                    },
-                    Pos {
-                        x: pos.x.saturating_sub(1),
-                        y: pos.y,
-                    },
-                    Pos {
-                        x: pos.x,
-                        y: pos.y + 1,
-                    },
-                    Pos {
-                        x: pos.x,
-                        y: pos.y.saturating_sub(1),
-                    },
-                ];
+    /// Iterates over all cells, ring by ring from the center.
+    pub fn positions(&self) -> impl Iterator<Item = PolarPos> + '_ {
+        self.ring_sizes
+            .iter()
+            .enumerate()
+            .flat_map(|(ring, &size)| (0..size).map(move |index| PolarPos { ring, index }))
+    }
 
-                for adj in adjacent.iter() {
-                    if adj.x < self.width && adj.y < self.height {
-                        occupied_and_adjacent.insert(*adj);
-                    }
-                }
-            }
+    fn id(&self, pos: PolarPos) -> usize {
+        self.offsets[pos.ring] + pos.index
+    }
+
+    fn clockwise(&self, pos: PolarPos) -> PolarPos {
+        PolarPos {
+            index: (pos.index + 1) % self.ring_size(pos.ring),
+            ..pos
         }
+    }
 
-        // Place dangers
-        let mut danger_placed = 0;
-        for pos in &valid_positions {
-            if danger_placed >= danger_count {
-                break;
-            }
+    fn counter_clockwise(&self, pos: PolarPos) -> PolarPos {
+        let size = self.ring_size(pos.ring);
+        PolarPos {
+            index: (pos.index + size - 1) % size,
+            ..pos
+        }
+    }
+
+    /// The cell inwards of `pos`, none for the center
+    fn inward(&self, pos: PolarPos) -> Option<PolarPos> {
+        let ring = pos.ring.checked_sub(1)?;
+        let ratio = self.ring_size(pos.ring) / self.ring_size(ring);
+        Some(PolarPos {
+            ring,
+            index: pos.index / ratio,
+        })
+    }
+
+    /// The cells outwards of `pos`, none on the outermost ring
+    fn outward(&self, pos: PolarPos) -> impl Iterator<Item = PolarPos> + use<> {
+        let ring = pos.ring + 1;
+        let ratio = self
+            .ring_sizes
+            .get(ring)
+            .map_or(0, |size| size / self.ring_size(pos.ring));
+        (pos.index * ratio..(pos.index + 1) * ratio).map(move |index| PolarPos { ring, index })
+    }
+
+    /// The cells next to `pos`, whether there's a wall in between or not.
+    /// The center has every cell of the first ring as neighbor.
+    pub fn neighbors(&self, pos: PolarPos) -> Vec<PolarPos> {
+        let mut neighbors = Vec::new();
+        if pos.ring > 0 {
+            neighbors.push(self.clockwise(pos));
+            neighbors.push(self.counter_clockwise(pos));
+        }
+        neighbors.extend(self.inward(pos));
+        neighbors.extend(self.outward(pos));
+        neighbors
+    }
+
+    /// Where the open flag of the wall between neighbors `a` and `b` is
+    /// kept: whether it's an inward wall, and the cell it belongs to. That is
+    /// the outer cell, or the cell the other one is clockwise of.
+    fn wall(&self, a: PolarPos, b: PolarPos) -> (bool, usize) {
+        if a.ring != b.ring {
+            (true, self.id(if a.ring > b.ring { a } else { b }))
+        } else {
+            (false, self.id(if self.clockwise(a) == b { a } else { b }))
+        }
+    }
 
-            if !occupied_and_adjacent.contains(pos) {
-                // Place the danger
-                let danger = *DANGERS.choose(&mut rng).unwrap();
-                self.set(pos.x, pos.y, danger);
-                danger_placed += 1;
+    /// Whether one can walk from `a` to its neighbor `b`.
+    pub fn is_open(&self, a: PolarPos, b: PolarPos) -> bool {
+        match self.wall(a, b) {
+            (true, id) => self.inward_open[id],
+            (false, id) => self.clockwise_open[id],
+        }
+    }
 
-                // Mark this position and adjacent positions as occupied
-                occupied_and_adjacent.insert(*pos);
+    /// The neighbors of `pos` without a wall in between.
+    pub fn passages(&self, pos: PolarPos) -> Vec<PolarPos> {
+        self.neighbors(pos)
+            .into_iter()
+            .filter(|&next| self.is_open(pos, next))
+            .collect()
+    }
 
-                // Mark adjacent cells as unavailable
-                let adjacent = [
-                    Pos {
-                        x: pos.x + 1,
-                        y: pos.y,
-                    },
-                    Pos {
-                        x: pos.x.saturating_sub(1),
-                        y: pos.y,
-                    },
-                    Pos {
-                        x: pos.x,
-                        y: pos.y + 1,
-                    },
-                    Pos {
-                        x: pos.x,
-                        y: pos.y.saturating_sub(1),
-                    },
-                ];
+    /// Carves the maze using the thread-local RNG.
+    pub fn generate(&mut self) {
+        self.generate_with_rng(&mut rand::rng());
+    }
 
-                for adj in adjacent.iter() {
-                    if adj.x < self.width && adj.y < self.height {
-                        occupied_and_adjacent.insert(*adj);
+    /// Carves the maze with a randomized depth-first search from the center
+    /// and puts the exit on a random cell of the outermost ring. All
+    /// randomness comes from `rng`.
+    pub fn generate_with_rng<R: Rng + ?Sized>(&mut self, rng: &mut R) {
+        self.inward_open.fill(false);
+        self.clockwise_open.fill(false);
+        self.cells.fill(CellType::Path);
+
+        let start = self.start_pos();
+        let mut visited = HashSet::from([start]);
+        let mut stack = vec![start];
+        while let Some(&pos) = stack.last() {
+            let unvisited: Vec<PolarPos> = self
+                .neighbors(pos)
+                .into_iter()
+                .filter(|next| !visited.contains(next))
+                .collect();
+            match unvisited.choose(rng) {
+                Some(&next) => {
+                    match self.wall(pos, next) {
+                        (true, id) => self.inward_open[id] = true,
+                        (false, id) => self.clockwise_open[id] = true,
                     }
+                    visited.insert(next);
+                    stack.push(next);
+                }
+                None => {
+                    stack.pop();
                 }
             }
         }
-    }
 
-    pub fn shortest_path(&mut self) -> Option<Vec<Pos>> {
-        let center_x = self.width / 2;
-        let center_y = self.height / 2;
-        let start = Pos {
-            x: center_x,
-            y: center_y,
+        let ring = self.rings();
+        let exit = PolarPos {
+            ring,
+            index: rng.random_range(0..self.ring_size(ring)),
         };
+        self.set(start, CellType::Start);
+        self.set(exit, CellType::Exit);
+    }
 
-        let mut visited = HashSet::new();
-        let mut queue = Vec::new();
-
-        queue.push((start, vec![start]));
-        visited.insert(start);
-
-        // For the center room, add all edge cells that lead outside the room
-        // Calculate the boundaries of the center room
-        let room_min_x = center_x - self.room_size / 2;
-        let room_max_x = center_x + self.room_size / 2;
-        let room_min_y = center_y - self.room_size / 2;
-        let room_max_y = center_y + self.room_size / 2;
-
-        // Check all cells at the edge of the room
-        for y in room_min_y..=room_max_y {
-            for x in room_min_x..=room_max_x {
-                if x == room_min_x || x == room_max_x || y == room_min_y || y == room_max_y {
-                    // This is an edge cell of the room
-                    let pos = Pos { x, y };
-
-                    // Check if there's a path leading out from this edge
-                    let directions = [
-                        (x + 1, y),
-                        (x.saturating_sub(1), y),
-                        (x, y + 1),
-                        (x, y.saturating_sub(1)),
-                    ];
-
-                    for (nx, ny) in directions {
-                        if nx < self.width
-                            && ny < self.height
-                            && TRAVERSABLE.contains(&self.get(nx, ny))
-                            && !(nx >= room_min_x
-                                && nx <= room_max_x
-                                && ny >= room_min_y
-                                && ny <= room_max_y)
-                        {
-                            // This edge cell has a path leading outside the room
-                            let path = vec![pos];
-                            queue.insert(0, (pos, path));
-                            visited.insert(pos);
-                            break;
-                        }
-                    }
-                }
+    /// Places rewards and dangers on up to `fill_ratio` of the path cells,
+    /// keeping them apart like `Maze::place_artifacts_with_rng` does.
+    pub fn place_artifacts_with_rng<R: Rng + ?Sized>(&mut self, fill_ratio: f32, rng: &mut R) {
+        let mut valid_positions: Vec<PolarPos> = self
+            .positions()
+            .filter(|&pos| self.get(pos) == CellType::Path)
+            .collect();
+        let artifacts_count = (valid_positions.len() as f32 * fill_ratio) as usize;
+        valid_positions.shuffle(rng);
+
+        let reward_count = (artifacts_count as f32 * 0.4) as usize;
+        let mut occupied_and_adjacent = HashSet::new();
+        let mut placed = 0;
+        for pos in valid_positions {
+            if placed >= artifacts_count {
+                break;
             }
+            if occupied_and_adjacent.contains(&pos) {
+                continue;
+            }
+            let artifact = if placed < reward_count {
+                *REWARDS.choose(rng).unwrap()
+            } else {
+                *DANGERS.choose(rng).unwrap()
+            };
+            self.set(pos, artifact);
+            placed += 1;
+            occupied_and_adjacent.insert(pos);
+            occupied_and_adjacent.extend(self.neighbors(pos));
         }
-        while let Some((pos, path)) = queue.pop() {
-            if self.get(pos.x, pos.y) == CellType::Exit {
+    }
+
+    /// The shortest way from the center to the exit, found with a
+    /// breadth-first search.
+    pub fn solve(&self) -> Option<Vec<PolarPos>> {
+        let start = self.start_pos();
+        let exit = self.exit_pos()?;
+        let mut came_from = HashMap::from([(start, start)]);
+        let mut queue = std::collections::VecDeque::from([start]);
+        while let Some(pos) = queue.pop_front() {
+            if pos == exit {
+                let mut path = vec![exit];
+                let mut current = exit;
+                while current != start {
+                    current = came_from[&current];
+                    path.push(current);
+                }
+                path.reverse();
                 return Some(path);
             }
-
-            // Explore neighbors
-            let directions = [
-                Pos {
-                    x: pos.x + 1,
-                    y: pos.y,
-                }, // Right
-                Pos {
-                    x: pos.x.saturating_sub(1),
-                    y: pos.y,
-                }, // Left
-                Pos {
-                    x: pos.x,
-                    y: pos.y + 1,
-                }, // Down
-                Pos {
-                    x: pos.x,
-                    y: pos.y.saturating_sub(1),
-                }, // Up
-            ];
-
-            for next in directions.iter() {
-                if next.x < self.width && next.y < self.height && !visited.contains(next) {
-                    let cell_type = self.get(next.x, next.y);
-                    if TRAVERSABLE.contains(&cell_type) {
-                        let mut new_path = path.clone();
-                        new_path.push(*next);
-                        queue.insert(0, (*next, new_path));
-                        visited.insert(*next);
-                    }
+            for next in self.passages(pos) {
+                if let std::collections::hash_map::Entry::Vacant(entry) = came_from.entry(next) {
+                    entry.insert(pos);
+                    queue.push_back(next);
                 }
             }
         }
+        None
+    }
 
-        None // No solution found
+    /// Angle of the counter-clockwise edge of `index` in a ring of `size`
+    /// cells, in radians clockwise from the top.
+    fn angle(index: usize, size: usize) -> f32 {
+        2.0 * std::f32::consts::PI * index as f32 / size as f32
     }
 
-    pub fn export_to_svg(
-        &self,
-        filename: &str,
-        scale: f32,
-        with_solution: SolutionType,
-    ) -> std::io::Result<()> {
-        let mut maze = self.clone();
-        let mut file = File::create(filename)?;
+    /// The point at `radius` and `angle` around the center at `(center,
+    /// center)`, in the unscaled SVG coordinates.
+    fn point(center: f32, radius: f32, angle: f32) -> (f32, f32) {
+        (center + radius * angle.sin(), center - radius * angle.cos())
+    }
+
+    /// The middle of the cell at `pos`, used to draw the solution and the
+    /// artifacts.
+    fn cell_center(&self, center: f32, pos: PolarPos) -> (f32, f32) {
+        if pos.ring == 0 {
+            return (center, center);
+        }
+        let angle = CircularMaze::angle(2 * pos.index + 1, 2 * self.ring_size(pos.ring));
+        CircularMaze::point(center, pos.ring as f32 + 0.5, angle)
+    }
 
-        // Write SVG header with scaled dimensions
+    pub fn export_to_svg(&self, filename: &str, options: &SvgOptions) -> Result<(), MazeError> {
+        write_file(filename, |file| self.write_svg(file, options))
+    }
+
+    /// Writes the maze as SVG, each ring one unit deep before scaling. Walls
+    /// between rings are drawn as arcs and walls within a ring as radial
+    /// segments. Of the options, the theme, the scale, the solution, drawn
+    /// with a breadth-first search whatever the solver, and the artifacts
+    /// are used.
+    pub fn write_svg<W: Write>(&self, file: &mut W, options: &SvgOptions) -> std::io::Result<()> {
+        let scale = options.scale;
+        let center = self.rings() as f32 + 1.5;
+        let size = 2.0 * center * scale;
         writeln!(
             file,
             "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{}\" height=\"{}\" viewBox=\"0 0 {} {}\">",
-            maze.width as f32 * scale,
-            maze.height as f32 * scale,
-            maze.width as f32 * scale,
-            maze.height as f32 * scale
+            size, size, size, size
         )?;
-
         writeln!(
             file,
-            "<rect width=\"100%\" height=\"100%\" fill=\"#eee\" />"
+            "<rect width=\"100%\" height=\"100%\" fill=\"{}\" />",
+            hex_color(options.theme.path_color)
         )?;
         writeln!(file, "  <g transform=\"scale({})\" >", scale)?;
 
-        match with_solution {
-            SolutionType::ShortestPath => {
-                if let Some(solution) = maze.shortest_path() {
-                    writeln!(
-                        file,
-                        "    <polyline fill=\"none\" stroke=\"rgb(28, 163, 163)\" stroke-width=\"0.35\" points=\"",
-                    )?;
-                    for pos in solution {
-                        write!(file, "{},{} ", (pos.x as f32 + 0.5), (pos.y as f32 + 0.5))?;
-                    }
-                    writeln!(file, "\" />")?;
-                }
-            }
-            SolutionType::MinimumSpanningTree => {}
-            SolutionType::None => {}
-        }
-
-        // Draw the maze
-        for y in 0..maze.height {
-            for x in 0..maze.width {
-                match maze.get(x, y) {
-                    CellType::Zombie
-                    | CellType::Ghost
-                    | CellType::Witch
-                    | CellType::Fog
-                    | CellType::Shadows
-                    | CellType::Crow
-                    | CellType::BlackCat
-                    | CellType::Skeleton
-                    | CellType::Spider
-                    | CellType::Bat
-                    | CellType::Pumpkin => {
-                        writeln!(
-                            file,
-                            "    <circle cx=\"{}\" cy=\"{}\" r=\"0.4\" fill=\"#e43\" title=\"{}\" />",
-                            x as f32 + 0.5,
-                            y as f32 + 0.5,
-                            maze.get(x, y)
-                        )?;
-                    }
-                    CellType::Marshmallows
-                    | CellType::GummyBears
-                    | CellType::Cookies
-                    | CellType::Candy
-                    | CellType::Chocolate => {
-                        writeln!(
-                            file,
-                            "    <circle cx=\"{}\" cy=\"{}\" r=\"0.4\" fill=\"#2d1\" title=\"{}\" />",
-                            x as f32 + 0.5,
-                            y as f32 + 0.5,
-                            maze.get(x, y)
-                        )?;
-                    }
-                    CellType::Wall => {
-                        writeln!(
-                            file,
-                            "    <rect x=\"{}\" y=\"{}\" width=\"1\" height=\"1\" fill=\"#222\" />",
-                            x, y
-                        )?;
-                    }
-                    _ => {}
+        if options.show_artifacts {
+            for pos in self.positions() {
+                let cell = self.get(pos);
+                if !REWARDS.contains(&cell) && !DANGERS.contains(&cell) {
+                    continue;
                 }
+                let color = artifact_color(cell, options);
+                let (x, y) = self.cell_center(center, pos);
+                writeln!(
+                    file,
+                    "    <circle cx=\"{}\" cy=\"{}\" r=\"0.3\" fill=\"{}\"><title>{}</title></circle>",
+                    x,
+                    y,
+                    hex_color(color),
+                    cell
+                )?;
+            }
+        }
+
+        writeln!(
+            file,
+            "    <path fill=\"none\" stroke=\"{}\" stroke-width=\"0.2\" stroke-linecap=\"round\" d=\"",
+            hex_color(options.theme.wall_color)
+        )?;
+        let arc = |file: &mut W, radius: f32, from: f32, to: f32| {
+            let (x1, y1) = CircularMaze::point(center, radius, from);
+            let (x2, y2) = CircularMaze::point(center, radius, to);
+            writeln!(
+                file,
+                "      M {} {} A {} {} 0 0 1 {} {}",
+                x1, y1, radius, radius, x2, y2
+            )
+        };
+        for pos in self.positions().filter(|pos| pos.ring > 0) {
+            let size = self.ring_size(pos.ring);
+            let from = CircularMaze::angle(pos.index, size);
+            let to = CircularMaze::angle(pos.index + 1, size);
+            let radius = pos.ring as f32;
+            if !self.inward_open[self.id(pos)] {
+                arc(file, radius, from, to)?;
             }
+            if !self.clockwise_open[self.id(pos)] {
+                let (x1, y1) = CircularMaze::point(center, radius, to);
+                let (x2, y2) = CircularMaze::point(center, radius + 1.0, to);
+                writeln!(file, "      M {} {} L {} {}", x1, y1, x2, y2)?;
+            }
+            // The rim, open at the exit
+            if pos.ring == self.rings() && self.get(pos) != CellType::Exit {
+                arc(file, radius + 1.0, from, to)?;
+            }
+        }
+        writeln!(file, "    \" />")?;
+
+        if options.with_solution == SolutionType::ShortestPath
+            && let Some(solution) = self.solve()
+        {
+            let points = solution
+                .into_iter()
+                .map(|pos| self.cell_center(center, pos))
+                .collect();
+            write_svg_overlay(file, &[points], OverlayColor::Solution, options)?;
         }
 
         writeln!(file, "  </g>")?;
         writeln!(file, "</svg>")?;
         Ok(())
     }
+}
 
-    pub fn build_graph(&self) -> (Nodes, Edges) {
-        let mut nodes: Nodes = HashMap::new();
-        let mut edges: Edges = HashSet::new();
-        let mut node_id = 0;
-
-        // Special nodes: center (start) and exit
-        let center_x: usize = self.width / 2;
-        let center_y: usize = self.height / 2;
-        let center_pos: Pos = Pos {
-            x: center_x,
-            y: center_y,
-        };
-        nodes.insert(center_pos, node_id);
-        node_id += 1;
-
-        // Find exit node
-        let mut exit_pos: Option<Pos> = None;
-        for x in [0, self.width - 1].iter() {
-            for y in 0..self.height {
-                if self.get(*x, y) == CellType::Exit {
-                    exit_pos = Some(Pos { x: *x, y });
-                    break;
-                }
-            }
-        }
-        if exit_pos.is_none() {
-            return (nodes, edges);
-        }
+/// A window onto a `ChunkedMaze` in world coordinates, `x` and `y` being its
+/// top left corner.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct WorldRect {
+    pub x: i64,
+    pub y: i64,
+    pub width: usize,
+    pub height: usize,
+}
 
-        if let Some(pos) = exit_pos {
-            nodes.insert(pos, node_id);
-            node_id += 1;
+impl WorldRect {
+    pub fn new(x: i64, y: i64, width: usize, height: usize) -> Self {
+        WorldRect {
+            x,
+            y,
+            width,
+            height,
         }
+    }
+}
 
-        // Scan the maze to find all intersections and dead ends
-        for y in 1..self.height - 1 {
-            for x in 1..self.width - 1 {
-                let cell_type = self.get(x, y);
-                // Check if the cell is a path, reward or danger (traversable)
-                if TRAVERSABLE.contains(&cell_type) {
-                    let current_pos = Pos { x, y };
-                    let neighbors = [
-                        Pos { x: x + 1, y },
-                        Pos { x: x - 1, y },
-                        Pos { x, y: y + 1 },
-                        Pos { x, y: y - 1 },
-                    ]
-                    .iter()
-                    .filter(|pos| TRAVERSABLE.contains(&self.get(pos.x, pos.y)))
-                    .count();
+/// A maze without bounds, made of square chunks that are generated when
+/// first looked at. A chunk only depends on its coordinates and the world
+/// seed, so the same seed always gives the same world, whatever order the
+/// chunks are generated in.
+///
+/// Like in a `Maze`, corridors run along odd coordinates and walls along
+/// even ones. Each chunk is a perfect maze of its own, owning the wall on
+/// its west and north side. The openings in such a wall are picked with a
+/// hash of the seed and the wall, so the chunks on both sides agree on them
+/// without looking at each other. Only the last `capacity` chunks used are
+/// kept in memory.
+pub struct ChunkedMaze {
+    chunk_size: usize,
+    seed: u64,
+    capacity: usize,
+    /// Cached chunks with the use they were last looked at in
+    chunks: HashMap<(i64, i64), (u64, Maze)>,
+    /// The cached chunks by their last use, least recently used first
+    recent: BTreeMap<u64, (i64, i64)>,
+    /// Number of times a chunk was looked at
+    uses: u64,
+}
 
-                    // Create a node if this is an intersection (>2 neighbors) or dead end (1 neighbor)
-                    if neighbors != 2 && current_pos != center_pos && Some(current_pos) != exit_pos
-                    {
-                        nodes.insert(current_pos, node_id);
-                        node_id += 1;
-                    }
-                }
-            }
+impl ChunkedMaze {
+    /// A world of chunks `chunk_size` cells square, rounded up to 6, 10,
+    /// 14, and so on, so a chunk with the wall of its neighbors follows the
+    /// size rule of `Maze::new`.
+    pub fn new(chunk_size: usize, seed: u64, capacity: usize) -> Result<Self, MazeError> {
+        if capacity == 0 {
+            return Err(MazeError::InvalidOption {
+                name: "chunk cache capacity",
+                reason: "at least one chunk has to fit".to_string(),
+            });
         }
+        Ok(ChunkedMaze {
+            chunk_size: constrain_dimension!(chunk_size + 1) - 1,
+            seed,
+            capacity,
+            chunks: HashMap::new(),
+            recent: BTreeMap::new(),
+            uses: 0,
+        })
+    }
 
-        // Create edges between nodes by following paths
-        for (&start_pos, &start_id) in &nodes {
-            // For each direction, follow the path until another node is found
-            let directions = [(1, 0), (-1, 0), (0, 1), (0, -1)];
-
-            for &(dx, dy) in &directions {
-                let mut x = start_pos.x as isize + dx;
-                let mut y = start_pos.y as isize + dy;
+    pub fn chunk_size(&self) -> usize {
+        self.chunk_size
+    }
 
-                if x < 0 || x >= self.width as isize || y < 0 || y >= self.height as isize {
-                    continue;
-                }
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
 
-                let cell_type = self.get(x as usize, y as usize);
-                if cell_type == CellType::Wall {
-                    continue;
-                }
+    /// The cell at world coordinates `(x, y)`, generating its chunk if it
+    /// isn't cached.
+    pub fn get(&mut self, x: i64, y: i64) -> CellType {
+        let size = self.chunk_size as i64;
+        let chunk = self.chunk(x.div_euclid(size), y.div_euclid(size));
+        chunk.get(x.rem_euclid(size) as usize, y.rem_euclid(size) as usize)
+    }
 
-                let mut weight = cell_type.weight(); // Start with the weight of the first cell
-                let mut visited = HashSet::new();
-                visited.insert(start_pos);
+    /// Copies the cells in `rect` into a maze of its own, for exporting or
+    /// solving. The window has to follow the size rule of `Maze::new` and
+    /// start at even coordinates, so its border runs along walls. Like with
+    /// `Maze::crop` the border is walled up, the start goes to the center
+    /// and the exit to the border cell closest to it by walking. Parts of
+    /// the window only connected through the world outside of it stay cut
+    /// off.
+    pub fn region(&mut self, rect: WorldRect) -> Result<Maze, MazeError> {
+        if rect.x.rem_euclid(2) != 0 || rect.y.rem_euclid(2) != 0 {
+            return Err(MazeError::InvalidOption {
+                name: "region",
+                reason: format!("({}, {}) is not a corner of walls", rect.x, rect.y),
+            });
+        }
+        let mut window = Maze::new(rect.width, rect.height, 1, ExitLocation::Random);
+        if window.get_size() != (rect.width, rect.height) {
+            return Err(MazeError::InvalidDimensions {
+                width: rect.width,
+                height: rect.height,
+                reason: "sizes are 7, 11, 15, and so on".to_string(),
+            });
+        }
+        for y in 0..rect.height {
+            for x in 0..rect.width {
+                let cell = self.get(rect.x + x as i64, rect.y + y as i64);
+                window.set(x, y, cell);
+            }
+        }
+        window.crop(Rect::new(0, 0, rect.width, rect.height))
+    }
 
-                // Follow the path
-                while x >= 0 && x < self.width as isize && y >= 0 && y < self.height as isize {
-                    let current_pos = Pos {
-                        x: x as usize,
-                        y: y as usize,
-                    };
+    /// The chunk at `(chunk_x, chunk_y)`, from the cache or freshly
+    /// generated, evicting the least recently used one when the cache is
+    /// full.
+    fn chunk(&mut self, chunk_x: i64, chunk_y: i64) -> &Maze {
+        let key = (chunk_x, chunk_y);
+        self.uses += 1;
+        match self.chunks.get_mut(&key) {
+            Some((used, _)) => {
+                self.recent.remove(used);
+                *used = self.uses;
+            }
+            None => {
+                if self.chunks.len() >= self.capacity
+                    && let Some((_, oldest)) = self.recent.pop_first()
+                {
+                    self.chunks.remove(&oldest);
+                }
+                let chunk = self.generate_chunk(chunk_x, chunk_y);
+                self.chunks.insert(key, (self.uses, chunk));
+            }
+        }
+        self.recent.insert(self.uses, key);
+        &self.chunks[&key].1
+    }
 
-                    // If we've found another node, create an edge
-                    if let Some(&end_id) = nodes.get(&current_pos) {
-                        if start_id < end_id {
-                            // Only add each edge once
-                            edges.insert(Edge {
-                                start_id,
-                                end_id,
-                                weight,
-                            });
-                        }
-                        break;
-                    }
+    /// Carves the chunk with the same depth-first search as `Maze::generate`,
+    /// without room, exit or loops, then opens its west and north wall. The
+    /// chunk is generated one cell larger than it is, the extra row and
+    /// column being its neighbors' walls.
+    fn generate_chunk(&self, chunk_x: i64, chunk_y: i64) -> Maze {
+        let size = self.chunk_size + 1;
+        let mut chunk = Maze::new(size, size, 1, ExitLocation::Random);
+        let start = chunk.start_pos();
+        chunk.set(start.x, start.y, CellType::Path);
+        let mut rng = StdRng::seed_from_u64(self.hash([chunk_x, chunk_y, 0]));
+        chunk.generate_from(start, None, &mut rng, &mut |_| {});
+        for y in self.openings(chunk_x, chunk_y, Direction::West) {
+            chunk.set(0, y, CellType::Path);
+        }
+        for x in self.openings(chunk_x, chunk_y, Direction::North) {
+            chunk.set(x, 0, CellType::Path);
+        }
+        chunk
+    }
 
-                    // If not a node, check neighboring cells to continue the path
-                    visited.insert(current_pos);
+    /// Where the wall on the west or north `side` of a chunk is open, as
+    /// offsets along the wall. About one corridor in eight gets an opening,
+    /// at least one.
+    fn openings(&self, chunk_x: i64, chunk_y: i64, side: Direction) -> Vec<usize> {
+        let wall = match side {
+            Direction::West => 1,
+            _ => 2,
+        };
+        let mut rng = StdRng::seed_from_u64(self.hash([chunk_x, chunk_y, wall]));
+        let corridors: Vec<usize> = (1..self.chunk_size).step_by(2).collect();
+        let count = (corridors.len() / 8).max(1);
+        corridors
+            .choose_multiple(&mut rng, count)
+            .copied()
+            .collect()
+    }
 
-                    let mut next_found = false;
-                    for &(ndx, ndy) in &directions {
-                        let nx = x + ndx;
-                        let ny = y + ndy;
-
-                        if nx >= 0
-                            && nx < self.width as isize
-                            && ny >= 0
-                            && ny < self.height as isize
-                        {
-                            let next_pos = Pos {
-                                x: nx as usize,
-                                y: ny as usize,
-                            };
-                            let next_cell_type = self.get(next_pos.x, next_pos.y);
-
-                            if next_cell_type != CellType::Wall && !visited.contains(&next_pos) {
-                                x = nx;
-                                y = ny;
-                                weight += next_cell_type.weight();
-                                next_found = true;
-                                break;
-                            }
-                        }
-                    }
+    /// Mixes the seed and `values` with the SplitMix64 finalizer, which
+    /// doesn't depend on the platform or the release of the standard library.
+    fn hash(&self, values: [i64; 3]) -> u64 {
+        values.into_iter().fold(self.seed, |hash, value| {
+            let mut z = (hash ^ value as u64).wrapping_add(0x9e37_79b9_7f4a_7c15);
+            z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+            z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+            z ^ (z >> 31)
+        })
+    }
+}
 
-                    if !next_found {
-                        break;
-                    }
-                }
-            }
-        }
+/// Bindings for calling the maze engine from plain JavaScript, built as a
+/// `cdylib` with `--no-default-features --features wasm`, see the README.
+#[cfg(all(feature = "wasm", target_arch = "wasm32"))]
+pub mod wasm {
+    use super::*;
+    use wasm_bindgen::prelude::*;
 
-        (nodes, edges)
+    /// A maze with its own random generator, so a seed gives the same maze
+    /// as it does natively.
+    #[wasm_bindgen]
+    pub struct WasmMaze {
+        maze: Maze,
+        rng: StdRng,
     }
 
-    pub fn export_to_dot(&self, filename: &str) -> std::io::Result<()> {
-        let mut file = File::create(filename)?;
-        let (nodes, edges) = self.build_graph();
+    #[wasm_bindgen]
+    impl WasmMaze {
+        /// `exit` is one of random, left, right, top, bottom or farthest.
+        /// The seed is a BigInt on the JavaScript side.
+        #[wasm_bindgen(constructor)]
+        pub fn new(
+            width: usize,
+            height: usize,
+            room_size: usize,
+            exit: &str,
+            seed: u64,
+        ) -> Result<WasmMaze, JsError> {
+            let exit_type = match exit {
+                "random" => ExitLocation::Random,
+                "left" => ExitLocation::Left,
+                "right" => ExitLocation::Right,
+                "top" => ExitLocation::Top,
+                "bottom" => ExitLocation::Bottom,
+                "farthest" => ExitLocation::Farthest,
+                _ => return Err(JsError::new(&format!("unknown exit location {}", exit))),
+            };
+            Ok(WasmMaze {
+                maze: Maze::new(width, height, room_size, exit_type),
+                rng: StdRng::seed_from_u64(seed),
+            })
+        }
 
-        // Write DOT file header
-        writeln!(file, "graph Maze {{")?;
-        writeln!(file, "    node [shape=point];")?;
-        writeln!(file, "    edge [len=1.0];")?;
+        pub fn width(&self) -> usize {
+            self.maze.width()
+        }
 
-        // Write nodes
-        let center_pos = Pos {
-            x: self.width / 2,
-            y: self.height / 2,
-        };
+        pub fn height(&self) -> usize {
+            self.maze.height()
+        }
 
-        // Find the exit pos
-        let mut exit_pos = None;
-        for x in [0, self.width - 1].iter() {
-            for y in 0..self.height {
-                if self.get(*x, y) == CellType::Path {
-                    exit_pos = Some(Pos { x: *x, y });
-                    break;
-                }
-            }
+        pub fn generate(&mut self) {
+            self.maze.generate_with_rng(&mut self.rng);
         }
-        if exit_pos.is_none() {
-            for y in [0, self.height - 1].iter() {
-                for x in 0..self.width {
-                    if self.get(x, *y) == CellType::Path {
-                        exit_pos = Some(Pos { x, y: *y });
-                        break;
-                    }
-                }
-            }
+
+        pub fn place_artifacts(&mut self, ratio: f32) {
+            self.maze.place_artifacts_with_rng(ratio, &mut self.rng);
         }
 
-        for (&pos, &node_id) in &nodes {
-            if pos == center_pos {
-                writeln!(
-                    file,
-                    "    n{} [color=green, shape=circle, label=\"Start\"];",
-                    node_id
-                )?;
-            } else if Some(pos) == exit_pos {
-                writeln!(
-                    file,
-                    "    n{} [color=red, shape=box, label=\"Exit\"];",
-                    node_id
-                )?;
-            } else {
-                // Determine if node is a dead end or junction
-                let neighbors = [
-                    Pos {
-                        x: pos.x + 1,
-                        y: pos.y,
-                    },
-                    Pos {
-                        x: pos.x.saturating_sub(1),
-                        y: pos.y,
-                    },
-                    Pos {
-                        x: pos.x,
-                        y: pos.y + 1,
-                    },
-                    Pos {
-                        x: pos.x,
-                        y: pos.y.saturating_sub(1),
-                    },
-                ]
+        /// The shortest path as x and y of each cell one after the other,
+        /// empty if there is none.
+        pub fn solve(&self) -> Vec<u32> {
+            self.maze
+                .shortest_path()
+                .unwrap_or_default()
                 .iter()
-                .filter(|p| self.get(p.x, p.y) == CellType::Path)
-                .count();
-
-                let label = if neighbors == 1 {
-                    "Dead End"
-                } else {
-                    "Junction"
-                };
-                writeln!(file, "    n{} [label=\"{}\"];", node_id, label)?;
-            }
+                .flat_map(|pos| [pos.x as u32, pos.y as u32])
+                .collect()
         }
 
-        // Write edges
-        for &edge in &edges {
-            writeln!(
-                file,
-                "    n{} -- n{} [len={:.1}, label=\"{}\"];",
-                edge.start_id, edge.end_id, edge.weight, edge.weight
-            )?;
+        pub fn to_svg(&self, scale: f32) -> String {
+            let options = SvgOptions {
+                scale,
+                ..Default::default()
+            };
+            let mut bytes = Vec::new();
+            self.maze
+                .write_svg(&mut bytes, &options)
+                .expect("writing to memory can't fail");
+            String::from_utf8(bytes).expect("the SVG is valid UTF-8")
         }
 
-        writeln!(file, "}}")?;
-        Ok(())
+        /// The share code of each cell, row by row. The codes are the
+        /// indices of the `CellType` variants in declaration order.
+        pub fn cells(&self) -> js_sys::Uint8Array {
+            let codes: Vec<u8> = self
+                .maze
+                .iter_cells()
+                .map(|(_, cell)| CELL_TYPES.iter().position(|&t| t == cell).unwrap() as u8)
+                .collect();
+            js_sys::Uint8Array::from(&codes[..])
+        }
     }
 }
@@ -1,18 +1,207 @@
-use clap::Parser;
+#[cfg(feature = "tui")]
+mod play;
 
-use mazegen::{ExitLocation, Maze, SolutionType};
+use clap::parser::ValueSource;
+use clap::{ArgMatches, CommandFactory, FromArgMatches};
+use rand::prelude::*;
+use rand::rngs::StdRng;
+use std::io::IsTerminal;
+use std::process::ExitCode;
 
+use mazegen::{
+    CircularMaze, DIFFICULTY_ATTEMPTS, DIFFICULTY_TOLERANCE, ExitLocation, Hand, LegendPosition,
+    Maze, Maze3D, MazeBuilder, MazeError, MazePreset, Preset, Replay, Simulation, SolutionStyle,
+    SolutionType, Solver, SvgAnimation, SvgOptions, SvgOverlay, Theme, WeightTable,
+    benchmark_solvers,
+};
+#[cfg(feature = "tui")]
+use mazegen::{SimulationOptions, StepOutcome};
+
+/// Maze generation algorithms
+#[derive(clap::ValueEnum, Clone, Copy, Debug)]
+enum Algorithm {
+    /// Randomized depth-first search (recursive backtracker)
+    Dfs,
+}
+
+/// Shapes of the maze
+#[derive(clap::ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+enum Topology {
+    /// A rectangular grid of cells
+    Grid,
+    /// Concentric rings around a center cell, exported as SVG only
+    Circular,
+}
+
+#[derive(clap::ValueEnum, Clone, Copy, Debug)]
+enum SolverArg {
+    Bfs,
+    AStar,
+    WallFollower,
+    DeadEndFill,
+}
+
+#[derive(clap::ValueEnum, Clone, Copy, Debug)]
+enum PathFormat {
+    /// The visited positions in order
+    Positions,
+    /// A string of R, D, L and U steps
+    Moves,
+}
+
+/// Without a subcommand the flags of `generate` are accepted directly, so
+/// the flat usage of earlier versions keeps working.
 #[derive(clap::Parser, Debug)]
-#[command(name = "maze", version = "0.1.0", about = "Generate and solve mazes")]
+#[command(
+    name = "maze",
+    version = "0.1.0",
+    about = "Generate and solve mazes",
+    args_conflicts_with_subcommands = true,
+    disable_help_flag = true
+)]
 struct Cli {
-    #[arg(short, long, default_value_t = 60, help = "Width of the maze")]
-    width: usize,
-    #[arg(short, long, default_value_t = 30, help = "Height of the maze")]
-    height: usize,
-    #[arg(short, long, default_value_t = 3, help = "Size if the central room")]
-    room_size: usize,
-    #[arg(short, long, help = "Ratio of empty cells to cells with artifacts")]
-    artifacts_ratio: Option<f32>,
+    #[command(subcommand)]
+    command: Option<Command>,
+    #[command(flatten)]
+    generate: GenerateArgs,
+    // `-h` is taken by `--height`, so help is only offered as `--help`.
+    #[arg(long, global = true, action = clap::ArgAction::Help, help = "Print help")]
+    help: Option<bool>,
+}
+
+#[derive(clap::Subcommand, Debug)]
+enum Command {
+    /// Generate a new maze and save or export it
+    Generate(Box<GenerateArgs>),
+    /// Solve a saved maze and print the solution
+    Solve(SolveArgs),
+    /// Export a saved maze to SVG, DOT, PNG and the other formats
+    Export(ExportArgs),
+    /// Print statistics of a saved maze as JSON
+    Stats {
+        #[arg(help = "Maze file written by `maze generate --out`")]
+        maze: String,
+    },
+    /// Time every solver on a saved maze and compare their paths
+    Bench {
+        #[arg(help = "Maze file written by `maze generate --out`")]
+        maze: String,
+        #[arg(long, help = "Print the results as JSON instead of a table")]
+        json: bool,
+    },
+    /// Compare two saved mazes cell by cell, failing if they differ
+    Diff {
+        #[arg(help = "Maze file written by `maze generate --out`")]
+        left: String,
+        #[arg(help = "Maze file to compare it with")]
+        right: String,
+    },
+    /// Walk through a maze in the terminal
+    #[cfg(feature = "tui")]
+    Play(PlayArgs),
+    /// Check a run recorded with `maze play --record` and print it turn by
+    /// turn
+    Replay {
+        #[arg(help = "Replay file written by `maze play --record`")]
+        replay: String,
+    },
+}
+
+#[derive(clap::Args, Debug)]
+struct SolverArgs {
+    #[arg(
+        long,
+        value_enum,
+        default_value_t = SolverArg::Bfs,
+        help = "Solver used to find the solution path"
+    )]
+    solver: SolverArg,
+    #[arg(
+        long,
+        value_enum,
+        default_value_t = Hand::Left,
+        help = "Hand kept on the wall by the wall follower"
+    )]
+    hand: Hand,
+}
+
+impl SolverArgs {
+    fn solver(&self) -> Solver {
+        match self.solver {
+            SolverArg::Bfs => Solver::Bfs,
+            SolverArg::AStar => Solver::AStar,
+            SolverArg::WallFollower => Solver::WallFollower(self.hand),
+            SolverArg::DeadEndFill => Solver::DeadEndFill,
+        }
+    }
+}
+
+#[derive(clap::Args, Debug)]
+struct SolutionArgs {
+    #[arg(
+        long,
+        value_name = "FILE",
+        help = "Write the solution found by the solver to a JSON file"
+    )]
+    solution_out: Option<String>,
+    #[arg(
+        long,
+        value_enum,
+        default_value_t = PathFormat::Positions,
+        help = "Format of the solution written with --solution-out"
+    )]
+    solution_format: PathFormat,
+    #[arg(
+        long,
+        value_name = "WEIGHT",
+        requires = "solution_out",
+        help = "Write the shortest solution whose dangers weigh at most this much in total instead"
+    )]
+    max_danger: Option<i32>,
+}
+
+impl SolutionArgs {
+    /// Writes the solution of `maze` if requested. An unsolvable maze is an
+    /// error rather than an empty file.
+    fn write(&self, maze: &Maze, solver: Solver) -> Result<(), Box<dyn std::error::Error>> {
+        let Some(solution_out) = &self.solution_out else {
+            return Ok(());
+        };
+        let (path, danger) = match self.max_danger {
+            Some(max_danger) => {
+                let (path, danger) = maze.path_with_danger_budget(max_danger).ok_or_else(|| {
+                    format!(
+                        "no way to the exit with dangers weighing {} at most",
+                        max_danger
+                    )
+                })?;
+                (path, Some(danger))
+            }
+            None => (maze.solve(solver).ok_or(MazeError::Unsolvable)?, None),
+        };
+        let mut json = match self.solution_format {
+            PathFormat::Positions => serde_json::json!({
+                "format": "positions",
+                "path": path.iter().map(|pos| [pos.x, pos.y]).collect::<Vec<_>>(),
+            }),
+            PathFormat::Moves => serde_json::json!({
+                "format": "moves",
+                "moves": Maze::path_to_moves(&path)?,
+            }),
+        };
+        json["length"] = path.len().into();
+        json["cost"] = maze.path_cost(&path).into();
+        if let Some(danger) = danger {
+            json["danger"] = danger.into();
+        }
+        std::fs::write(solution_out, serde_json::to_string_pretty(&json)?)
+            .map_err(|err| format!("{}: {}", solution_out, err))?;
+        Ok(())
+    }
+}
+
+#[derive(clap::Args, Debug)]
+struct OutputArgs {
     #[arg(short, long, help = "Output maze to DOT file for GraphViz")]
     dot_file: Option<String>,
     #[arg(short, long, help = "Output maze to SVG file")]
@@ -25,24 +214,1023 @@ struct Cli {
         help = "Show solution path in SVG output"
     )]
     with_path: SolutionType,
+    #[command(flatten)]
+    solver: SolverArgs,
+    #[arg(
+        long,
+        default_value_t = false,
+        help = "Shade the cells visited by the solver in SVG output"
+    )]
+    show_visited: bool,
+    #[arg(
+        long,
+        value_enum,
+        default_value_t = SvgOverlay::None,
+        help = "Overlay drawn over the pathways in SVG output"
+    )]
+    overlay: SvgOverlay,
+    #[arg(
+        long,
+        default_value_t = SolutionStyle::Line,
+        help = "Draw the solution in SVG output as a line, or dots or arrows on every Nth cell, e.g. dots:3"
+    )]
+    solution_style: SolutionStyle,
+    #[arg(
+        long,
+        default_value_t = false,
+        help = "List the artifacts with their weights next to the maze in SVG output"
+    )]
+    legend: bool,
+    #[arg(
+        long,
+        value_enum,
+        default_value_t = LegendPosition::Bottom,
+        help = "Where the legend from --legend goes"
+    )]
+    legend_position: LegendPosition,
+    #[arg(
+        long,
+        help = "Title of the SVG output, which then also gets a description and the generation parameters"
+    )]
+    title: Option<String>,
+    #[arg(
+        long,
+        value_name = "SECONDS",
+        help = "Animate the solution from --with-path drawing itself in SVG output"
+    )]
+    animate_solution: Option<f32>,
+    #[arg(
+        long,
+        default_value_t = false,
+        help = "Label the columns with letters and the rows with numbers in SVG and printed output"
+    )]
+    coordinates: bool,
+    #[arg(
+        long,
+        value_name = "CELLS",
+        help = "Draw a light grid line every this many cells in SVG output, e.g. 1 or 5"
+    )]
+    grid: Option<usize>,
+    #[arg(
+        long,
+        default_value_t = false,
+        help = "Write SVG output with whole pixels per cell and no seams between walls"
+    )]
+    crisp: bool,
+    #[cfg(feature = "image")]
+    #[arg(
+        long,
+        value_name = "FILE",
+        help = "Output maze to PNG file, with cells as wide as --scale"
+    )]
+    png_file: Option<String>,
+    #[arg(
+        long,
+        value_name = "FILE",
+        help = "Output maze to OpenSCAD file for 3D printing"
+    )]
+    scad_file: Option<String>,
+    #[arg(
+        long,
+        value_name = "FILE",
+        help = "Output maze to binary STL file for 3D printing"
+    )]
+    stl_file: Option<String>,
+    #[arg(
+        long,
+        value_name = "FILE",
+        help = "Output the wall outlines to SVG file as cut lines for a laser cutter"
+    )]
+    laser_file: Option<String>,
+    #[arg(
+        long,
+        default_value_t = 4.0,
+        help = "Size of a cell in 3D models and laser cuts, in mm"
+    )]
+    cell_mm: f32,
+    #[arg(
+        long,
+        default_value_t = 8.0,
+        help = "Height of the walls in 3D models, in mm"
+    )]
+    wall_height_mm: f32,
+    #[arg(
+        long,
+        default_value_t = 2.0,
+        help = "Thickness of the base plate in 3D models, in mm"
+    )]
+    base_mm: f32,
+    #[arg(
+        long,
+        default_value_t = false,
+        help = "Print the maze in color, or as plain text when not writing to a terminal"
+    )]
+    print_color: bool,
+    /// Colors of the preset, if any
+    #[arg(skip)]
+    theme: Theme,
+}
+
+impl OutputArgs {
+    fn animation(&self) -> SvgAnimation {
+        match self.animate_solution {
+            Some(duration_secs) => SvgAnimation::SolutionDraw { duration_secs },
+            None => SvgAnimation::None,
+        }
+    }
+
+    /// `params` are embedded in the SVG output if it has a title
+    fn write(
+        &self,
+        maze: &Maze,
+        params: Option<&MazeBuilder>,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        if self.print_color {
+            let solution = match self.with_path {
+                SolutionType::ShortestPath => maze.solve(self.solver.solver()).unwrap_or_default(),
+                SolutionType::FullCollection => maze
+                    .full_collection_route()
+                    .map(|route| route.path)
+                    .unwrap_or_default(),
+                _ => Vec::new(),
+            };
+            let mut stdout = std::io::stdout().lock();
+            if self.coordinates {
+                maze.write_text_with_coordinates(&mut stdout, &solution)?;
+            } else if stdout.is_terminal() {
+                maze.write_ansi(&mut stdout, &self.theme, &solution)?;
+            } else {
+                maze.write_text(&mut stdout, &solution)?;
+            }
+        }
+        if let Some(dot_file) = &self.dot_file {
+            match self.with_path {
+                SolutionType::MinimumSpanningTree => {
+                    maze.export_to_dot_with_mst(dot_file, &maze.mst_kruskal())?
+                }
+                _ => maze.export_to_dot(dot_file)?,
+            }
+        }
+        if let Some(svg_file) = &self.svg_file {
+            let options = SvgOptions {
+                scale: self.scale,
+                with_solution: self.with_path.clone(),
+                solver: self.solver.solver(),
+                show_visited: self.show_visited,
+                overlay: self.overlay,
+                animation: self.animation(),
+                solution_style: self.solution_style,
+                include_legend: self.legend,
+                legend_position: self.legend_position,
+                title: self.title.clone(),
+                params: params.filter(|_| self.title.is_some()).cloned(),
+                coordinates: self.coordinates,
+                grid: self.grid,
+                crisp: self.crisp,
+                theme: self.theme,
+                ..Default::default()
+            };
+            maze.export_to_svg(svg_file, &options)?;
+        }
+        #[cfg(feature = "image")]
+        if let Some(png_file) = &self.png_file {
+            let cell_px = (self.scale.round() as u32).max(1);
+            maze.export_to_png(png_file, cell_px, &self.theme)?;
+        }
+        if let Some(scad_file) = &self.scad_file {
+            maze.export_to_scad(scad_file, self.wall_height_mm, self.cell_mm, self.base_mm)?;
+        }
+        if let Some(stl_file) = &self.stl_file {
+            maze.export_to_stl(stl_file, self.wall_height_mm, self.cell_mm, self.base_mm)?;
+        }
+        if let Some(laser_file) = &self.laser_file {
+            maze.export_to_laser_svg(laser_file, self.cell_mm)?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(clap::Args, Debug)]
+struct GenerateArgs {
+    #[arg(
+        short,
+        long,
+        default_value_t = 63,
+        help = "Width of the maze, rounded up to 7, 11, 15, and so on"
+    )]
+    width: usize,
+    #[arg(
+        short,
+        long,
+        default_value_t = 31,
+        help = "Height of the maze, rounded up to 7, 11, 15, and so on"
+    )]
+    height: usize,
+    #[arg(long, help = "Fail instead of rounding up the width or height")]
+    exact: bool,
+    #[arg(short, long, default_value_t = 3, help = "Size if the central room")]
+    room_size: usize,
+    #[arg(
+        long,
+        value_enum,
+        default_value_t = ExitLocation::Right,
+        help = "Side of the maze where the exit is placed"
+    )]
+    exit: ExitLocation,
+    #[arg(long, help = "Seed for the random generator, random if omitted")]
+    seed: Option<u64>,
+    #[arg(
+        long,
+        value_enum,
+        default_value_t = Algorithm::Dfs,
+        help = "Algorithm used to generate the maze"
+    )]
+    algorithm: Algorithm,
+    #[arg(short, long, help = "Ratio of empty cells to cells with artifacts")]
+    artifacts_ratio: Option<f32>,
+    #[arg(
+        long,
+        help = "Regenerate until the difficulty (0-100) is close to this value"
+    )]
+    difficulty: Option<f32>,
+    #[arg(
+        long,
+        requires = "difficulty",
+        default_value_t = DIFFICULTY_TOLERANCE,
+        help = "How far the difficulty may be off the value of --difficulty"
+    )]
+    difficulty_tolerance: f32,
+    #[arg(
+        long,
+        requires = "difficulty",
+        default_value_t = DIFFICULTY_ATTEMPTS,
+        help = "Mazes to generate at most trying to hit --difficulty"
+    )]
+    difficulty_attempts: usize,
+    #[arg(
+        long,
+        value_name = "CELLS",
+        help = "Regenerate until the shortest path has at least this many cells"
+    )]
+    min_path_length: Option<usize>,
+    #[arg(
+        long,
+        value_name = "PROB",
+        help = "Chance of a reward on every dead end, on top of --artifacts-ratio"
+    )]
+    deadend_rewards: Option<f32>,
+    #[arg(
+        long,
+        value_name = "PROB",
+        help = "Chance of a danger on every dead end without a reward"
+    )]
+    deadend_dangers: Option<f32>,
+    #[arg(
+        long,
+        value_name = "FILE",
+        help = "JSON file overriding the weights of cell types, e.g. {\"Witch\": 100}"
+    )]
+    weights: Option<String>,
+    #[arg(
+        long,
+        default_value_t = 1,
+        help = "Width of the corridors in cells, walls stay one cell thick"
+    )]
+    corridor_width: usize,
+    #[arg(
+        long,
+        default_value_t = 0.0,
+        help = "Chance (0-1) of tunneling under a corridor at a dead end, making a bridge"
+    )]
+    weave: f32,
+    #[arg(
+        long,
+        value_name = "SHARE",
+        help = "Share (0-1) of the dead ends opened up into loops, 0 for a perfect maze"
+    )]
+    braid: Option<f32>,
+    #[cfg(feature = "parallel")]
+    #[arg(
+        long,
+        help = "Generate in chunks of about this many cells square on several threads"
+    )]
+    chunk_size: Option<usize>,
+    #[arg(
+        long,
+        value_enum,
+        default_value_t = Topology::Grid,
+        help = "Shape of the maze"
+    )]
+    topology: Topology,
+    #[arg(
+        long,
+        default_value_t = 10,
+        help = "Number of rings around the center of a circular maze"
+    )]
+    rings: usize,
+    #[arg(
+        long,
+        default_value_t = 1,
+        help = "Number of levels connected by stairs, more than one is exported as SVG only"
+    )]
+    levels: usize,
+    #[arg(
+        long,
+        value_name = "RATIO",
+        help = "Put random letters on this share (0-1) of the corridor cells"
+    )]
+    letters: Option<f32>,
+    #[arg(long, help = "Hide a word along the way out, letter by letter")]
+    word: Option<String>,
+    #[arg(
+        long,
+        value_enum,
+        help = "Start from the options and colors of a preset, flags given as well take precedence"
+    )]
+    preset: Option<Preset>,
+    #[arg(
+        long,
+        value_name = "FILE",
+        conflicts_with = "preset",
+        help = "Like --preset, but read from a TOML file with the fields of a preset"
+    )]
+    preset_file: Option<String>,
+    #[arg(short, long, help = "Save the maze to a JSON file")]
+    out: Option<String>,
+    #[cfg(feature = "image")]
+    #[arg(
+        long,
+        value_name = "FILE",
+        conflicts_with_all = [
+            "load",
+            "from_share",
+            "difficulty",
+            "min_path_length",
+            "deadend_rewards",
+            "deadend_dangers"
+        ],
+        help = "Record the generation as an animated GIF, with cells as wide as --scale"
+    )]
+    gif_file: Option<String>,
+    #[cfg(feature = "image")]
+    #[arg(
+        long,
+        default_value_t = 25,
+        help = "Generation events per GIF frame, more for long recordings"
+    )]
+    gif_step: usize,
+    #[arg(
+        long,
+        value_name = "FILE",
+        conflicts_with_all = [
+            "width",
+            "height",
+            "exact",
+            "room_size",
+            "exit",
+            "algorithm",
+            "difficulty",
+            "min_path_length",
+            "corridor_width",
+            "weave",
+            "braid",
+            "topology",
+            "rings",
+            "levels",
+            "from_share"
+        ],
+        help = "Load the maze from a JSON file instead of generating one"
+    )]
+    load: Option<String>,
+    #[command(flatten)]
+    output: OutputArgs,
+    #[command(flatten)]
+    solution: SolutionArgs,
+    #[arg(
+        long,
+        default_value_t = false,
+        help = "Print a share code for the maze"
+    )]
+    share: bool,
+    #[arg(
+        long,
+        default_value_t = false,
+        help = "Print the edges of a minimum spanning tree of the junctions"
+    )]
+    print_mst: bool,
+    #[arg(
+        long,
+        value_name = "CODE",
+        help = "Load the maze from a share code instead of generating one"
+    )]
+    from_share: Option<String>,
     #[arg(short, long, default_value_t = false, help = "Enable verbose output")]
     verbose: bool,
 }
 
-fn main() -> Result<(), Box<dyn std::error::Error>> {
-    let cli = Cli::parse();
-    let mut maze = Maze::new(cli.width, cli.height, cli.room_size, ExitLocation::Right);
-    maze.generate();
-    if let Some(artifacts_ratio) = cli.artifacts_ratio {
-        maze.place_artifacts(artifacts_ratio);
+#[derive(clap::Args, Debug)]
+struct SolveArgs {
+    #[arg(help = "Maze file written by `maze generate --out`")]
+    maze: String,
+    #[command(flatten)]
+    solver: SolverArgs,
+    #[arg(
+        long,
+        value_enum,
+        default_value_t = PathFormat::Positions,
+        help = "How to print the solution"
+    )]
+    format: PathFormat,
+    #[command(flatten)]
+    solution: SolutionArgs,
+}
+
+#[derive(clap::Args, Debug)]
+struct ExportArgs {
+    #[arg(help = "Maze file written by `maze generate --out`")]
+    maze: String,
+    #[command(flatten)]
+    output: OutputArgs,
+}
+
+#[cfg(feature = "tui")]
+#[derive(clap::Args, Debug)]
+struct PlayArgs {
+    #[arg(help = "Maze file to play, a new maze is generated if omitted")]
+    maze: Option<String>,
+    #[arg(long, default_value_t = 41, help = "Width of the maze")]
+    width: usize,
+    #[arg(long, default_value_t = 21, help = "Height of the maze")]
+    height: usize,
+    #[arg(long, default_value_t = 3, help = "Size if the central room")]
+    room_size: usize,
+    #[arg(long, help = "Seed for the random generator, random if omitted")]
+    seed: Option<u64>,
+    #[arg(
+        short,
+        long,
+        default_value_t = 0.1,
+        help = "Ratio of empty cells to cells with artifacts"
+    )]
+    artifacts_ratio: f32,
+    #[arg(
+        long,
+        help = "Let zombies, bats and spiders wander around and ghosts and witches chase you"
+    )]
+    monsters: bool,
+    #[arg(
+        long,
+        requires = "monsters",
+        help = "Monsters only give chase once they see you down a corridor"
+    )]
+    line_of_sight: bool,
+    #[arg(
+        long,
+        value_name = "FILE",
+        help = "Save the run as JSON for `maze replay`"
+    )]
+    record: Option<String>,
+    #[arg(long, help = "Health to start with, dangers take their weight off it")]
+    health: Option<i32>,
+}
+
+impl GenerateArgs {
+    /// Replaces the options not given on the command line with those of the
+    /// preset, if there is one. A loaded maze only takes the colors.
+    fn apply_preset(&mut self, matches: &ArgMatches) -> Result<(), Box<dyn std::error::Error>> {
+        let preset = match (self.preset, &self.preset_file) {
+            (Some(preset), _) => preset.preset(),
+            (None, Some(filename)) => load_preset(filename)?,
+            (None, None) => return Ok(()),
+        };
+        self.output.theme = preset.theme;
+        if self.load.is_some() || self.from_share.is_some() {
+            return Ok(());
+        }
+        let unset = |id: &str| matches.value_source(id) != Some(ValueSource::CommandLine);
+        if unset("width") {
+            self.width = preset.width;
+        }
+        if unset("height") {
+            self.height = preset.height;
+        }
+        if unset("room_size") {
+            self.room_size = preset.room_size;
+        }
+        if unset("exit") {
+            self.exit = preset.exit;
+        }
+        if unset("corridor_width") {
+            self.corridor_width = preset.corridor_width;
+        }
+        if unset("weave") {
+            self.weave = preset.weave;
+        }
+        if unset("artifacts_ratio") && preset.artifacts > 0.0 {
+            self.artifacts_ratio = Some(preset.artifacts);
+        }
+        if unset("deadend_rewards") && preset.dead_end_rewards > 0.0 {
+            self.deadend_rewards = Some(preset.dead_end_rewards);
+        }
+        if unset("deadend_dangers") && preset.dead_end_dangers > 0.0 {
+            self.deadend_dangers = Some(preset.dead_end_dangers);
+        }
+        if unset("difficulty") && preset.difficulty.is_some() {
+            self.difficulty = preset.difficulty;
+        }
+        Ok(())
+    }
+
+    /// Fails if options of rectangular mazes were given on the command line
+    /// for a circular one, which would silently ignore them.
+    fn check_topology(&self, matches: &ArgMatches) -> Result<(), Box<dyn std::error::Error>> {
+        if self.topology != Topology::Circular {
+            return Ok(());
+        }
+        let grid_only = [
+            "width",
+            "height",
+            "exact",
+            "room_size",
+            "exit",
+            "algorithm",
+            "difficulty",
+            "difficulty_tolerance",
+            "difficulty_attempts",
+            "corridor_width",
+            "weave",
+            "braid",
+            #[cfg(feature = "parallel")]
+            "chunk_size",
+            "preset",
+            "preset_file",
+        ];
+        match grid_only
+            .iter()
+            .find(|id| matches.value_source(id) == Some(ValueSource::CommandLine))
+        {
+            Some(id) => Err(format!(
+                "--{} isn't supported for circular mazes",
+                id.replace('_', "-")
+            )
+            .into()),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Reads a preset from a TOML file. Fields left out keep their defaults,
+/// e.g. `width = 41` and `theme = { wall_color = [0, 0, 128] }`.
+fn load_preset(filename: &str) -> Result<MazePreset, Box<dyn std::error::Error>> {
+    let text = std::fs::read_to_string(filename).map_err(|err| format!("{}: {}", filename, err))?;
+    Ok(toml::from_str(&text).map_err(|err| format!("{}: {}", filename, err))?)
+}
+
+/// Loads a maze file, warning about anything that would keep the maze from
+/// being solved as intended.
+fn load(filename: &str) -> Result<Maze, Box<dyn std::error::Error>> {
+    let maze = Maze::load_json(filename)?;
+    for violation in maze.validate() {
+        eprintln!("{}: {}", filename, violation);
+    }
+    Ok(maze)
+}
+
+/// Shows the library's log messages, debug messages too if `verbose`.
+/// `RUST_LOG` overrides the level.
+fn init_logging(verbose: bool) {
+    let level = if verbose { "debug" } else { "info" };
+    env_logger::Builder::from_env(env_logger::Env::default().default_filter_or(level))
+        .format_timestamp(None)
+        .format_target(false)
+        .init();
+}
+
+fn generate(args: &GenerateArgs) -> Result<(), Box<dyn std::error::Error>> {
+    let seed = args.seed.unwrap_or_else(|| rand::rng().random());
+    let mut rng = StdRng::seed_from_u64(seed);
+    if args.topology == Topology::Circular {
+        return generate_circular(args, seed, &mut rng);
+    }
+    if args.levels > 1 {
+        return generate_levels(args, seed, &mut rng);
+    }
+    // Only replaces the weights saved with a loaded maze if given
+    let weights = match &args.weights {
+        Some(filename) => Some(WeightTable::load_json(filename)?),
+        None => None,
+    };
+    let mut params = None;
+    let mut maze = if let Some(code) = &args.from_share {
+        Maze::from_share_string(code)?
+    } else if let Some(filename) = &args.load {
+        let mut maze = load(filename)?;
+        if let Some(artifacts_ratio) = args.artifacts_ratio {
+            if args.verbose {
+                println!("Seed: {}", seed);
+            }
+            maze.place_artifacts_with_rng(artifacts_ratio, &mut rng);
+        }
+        if args.deadend_rewards.is_some() || args.deadend_dangers.is_some() {
+            maze.decorate_dead_ends(
+                args.deadend_rewards.unwrap_or(0.0),
+                args.deadend_dangers.unwrap_or(0.0),
+                &mut rng,
+            );
+        }
+        maze
+    } else {
+        let mut builder = MazeBuilder::new()
+            .width(args.width)
+            .height(args.height)
+            .exact(args.exact)
+            .room_size(args.room_size)
+            .exit(args.exit.clone())
+            .corridor_width(args.corridor_width)
+            .weave(args.weave)
+            .weights(weights.clone().unwrap_or_default())
+            .artifacts(args.artifacts_ratio.unwrap_or(0.0));
+        if let Some(braid) = args.braid {
+            builder = builder.braid(braid);
+        }
+        if let Some(difficulty) = args.difficulty {
+            builder = builder
+                .difficulty(difficulty)
+                .difficulty_tolerance(args.difficulty_tolerance, args.difficulty_attempts);
+        }
+        if let Some(length) = args.min_path_length {
+            builder = builder.min_solution_length(length);
+        }
+        if args.deadend_rewards.is_some() || args.deadend_dangers.is_some() {
+            builder = builder.dead_end_artifacts(
+                args.deadend_rewards.unwrap_or(0.0),
+                args.deadend_dangers.unwrap_or(0.0),
+            );
+        }
+        #[cfg(feature = "parallel")]
+        if let Some(chunk_size) = args.chunk_size {
+            builder = builder.chunk_size(chunk_size);
+        }
+        let (width, height) = builder.blank()?.get_size();
+        if !args.verbose && (width, height) != (args.width, args.height) {
+            eprintln!("Size: {}x{}", width, height);
+        }
+        if args.verbose {
+            println!("Size: {}x{}", width, height);
+            println!("Room size: {}", args.room_size);
+            println!("Algorithm: {:?}", args.algorithm);
+            println!("Seed: {}", seed);
+        }
+        let maze = builder.build_with_rng(&mut rng)?;
+        params = Some(builder.clone().seed(seed));
+        if args.verbose && args.difficulty.is_some() {
+            println!("Difficulty: {:.1}", maze.difficulty());
+        }
+        #[cfg(feature = "image")]
+        if let Some(gif_file) = &args.gif_file {
+            write_generation_gif(args, &builder, seed, &maze, gif_file)?;
+        }
+        maze
+    };
+    if let Some(weights) = weights {
+        maze.set_weight_table(weights);
+    }
+    if let Some(ratio) = args.letters {
+        let alphabet: Vec<(char, u32)> = ('A'..='Z').map(|letter| (letter, 1)).collect();
+        maze.place_labels(&alphabet, ratio, &mut rng);
+    }
+    if let Some(word) = &args.word {
+        maze.embed_word_on_solution(word)?;
+    }
+    if args.verbose {
+        println!("Fingerprint: {:016x}", maze.fingerprint());
+        match maze.exit_pos() {
+            Some(exit) => {
+                let side = if exit.x == 0 {
+                    ExitLocation::Left
+                } else if exit.x == maze.width() - 1 {
+                    ExitLocation::Right
+                } else if exit.y == 0 {
+                    ExitLocation::Top
+                } else {
+                    ExitLocation::Bottom
+                };
+                println!("Exit: {:?} at {}", side, exit);
+            }
+            None => println!("Exit: none"),
+        }
     }
-    if let Some(dot_file) = cli.dot_file {
-        maze.export_to_dot(&dot_file)?;
+    if args.share {
+        println!("{}", maze.to_share_string()?);
     }
-    if let Some(svg_file) = cli.svg_file {
-        maze.export_to_svg(&svg_file, cli.scale, cli.with_path)?;
+    if args.print_mst {
+        print!("{}", maze.mst_prim());
     }
+    if let Some(out) = &args.out {
+        maze.save_json(out)?;
+    }
+    args.output.write(&maze, params.as_ref())?;
+    args.solution.write(&maze, args.output.solver.solver())?;
+    Ok(())
+}
+
+/// Fails if any output other than SVG is requested for a kind of maze that
+/// only has an SVG export so far.
+fn check_svg_only(args: &GenerateArgs, kind: &str) -> Result<(), Box<dyn std::error::Error>> {
+    let unsupported = [
+        (args.from_share.is_some(), "--from-share"),
+        (args.out.is_some(), "--out"),
+        (args.share, "--share"),
+        (args.print_mst, "--print-mst"),
+        (args.weights.is_some(), "--weights"),
+        (args.min_path_length.is_some(), "--min-path-length"),
+        (args.deadend_rewards.is_some(), "--deadend-rewards"),
+        (args.deadend_dangers.is_some(), "--deadend-dangers"),
+        (args.letters.is_some(), "--letters"),
+        (args.word.is_some(), "--word"),
+        (args.output.dot_file.is_some(), "--dot-file"),
+        (args.output.scad_file.is_some(), "--scad-file"),
+        #[cfg(feature = "image")]
+        (args.output.png_file.is_some(), "--png-file"),
+        (args.output.stl_file.is_some(), "--stl-file"),
+        (args.output.laser_file.is_some(), "--laser-file"),
+        (args.output.print_color, "--print-color"),
+        (args.output.coordinates, "--coordinates"),
+        (args.output.grid.is_some(), "--grid"),
+        (args.output.crisp, "--crisp"),
+        (args.solution.solution_out.is_some(), "--solution-out"),
+    ];
+    #[cfg(feature = "image")]
+    if args.gif_file.is_some() {
+        return Err(format!("--gif-file isn't supported for {}", kind).into());
+    }
+    match unsupported.iter().find(|(used, _)| *used) {
+        Some((_, flag)) => Err(format!("{} isn't supported for {}", flag, kind).into()),
+        None => Ok(()),
+    }
+}
+
+/// Generates the maze again from `seed`, recording the events, and writes
+/// them as an animated GIF. Fails if the recording doesn't end up as
+/// `maze`, which happens when it was generated in chunks.
+#[cfg(feature = "image")]
+fn write_generation_gif(
+    args: &GenerateArgs,
+    builder: &MazeBuilder,
+    seed: u64,
+    maze: &Maze,
+    gif_file: &str,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let blank = builder.blank()?;
+    let mut replay = blank.clone();
+    let mut events = Vec::new();
+    let mut rng = StdRng::seed_from_u64(seed);
+    replay.generate_observed(&mut rng, |event| events.push(event));
+    replay.place_artifacts_observed(args.artifacts_ratio.unwrap_or(0.0), &mut rng, |event| {
+        events.push(event)
+    });
+    if replay != *maze {
+        return Err("the generation of this maze can't be recorded".into());
+    }
+    let cell_px = (args.output.scale.round() as u32).max(1);
+    blank.export_generation_gif(gif_file, &events, args.gif_step, cell_px)?;
+    Ok(())
+}
 
-    maze.mst_prim();
+fn generate_circular(
+    args: &GenerateArgs,
+    seed: u64,
+    rng: &mut StdRng,
+) -> Result<(), Box<dyn std::error::Error>> {
+    if args.levels > 1 {
+        return Err("circular mazes have a single level".into());
+    }
+    check_svg_only(args, "circular mazes")?;
+    let mut maze = CircularMaze::new(args.rings)?;
+    if args.verbose {
+        println!("Rings: {}", maze.rings());
+        println!("Seed: {}", seed);
+    }
+    maze.generate_with_rng(rng);
+    if let Some(artifacts_ratio) = args.artifacts_ratio {
+        maze.place_artifacts_with_rng(artifacts_ratio, rng);
+    }
+    if args.verbose
+        && let Some(exit) = maze.exit_pos()
+    {
+        println!("Exit: {}", exit);
+    }
+    if let Some(svg_file) = &args.output.svg_file {
+        let options = SvgOptions {
+            scale: args.output.scale,
+            with_solution: args.output.with_path.clone(),
+            animation: args.output.animation(),
+            ..Default::default()
+        };
+        maze.export_to_svg(svg_file, &options)?;
+    }
     Ok(())
 }
+
+fn generate_levels(
+    args: &GenerateArgs,
+    seed: u64,
+    rng: &mut StdRng,
+) -> Result<(), Box<dyn std::error::Error>> {
+    check_svg_only(args, "mazes with several levels")?;
+    let builder = MazeBuilder::new()
+        .width(args.width)
+        .height(args.height)
+        .exact(args.exact)
+        .room_size(args.room_size)
+        .exit(args.exit.clone())
+        .corridor_width(args.corridor_width)
+        .weave(args.weave)
+        .artifacts(args.artifacts_ratio.unwrap_or(0.0));
+    if args.verbose {
+        let (width, height) = builder.blank()?.get_size();
+        println!("Size: {}x{}x{}", width, height, args.levels);
+        println!("Room size: {}", args.room_size);
+        println!("Seed: {}", seed);
+    }
+    let maze = Maze3D::generate_with_rng(&builder, args.levels, rng)?;
+    if args.verbose {
+        if let Some((z, pos)) = maze.exit_pos() {
+            println!("Exit: level {} at {}", z + 1, pos);
+        }
+        println!("Stairs: {}", maze.stairs.len());
+    }
+    if let Some(svg_file) = &args.output.svg_file {
+        let options = SvgOptions {
+            scale: args.output.scale,
+            with_solution: args.output.with_path.clone(),
+            overlay: args.output.overlay,
+            animation: args.output.animation(),
+            ..Default::default()
+        };
+        maze.export_to_svg(svg_file, &options)?;
+    }
+    Ok(())
+}
+
+fn solve(args: &SolveArgs) -> Result<(), Box<dyn std::error::Error>> {
+    let maze = load(&args.maze)?;
+    let path = maze
+        .solve(args.solver.solver())
+        .ok_or(MazeError::Unsolvable)?;
+    args.solution.write(&maze, args.solver.solver())?;
+    match args.format {
+        PathFormat::Positions => {
+            for pos in path {
+                println!("{},{}", pos.x, pos.y);
+            }
+        }
+        PathFormat::Moves => println!("{}", Maze::path_to_moves(&path)?),
+    }
+    Ok(())
+}
+
+fn bench(filename: &str, json: bool) -> Result<(), Box<dyn std::error::Error>> {
+    let results = benchmark_solvers(&load(filename)?)?;
+    if json {
+        println!("{}", serde_json::to_string_pretty(&results)?);
+        return Ok(());
+    }
+    let or_dash = |value: Option<String>| value.unwrap_or_else(|| "-".to_string());
+    println!(
+        "{:<22} {:>10} {:>10} {:>8} {:>6}",
+        "Solver", "Time (ms)", "Expanded", "Length", "Cost"
+    );
+    for result in &results {
+        println!(
+            "{:<22} {:>10.3} {:>10} {:>8} {:>6}",
+            result.solver,
+            result.time_ms,
+            or_dash(result.expansions.map(|n| n.to_string())),
+            or_dash(result.path_length.map(|n| n.to_string())),
+            or_dash(result.path_cost.map(|n| n.to_string())),
+        );
+    }
+    Ok(())
+}
+
+fn replay(filename: &str) -> Result<(), Box<dyn std::error::Error>> {
+    let replay = Replay::load(filename)?;
+    let summary = Simulation::replay(&replay)?;
+    for turn in &replay.turns {
+        let monsters: Vec<String> = turn.monsters.iter().map(|pos| pos.to_string()).collect();
+        println!(
+            "Turn {}: {:?}, player at {}, monsters at [{}]",
+            turn.turn,
+            turn.player_move,
+            turn.player,
+            monsters.join(", ")
+        );
+    }
+    println!("Outcome: {:?}", summary.outcome);
+    println!("Steps taken: {}", summary.steps);
+    println!("Score: {}", summary.score);
+    if let Some(health) = summary.health {
+        println!("Health: {}", health);
+    }
+    Ok(())
+}
+
+fn diff(left: &str, right: &str) -> Result<(), Box<dyn std::error::Error>> {
+    let differences = load(left)?.diff(&load(right)?)?;
+    if differences.is_empty() {
+        println!("The mazes are equal");
+        return Ok(());
+    }
+    for difference in &differences {
+        println!("{}", difference);
+    }
+    Err(format!("the mazes differ in {} cell(s)", differences.len()).into())
+}
+
+#[cfg(feature = "tui")]
+fn play(args: &PlayArgs) -> Result<(), Box<dyn std::error::Error>> {
+    let seed = args.seed.unwrap_or_else(|| rand::rng().random());
+    let maze = match &args.maze {
+        Some(filename) => load(filename)?,
+        None => MazeBuilder::new()
+            .width(args.width)
+            .height(args.height)
+            .room_size(args.room_size)
+            .artifacts(args.artifacts_ratio)
+            .seed(seed)
+            .build()?,
+    };
+    let mut options = SimulationOptions {
+        health: args.health,
+        line_of_sight: args.line_of_sight,
+        ..Default::default()
+    };
+    if args.monsters {
+        options = options.monsters();
+    }
+    let (game, elapsed) = play::play(&maze, options, seed)?;
+    match game.outcome() {
+        StepOutcome::Win => println!("You made it out!"),
+        StepOutcome::Lose => println!("You ran out of health!"),
+        _ => {}
+    }
+    println!("Steps taken: {}", game.steps());
+    println!("Score: {}", game.score());
+    println!("Time: {:.1}s", elapsed.as_secs_f32());
+    if let Some(optimal) = game.optimal_steps() {
+        println!("Shortest way out: {} steps", optimal);
+    }
+    if let Some(filename) = &args.record {
+        game.record().save(filename)?;
+    }
+    Ok(())
+}
+
+fn run(cli: &Cli) -> Result<(), Box<dyn std::error::Error>> {
+    match &cli.command {
+        None => generate(&cli.generate),
+        Some(Command::Generate(args)) => generate(args),
+        Some(Command::Solve(args)) => solve(args),
+        Some(Command::Export(args)) => args.output.write(&load(&args.maze)?, None),
+        Some(Command::Stats { maze }) => {
+            println!("{}", serde_json::to_string_pretty(&load(maze)?.stats())?);
+            Ok(())
+        }
+        Some(Command::Bench { maze, json }) => bench(maze, *json),
+        Some(Command::Diff { left, right }) => diff(left, right),
+        Some(Command::Replay { replay: filename }) => replay(filename),
+        #[cfg(feature = "tui")]
+        Some(Command::Play(args)) => play(args),
+    }
+}
+
+fn main() -> ExitCode {
+    let matches = Cli::command().get_matches();
+    let mut cli = Cli::from_arg_matches(&matches).unwrap_or_else(|err| err.exit());
+    let preset = match &mut cli.command {
+        None => cli
+            .generate
+            .apply_preset(&matches)
+            .and_then(|()| cli.generate.check_topology(&matches)),
+        Some(Command::Generate(args)) => match matches.subcommand_matches("generate") {
+            Some(matches) => args
+                .apply_preset(matches)
+                .and_then(|()| args.check_topology(matches)),
+            None => Ok(()),
+        },
+        _ => Ok(()),
+    };
+    init_logging(match &cli.command {
+        None => cli.generate.verbose,
+        Some(Command::Generate(args)) => args.verbose,
+        _ => false,
+    });
+    match preset.and_then(|()| run(&cli)) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            eprintln!("maze: {}", err);
+            ExitCode::FAILURE
+        }
+    }
+}
@@ -0,0 +1,258 @@
+use crossterm::cursor::{Hide, MoveTo, Show};
+use crossterm::event::{self, Event, KeyCode, KeyEventKind, KeyModifiers};
+use crossterm::style::{Color, Print, ResetColor, SetBackgroundColor, SetForegroundColor};
+use crossterm::terminal::{self, Clear, ClearType, EnterAlternateScreen, LeaveAlternateScreen};
+use crossterm::{execute, queue};
+use std::collections::HashSet;
+use std::io::{self, Write};
+use std::time::{Duration, Instant};
+
+use mazegen::{
+    CellType, DANGERS, Direction, Maze, PlayerMove, Pos, REWARDS, Simulation, SimulationOptions,
+    Solver, Theme,
+};
+
+/// Puts the terminal into raw mode on the alternate screen and restores it
+/// when dropped, also when leaving through an error.
+struct TerminalGuard;
+
+impl TerminalGuard {
+    fn enter() -> io::Result<Self> {
+        terminal::enable_raw_mode()?;
+        execute!(io::stdout(), EnterAlternateScreen, Hide)?;
+        Ok(TerminalGuard)
+    }
+}
+
+impl Drop for TerminalGuard {
+    fn drop(&mut self) {
+        let _ = execute!(io::stdout(), Show, LeaveAlternateScreen);
+        let _ = terminal::disable_raw_mode();
+    }
+}
+
+fn rgb([r, g, b]: [u8; 3]) -> Color {
+    Color::Rgb { r, g, b }
+}
+
+/// Background color and label of the cell at `pos`. Cells on the `hint`
+/// are drawn in the solution color unless they hold an artifact.
+fn cell_look(
+    game: &Simulation,
+    pos: Pos,
+    hint: Option<&HashSet<Pos>>,
+    theme: &Theme,
+) -> ([u8; 3], &'static str) {
+    let cell = game.maze().get(pos.x, pos.y);
+    let color = match cell {
+        CellType::Wall => theme.wall_color,
+        cell if REWARDS.contains(&cell) => theme.reward_color,
+        cell if DANGERS.contains(&cell) => theme.danger_color,
+        _ if hint.is_some_and(|hint| hint.contains(&pos)) => theme.solution_color,
+        _ => theme.path_color,
+    };
+    let label = if pos == game.player() {
+        "@ "
+    } else {
+        match cell {
+            CellType::Start => "S ",
+            CellType::Exit => "E ",
+            _ => "  ",
+        }
+    };
+    (color, label)
+}
+
+/// The move a key stands for, if any
+fn player_move(code: KeyCode) -> Option<PlayerMove> {
+    match code {
+        KeyCode::Up | KeyCode::Char('w') => Some(PlayerMove::Go(Direction::North)),
+        KeyCode::Down | KeyCode::Char('s') => Some(PlayerMove::Go(Direction::South)),
+        KeyCode::Left | KeyCode::Char('a') => Some(PlayerMove::Go(Direction::West)),
+        KeyCode::Right | KeyCode::Char('d') => Some(PlayerMove::Go(Direction::East)),
+        KeyCode::Char(' ') => Some(PlayerMove::Wait),
+        _ => None,
+    }
+}
+
+fn render(
+    out: &mut impl Write,
+    game: &Simulation,
+    hint: Option<&HashSet<Pos>>,
+    elapsed: Duration,
+) -> io::Result<()> {
+    let theme = Theme::default();
+    let maze = game.maze();
+    let (width, height) = maze.get_size();
+    let (columns, rows) = terminal::size()?;
+    if (columns as usize) < 2 * width || (rows as usize) < height + 1 {
+        queue!(
+            out,
+            MoveTo(0, 0),
+            Clear(ClearType::All),
+            Print(format!(
+                "Enlarge the terminal to at least {}x{} to play, q to quit",
+                2 * width,
+                height + 1
+            ))
+        )?;
+        return out.flush();
+    }
+
+    for y in 0..height {
+        queue!(out, MoveTo(0, y as u16))?;
+        for x in 0..width {
+            let (color, label) = cell_look(game, Pos { x, y }, hint, &theme);
+            queue!(
+                out,
+                SetBackgroundColor(rgb(color)),
+                SetForegroundColor(rgb(theme.wall_color)),
+                Print(label)
+            )?;
+        }
+        queue!(out, ResetColor)?;
+    }
+    let health = match game.health() {
+        Some(health) => format!("Health: {}  ", health),
+        None => String::new(),
+    };
+    queue!(
+        out,
+        MoveTo(0, height as u16),
+        Clear(ClearType::CurrentLine),
+        Print(format!(
+            "Steps: {}  Score: {}  {}Time: {}s  [arrows/WASD] move  [space] wait  [h] hint  [q] quit",
+            game.steps(),
+            game.score(),
+            health,
+            elapsed.as_secs()
+        ))
+    )?;
+    out.flush()
+}
+
+/// Lets the player walk through `maze` in the terminal until they reach the
+/// exit, run out of health or quit. Returns the final state of the game and
+/// the time taken.
+pub fn play(
+    maze: &Maze,
+    options: SimulationOptions,
+    seed: u64,
+) -> io::Result<(Simulation, Duration)> {
+    let mut game = Simulation::new(maze, options, seed);
+    // A set, the hint is looked up for every cell of every frame
+    let hint: HashSet<Pos> = maze
+        .solve(Solver::AStar)
+        .unwrap_or_default()
+        .into_iter()
+        .collect();
+    let mut show_hint = false;
+    let started = Instant::now();
+
+    let _guard = TerminalGuard::enter()?;
+    let mut out = io::stdout();
+    execute!(out, Clear(ClearType::All))?;
+    while !game.is_over() {
+        let shown = show_hint.then_some(&hint);
+        render(&mut out, &game, shown, started.elapsed())?;
+        // Wake up regularly to keep the clock running
+        if !event::poll(Duration::from_millis(250))? {
+            continue;
+        }
+        match event::read()? {
+            Event::Key(key) if key.kind == KeyEventKind::Press => {
+                if let Some(player_move) = player_move(key.code) {
+                    game.step(player_move);
+                }
+                match key.code {
+                    KeyCode::Char('h') => show_hint = !show_hint,
+                    KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => break,
+                    KeyCode::Char('q') | KeyCode::Esc => break,
+                    _ => {}
+                }
+            }
+            Event::Resize(..) => execute!(out, Clear(ClearType::All))?,
+            _ => {}
+        }
+    }
+    Ok((game, started.elapsed()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mazegen::MazeBuilder;
+
+    fn game() -> Simulation {
+        let maze = MazeBuilder::new()
+            .width(41)
+            .height(21)
+            .artifacts(0.1)
+            .seed(1)
+            .build()
+            .unwrap();
+        Simulation::new(&maze, SimulationOptions::default(), 1)
+    }
+
+    #[test]
+    fn keys_move_the_player() {
+        for (code, direction) in [
+            (KeyCode::Up, Direction::North),
+            (KeyCode::Char('s'), Direction::South),
+            (KeyCode::Left, Direction::West),
+            (KeyCode::Char('d'), Direction::East),
+        ] {
+            assert_eq!(player_move(code), Some(PlayerMove::Go(direction)));
+        }
+        assert_eq!(player_move(KeyCode::Char(' ')), Some(PlayerMove::Wait));
+        assert_eq!(player_move(KeyCode::Char('h')), None);
+        assert_eq!(player_move(KeyCode::Char('q')), None);
+    }
+
+    #[test]
+    fn the_hint_shows_the_way_out() {
+        let game = game();
+        let theme = Theme::default();
+        let path = game.maze().solve(Solver::AStar).unwrap();
+        let hint: HashSet<Pos> = path.iter().copied().collect();
+        let exit = *path.last().unwrap();
+        assert_eq!(
+            cell_look(&game, exit, Some(&hint), &theme),
+            (theme.solution_color, "E ")
+        );
+        assert_eq!(
+            cell_look(&game, exit, None, &theme),
+            (theme.path_color, "E ")
+        );
+        assert_eq!(
+            cell_look(&game, path[0], Some(&hint), &theme),
+            (theme.solution_color, "@ ")
+        );
+        for (pos, cell) in game.maze().artifacts() {
+            let color = if REWARDS.contains(&cell) {
+                theme.reward_color
+            } else {
+                theme.danger_color
+            };
+            assert_eq!(cell_look(&game, pos, Some(&hint), &theme).0, color);
+        }
+        assert_eq!(
+            cell_look(&game, Pos::new(0, 0), Some(&hint), &theme),
+            (theme.wall_color, "  ")
+        );
+    }
+
+    #[test]
+    fn the_player_is_shown_where_they_went() {
+        let mut game = game();
+        let start = game.player();
+        let (direction, next) = Direction::ALL
+            .into_iter()
+            .find_map(|direction| Some((direction, start.step(direction, game.maze().get_size())?)))
+            .unwrap();
+        game.step(PlayerMove::Go(direction));
+        let theme = Theme::default();
+        assert_eq!(cell_look(&game, next, None, &theme).1, "@ ");
+        assert_eq!(cell_look(&game, start, None, &theme).1, "S ");
+    }
+}
@@ -3,12 +3,54 @@
 use eframe::Storage;
 use eframe::egui;
 use egui::{Color32, Pos2, Rect, Stroke, Vec2};
-use mazegen::{DANGERS, ExitLocation, Maze, MazeError, REWARDS, SolutionType, TRAVERSABLE};
+use mazegen::{
+    CellType, DANGERS, Direction, ExitLocation, Maze, MazeError, MazeEvent, MazeJournal, MazeStats,
+    PlayerMove, Pos, Preset, REWARDS, Simulation, SimulationOptions, SolutionStyle, SolutionType,
+    StepOutcome, SvgOptions, SvgOverlay, TRAVERSABLE, Theme,
+};
+use rand::prelude::*;
+use rand::rngs::StdRng;
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::mpsc;
+#[cfg(not(target_arch = "wasm32"))]
+use std::thread;
 
 #[cfg(not(target_arch = "wasm32"))]
 static APP_NAME: &str = "Maze";
 
+/// Storage key of the generation history, kept apart from the settings so
+/// it doesn't end up in exported settings
+const HISTORY_KEY: &str = "history";
+
+/// Mazes kept in the generation history
+const HISTORY_LENGTH: usize = 20;
+
+/// Thumbnails in the history are at most this many pixels across
+const THUMBNAIL_SIZE: usize = 48;
+
+/// How much + and - zoom in and out
+const ZOOM_STEP: f32 = 1.25;
+
+/// Milliseconds a download link stays valid after it was clicked
+#[cfg(target_arch = "wasm32")]
+const DOWNLOAD_URL_LIFETIME_MS: i32 = 10_000;
+
+/// Keys and what they do, as listed in the help window
+const SHORTCUTS: [(&str, &str); 11] = [
+    ("G", "Generate a new maze"),
+    ("S", "Cycle the solution overlay"),
+    ("A", "Show or hide the artifacts"),
+    ("+ / -", "Zoom in and out"),
+    ("Ctrl+E", "Export the maze as SVG"),
+    ("Ctrl+S", "Save the maze as JSON"),
+    ("Ctrl+Z", "Undo an edit"),
+    ("Ctrl+Y", "Redo an edit"),
+    ("Arrows / WASD", "Move while playing"),
+    ("Space", "Wait a turn while playing"),
+    ("F1", "Show or hide this help"),
+];
+
 #[derive(Debug, Serialize, Deserialize)]
 struct AppSettings {
     scale: f32,
@@ -18,11 +60,209 @@ struct AppSettings {
     show_artifacts: bool,
     width: usize,
     height: usize,
+    #[serde(with = "hex_color")]
     wall_color: Color32,
+    #[serde(with = "hex_color")]
     pathway_color: Color32,
+    #[serde(with = "hex_stroke")]
     solution_stroke: Stroke,
+    #[serde(with = "hex_color")]
     reward_color: Color32,
+    #[serde(with = "hex_color")]
     danger_color: Color32,
+    /// Colors of single artifact types, the others get the reward or danger
+    /// color
+    #[serde(default = "default_cell_colors", with = "hex_color_map")]
+    cell_colors: HashMap<CellType, Color32>,
+    #[serde(default = "default_artifacts_ratio")]
+    artifacts_ratio: f32,
+    /// Seed of the last generated maze
+    #[serde(default)]
+    seed: Option<u64>,
+    #[serde(default)]
+    animate_generation: bool,
+    /// Events replayed per frame while animating the generation
+    #[serde(default = "default_animation_speed")]
+    animation_speed: usize,
+    /// Grow the solution from start to exit instead of showing it at once
+    #[serde(default)]
+    animate_solution: bool,
+    /// Path cells added per frame while the solution grows
+    #[serde(default = "default_solution_speed")]
+    solution_speed: usize,
+    #[serde(default)]
+    solution_style: SolutionStyle,
+    /// Let dangers move while playing, see `SimulationOptions::monsters`
+    #[serde(default)]
+    mobile_dangers: bool,
+    /// Preset picked last, the options without a control of their own come
+    /// from it
+    #[serde(default)]
+    preset: Option<Preset>,
+    /// Edits that can be undone
+    #[serde(default = "default_undo_depth")]
+    undo_depth: usize,
+    /// Draw the graph of `Maze::build_graph` on top of the maze
+    #[serde(default)]
+    show_graph: bool,
+    /// Only `None` and `DistanceHeatmap` are shown on screen
+    #[serde(default = "default_overlay")]
+    overlay: SvgOverlay,
+    /// Heatmap colors, nearest to farthest from the start
+    #[serde(default = "default_heatmap_near", with = "hex_color")]
+    heatmap_near: Color32,
+    #[serde(default = "default_heatmap_far", with = "hex_color")]
+    heatmap_far: Color32,
+}
+
+/// Colors as `#rrggbb` strings, so exported settings are easy to read and
+/// edit. Earlier versions stored the RGBA arrays `Color32` serializes to,
+/// which still load.
+mod hex_color {
+    use egui::Color32;
+    use serde::de::Error;
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum StoredColor {
+        Hex(String),
+        Rgba([u8; 4]),
+        /// RON, which eframe's storage uses, keeps the parentheses around
+        /// the array of the `Color32` newtype
+        Wrapped(([u8; 4],)),
+    }
+
+    pub fn serialize<S: Serializer>(color: &Color32, serializer: S) -> Result<S::Ok, S::Error> {
+        let [r, g, b, a] = color.to_srgba_unmultiplied();
+        let hex = if a == u8::MAX {
+            format!("#{:02x}{:02x}{:02x}", r, g, b)
+        } else {
+            format!("#{:02x}{:02x}{:02x}{:02x}", r, g, b, a)
+        };
+        serializer.serialize_str(&hex)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Color32, D::Error> {
+        match StoredColor::deserialize(deserializer)? {
+            StoredColor::Hex(hex) => Color32::from_hex(&hex)
+                .map_err(|err| D::Error::custom(format!("invalid color {}: {:?}", hex, err))),
+            StoredColor::Rgba([r, g, b, a]) | StoredColor::Wrapped(([r, g, b, a],)) => {
+                Ok(Color32::from_rgba_premultiplied(r, g, b, a))
+            }
+        }
+    }
+}
+
+/// Colors of cell types like in `hex_color`
+mod hex_color_map {
+    use egui::Color32;
+    use mazegen::CellType;
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+    use std::collections::HashMap;
+
+    #[derive(Serialize, Deserialize)]
+    #[serde(transparent)]
+    struct HexColor(#[serde(with = "super::hex_color")] Color32);
+
+    pub fn serialize<S: Serializer>(
+        colors: &HashMap<CellType, Color32>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        colors
+            .iter()
+            .map(|(&cell, &color)| (cell, HexColor(color)))
+            .collect::<HashMap<_, _>>()
+            .serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<HashMap<CellType, Color32>, D::Error> {
+        let colors = HashMap::<CellType, HexColor>::deserialize(deserializer)?;
+        Ok(colors
+            .into_iter()
+            .map(|(cell, HexColor(color))| (cell, color))
+            .collect())
+    }
+}
+
+/// A `Stroke` as its width and a color like in `hex_color`
+mod hex_stroke {
+    use egui::{Color32, Stroke};
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    #[derive(Serialize, Deserialize)]
+    struct StoredStroke {
+        width: f32,
+        #[serde(with = "super::hex_color")]
+        color: Color32,
+    }
+
+    pub fn serialize<S: Serializer>(stroke: &Stroke, serializer: S) -> Result<S::Ok, S::Error> {
+        StoredStroke {
+            width: stroke.width,
+            color: stroke.color,
+        }
+        .serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Stroke, D::Error> {
+        let stroke = StoredStroke::deserialize(deserializer)?;
+        Ok(Stroke::new(stroke.width, stroke.color))
+    }
+}
+
+fn default_cell_colors() -> HashMap<CellType, Color32> {
+    [
+        (CellType::Marshmallows, "#e86fa8"),
+        (CellType::GummyBears, "#22dd11"),
+        (CellType::Cookies, "#c68642"),
+        (CellType::Candy, "#ff9f1c"),
+        (CellType::Chocolate, "#7b3f00"),
+        (CellType::Zombie, "#6b8e23"),
+        (CellType::Ghost, "#9fa8da"),
+        (CellType::Witch, "#8e44ad"),
+        (CellType::Fog, "#90a4ae"),
+        (CellType::Shadows, "#37474f"),
+        (CellType::Crow, "#263238"),
+        (CellType::BlackCat, "#1b1b1b"),
+        (CellType::Skeleton, "#b0a990"),
+        (CellType::Spider, "#5d4037"),
+        (CellType::Bat, "#4a148c"),
+        (CellType::Pumpkin, "#ff6d00"),
+    ]
+    .into_iter()
+    .map(|(cell, hex)| (cell, Color32::from_hex(hex).unwrap()))
+    .collect()
+}
+
+fn default_solution_speed() -> usize {
+    2
+}
+
+fn default_animation_speed() -> usize {
+    20
+}
+
+fn default_overlay() -> SvgOverlay {
+    SvgOverlay::None
+}
+
+fn default_heatmap_near() -> Color32 {
+    color32(SvgOptions::default().heatmap_colors.0)
+}
+
+fn default_heatmap_far() -> Color32 {
+    color32(SvgOptions::default().heatmap_colors.1)
+}
+
+fn default_undo_depth() -> usize {
+    100
+}
+
+fn default_artifacts_ratio() -> f32 {
+    0.1
 }
 
 impl Default for AppSettings {
@@ -40,13 +280,419 @@ impl Default for AppSettings {
             solution_stroke: Stroke::new(5.0, Color32::from_rgb(28, 163, 163)),
             reward_color: Color32::from_hex("#22dd11").unwrap(),
             danger_color: Color32::from_hex("#ee4433").unwrap(),
+            cell_colors: default_cell_colors(),
+            artifacts_ratio: default_artifacts_ratio(),
+            seed: None,
+            animate_generation: false,
+            animation_speed: default_animation_speed(),
+            animate_solution: false,
+            solution_speed: default_solution_speed(),
+            solution_style: SolutionStyle::Line,
+            mobile_dangers: false,
+            preset: None,
+            undo_depth: default_undo_depth(),
+            show_graph: false,
+            overlay: default_overlay(),
+            heatmap_near: default_heatmap_near(),
+            heatmap_far: default_heatmap_far(),
+        }
+    }
+}
+
+impl AppSettings {
+    /// Color of an artifact on screen, the reward or danger color unless its
+    /// type has one of its own
+    fn artifact_color(&self, cell: CellType) -> Color32 {
+        match self.cell_colors.get(&cell) {
+            Some(&color) => color,
+            None if REWARDS.contains(&cell) => self.reward_color,
+            None => self.danger_color,
+        }
+    }
+
+    fn reset_colors(&mut self) {
+        let defaults = AppSettings::default();
+        self.wall_color = defaults.wall_color;
+        self.pathway_color = defaults.pathway_color;
+        self.solution_stroke.color = defaults.solution_stroke.color;
+        self.reward_color = defaults.reward_color;
+        self.danger_color = defaults.danger_color;
+        self.cell_colors = defaults.cell_colors;
+    }
+}
+
+/// Sets of colors for all of the maze at once
+#[derive(Clone, Copy)]
+enum Palette {
+    Light,
+    Dark,
+}
+
+impl Palette {
+    fn apply(self, settings: &mut AppSettings) {
+        let [wall, pathway, solution, reward, danger] = match self {
+            Palette::Light => ["#9aa5b1", "#fafaf7", "#1c7cd6", "#2ea043", "#d73a49"],
+            Palette::Dark => ["#101014", "#3a3d46", "#4fd1d1", "#5cf04a", "#ff6b5c"],
+        }
+        .map(|hex| Color32::from_hex(hex).unwrap());
+        settings.wall_color = wall;
+        settings.pathway_color = pathway;
+        settings.solution_stroke.color = solution;
+        settings.reward_color = reward;
+        settings.danger_color = danger;
+        settings.cell_colors = default_cell_colors();
+        // Dark artifacts would vanish on dark paths
+        if let Palette::Dark = self {
+            for color in settings.cell_colors.values_mut() {
+                *color = color.lerp_to_gamma(Color32::WHITE, 0.3);
+            }
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+enum ExportFormat {
+    Svg,
+    Dot,
+    /// The maze itself, to load it again later
+    Json,
+}
+
+impl ExportFormat {
+    fn extension(&self) -> &'static str {
+        match self {
+            ExportFormat::Svg => "svg",
+            ExportFormat::Dot => "dot",
+            ExportFormat::Json => "json",
+        }
+    }
+}
+
+fn rgb(color: Color32) -> [u8; 3] {
+    [color.r(), color.g(), color.b()]
+}
+
+fn color32([r, g, b]: [u8; 3]) -> Color32 {
+    Color32::from_rgb(r, g, b)
+}
+
+/// Result of a generation run on the worker thread. `events` are only
+/// recorded when the generation is going to be animated.
+struct Generated {
+    maze: Maze,
+    events: Vec<MazeEvent>,
+    entry: HistoryEntry,
+    thumbnail: egui::ColorImage,
+}
+
+/// Pointer distance in pixels up to which an edge of the graph counts as
+/// hovered
+const GRAPH_HOVER_PX: f32 = 6.0;
+
+/// What a node of the graph is, decides its color
+#[derive(Clone, Copy)]
+enum NodeKind {
+    Start,
+    Exit,
+    Junction,
+    DeadEnd,
+}
+
+impl NodeKind {
+    fn color(self) -> Color32 {
+        match self {
+            NodeKind::Start => Color32::from_rgb(0x2e, 0xa0, 0x43),
+            NodeKind::Exit => Color32::from_rgb(0xd7, 0x3a, 0x49),
+            NodeKind::Junction => Color32::GRAY,
+            NodeKind::DeadEnd => Color32::from_rgb(0x1c, 0x7c, 0xd6),
+        }
+    }
+}
+
+/// The graph of `Maze::build_graph`, ready to draw: the nodes with their
+/// kind and the edges with the nodes they connect and their weight
+struct GraphOverlay {
+    nodes: Vec<(Pos, NodeKind)>,
+    edges: Vec<(Pos, Pos, i32)>,
+}
+
+impl GraphOverlay {
+    fn new(maze: &Maze) -> Self {
+        let (nodes, edges) = maze.build_graph();
+        let positions: HashMap<usize, Pos> = nodes.iter().map(|(&pos, &id)| (id, pos)).collect();
+        let kind = |pos: Pos| {
+            if pos == maze.start_pos() {
+                return NodeKind::Start;
+            }
+            if maze.get(pos.x, pos.y) == CellType::Exit {
+                return NodeKind::Exit;
+            }
+            let open = pos
+                .neighbors(maze.get_size())
+                .filter(|next| TRAVERSABLE.contains(&maze.get(next.x, next.y)))
+                .count();
+            if open > 2 {
+                NodeKind::Junction
+            } else {
+                NodeKind::DeadEnd
+            }
+        };
+        GraphOverlay {
+            nodes: nodes.keys().map(|&pos| (pos, kind(pos))).collect(),
+            edges: edges
+                .iter()
+                .map(|edge| {
+                    (
+                        positions[&edge.start_id()],
+                        positions[&edge.end_id()],
+                        edge.weight(),
+                    )
+                })
+                .collect(),
+        }
+    }
+}
+
+/// Draws the color scale of the heatmap in the top right corner of
+/// `visible`, from the start to the `farthest` distance.
+fn heatmap_legend(
+    painter: &egui::Painter,
+    visible: Rect,
+    near: Color32,
+    far: Color32,
+    farthest: usize,
+) {
+    const STEPS: usize = 32;
+    let bar = Rect::from_min_size(
+        visible.right_top() + Vec2::new(-172.0, 12.0),
+        Vec2::new(160.0, 12.0),
+    );
+    let background = bar
+        .expand2(Vec2::new(6.0, 6.0))
+        .with_max_y(bar.max.y + 22.0);
+    painter.rect_filled(background, 4.0, Color32::from_black_alpha(180));
+    let step = bar.width() / STEPS as f32;
+    for i in 0..STEPS {
+        let t = i as f32 / (STEPS - 1) as f32;
+        painter.rect_filled(
+            Rect::from_min_size(
+                bar.left_top() + Vec2::new(i as f32 * step, 0.0),
+                Vec2::new(step.ceil(), bar.height()),
+            ),
+            0.0,
+            near.lerp_to_gamma(far, t),
+        );
+    }
+    let font = egui::FontId::proportional(12.0);
+    painter.text(
+        bar.left_bottom() + Vec2::new(0.0, 2.0),
+        egui::Align2::LEFT_TOP,
+        "0",
+        font.clone(),
+        Color32::WHITE,
+    );
+    painter.text(
+        bar.right_bottom() + Vec2::new(0.0, 2.0),
+        egui::Align2::RIGHT_TOP,
+        farthest.to_string(),
+        font,
+        Color32::WHITE,
+    );
+}
+
+/// The cells a maze drawn at `origin` has in the `visible` part of the
+/// screen, including those only partly visible
+fn cells_on_screen(visible: Rect, origin: Pos2, scale: f32) -> mazegen::Rect {
+    let first_x = ((visible.min.x - origin.x) / scale).floor().max(0.0) as usize;
+    let first_y = ((visible.min.y - origin.y) / scale).floor().max(0.0) as usize;
+    let last_x = ((visible.max.x - origin.x) / scale).ceil().max(0.0) as usize;
+    let last_y = ((visible.max.y - origin.y) / scale).ceil().max(0.0) as usize;
+    mazegen::Rect::new(
+        first_x,
+        first_y,
+        last_x.saturating_sub(first_x),
+        last_y.saturating_sub(first_y),
+    )
+}
+
+/// What the tooltip says about the cell at `pos`
+fn hover_lines(maze: &Maze, pos: Pos, on_solution: bool) -> Vec<String> {
+    let cell = maze.try_get(pos.x, pos.y).unwrap_or(CellType::Wall);
+    let mut lines = vec![
+        format!("{} at {}", cell, pos),
+        format!("Weight: {}", maze.weight_table().get(cell)),
+    ];
+    if on_solution {
+        lines.push("On the solution path".to_string());
+    }
+    lines
+}
+
+/// The scroll offset that keeps the point under `cursor`, relative to the
+/// visible area scrolled to `offset`, in place when zooming by `ratio`
+fn zoomed_offset(offset: Vec2, cursor: Vec2, ratio: f32) -> Vec2 {
+    ((offset + cursor) * ratio - cursor).max(Vec2::ZERO)
+}
+
+/// Distance of `point` from the line segment between `a` and `b`
+fn distance_to_segment(point: Pos2, a: Pos2, b: Pos2) -> f32 {
+    let ab = b - a;
+    let t = if ab.length_sq() > 0.0 {
+        ((point - a).dot(ab) / ab.length_sq()).clamp(0.0, 1.0)
+    } else {
+        0.0
+    };
+    point.distance(a + ab * t)
+}
+
+/// A maze generated earlier, with the options to generate it again
+#[derive(Clone, Debug, Serialize, Deserialize)]
+struct HistoryEntry {
+    seed: u64,
+    width: usize,
+    height: usize,
+    room_size: usize,
+    exit_type: ExitLocation,
+    artifacts_ratio: f32,
+    #[serde(default)]
+    preset: Option<Preset>,
+    /// Seconds since the Unix epoch when it was generated
+    timestamp: u64,
+    difficulty: f32,
+}
+
+impl HistoryEntry {
+    /// Whether both entries generate the same maze, no matter when
+    fn same_maze(&self, other: &HistoryEntry) -> bool {
+        (
+            self.seed,
+            self.width,
+            self.height,
+            self.room_size,
+            &self.exit_type,
+            self.artifacts_ratio,
+            self.preset,
+        ) == (
+            other.seed,
+            other.width,
+            other.height,
+            other.room_size,
+            &other.exit_type,
+            other.artifacts_ratio,
+            other.preset,
+        )
+    }
+}
+
+/// Entries loaded from storage have no thumbnail, only the mazes generated
+/// since the start do.
+struct HistoryItem {
+    entry: HistoryEntry,
+    thumbnail: Option<egui::TextureHandle>,
+}
+
+/// Shrinks the maze to at most `THUMBNAIL_SIZE` pixels across, each pixel
+/// shaded by the share of path cells it covers.
+fn thumbnail(maze: &Maze, wall: Color32, path: Color32) -> egui::ColorImage {
+    let (width, height) = maze.get_size();
+    let factor = width.max(height).div_ceil(THUMBNAIL_SIZE);
+    let size = [width.div_ceil(factor), height.div_ceil(factor)];
+    let mut image = egui::ColorImage::new(size, wall);
+    for py in 0..size[1] {
+        for px in 0..size[0] {
+            let (mut open, mut total) = (0, 0);
+            for y in py * factor..((py + 1) * factor).min(height) {
+                for x in px * factor..((px + 1) * factor).min(width) {
+                    total += 1;
+                    if maze.get(x, y) != CellType::Wall {
+                        open += 1;
+                    }
+                }
+            }
+            image[(px, py)] = wall.lerp_to_gamma(path, open as f32 / total as f32);
         }
     }
+    image
+}
+
+/// Seconds since the Unix epoch. `SystemTime` isn't available in the
+/// browser.
+#[cfg(not(target_arch = "wasm32"))]
+fn unix_time() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_secs())
+}
+
+#[cfg(target_arch = "wasm32")]
+fn unix_time() -> u64 {
+    (js_sys::Date::now() / 1000.0) as u64
+}
+
+/// How long ago `timestamp` was, roughly
+fn time_ago(timestamp: u64) -> String {
+    let seconds = unix_time().saturating_sub(timestamp);
+    match seconds {
+        0..60 => "just now".to_string(),
+        60..3600 => format!("{} min ago", seconds / 60),
+        3600..86400 => format!("{} h ago", seconds / 3600),
+        _ => format!("{} d ago", seconds / 86400),
+    }
+}
+
+/// Replays the recorded events of a generation onto an all-wall maze.
+/// Once all events are applied, the generated maze itself is swapped in,
+/// so the result is identical to generating without animation.
+struct GenerationAnimation {
+    events: Vec<MazeEvent>,
+    next: usize,
+    result: Maze,
+    paused: bool,
 }
 
 struct MazeApp {
     maze: Maze,
     settings: AppSettings,
+    edit_mode: bool,
+    edit_error: Option<String>,
+    /// Border cell the exit couldn't be moved to, with the reason shown
+    /// while hovering it
+    rejected_exit: Option<(Pos, String)>,
+    /// Edits to the maze shown, cleared when another maze is shown
+    journal: MazeJournal,
+    /// Receives the maze currently being generated on a worker thread
+    pending: Option<mpsc::Receiver<Result<Generated, MazeError>>>,
+    animation: Option<GenerationAnimation>,
+    /// Contents of the seed field, blank for a random seed
+    seed_input: String,
+    /// Title and message of the error dialog, if one is open
+    error: Option<(&'static str, String)>,
+    /// Contents of the share code field
+    share_input: String,
+    share_error: Option<String>,
+    fit_requested: bool,
+    /// Scroll offset to apply on the next frame, used to keep the point under
+    /// the cursor in place while zooming
+    scroll_offset: Option<Vec2>,
+    game: Option<Simulation>,
+    /// Show the shortest way out while playing
+    show_hint: bool,
+    /// Show the window listing the keyboard shortcuts
+    show_help: bool,
+    /// Number of solution cells drawn so far when animating the solution
+    solution_progress: usize,
+    /// Mazes generated last, newest first
+    history: std::collections::VecDeque<HistoryItem>,
+    /// Stats of the maze shown, `None` while they are computed
+    stats: Option<MazeStats>,
+    /// Receives the stats computed on a worker thread
+    stats_pending: Option<mpsc::Receiver<MazeStats>>,
+    /// The maze changed since the stats were last computed
+    stats_stale: bool,
+    /// Graph of the maze shown, built when first drawn
+    graph: Option<GraphOverlay>,
+    /// Distances from the start for the heatmap, found when first drawn
+    distances: Option<Vec<Option<usize>>>,
 }
 
 impl Default for MazeApp {
@@ -56,203 +702,1550 @@ impl Default for MazeApp {
 }
 
 impl MazeApp {
-    #[cfg(not(target_arch = "wasm32"))]
     fn new() -> Self {
-        MazeApp {
+        let mut app = MazeApp {
             maze: Maze::new(61, 31, 3, ExitLocation::Right),
             settings: AppSettings::default(),
+            edit_mode: false,
+            edit_error: None,
+            rejected_exit: None,
+            journal: MazeJournal::default(),
+            pending: None,
+            animation: None,
+            seed_input: String::new(),
+            error: None,
+            share_input: String::new(),
+            share_error: None,
+            fit_requested: false,
+            scroll_offset: None,
+            game: None,
+            show_hint: false,
+            show_help: false,
+            solution_progress: 0,
+            history: std::collections::VecDeque::new(),
+            stats: None,
+            stats_pending: None,
+            stats_stale: false,
+            graph: None,
+            distances: None,
+        };
+        app.regenerate();
+        app
+    }
+
+    /// Starts generating a new maze on a worker thread. Replacing the
+    /// receiver supersedes any generation still in flight: its result is
+    /// dropped when the old thread fails to send it. The browser has no
+    /// threads, so there the maze is generated right away and picked up by
+    /// the next frame.
+    fn regenerate(&mut self) {
+        // Without a valid seed pick a random one and show it, so the maze can
+        // always be reproduced
+        let seed: u64 = match self.seed_input.trim().parse() {
+            Ok(seed) => seed,
+            Err(_) => rand::rng().random(),
+        };
+        self.seed_input = seed.to_string();
+        self.settings.seed = Some(seed);
+
+        let builder = self
+            .settings
+            .preset
+            .map(Preset::preset)
+            .unwrap_or_default()
+            .builder()
+            .width(self.settings.width)
+            .height(self.settings.height)
+            .room_size(self.settings.room_size)
+            .exit(self.settings.exit_type.clone())
+            .artifacts(self.settings.artifacts_ratio)
+            .seed(seed);
+        // Checked here already, so the worker below can't fail
+        let mut maze = match builder.blank() {
+            Ok(maze) => maze,
+            Err(err) => {
+                log::error!("Can't generate the maze: {}", err);
+                self.error = Some(("Can't generate the maze", err.to_string()));
+                return;
+            }
+        };
+        let (artifacts_ratio, animate) = (
+            self.settings.artifacts_ratio,
+            self.settings.animate_generation,
+        );
+        let mut entry = HistoryEntry {
+            seed,
+            width: self.settings.width,
+            height: self.settings.height,
+            room_size: self.settings.room_size,
+            exit_type: self.settings.exit_type.clone(),
+            artifacts_ratio,
+            preset: self.settings.preset,
+            timestamp: unix_time(),
+            difficulty: 0.0,
+        };
+        let (wall_color, pathway_color) = (self.settings.wall_color, self.settings.pathway_color);
+        let (sender, receiver) = mpsc::channel();
+        let generate = move || {
+            let mut events = Vec::new();
+            if animate {
+                let mut rng = StdRng::seed_from_u64(seed);
+                maze.generate_observed(&mut rng, |event| events.push(event));
+                maze.place_artifacts_observed(artifacts_ratio, &mut rng, |event| {
+                    events.push(event)
+                });
+            } else {
+                match builder.build() {
+                    Ok(built) => maze = built,
+                    Err(err) => {
+                        let _ = sender.send(Err(err));
+                        return;
+                    }
+                }
+            }
+            entry.difficulty = maze.difficulty();
+            let thumbnail = thumbnail(&maze, wall_color, pathway_color);
+            let _ = sender.send(Ok(Generated {
+                maze,
+                events,
+                entry,
+                thumbnail,
+            }));
+        };
+        #[cfg(not(target_arch = "wasm32"))]
+        thread::spawn(generate);
+        #[cfg(target_arch = "wasm32")]
+        generate();
+        self.pending = Some(receiver);
+        self.animation = None;
+    }
+
+    /// Generates the maze of a history entry again, from its seed and
+    /// options.
+    fn restore(&mut self, entry: HistoryEntry) {
+        self.settings.width = entry.width;
+        self.settings.height = entry.height;
+        self.settings.room_size = entry.room_size;
+        self.settings.exit_type = entry.exit_type;
+        self.settings.artifacts_ratio = entry.artifacts_ratio;
+        self.settings.preset = entry.preset;
+        self.seed_input = entry.seed.to_string();
+        self.regenerate();
+    }
+
+    /// Puts a generated maze first in the history, moving it up if it is
+    /// there already, and forgets the oldest beyond `HISTORY_LENGTH`.
+    fn remember(&mut self, ctx: &egui::Context, entry: HistoryEntry, thumbnail: egui::ColorImage) {
+        self.history.retain(|item| !item.entry.same_maze(&entry));
+        let texture = ctx.load_texture(
+            format!("history-{}", entry.seed),
+            thumbnail,
+            egui::TextureOptions::NEAREST,
+        );
+        self.history.push_front(HistoryItem {
+            entry,
+            thumbnail: Some(texture),
+        });
+        self.history.truncate(HISTORY_LENGTH);
+    }
+
+    /// Replaces the maze with the one encoded in the share code field.
+    /// A shared maze has no seed, so any generation in flight is dropped
+    /// and the seed is cleared.
+    fn load_share_code(&mut self) {
+        match Maze::from_share_string(&self.share_input) {
+            Ok(maze) => {
+                self.pending = None;
+                self.animation = None;
+                (self.settings.width, self.settings.height) = maze.get_size();
+                self.settings.seed = None;
+                self.seed_input.clear();
+                self.share_input.clear();
+                self.share_error = None;
+                self.show_maze(maze);
+            }
+            Err(err) => self.share_error = Some(err.to_string()),
         }
     }
 
-    #[cfg(target_arch = "wasm32")]
-    pub fn new(_cc: &eframe::CreationContext<'_>) -> Self {
-        if let Some(storage) = cc.storage {
-            return eframe::get_value(storage, eframe::APP_KEY).unwrap_or_default();
+    fn show_maze(&mut self, maze: Maze) {
+        self.maze = maze;
+        self.journal.clear();
+        self.maze_changed();
+        self.solution_progress = 0;
+        if self.game.is_some() {
+            self.start_game();
         }
-        Default::default()
     }
 
-    pub fn draw(&mut self, ui: &mut egui::Ui) {
-        let total_width = self.settings.width as f32 * self.settings.scale;
-        let total_height = self.settings.height as f32 * self.settings.scale;
+    /// Starts playing the maze shown from the start, with a hidden solution
+    fn start_game(&mut self) {
+        let mut options = SimulationOptions::default();
+        if self.settings.mobile_dangers {
+            options = options.monsters();
+        }
+        self.game = Some(Simulation::new(&self.maze, options, rand::rng().random()));
+        self.show_hint = false;
+    }
 
-        let (response, painter) =
-            ui.allocate_painter(Vec2::new(total_width, total_height), egui::Sense::hover());
-        let origin = response.rect.min;
+    /// Applies the next batch of recorded events, or all of them at once
+    /// when `steps` covers the rest.
+    fn advance_animation(&mut self, steps: usize) {
+        let Some(animation) = &mut self.animation else {
+            return;
+        };
+        let end = animation
+            .next
+            .saturating_add(steps)
+            .min(animation.events.len());
+        for &event in &animation.events[animation.next..end] {
+            self.maze.apply(event);
+        }
+        animation.next = end;
+        if end == animation.events.len() {
+            let result = self.animation.take().unwrap().result;
+            self.show_maze(result);
+        }
+    }
 
-        // Draw the walls
-        for y in 0..self.settings.height {
-            for x in 0..self.settings.width {
-                let cell_x = origin.x + x as f32 * self.settings.scale;
-                let cell_y = origin.y + y as f32 * self.settings.scale;
-
-                // Draw walls
-                let cell = self.maze.get(x, y);
-                if TRAVERSABLE.contains(&cell) {
-                    // Draw white square for path
-                    painter.rect_filled(
-                        Rect::from_min_size(
-                            Pos2::new(cell_x, cell_y),
-                            Vec2::new(self.settings.scale, self.settings.scale),
-                        ),
-                        0.0,
-                        self.settings.pathway_color,
-                    );
+    /// Swaps in the generated maze once the worker thread is done and adds
+    /// it to the history.
+    fn poll_pending(&mut self, ctx: &egui::Context) {
+        let Some(receiver) = &self.pending else {
+            return;
+        };
+        match receiver.try_recv() {
+            Ok(Ok(Generated {
+                maze,
+                events,
+                entry,
+                thumbnail,
+            })) => {
+                self.pending = None;
+                self.remember(ctx, entry, thumbnail);
+                if events.is_empty() {
+                    self.show_maze(maze);
                 } else {
-                    // Draw black square for wall
-                    painter.rect_filled(
-                        Rect::from_min_size(
-                            Pos2::new(cell_x, cell_y),
-                            Vec2::new(self.settings.scale, self.settings.scale),
-                        ),
-                        0.0,
-                        self.settings.wall_color,
+                    let (width, height) = maze.get_size();
+                    self.maze = Maze::new(
+                        width,
+                        height,
+                        self.settings.room_size,
+                        self.settings.exit_type.clone(),
                     );
+                    self.animation = Some(GenerationAnimation {
+                        events,
+                        next: 0,
+                        result: maze,
+                        paused: false,
+                    });
                 }
+            }
+            Ok(Err(err)) => {
+                log::error!("Can't generate the maze: {}", err);
+                self.pending = None;
+                self.error = Some(("Can't generate the maze", err.to_string()));
+            }
+            Err(mpsc::TryRecvError::Empty) => {}
+            Err(mpsc::TryRecvError::Disconnected) => {
+                log::error!("Maze generation thread ended without a result");
+                self.pending = None;
+            }
+        }
+    }
 
-                // Draw rewards and dangers if enabled
-                if self.settings.show_artifacts {
-                    if REWARDS.contains(&self.maze.get(x, y)) {
-                        let center = Pos2::new(
-                            cell_x + self.settings.scale / 2.0,
-                            cell_y + self.settings.scale / 2.0,
-                        );
-                        painter.circle(
-                            center,
-                            self.settings.scale * 0.3,
-                            self.settings.reward_color,
-                            Stroke::NONE,
-                        );
-                    } else if DANGERS.contains(&self.maze.get(x, y)) {
-                        let center = Pos2::new(
-                            cell_x + self.settings.scale / 2.0,
-                            cell_y + self.settings.scale / 2.0,
-                        );
-                        painter.circle(
-                            center,
-                            self.settings.scale * 0.3,
-                            self.settings.danger_color,
-                            Stroke::NONE,
-                        );
+    pub fn draw(&mut self, ui: &mut egui::Ui) {
+        // Always use the maze's own size: the settings may already hold the
+        // dimensions for the next maze while the old one is still shown, and
+        // indexing with those used to panic with an out-of-bounds `get`.
+        let (width, height) = (self.maze.width(), self.maze.height());
+        let total_width = width as f32 * self.settings.scale;
+        let total_height = height as f32 * self.settings.scale;
+
+        let sense = if self.edit_mode {
+            egui::Sense::click()
+        } else {
+            egui::Sense::hover()
+        };
+        let (response, painter) = ui.allocate_painter(Vec2::new(total_width, total_height), sense);
+        let origin = response.rect.min;
+
+        if self.edit_mode {
+            self.handle_edit(&response, origin);
+            if let Some((pos, reason)) = &self.rejected_exit
+                && response
+                    .hover_pos()
+                    .and_then(|pointer| self.cell_at(origin, pointer))
+                    == Some(*pos)
+            {
+                response.clone().on_hover_text_at_pointer(reason);
+            }
+        }
+
+        // While playing the game's maze is shown, without what was collected
+        // and with the monsters where they went
+        let maze = match &self.game {
+            Some(game) => game.maze(),
+            None => &self.maze,
+        };
+        // The solution is hidden while playing, unless asking for a hint
+        let hidden = self.game.is_some() && !self.show_hint;
+        let solution = match self.settings.with_path {
+            SolutionType::ShortestPath if !hidden => self.maze.shortest_path(),
+            _ => None,
+        };
+
+        if let Some(pos) = response
+            .hover_pos()
+            .and_then(|pointer| self.cell_at(origin, pointer))
+        {
+            let on_solution = solution.as_ref().is_some_and(|path| path.contains(&pos));
+            response.clone().on_hover_ui_at_pointer(|ui| {
+                for line in hover_lines(maze, pos, on_solution) {
+                    ui.label(line);
+                }
+            });
+        }
+
+        // Fill everything with the wall color in one go, then only draw the
+        // pathways and artifacts of the rows and columns actually on screen.
+        // Painting every cell made huge mazes crawl.
+        let visible = ui.clip_rect().intersect(response.rect);
+        painter.rect_filled(visible, 0.0, self.settings.wall_color);
+        let scale = self.settings.scale;
+        let on_screen = cells_on_screen(visible, origin, scale);
+
+        // `cells_in` clips to the maze, whose size may briefly disagree with
+        // the settings while one is being generated
+        // Not while the generation is animated, the distances would have to
+        // be found again every frame
+        let heatmap =
+            if self.settings.overlay == SvgOverlay::DistanceHeatmap && self.animation.is_none() {
+                let start = self.maze.start_pos();
+                let distances = self
+                    .distances
+                    .get_or_insert_with(|| self.maze.distance_map(start));
+                let farthest = distances.iter().flatten().max().copied().unwrap_or(0);
+                Some((&*distances, farthest))
+            } else {
+                None
+            };
+        let (near, far) = (self.settings.heatmap_near, self.settings.heatmap_far);
+        for (pos, cell) in maze.cells_in(on_screen) {
+            if !TRAVERSABLE.contains(&cell) {
+                continue;
+            }
+            let cell_x = origin.x + pos.x as f32 * scale;
+            let cell_y = origin.y + pos.y as f32 * scale;
+            let color = match heatmap {
+                Some((distances, farthest)) => match distances.get(pos.y * width + pos.x) {
+                    Some(&Some(distance)) => {
+                        near.lerp_to_gamma(far, distance as f32 / farthest.max(1) as f32)
                     }
+                    _ => self.settings.pathway_color,
+                },
+                None => self.settings.pathway_color,
+            };
+            painter.rect_filled(
+                Rect::from_min_size(Pos2::new(cell_x, cell_y), Vec2::new(scale, scale)),
+                0.0,
+                color,
+            );
+
+            // Draw rewards and dangers if enabled
+            if self.settings.show_artifacts {
+                if !REWARDS.contains(&cell) && !DANGERS.contains(&cell) {
+                    continue;
                 }
+                let color = self.settings.artifact_color(cell);
+                let center = Pos2::new(cell_x + scale / 2.0, cell_y + scale / 2.0);
+                painter.circle(center, scale * 0.3, color, Stroke::NONE);
             }
         }
+        if let Some((_, farthest)) = heatmap {
+            heatmap_legend(&painter, visible, near, far, farthest);
+        }
 
-        match self.settings.with_path {
-            SolutionType::ShortestPath => {
-                if let Some(path) = self.maze.shortest_path() {
-                    let mut points = Vec::with_capacity(path.len());
-                    // Convert all path positions to screen positions
-                    for pos in path {
-                        points.push(Pos2::new(
-                            origin.x + (pos.x as f32 + 0.5) * self.settings.scale,
-                            origin.y + (pos.y as f32 + 0.5) * self.settings.scale,
+        if let Some(mut path) = solution {
+            if self.settings.animate_solution {
+                if self.solution_progress < path.len() {
+                    self.solution_progress += self.settings.solution_speed;
+                    ui.ctx().request_repaint();
+                }
+                path.truncate(self.solution_progress);
+            }
+            let center = |pos: Pos| {
+                Pos2::new(
+                    origin.x + (pos.x as f32 + 0.5) * scale,
+                    origin.y + (pos.y as f32 + 0.5) * scale,
+                )
+            };
+            let color = self.settings.solution_stroke.color;
+            match self.settings.solution_style {
+                SolutionStyle::Line => {
+                    let points = path.iter().map(|&pos| center(pos)).collect();
+                    painter.add(egui::Shape::line(points, self.settings.solution_stroke));
+                }
+                SolutionStyle::Dots { .. } => {
+                    for (pos, _) in self.settings.solution_style.markers(&path) {
+                        painter.circle_filled(
+                            center(pos),
+                            self.settings.solution_stroke.width / 2.0,
+                            color,
+                        );
+                    }
+                }
+                SolutionStyle::Arrows { .. } => {
+                    for (pos, direction) in self.settings.solution_style.markers(&path) {
+                        // Tip ahead of the center, the two back corners behind it
+                        let (dx, dy) = direction.offset();
+                        let ahead = Vec2::new(dx as f32, dy as f32) * scale;
+                        let side = ahead.rot90();
+                        let tip = center(pos) + ahead * 0.3;
+                        let back = center(pos) - ahead * 0.2;
+                        painter.add(egui::Shape::convex_polygon(
+                            vec![tip, back + side * 0.25, back - side * 0.25],
+                            color,
+                            Stroke::NONE,
                         ));
                     }
-
-                    painter.add(egui::Shape::line(points, self.settings.solution_stroke));
                 }
             }
-            SolutionType::MinimumSpanningTree => {}
-            _ => {}
         }
-    }
 
-    #[cfg(not(target_arch = "wasm32"))]
-    fn load(&mut self, storage: &dyn Storage) -> Result<(), MazeError> {
-        if let Some(path) = eframe::storage_dir(APP_NAME) {
-            log::info!("Trying to load settings from {}", path.display());
+        // Not while the generation is animated, the graph would have to be
+        // built again every frame
+        if self.settings.show_graph && self.animation.is_none() {
+            let graph = self
+                .graph
+                .get_or_insert_with(|| GraphOverlay::new(&self.maze));
+            let center = |pos: Pos| {
+                Pos2::new(
+                    origin.x + (pos.x as f32 + 0.5) * scale,
+                    origin.y + (pos.y as f32 + 0.5) * scale,
+                )
+            };
+            let pointer = response.hover_pos();
+            let mut hovered = None;
+            let mut closest = GRAPH_HOVER_PX;
+            let edge_stroke = Stroke::new((scale * 0.15).max(1.0), Color32::from_gray(120));
+            for &(from, to, weight) in &graph.edges {
+                let (a, b) = (center(from), center(to));
+                if !visible.intersects(Rect::from_two_pos(a, b)) {
+                    continue;
+                }
+                painter.line_segment([a, b], edge_stroke);
+                if let Some(pointer) = pointer {
+                    let distance = distance_to_segment(pointer, a, b);
+                    if distance < closest {
+                        closest = distance;
+                        hovered = Some((a, b, weight, pointer));
+                    }
+                }
+            }
+            let radius = (scale * 0.3).max(2.0);
+            for &(pos, kind) in &graph.nodes {
+                let node = center(pos);
+                if visible.expand(radius).contains(node) {
+                    painter.circle_filled(node, radius, kind.color());
+                }
+            }
+            if let Some((a, b, weight, pointer)) = hovered {
+                painter.line_segment(
+                    [a, b],
+                    Stroke::new(edge_stroke.width * 2.0, Color32::YELLOW),
+                );
+                let galley = painter.layout_no_wrap(
+                    format!("Weight: {}", weight),
+                    egui::FontId::proportional(14.0),
+                    Color32::WHITE,
+                );
+                let at = pointer + Vec2::new(12.0, -12.0 - galley.size().y);
+                painter.rect_filled(
+                    Rect::from_min_size(at, galley.size()).expand(3.0),
+                    3.0,
+                    Color32::from_black_alpha(200),
+                );
+                painter.galley(at, galley, Color32::WHITE);
+            }
         }
-        if let Some(settings) = eframe::get_value::<AppSettings>(storage, eframe::APP_KEY) {
-            log::info!("Loaded settings from storage: {:?}", settings);
-            self.settings = settings;
+
+        if let Some(game) = &self.game {
+            let player = game.player();
+            let center = Pos2::new(
+                origin.x + (player.x as f32 + 0.5) * self.settings.scale,
+                origin.y + (player.y as f32 + 0.5) * self.settings.scale,
+            );
+            painter.circle(
+                center,
+                self.settings.scale * 0.4,
+                self.settings.solution_stroke.color,
+                Stroke::new(1.0, self.settings.wall_color),
+            );
         }
-        Ok(())
     }
-}
 
-impl eframe::App for MazeApp {
-    fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
-        // Left panel with controls
-        egui::SidePanel::left("controls").show(ctx, |ui| {
-            ui.vertical(|ui| {
-                ui.add(
-                    egui::Slider::new(&mut self.settings.width, 7..=999)
-                        .step_by(4.0)
-                        .text("Width"),
-                );
-                ui.add(
-                    egui::Slider::new(&mut self.settings.height, 7..=999)
-                        .step_by(4.0)
-                        .text("Height"),
-                );
+    fn set_scale(&mut self, scale: f32) {
+        self.settings.scale = scale.clamp(1.0, 20.0);
+        self.settings.solution_stroke.width = self.settings.scale * 0.4;
+    }
 
-                // Only rebuild maze if dimensions have changed
-                if self.settings.width != self.maze.get_size().0
-                    || self.settings.height != self.maze.get_size().1
-                {
-                    self.maze = Maze::new(
-                        self.settings.width,
-                        self.settings.height,
-                        self.settings.room_size,
-                        self.settings.exit_type.clone(),
-                    );
-                }
+    /// Scales the maze to fit into `available` and scrolls back to its top
+    /// left corner.
+    fn fit_to(&mut self, available: Vec2) {
+        let (width, height) = self.maze.get_size();
+        self.set_scale((available.x / width as f32).min(available.y / height as f32));
+        self.scroll_offset = Some(Vec2::ZERO);
+    }
 
-                if ui.button("Generate New Maze").clicked() {
-                    self.maze = Maze::new(
-                        self.settings.width,
-                        self.settings.height,
-                        self.settings.room_size,
-                        self.settings.exit_type.clone(),
-                    );
-                    self.maze.generate();
-                    self.maze.place_artifacts(0.1);
-                }
+    /// Takes over the size, artifacts and colors of `preset`.
+    fn apply_preset(&mut self, preset: Preset) {
+        let options = preset.preset();
+        self.settings.preset = Some(preset);
+        self.settings.width = options.width;
+        self.settings.height = options.height;
+        self.settings.room_size = options.room_size;
+        self.settings.exit_type = options.exit;
+        self.settings.artifacts_ratio = options.artifacts;
+        self.settings.wall_color = color32(options.theme.wall_color);
+        self.settings.pathway_color = color32(options.theme.path_color);
+        self.settings.reward_color = color32(options.theme.reward_color);
+        self.settings.danger_color = color32(options.theme.danger_color);
+        self.settings.solution_stroke.color = color32(options.theme.solution_color);
+        // The preset has a single color for all rewards and one for dangers
+        self.settings.cell_colors.clear();
+    }
 
-                ui.checkbox(&mut self.settings.show_artifacts, "Show Artifacts");
+    /// SVG options matching what is currently shown on screen.
+    fn svg_options(&self) -> SvgOptions {
+        SvgOptions {
+            scale: self.settings.scale,
+            with_solution: self.settings.with_path.clone(),
+            show_artifacts: self.settings.show_artifacts,
+            theme: Theme {
+                wall_color: rgb(self.settings.wall_color),
+                path_color: rgb(self.settings.pathway_color),
+                reward_color: rgb(self.settings.reward_color),
+                danger_color: rgb(self.settings.danger_color),
+                solution_color: rgb(self.settings.solution_stroke.color),
+            },
+            cell_colors: self
+                .settings
+                .cell_colors
+                .iter()
+                .map(|(&cell, &color)| (cell, rgb(color)))
+                .collect(),
+            overlay: self.settings.overlay,
+            heatmap_colors: (
+                rgb(self.settings.heatmap_near),
+                rgb(self.settings.heatmap_far),
+            ),
+            solution_width: self.settings.solution_stroke.width / self.settings.scale,
+            solution_style: self.settings.solution_style,
+            ..Default::default()
+        }
+    }
 
-                ui.add(egui::Slider::new(&mut self.settings.scale, 1.0..=20.0).text("Scale"));
-                self.settings.solution_stroke.width = self.settings.scale * 0.4;
+    fn export(&mut self, format: ExportFormat) {
+        let mut bytes = Vec::new();
+        let written = match format {
+            ExportFormat::Svg => self.maze.write_svg(&mut bytes, &self.svg_options()),
+            ExportFormat::Dot => self.maze.write_dot(&mut bytes),
+            ExportFormat::Json => {
+                bytes = self.maze.to_json().into_bytes();
+                Ok(())
+            }
+        };
+        let filename = format!("maze.{}", format.extension());
+        let result = written
+            .map_err(|err| err.to_string())
+            .and_then(|_| save_file(&filename, format.extension(), &bytes));
+        if let Err(err) = result {
+            log::error!("Export failed: {}", err);
+            self.error = Some(("Export failed", err));
+        }
+    }
 
-                egui::ComboBox::from_label("Solution")
-                    .selected_text(format!("{:?}", self.settings.with_path))
-                    .show_ui(ui, |ui| {
-                        ui.selectable_value(
-                            &mut self.settings.with_path,
-                            SolutionType::None,
-                            "None",
-                        );
-                        ui.selectable_value(
-                            &mut self.settings.with_path,
-                            SolutionType::ShortestPath,
-                            "Shortest Path",
-                        );
-                        ui.selectable_value(
-                            &mut self.settings.with_path,
-                            SolutionType::MinimumSpanningTree,
-                            "MST",
-                        );
+    /// Saves the settings as JSON, for others to import and get the same
+    /// look and mazes.
+    fn export_settings(&mut self) {
+        let result = serde_json::to_vec_pretty(&self.settings)
+            .map_err(|err| err.to_string())
+            .and_then(|bytes| save_file("maze-settings.json", "json", &bytes));
+        if let Err(err) = result {
+            log::error!("Export failed: {}", err);
+            self.error = Some(("Export failed", err));
+        }
+    }
+
+    /// Asks for settings saved by `export_settings` and switches to them.
+    /// Cancelling the dialog is not an error. Only available natively so
+    /// far, the browser build has no file dialog.
+    #[cfg(not(target_arch = "wasm32"))]
+    fn import_settings(&mut self) {
+        let Some(path) = rfd::FileDialog::new()
+            .add_filter("JSON", &["json"])
+            .pick_file()
+        else {
+            return;
+        };
+        self.import_settings_from(&path);
+    }
+
+    /// Switches to the settings in the file at `path`, or shows why they
+    /// can't be read
+    #[cfg(not(target_arch = "wasm32"))]
+    fn import_settings_from(&mut self, path: &std::path::Path) {
+        let result = std::fs::read(path)
+            .map_err(|err| err.to_string())
+            .and_then(|bytes| {
+                serde_json::from_slice::<AppSettings>(&bytes).map_err(|err| err.to_string())
+            });
+        match result {
+            Ok(settings) => self.apply_settings(settings),
+            Err(err) => {
+                log::error!("Import failed: {}", err);
+                self.error = Some(("Import failed", format!("{}: {}", path.display(), err)));
+            }
+        }
+    }
+
+    /// Switches to `settings` and generates a maze with them, using their
+    /// seed if they have one.
+    fn apply_settings(&mut self, settings: AppSettings) {
+        self.seed_input = settings
+            .seed
+            .map(|seed| seed.to_string())
+            .unwrap_or_default();
+        self.settings = settings;
+        self.journal.set_depth(self.settings.undo_depth);
+        self.regenerate();
+    }
+
+    /// Moves the player with the arrow keys or WASD and waits a turn with
+    /// the space bar, unless a text field has the keyboard focus.
+    fn handle_game_input(&mut self, ctx: &egui::Context) {
+        let Some(game) = &mut self.game else {
+            return;
+        };
+        if ctx.wants_keyboard_input() {
+            return;
+        }
+        let moves = ctx.input(|i| {
+            let mut moves: Vec<PlayerMove> = [
+                (egui::Key::ArrowLeft, egui::Key::A, Direction::West),
+                (egui::Key::ArrowRight, egui::Key::D, Direction::East),
+                (egui::Key::ArrowUp, egui::Key::W, Direction::North),
+                (egui::Key::ArrowDown, egui::Key::S, Direction::South),
+            ]
+            .into_iter()
+            .filter(|&(arrow, letter, _)| i.key_pressed(arrow) || i.key_pressed(letter))
+            .map(|(_, _, direction)| PlayerMove::Go(direction))
+            .collect();
+            if i.key_pressed(egui::Key::Space) {
+                moves.push(PlayerMove::Wait);
+            }
+            moves
+        });
+        for player_move in moves {
+            game.step(player_move);
+        }
+    }
+
+    /// Maps a screen position back to the maze cell underneath it.
+    fn cell_at(&self, origin: Pos2, pointer: Pos2) -> Option<Pos> {
+        let offset = pointer - origin;
+        if offset.x < 0.0 || offset.y < 0.0 {
+            return None;
+        }
+        let pos = Pos {
+            x: (offset.x / self.settings.scale) as usize,
+            y: (offset.y / self.settings.scale) as usize,
+        };
+        let (width, height) = self.maze.get_size();
+        (pos.x < width && pos.y < height).then_some(pos)
+    }
+
+    /// Edits the cell clicked in edit mode, see `edit_cell`
+    fn handle_edit(&mut self, response: &egui::Response, origin: Pos2) {
+        let primary = response.clicked();
+        let secondary = response.secondary_clicked();
+        if !primary && !secondary {
+            return;
+        }
+        if let Some(pos) = response
+            .interact_pointer_pos()
+            .and_then(|pointer| self.cell_at(origin, pointer))
+        {
+            self.edit_cell(pos, primary);
+        }
+    }
+
+    /// Left-click (`primary`) toggles between wall and path, or moves the
+    /// exit to a border cell, right-click cycles a path cell through reward,
+    /// danger and empty again.
+    fn edit_cell(&mut self, pos: Pos, primary: bool) {
+        self.rejected_exit = None;
+        let (width, height) = self.maze.get_size();
+        if primary && (pos.x == 0 || pos.y == 0 || pos.x == width - 1 || pos.y == height - 1) {
+            self.move_exit(pos);
+            return;
+        }
+
+        let cell = self.maze.get(pos.x, pos.y);
+        let result = if primary {
+            if cell == CellType::Wall {
+                self.journal
+                    .edit(&mut self.maze, pos, |maze| maze.carve(pos))
+            } else {
+                self.journal
+                    .edit(&mut self.maze, pos, |maze| maze.fill(pos))
+            }
+        } else if cell == CellType::Wall {
+            return;
+        } else {
+            let mut rng = rand::rng();
+            let next = if cell == CellType::Path {
+                *REWARDS.choose(&mut rng).unwrap()
+            } else if REWARDS.contains(&cell) {
+                *DANGERS.choose(&mut rng).unwrap()
+            } else {
+                CellType::Path
+            };
+            self.journal
+                .edit(&mut self.maze, pos, |maze| maze.place(pos, next))
+        };
+        if result.is_ok() {
+            self.maze_changed();
+        }
+        self.edit_error = result.err().map(|err| err.to_string());
+    }
+
+    /// Moves the exit to the border cell at `pos` and keeps it there for the
+    /// next maze. The journal only holds single cells, so this can't be
+    /// undone but is moved back the same way.
+    fn move_exit(&mut self, pos: Pos) {
+        match self.maze.move_exit(pos) {
+            Ok(()) => {
+                self.settings.exit_type = ExitLocation::Custom(pos);
+                self.edit_error = None;
+                self.maze_changed();
+            }
+            Err(err) => {
+                self.edit_error = Some(err.to_string());
+                self.rejected_exit = Some((pos, err.to_string()));
+            }
+        }
+    }
+
+    /// Walks the edit history with Ctrl+Z and Ctrl+Y, or Ctrl+Shift+Z,
+    /// unless playing or a text field has the keyboard focus.
+    fn handle_undo_keys(&mut self, ctx: &egui::Context) {
+        if self.game.is_some() || ctx.wants_keyboard_input() {
+            return;
+        }
+        let (undo, redo) = ctx.input_mut(|i| {
+            let redo = i.consume_shortcut(&egui::KeyboardShortcut::new(
+                egui::Modifiers::COMMAND | egui::Modifiers::SHIFT,
+                egui::Key::Z,
+            )) || i.consume_shortcut(&egui::KeyboardShortcut::new(
+                egui::Modifiers::COMMAND,
+                egui::Key::Y,
+            ));
+            let undo = i.consume_shortcut(&egui::KeyboardShortcut::new(
+                egui::Modifiers::COMMAND,
+                egui::Key::Z,
+            ));
+            (undo, redo)
+        });
+        if undo {
+            self.undo();
+        } else if redo {
+            self.redo();
+        }
+    }
+
+    /// Lets the usual round of generating, looking at and exporting mazes
+    /// be done from the keyboard, see `SHORTCUTS`. Nothing happens while a
+    /// text field has the keyboard focus, and while playing only F1 works,
+    /// as the letters move the player.
+    fn handle_shortcuts(&mut self, ctx: &egui::Context) {
+        if ctx.wants_keyboard_input() {
+            return;
+        }
+        if ctx.input_mut(|i| i.consume_key(egui::Modifiers::NONE, egui::Key::F1)) {
+            self.show_help = !self.show_help;
+        }
+        if self.game.is_some() {
+            return;
+        }
+        // The shortcuts with Ctrl go first, so Ctrl+S doesn't cycle the
+        // solution as well
+        let (export, save) = ctx.input_mut(|i| {
+            (
+                i.consume_shortcut(&egui::KeyboardShortcut::new(
+                    egui::Modifiers::COMMAND,
+                    egui::Key::E,
+                )),
+                i.consume_shortcut(&egui::KeyboardShortcut::new(
+                    egui::Modifiers::COMMAND,
+                    egui::Key::S,
+                )),
+            )
+        });
+        if export {
+            self.export(ExportFormat::Svg);
+        }
+        if save {
+            self.export(ExportFormat::Json);
+        }
+        let (generate, cycle, artifacts, zoom_in, zoom_out) = ctx.input_mut(|i| {
+            let mut pressed = |key| i.consume_key(egui::Modifiers::NONE, key);
+            (
+                pressed(egui::Key::G),
+                pressed(egui::Key::S),
+                pressed(egui::Key::A),
+                // + is Shift and = on most keyboards
+                pressed(egui::Key::Plus) || pressed(egui::Key::Equals),
+                pressed(egui::Key::Minus),
+            )
+        });
+        if generate && self.pending.is_none() {
+            self.regenerate();
+        }
+        if cycle {
+            self.settings.with_path = match self.settings.with_path {
+                SolutionType::None => SolutionType::ShortestPath,
+                SolutionType::ShortestPath => SolutionType::MinimumSpanningTree,
+                SolutionType::MinimumSpanningTree | SolutionType::FullCollection => {
+                    SolutionType::None
+                }
+            };
+            self.solution_progress = 0;
+        }
+        if artifacts {
+            self.settings.show_artifacts = !self.settings.show_artifacts;
+        }
+        if zoom_in {
+            self.set_scale(self.settings.scale * ZOOM_STEP);
+        } else if zoom_out {
+            self.set_scale(self.settings.scale / ZOOM_STEP);
+        }
+    }
+
+    fn help_window(&mut self, ctx: &egui::Context) {
+        egui::Window::new("Keyboard shortcuts")
+            .open(&mut self.show_help)
+            .collapsible(false)
+            .resizable(false)
+            .show(ctx, |ui| {
+                egui::Grid::new("shortcuts").striped(true).show(ui, |ui| {
+                    for (keys, action) in SHORTCUTS {
+                        ui.strong(keys);
+                        ui.label(action);
+                        ui.end_row();
+                    }
+                });
+            });
+    }
+
+    fn undo(&mut self) {
+        if self.journal.undo(&mut self.maze).is_some() {
+            self.edit_error = None;
+            self.maze_changed();
+        }
+    }
+
+    fn redo(&mut self) {
+        if self.journal.redo(&mut self.maze).is_some() {
+            self.edit_error = None;
+            self.maze_changed();
+        }
+    }
+
+    /// Forgets what was worked out for the maze shown, after it changed
+    fn maze_changed(&mut self) {
+        self.stats_stale = true;
+        self.graph = None;
+        self.distances = None;
+    }
+
+    /// Picks up the stats computed on the worker thread and, if the maze
+    /// changed since, computes them again. Not while the generation is
+    /// animated, where the maze changes every frame.
+    fn update_stats(&mut self) {
+        if let Some(receiver) = &self.stats_pending {
+            match receiver.try_recv() {
+                Ok(stats) => {
+                    self.stats = Some(stats);
+                    self.stats_pending = None;
+                }
+                Err(mpsc::TryRecvError::Empty) => {}
+                Err(mpsc::TryRecvError::Disconnected) => {
+                    log::error!("Stats thread ended without a result");
+                    self.stats_pending = None;
+                }
+            }
+        }
+        if !self.stats_stale || self.pending.is_some() || self.animation.is_some() {
+            return;
+        }
+        self.stats_stale = false;
+        self.stats = None;
+        let maze = self.maze.clone();
+        let (sender, receiver) = mpsc::channel();
+        let compute = move || {
+            let _ = sender.send(maze.stats());
+        };
+        #[cfg(not(target_arch = "wasm32"))]
+        thread::spawn(compute);
+        #[cfg(target_arch = "wasm32")]
+        compute();
+        self.stats_pending = Some(receiver);
+    }
+
+    /// Size, seed and the stats of the maze shown, in the bottom panel.
+    /// Clicking the solution length shows or hides the shortest path.
+    fn status_bar(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal_wrapped(|ui| {
+            let (width, height) = self.maze.get_size();
+            ui.label(format!("{}x{}", width, height));
+            ui.separator();
+            match self.settings.seed {
+                Some(seed) => ui.label(format!("Seed: {}", seed)),
+                None => ui.label("Seed: none"),
+            };
+            ui.separator();
+            let Some(stats) = &self.stats else {
+                ui.spinner();
+                ui.label("computing…");
+                return;
+            };
+            let solution = match stats.solution_length {
+                Some(length) => format!("Solution: {} cells", length),
+                None => "No solution".to_string(),
+            };
+            let showing = self.settings.with_path == SolutionType::ShortestPath;
+            if ui
+                .selectable_label(showing, solution)
+                .on_hover_text("Show or hide the shortest path")
+                .clicked()
+            {
+                self.settings.with_path = if showing {
+                    SolutionType::None
+                } else {
+                    SolutionType::ShortestPath
+                };
+                self.solution_progress = 0;
+            }
+            for (label, value) in [
+                ("Dead ends", stats.dead_ends),
+                ("Loops", stats.loops),
+                ("Rewards", stats.rewards),
+                ("Dangers", stats.dangers),
+            ] {
+                ui.separator();
+                ui.label(format!("{}: {}", label, value));
+            }
+            ui.separator();
+            ui.label(format!("Difficulty: {:.1}", stats.difficulty));
+        });
+    }
+
+    fn load(&mut self, storage: &dyn Storage) -> Result<(), MazeError> {
+        #[cfg(not(target_arch = "wasm32"))]
+        if let Some(path) = eframe::storage_dir(APP_NAME) {
+            log::info!("Trying to load settings from {}", path.display());
+        }
+        if let Some(entries) = eframe::get_value::<Vec<HistoryEntry>>(storage, HISTORY_KEY) {
+            self.history = entries
+                .into_iter()
+                .take(HISTORY_LENGTH)
+                .map(|entry| HistoryItem {
+                    entry,
+                    thumbnail: None,
+                })
+                .collect();
+        }
+        if let Some(settings) = eframe::get_value::<AppSettings>(storage, eframe::APP_KEY) {
+            log::info!("Loaded settings from storage: {:?}", settings);
+            self.apply_settings(settings);
+        }
+        Ok(())
+    }
+}
+
+impl eframe::App for MazeApp {
+    fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
+        self.poll_pending(ctx);
+        self.update_stats();
+        if self.pending.is_some() || self.stats_pending.is_some() {
+            ctx.request_repaint_after(std::time::Duration::from_millis(50));
+        }
+        if let Some(animation) = &self.animation
+            && !animation.paused
+        {
+            self.advance_animation(self.settings.animation_speed);
+            ctx.request_repaint();
+        }
+
+        // Left panel with controls
+        egui::SidePanel::left("controls").show(ctx, |ui| {
+            ui.vertical(|ui| {
+                let mut preset = self.settings.preset;
+                egui::ComboBox::from_label("Preset")
+                    .selected_text(preset.map_or("Custom", Preset::name))
+                    .show_ui(ui, |ui| {
+                        for choice in Preset::ALL {
+                            ui.selectable_value(&mut preset, Some(choice), choice.name());
+                        }
+                    });
+                if preset != self.settings.preset
+                    && let Some(preset) = preset
+                {
+                    self.apply_preset(preset);
+                    self.regenerate();
+                }
+
+                let width_response = ui.add(
+                    egui::Slider::new(&mut self.settings.width, 7..=999)
+                        .step_by(4.0)
+                        .text("Width"),
+                );
+                let height_response = ui.add(
+                    egui::Slider::new(&mut self.settings.height, 7..=999)
+                        .step_by(4.0)
+                        .text("Height"),
+                );
+
+                // Regression: this used to call only `Maze::new` on every
+                // slider change, which left an all-wall maze on screen. Now a
+                // full maze is generated, but only once the drag has ended
+                // (or after a keyboard/text edit) so dragging across hundreds
+                // of sizes doesn't generate hundreds of mazes.
+                let size_committed = [&width_response, &height_response].iter().any(|response| {
+                    response.drag_stopped() || (response.changed() && !response.dragged())
+                });
+                if size_committed
+                    && (self.settings.width, self.settings.height) != self.maze.get_size()
+                {
+                    // A moved exit only fits the size it was moved in
+                    if let ExitLocation::Custom(_) = self.settings.exit_type {
+                        self.settings.exit_type = self.maze.exit_side();
+                    }
+                    self.regenerate();
+                }
+
+                egui::ComboBox::from_label("Exit")
+                    .selected_text(format!("{:?}", self.settings.exit_type))
+                    .show_ui(ui, |ui| {
+                        for exit_type in [
+                            ExitLocation::Random,
+                            ExitLocation::Left,
+                            ExitLocation::Right,
+                            ExitLocation::Top,
+                            ExitLocation::Bottom,
+                            ExitLocation::Farthest,
+                        ] {
+                            let label = format!("{:?}", exit_type);
+                            ui.selectable_value(&mut self.settings.exit_type, exit_type, label);
+                        }
                     });
+
+                // The room has to leave a corridor ring and the border intact
+                let max_room_size =
+                    (self.settings.width.min(self.settings.height) - 4).clamp(1, 15) | 1;
+                self.settings.room_size = self.settings.room_size.clamp(1, max_room_size) | 1;
+                ui.add(
+                    egui::Slider::new(&mut self.settings.room_size, 1..=max_room_size)
+                        .step_by(2.0)
+                        .text("Room Size"),
+                );
+                ui.add(
+                    egui::Slider::new(&mut self.settings.artifacts_ratio, 0.0..=0.5)
+                        .text("Artifacts"),
+                );
+
+                ui.horizontal(|ui| {
+                    ui.label("Seed");
+                    ui.add(
+                        egui::TextEdit::singleline(&mut self.seed_input)
+                            .hint_text("random")
+                            .desired_width(140.0),
+                    );
+                    if ui.button("Random seed").clicked() {
+                        self.seed_input = rand::rng().random::<u64>().to_string();
+                    }
+                });
+
+                ui.checkbox(&mut self.settings.animate_generation, "Animate generation");
+                if self.settings.animate_generation {
+                    ui.add(
+                        egui::Slider::new(&mut self.settings.animation_speed, 1..=1000)
+                            .logarithmic(true)
+                            .text("Steps per frame"),
+                    );
+                }
+
+                let generating = self.pending.is_some();
+                if ui
+                    .add_enabled(!generating, egui::Button::new("Generate New Maze"))
+                    .on_hover_text("G")
+                    .clicked()
+                {
+                    self.regenerate();
+                }
+                if generating {
+                    ui.horizontal(|ui| {
+                        ui.spinner();
+                        ui.label("Generating…");
+                    });
+                } else if let Some(animation) = &mut self.animation {
+                    let mut step = false;
+                    let mut skip = false;
+                    ui.horizontal(|ui| {
+                        let label = if animation.paused { "Resume" } else { "Pause" };
+                        if ui.button(label).clicked() {
+                            animation.paused = !animation.paused;
+                        }
+                        step = ui
+                            .add_enabled(animation.paused, egui::Button::new("Step"))
+                            .clicked();
+                        skip = ui.button("Skip").clicked();
+                        ui.label(format!("{}/{}", animation.next, animation.events.len()));
+                    });
+                    if step {
+                        self.advance_animation(1);
+                    } else if skip {
+                        self.advance_animation(usize::MAX);
+                    }
+                } else if let Some(seed) = self.settings.seed {
+                    ui.label(format!("Seed: {}", seed));
+                }
+
+                ui.checkbox(&mut self.settings.show_artifacts, "Show Artifacts");
+                ui.checkbox(&mut self.settings.show_graph, "Show graph");
+
+                let mut playing = self.game.is_some();
+                if ui.checkbox(&mut playing, "Play").changed() {
+                    if playing {
+                        self.start_game();
+                        self.edit_mode = false;
+                    } else {
+                        self.game = None;
+                    }
+                }
+                if ui
+                    .checkbox(&mut self.settings.mobile_dangers, "Moving Monsters")
+                    .changed()
+                    && self.game.is_some()
+                {
+                    self.start_game();
+                }
+                if let Some(game) = &self.game {
+                    ui.label("Move with the arrow keys or WASD, wait with space");
+                    ui.label(format!("Steps: {}  Score: {}", game.steps(), game.score()));
+                    ui.toggle_value(&mut self.show_hint, "Hint");
+                }
+
+                ui.add_enabled(
+                    self.game.is_none(),
+                    egui::Checkbox::new(&mut self.edit_mode, "Edit Mode"),
+                );
+                if self.edit_mode {
+                    ui.label(
+                        "Left-click: wall/path, or move the exit on the border, \
+                         right-click: reward/danger/empty",
+                    );
+                    if let Some(error) = &self.edit_error {
+                        ui.colored_label(self.settings.danger_color, error);
+                    }
+                    if ui
+                        .add(
+                            egui::Slider::new(&mut self.settings.undo_depth, 1..=1000)
+                                .logarithmic(true)
+                                .text("Undo steps"),
+                        )
+                        .changed()
+                    {
+                        self.journal.set_depth(self.settings.undo_depth);
+                    }
+                }
+                ui.horizontal(|ui| {
+                    let idle = self.game.is_none();
+                    if ui
+                        .add_enabled(idle && self.journal.can_undo(), egui::Button::new("Undo"))
+                        .on_hover_text("Ctrl+Z")
+                        .clicked()
+                    {
+                        self.undo();
+                    }
+                    if ui
+                        .add_enabled(idle && self.journal.can_redo(), egui::Button::new("Redo"))
+                        .on_hover_text("Ctrl+Y")
+                        .clicked()
+                    {
+                        self.redo();
+                    }
+                });
+
+                ui.horizontal(|ui| {
+                    ui.add(egui::Slider::new(&mut self.settings.scale, 1.0..=20.0).text("Scale"));
+                    if ui.button("Fit").clicked() {
+                        self.fit_requested = true;
+                    }
+                });
+                self.settings.solution_stroke.width = self.settings.scale * 0.4;
+
+                let previous_solution = self.settings.with_path.clone();
+                ui.horizontal(|ui| {
+                    egui::ComboBox::from_label("Solution")
+                        .selected_text(format!("{:?}", self.settings.with_path))
+                        .show_ui(ui, |ui| {
+                            ui.selectable_value(
+                                &mut self.settings.with_path,
+                                SolutionType::None,
+                                "None",
+                            );
+                            ui.selectable_value(
+                                &mut self.settings.with_path,
+                                SolutionType::ShortestPath,
+                                "Shortest Path",
+                            );
+                            ui.selectable_value(
+                                &mut self.settings.with_path,
+                                SolutionType::MinimumSpanningTree,
+                                "MST",
+                            );
+                        });
+                    if ui
+                        .add_enabled(self.settings.animate_solution, egui::Button::new("Replay"))
+                        .clicked()
+                    {
+                        self.solution_progress = 0;
+                    }
+                });
+                if self.settings.with_path != previous_solution {
+                    self.solution_progress = 0;
+                }
+                ui.horizontal(|ui| {
+                    egui::ComboBox::from_label("Overlay")
+                        .selected_text(match self.settings.overlay {
+                            SvgOverlay::DistanceHeatmap => "Heatmap",
+                            _ => "None",
+                        })
+                        .show_ui(ui, |ui| {
+                            ui.selectable_value(
+                                &mut self.settings.overlay,
+                                SvgOverlay::None,
+                                "None",
+                            );
+                            ui.selectable_value(
+                                &mut self.settings.overlay,
+                                SvgOverlay::DistanceHeatmap,
+                                "Heatmap",
+                            );
+                        });
+                    if self.settings.overlay == SvgOverlay::DistanceHeatmap {
+                        ui.color_edit_button_srgba(&mut self.settings.heatmap_near)
+                            .on_hover_text("Nearest to the start");
+                        ui.color_edit_button_srgba(&mut self.settings.heatmap_far)
+                            .on_hover_text("Farthest from the start");
+                    }
+                });
+                ui.checkbox(&mut self.settings.animate_solution, "Animate solution");
+                if self.settings.animate_solution {
+                    ui.add(
+                        egui::Slider::new(&mut self.settings.solution_speed, 1..=50)
+                            .text("Cells per frame"),
+                    );
+                }
+                ui.horizontal(|ui| {
+                    let style = &mut self.settings.solution_style;
+                    let every = match *style {
+                        SolutionStyle::Line => 3,
+                        SolutionStyle::Dots { every } | SolutionStyle::Arrows { every } => every,
+                    };
+                    egui::ComboBox::from_label("Style")
+                        .selected_text(match style {
+                            SolutionStyle::Line => "Line",
+                            SolutionStyle::Dots { .. } => "Dots",
+                            SolutionStyle::Arrows { .. } => "Arrows",
+                        })
+                        .show_ui(ui, |ui| {
+                            ui.selectable_value(style, SolutionStyle::Line, "Line");
+                            ui.selectable_value(style, SolutionStyle::Dots { every }, "Dots");
+                            ui.selectable_value(style, SolutionStyle::Arrows { every }, "Arrows");
+                        });
+                    if let SolutionStyle::Dots { every } | SolutionStyle::Arrows { every } = style {
+                        ui.add(egui::Slider::new(every, 1..=10).text("Every"));
+                    }
+                });
+
+                ui.separator();
+                egui::CollapsingHeader::new("Colors").show(ui, |ui| {
+                    ui.horizontal(|ui| {
+                        if ui.button("Light").clicked() {
+                            Palette::Light.apply(&mut self.settings);
+                        }
+                        if ui.button("Dark").clicked() {
+                            Palette::Dark.apply(&mut self.settings);
+                        }
+                        if ui.button("Reset to defaults").clicked() {
+                            self.settings.reset_colors();
+                        }
+                    });
+                    egui::Grid::new("colors").num_columns(2).show(ui, |ui| {
+                        let settings = &mut self.settings;
+                        for (label, color) in [
+                            ("Walls", &mut settings.wall_color),
+                            ("Paths", &mut settings.pathway_color),
+                            ("Solution", &mut settings.solution_stroke.color),
+                            ("Rewards", &mut settings.reward_color),
+                            ("Dangers", &mut settings.danger_color),
+                        ] {
+                            ui.label(label);
+                            ui.color_edit_button_srgba(color);
+                            ui.end_row();
+                        }
+                        for &cell in REWARDS.iter().chain(DANGERS.iter()) {
+                            let mut color = settings.artifact_color(cell);
+                            ui.label(cell.to_string());
+                            if ui.color_edit_button_srgba(&mut color).changed() {
+                                settings.cell_colors.insert(cell, color);
+                            }
+                            ui.end_row();
+                        }
+                    });
+                });
+
+                ui.separator();
+                ui.label("Export");
+                ui.horizontal(|ui| {
+                    if ui.button("SVG").on_hover_text("Ctrl+E").clicked() {
+                        self.export(ExportFormat::Svg);
+                    }
+                    if ui.button("DOT").clicked() {
+                        self.export(ExportFormat::Dot);
+                    }
+                    if ui.button("JSON").on_hover_text("Ctrl+S").clicked() {
+                        self.export(ExportFormat::Json);
+                    }
+                    ui.add_enabled(false, egui::Button::new("PNG"))
+                        .on_disabled_hover_text("PNG export is not available yet");
+                });
+
+                ui.separator();
+                ui.label("Share");
+                if ui.button("Copy share code").clicked() {
+                    match self.maze.to_share_string() {
+                        Ok(code) => ui.ctx().copy_text(code),
+                        Err(err) => self.error = Some(("Can't share the maze", err.to_string())),
+                    }
+                }
+                ui.horizontal(|ui| {
+                    ui.add(
+                        egui::TextEdit::singleline(&mut self.share_input)
+                            .hint_text("share code")
+                            .desired_width(140.0),
+                    );
+                    if ui
+                        .add_enabled(
+                            !self.share_input.trim().is_empty(),
+                            egui::Button::new("Paste share code"),
+                        )
+                        .clicked()
+                    {
+                        self.load_share_code();
+                    }
+                });
+                if let Some(error) = &self.share_error {
+                    ui.colored_label(self.settings.danger_color, error);
+                }
+
+                ui.separator();
+                ui.label("Settings");
+                ui.horizontal(|ui| {
+                    if ui.button("Export settings…").clicked() {
+                        self.export_settings();
+                    }
+                    #[cfg(not(target_arch = "wasm32"))]
+                    if ui.button("Import settings…").clicked() {
+                        self.import_settings();
+                    }
+                });
+                ui.toggle_value(&mut self.show_help, "Keyboard shortcuts")
+                    .on_hover_text("F1");
+
+                ui.separator();
+                let mut restored = None;
+                egui::CollapsingHeader::new("History").show(ui, |ui| {
+                    if self.history.is_empty() {
+                        ui.label("No mazes generated yet");
+                    }
+                    for item in &self.history {
+                        let entry = &item.entry;
+                        ui.horizontal(|ui| {
+                            match &item.thumbnail {
+                                Some(texture) => {
+                                    ui.image((texture.id(), texture.size_vec2()));
+                                }
+                                None => {
+                                    ui.allocate_space(Vec2::splat(THUMBNAIL_SIZE as f32));
+                                }
+                            }
+                            let text = format!(
+                                "{}x{}, seed {}\nDifficulty {:.0}, {}",
+                                entry.width,
+                                entry.height,
+                                entry.seed,
+                                entry.difficulty,
+                                time_ago(entry.timestamp)
+                            );
+                            if ui.button(text).clicked() {
+                                restored = Some(entry.clone());
+                            }
+                        });
+                    }
+                });
+                if let Some(entry) = restored {
+                    self.restore(entry);
+                }
             });
         });
 
+        self.handle_game_input(ctx);
+        self.handle_undo_keys(ctx);
+        self.handle_shortcuts(ctx);
+        self.help_window(ctx);
+        if let Some(game) = &self.game
+            && game.is_over()
+        {
+            let mut close = false;
+            let title = match game.outcome() {
+                StepOutcome::Lose => "You ran out of health!",
+                _ => "You made it out!",
+            };
+            egui::Window::new(title)
+                .collapsible(false)
+                .resizable(false)
+                .show(ctx, |ui| {
+                    ui.label(format!("Steps taken: {}", game.steps()));
+                    ui.label(format!("Score: {}", game.score()));
+                    if let Some(optimal) = game.optimal_steps() {
+                        ui.label(format!("Shortest way out: {} steps", optimal));
+                    }
+                    close = ui.button("Close").clicked();
+                });
+            if close {
+                self.game = None;
+            }
+        }
+
+        if let Some((title, error)) = &self.error {
+            let modal = egui::Modal::new(egui::Id::new("error")).show(ctx, |ui| {
+                ui.heading(*title);
+                ui.label(error);
+                ui.button("OK").clicked()
+            });
+            if modal.inner || modal.should_close() {
+                self.error = None;
+            }
+        }
+
+        egui::TopBottomPanel::bottom("status").show(ctx, |ui| self.status_bar(ui));
+
         // Central panel with the maze
         egui::CentralPanel::default().show(ctx, |ui| {
+            if self.fit_requested {
+                self.fit_to(ui.available_size());
+                self.fit_requested = false;
+            }
+
             // Create scrollable area for the maze
-            egui::ScrollArea::both().show(ui, |ui| {
+            let mut scroll_area = egui::ScrollArea::both();
+            if let Some(offset) = self.scroll_offset.take() {
+                scroll_area = scroll_area.scroll_offset(offset);
+            }
+            let output = scroll_area.show(ui, |ui| {
                 self.draw(ui);
             });
+
+            // Ctrl+scroll zooms around the cursor
+            let (zoom, pointer) = ui.input(|i| (i.zoom_delta(), i.pointer.hover_pos()));
+            if let Some(pointer) = pointer
+                && zoom != 1.0
+                && output.inner_rect.contains(pointer)
+            {
+                let old_scale = self.settings.scale;
+                self.set_scale(old_scale * zoom);
+                let cursor = pointer - output.inner_rect.min;
+                self.scroll_offset = Some(zoomed_offset(
+                    output.state.offset,
+                    cursor,
+                    self.settings.scale / old_scale,
+                ));
+            }
         });
     }
 
     fn save(&mut self, storage: &mut dyn Storage) {
         log::info!("Saving settings: {:?}", self.settings);
         eframe::set_value(storage, eframe::APP_KEY, &self.settings);
+        let entries: Vec<&HistoryEntry> = self.history.iter().map(|item| &item.entry).collect();
+        eframe::set_value(storage, HISTORY_KEY, &entries);
     }
 }
 
+/// Asks where to save `bytes` and writes them there. Cancelling the dialog
+/// is not an error.
+#[cfg(not(target_arch = "wasm32"))]
+fn save_file(filename: &str, extension: &str, bytes: &[u8]) -> Result<(), String> {
+    let Some(path) = rfd::FileDialog::new()
+        .set_file_name(filename)
+        .add_filter(extension.to_uppercase(), &[extension])
+        .save_file()
+    else {
+        return Ok(());
+    };
+    std::fs::write(&path, bytes).map_err(|err| format!("{}: {}", path.display(), err))
+}
+
+/// Hands `bytes` to the browser as a download named `filename`.
+#[cfg(target_arch = "wasm32")]
+fn save_file(filename: &str, _extension: &str, bytes: &[u8]) -> Result<(), String> {
+    use wasm_bindgen::JsCast;
+
+    let js_error = |err: wasm_bindgen::JsValue| format!("{:?}", err);
+    let parts = js_sys::Array::of1(&js_sys::Uint8Array::from(bytes));
+    let blob = web_sys::Blob::new_with_u8_array_sequence(&parts).map_err(js_error)?;
+    let url = web_sys::Url::create_object_url_with_blob(&blob).map_err(js_error)?;
+    let document = web_sys::window()
+        .and_then(|window| window.document())
+        .ok_or("No document to download from")?;
+    let anchor: web_sys::HtmlAnchorElement = document
+        .create_element("a")
+        .map_err(js_error)?
+        .dyn_into()
+        .map_err(|_| "Failed to create a download link")?;
+    anchor.set_href(&url);
+    anchor.set_download(filename);
+    anchor.click();
+    // The download starts asynchronously, revoking the URL right away can
+    // cancel it
+    let revoke = wasm_bindgen::closure::Closure::once_into_js(move || {
+        let _ = web_sys::Url::revoke_object_url(&url);
+    });
+    web_sys::window()
+        .ok_or("No window to download from")?
+        .set_timeout_with_callback_and_timeout_and_arguments_0(
+            revoke.unchecked_ref(),
+            DOWNLOAD_URL_LIFETIME_MS,
+        )
+        .map_err(js_error)?;
+    Ok(())
+}
+
 #[cfg(not(target_arch = "wasm32"))]
 fn main() -> eframe::Result<()> {
     env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("debug"))
@@ -272,3 +2265,1190 @@ fn main() -> eframe::Result<()> {
         }),
     )
 }
+
+#[cfg(target_arch = "wasm32")]
+fn main() {
+    use wasm_bindgen::JsCast;
+
+    eframe::WebLogger::init(log::LevelFilter::Debug).ok();
+    let options = eframe::WebOptions::default();
+    wasm_bindgen_futures::spawn_local(async {
+        let canvas = web_sys::window()
+            .and_then(|window| window.document())
+            .and_then(|document| document.get_element_by_id("maze_canvas"))
+            .and_then(|element| element.dyn_into::<web_sys::HtmlCanvasElement>().ok())
+            .expect("No canvas with id maze_canvas");
+        let result = eframe::WebRunner::new()
+            .start(
+                canvas,
+                options,
+                Box::new(|cc| {
+                    let mut app = MazeApp::default();
+                    if let Some(storage) = cc.storage {
+                        app.load(storage)?;
+                    }
+                    Ok(Box::new(app))
+                }),
+            )
+            .await;
+        if let Err(err) = result {
+            log::error!("Failed to start the maze app: {:?}", err);
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mazegen::MazeBuilder;
+
+    /// The app showing a seeded maze without loops, with nothing pending
+    fn app() -> MazeApp {
+        let mut app = MazeApp::new();
+        app.pending = None;
+        let maze = MazeBuilder::new()
+            .width(31)
+            .height(23)
+            .braid(0.0)
+            .seed(1)
+            .build()
+            .unwrap();
+        app.show_maze(maze);
+        app
+    }
+
+    fn dead_end_tip(maze: &Maze) -> Pos {
+        maze.dead_ends()
+            .into_iter()
+            .find(|pos| maze.get(pos.x, pos.y) == CellType::Path)
+            .unwrap()
+    }
+
+    /// Polls until the maze being generated is shown
+    fn wait_for_maze(app: &mut MazeApp) {
+        let ctx = egui::Context::default();
+        while app.pending.is_some() {
+            app.poll_pending(&ctx);
+            thread::sleep(std::time::Duration::from_millis(5));
+        }
+    }
+
+    /// Polls until the stats of the maze shown are worked out
+    fn wait_for_stats(app: &mut MazeApp) -> MazeStats {
+        loop {
+            app.update_stats();
+            if app.stats_pending.is_none()
+                && let Some(stats) = &app.stats
+            {
+                return stats.clone();
+            }
+            thread::sleep(std::time::Duration::from_millis(5));
+        }
+    }
+
+    /// Draws one frame of the maze
+    fn draw_frame(app: &mut MazeApp, ctx: &egui::Context) {
+        let _ = ctx.run(egui::RawInput::default(), |ctx| {
+            egui::CentralPanel::default().show(ctx, |ui| app.draw(ui));
+        });
+    }
+
+    /// Runs a frame of game input with `key` pressed
+    fn press(app: &mut MazeApp, key: egui::Key) {
+        let input = egui::RawInput {
+            events: vec![egui::Event::Key {
+                key,
+                physical_key: None,
+                pressed: true,
+                repeat: false,
+                modifiers: egui::Modifiers::NONE,
+            }],
+            ..Default::default()
+        };
+        let _ = egui::Context::default().run(input, |ctx| app.handle_game_input(ctx));
+    }
+
+    /// Runs a frame of edit shortcuts with Ctrl and `key` pressed
+    fn press_command(app: &mut MazeApp, key: egui::Key) {
+        let input = egui::RawInput {
+            events: vec![egui::Event::Key {
+                key,
+                physical_key: None,
+                pressed: true,
+                repeat: false,
+                modifiers: egui::Modifiers::COMMAND,
+            }],
+            modifiers: egui::Modifiers::COMMAND,
+            ..Default::default()
+        };
+        let _ = egui::Context::default().run(input, |ctx| app.handle_undo_keys(ctx));
+    }
+
+    /// Runs a frame of the shortcuts with `key` pressed, while typing into
+    /// a text field if `typing`
+    fn press_shortcut(app: &mut MazeApp, key: egui::Key, typing: bool) {
+        let input = egui::RawInput {
+            events: vec![egui::Event::Key {
+                key,
+                physical_key: None,
+                pressed: true,
+                repeat: false,
+                modifiers: egui::Modifiers::NONE,
+            }],
+            ..Default::default()
+        };
+        let _ = egui::Context::default().run(input, |ctx| {
+            if typing {
+                ctx.memory_mut(|memory| memory.request_focus(egui::Id::new("seed")));
+            }
+            app.handle_shortcuts(ctx);
+        });
+    }
+
+    fn key_towards(from: Pos, to: Pos) -> egui::Key {
+        match (
+            to.x as isize - from.x as isize,
+            to.y as isize - from.y as isize,
+        ) {
+            (1, 0) => egui::Key::ArrowRight,
+            (-1, 0) => egui::Key::A,
+            (0, 1) => egui::Key::S,
+            _ => egui::Key::ArrowUp,
+        }
+    }
+
+    /// What eframe keeps on disk or in the browser's local storage
+    #[derive(Default)]
+    struct MemoryStorage(HashMap<String, String>);
+
+    impl Storage for MemoryStorage {
+        fn get_string(&self, key: &str) -> Option<String> {
+            self.0.get(key).cloned()
+        }
+
+        fn set_string(&mut self, key: &str, value: String) {
+            self.0.insert(key.to_string(), value);
+        }
+
+        fn flush(&mut self) {}
+    }
+
+    #[test]
+    fn cell_at_maps_the_pointer_to_a_cell() {
+        let app = app();
+        let scale = app.settings.scale;
+        let origin = Pos2::new(10.0, 20.0);
+        assert_eq!(
+            app.cell_at(origin, origin + Vec2::new(2.5 * scale, 0.5 * scale)),
+            Some(Pos::new(2, 0))
+        );
+        assert_eq!(app.cell_at(origin, origin - Vec2::new(1.0, 0.0)), None);
+        assert_eq!(
+            app.cell_at(origin, origin + Vec2::new(31.0 * scale, 0.0)),
+            None
+        );
+    }
+
+    #[test]
+    fn left_click_toggles_walls_safely() {
+        let mut app = app();
+        let tip = dead_end_tip(&app.maze);
+        app.edit_cell(tip, true);
+        assert_eq!(app.maze.get(tip.x, tip.y), CellType::Wall);
+        assert!(app.edit_error.is_none());
+        app.edit_cell(tip, true);
+        assert_eq!(app.maze.get(tip.x, tip.y), CellType::Path);
+
+        // Right next to the room, on the only way to the exit
+        assert!(app.maze.is_perfect());
+        let room = app.maze.room();
+        let bottleneck = app
+            .maze
+            .shortest_path()
+            .unwrap()
+            .into_iter()
+            .find(|&pos| !room.contains(pos))
+            .unwrap();
+        app.edit_cell(bottleneck, true);
+        assert_eq!(app.maze.get(bottleneck.x, bottleneck.y), CellType::Path);
+        assert!(app.edit_error.is_some());
+        assert!(app.maze.shortest_path().is_some());
+    }
+
+    #[test]
+    fn right_click_cycles_artifacts() {
+        let mut app = app();
+        let tip = dead_end_tip(&app.maze);
+        app.edit_cell(tip, false);
+        assert!(REWARDS.contains(&app.maze.get(tip.x, tip.y)));
+        app.edit_cell(tip, false);
+        assert!(DANGERS.contains(&app.maze.get(tip.x, tip.y)));
+        app.edit_cell(tip, false);
+        assert_eq!(app.maze.get(tip.x, tip.y), CellType::Path);
+    }
+
+    #[test]
+    fn edits_invalidate_what_was_worked_out() {
+        let mut app = app();
+        app.stats_stale = false;
+        app.distances = Some(Vec::new());
+        let tip = dead_end_tip(&app.maze);
+        app.edit_cell(tip, true);
+        assert!(app.stats_stale);
+        assert!(app.distances.is_none());
+        app.undo();
+        assert_eq!(app.maze.get(tip.x, tip.y), CellType::Path);
+    }
+
+    #[test]
+    fn a_new_size_generates_a_full_maze() {
+        let mut app = app();
+        app.settings.width = 23;
+        app.settings.height = 15;
+        app.regenerate();
+        wait_for_maze(&mut app);
+        assert_eq!(app.maze.get_size(), (23, 15));
+        assert!(app.maze.shortest_path().is_some());
+    }
+
+    #[test]
+    fn drawing_uses_the_size_of_the_maze_shown() {
+        let mut app = app();
+        // The sliders are ahead of the maze until it is generated again
+        app.settings.width = 99;
+        app.settings.height = 99;
+        draw_frame(&mut app, &egui::Context::default());
+        assert_eq!(app.maze.get_size(), (31, 23));
+    }
+
+    #[test]
+    fn generation_runs_on_a_worker_and_the_last_one_wins() {
+        let mut app = app();
+        let shown = app.maze.clone();
+        app.seed_input = "1".to_string();
+        app.regenerate();
+        app.seed_input = "2".to_string();
+        app.regenerate();
+        // The maze shown stays until the worker is done
+        assert!(app.pending.is_some());
+        assert!(app.maze == shown);
+        wait_for_maze(&mut app);
+        assert_eq!(app.settings.seed, Some(2));
+        assert_eq!(app.history.front().map(|item| item.entry.seed), Some(2));
+        assert!(app.history.iter().all(|item| item.entry.seed != 1));
+    }
+
+    #[test]
+    fn exit_room_and_artifact_settings_are_used() {
+        let mut app = app();
+        app.settings.exit_type = ExitLocation::Top;
+        app.settings.room_size = 5;
+        app.settings.artifacts_ratio = 0.3;
+        app.regenerate();
+        wait_for_maze(&mut app);
+        assert_eq!(app.maze.exit_side(), ExitLocation::Top);
+        assert_eq!(app.maze.exit_pos().map(|pos| pos.y), Some(0));
+        assert_eq!(app.maze.room_size(), 5);
+        assert!(app.maze.artifacts().count() > 0);
+
+        app.settings.artifacts_ratio = 0.0;
+        app.regenerate();
+        wait_for_maze(&mut app);
+        assert_eq!(app.maze.artifacts().count(), 0);
+    }
+
+    #[test]
+    fn exports_match_what_is_shown() {
+        let mut app = app();
+        app.settings.show_artifacts = false;
+        app.settings.wall_color = Color32::from_rgb(1, 2, 3);
+        app.settings.with_path = SolutionType::ShortestPath;
+        let options = app.svg_options();
+        assert!(!options.show_artifacts);
+        assert_eq!(options.theme.wall_color, [1, 2, 3]);
+        assert_eq!(options.with_solution, SolutionType::ShortestPath);
+        assert_eq!(options.scale, app.settings.scale);
+    }
+
+    #[test]
+    fn zoom_is_clamped_and_scales_the_solution() {
+        let mut app = app();
+        app.set_scale(100.0);
+        assert_eq!(app.settings.scale, 20.0);
+        app.set_scale(0.1);
+        assert_eq!(app.settings.scale, 1.0);
+        app.set_scale(5.0);
+        assert_eq!(app.settings.solution_stroke.width, 2.0);
+    }
+
+    #[test]
+    fn fit_to_window_scales_the_whole_maze_into_view() {
+        let mut app = app();
+        // 31x23 cells, the height is the tighter fit
+        app.fit_to(Vec2::new(620.0, 230.0));
+        assert_eq!(app.settings.scale, 10.0);
+        assert_eq!(app.scroll_offset, Some(Vec2::ZERO));
+    }
+
+    #[test]
+    fn zooming_keeps_the_point_under_the_cursor() {
+        let (offset, cursor) = (Vec2::new(100.0, 40.0), Vec2::new(30.0, 20.0));
+        let zoomed = zoomed_offset(offset, cursor, 2.0);
+        // The content point was 130/60 from the top left, and is twice that
+        assert_eq!(zoomed + cursor, Vec2::new(260.0, 120.0));
+        assert_eq!(zoomed_offset(Vec2::ZERO, cursor, 0.5), Vec2::ZERO);
+    }
+
+    #[test]
+    fn hover_describes_the_cell() {
+        let app = app();
+        let start = app.maze.start_pos();
+        assert_eq!(
+            hover_lines(&app.maze, start, true),
+            [
+                format!("Start at {}", start),
+                format!("Weight: {}", app.maze.weight_table().get(CellType::Start)),
+                "On the solution path".to_string(),
+            ]
+        );
+        let corner = Pos::new(0, 0);
+        assert_eq!(
+            hover_lines(&app.maze, corner, false),
+            [
+                "Wall at (0, 0)".to_string(),
+                format!("Weight: {}", app.maze.weight_table().get(CellType::Wall)),
+            ]
+        );
+    }
+
+    #[test]
+    fn the_same_seed_generates_the_same_maze() {
+        let mut app = app();
+        app.seed_input = " 42 ".to_string();
+        app.regenerate();
+        wait_for_maze(&mut app);
+        assert_eq!(app.seed_input, "42");
+        assert_eq!(app.settings.seed, Some(42));
+        let first = app.maze.clone();
+        app.regenerate();
+        wait_for_maze(&mut app);
+        assert!(app.maze == first);
+    }
+
+    #[test]
+    fn a_blank_seed_is_replaced_by_a_random_one() {
+        let mut app = app();
+        for input in ["", "not a seed"] {
+            app.seed_input = input.to_string();
+            app.regenerate();
+            let seed: u64 = app.seed_input.parse().unwrap();
+            assert_eq!(app.settings.seed, Some(seed));
+            wait_for_maze(&mut app);
+            assert_eq!(app.history.front().map(|item| item.entry.seed), Some(seed));
+        }
+    }
+
+    #[test]
+    fn visual_settings_keep_the_seed() {
+        let mut app = app();
+        app.seed_input = "7".to_string();
+        app.regenerate();
+        wait_for_maze(&mut app);
+        app.settings.wall_color = Color32::RED;
+        app.settings.scale *= 2.0;
+        draw_frame(&mut app, &egui::Context::default());
+        assert_eq!(app.seed_input, "7");
+        assert_eq!(app.settings.seed, Some(7));
+    }
+
+    #[test]
+    fn only_cells_on_screen_are_drawn() {
+        let origin = Pos2::new(10.0, 20.0);
+        assert_eq!(
+            cells_on_screen(
+                Rect::from_min_max(Pos2::new(15.0, 25.0), Pos2::new(45.0, 46.0)),
+                origin,
+                10.0
+            ),
+            mazegen::Rect::new(0, 0, 4, 3)
+        );
+        // Scrolled to the right and down
+        assert_eq!(
+            cells_on_screen(
+                Rect::from_min_max(Pos2::ZERO, Pos2::new(200.0, 100.0)),
+                Pos2::new(-100.0, -50.0),
+                10.0
+            ),
+            mazegen::Rect::new(10, 5, 20, 10)
+        );
+    }
+
+    #[test]
+    fn huge_mazes_draw_a_screenful_of_shapes() {
+        let mut app = app();
+        let maze = MazeBuilder::new()
+            .width(999)
+            .height(999)
+            .artifacts(0.1)
+            .seed(1)
+            .build()
+            .unwrap();
+        let open = maze
+            .cells()
+            .iter()
+            .filter(|&&cell| TRAVERSABLE.contains(&cell))
+            .count();
+        app.show_maze(maze);
+        app.settings.show_artifacts = true;
+        let input = egui::RawInput {
+            screen_rect: Some(Rect::from_min_size(Pos2::ZERO, Vec2::new(800.0, 600.0))),
+            ..Default::default()
+        };
+        let output = egui::Context::default().run(input, |ctx| {
+            egui::CentralPanel::default().show(ctx, |ui| app.draw(ui));
+        });
+        // A few thousand cells fit on the screen, a path cell and an
+        // artifact each at most
+        assert!(output.shapes.len() < 2 * 81 * 61 + 100);
+        assert!(open > 100_000);
+    }
+
+    #[test]
+    fn the_player_walks_to_the_exit_with_the_keyboard() {
+        let mut app = app();
+        app.start_game();
+        let path = app.maze.shortest_path().unwrap();
+        let game = |app: &MazeApp| app.game.as_ref().unwrap().player();
+        assert_eq!(game(&app), path[0]);
+
+        let room = app.maze.room();
+        let outside = path.iter().position(|&pos| !room.contains(pos)).unwrap();
+        for pair in path[..=outside].windows(2) {
+            press(&mut app, key_towards(pair[0], pair[1]));
+            assert_eq!(game(&app), pair[1]);
+        }
+        // Walls block the way
+        let corridor = path[outside];
+        let wall = Direction::ALL
+            .into_iter()
+            .filter_map(|direction| corridor.step(direction, app.maze.get_size()))
+            .find(|pos| app.maze.get(pos.x, pos.y) == CellType::Wall)
+            .unwrap();
+        press(&mut app, key_towards(corridor, wall));
+        assert_eq!(game(&app), corridor);
+
+        for pair in path[outside..].windows(2) {
+            assert!(!app.game.as_ref().unwrap().is_over());
+            press(&mut app, key_towards(pair[0], pair[1]));
+            assert_eq!(game(&app), pair[1]);
+        }
+        let game = app.game.as_ref().unwrap();
+        assert!(game.is_over());
+        assert_eq!(game.optimal_steps(), Some(path.len() - 1));
+    }
+
+    #[test]
+    fn the_solution_is_hidden_while_playing() {
+        let mut app = app();
+        app.settings.with_path = SolutionType::ShortestPath;
+        app.start_game();
+        assert!(!app.show_hint);
+        app.show_hint = true;
+        // A new game hides the hint again
+        app.start_game();
+        assert!(!app.show_hint);
+        assert_eq!(app.game.as_ref().unwrap().score(), 0);
+    }
+
+    #[test]
+    fn animated_generation_ends_with_the_same_maze() {
+        let mut app = app();
+        app.settings.artifacts_ratio = 0.2;
+        app.seed_input = "3".to_string();
+        app.regenerate();
+        wait_for_maze(&mut app);
+        let still = app.maze.clone();
+
+        app.settings.animate_generation = true;
+        app.regenerate();
+        wait_for_maze(&mut app);
+        let animation = app.animation.as_ref().unwrap();
+        assert!(animation.result == still);
+        let last = *animation.events.last().unwrap();
+        app.advance_animation(animation.events.len() - 1);
+        assert!(app.animation.is_some());
+        // The events replayed carve the maze generated without them
+        let mut replayed = app.maze.clone();
+        replayed.apply(last);
+        assert!(replayed == still);
+        app.advance_animation(1);
+        assert!(app.animation.is_none());
+        assert!(app.maze == still);
+    }
+
+    #[test]
+    fn the_solution_grows_a_few_cells_per_frame() {
+        let mut app = app();
+        let ctx = egui::Context::default();
+        app.settings.with_path = SolutionType::ShortestPath;
+        app.settings.animate_solution = true;
+        app.settings.solution_speed = 5;
+        draw_frame(&mut app, &ctx);
+        assert_eq!(app.solution_progress, 5);
+        draw_frame(&mut app, &ctx);
+        assert_eq!(app.solution_progress, 10);
+
+        let length = app.maze.shortest_path().unwrap().len();
+        for _ in 0..length {
+            draw_frame(&mut app, &ctx);
+        }
+        // It stops once the whole path is drawn
+        assert!((length..length + 5).contains(&app.solution_progress));
+
+        // A new maze starts over
+        app.show_maze(app.maze.clone());
+        assert_eq!(app.solution_progress, 0);
+    }
+
+    #[test]
+    fn the_solution_can_be_drawn_at_once() {
+        let mut app = app();
+        app.settings.with_path = SolutionType::ShortestPath;
+        app.settings.animate_solution = false;
+        draw_frame(&mut app, &egui::Context::default());
+        assert_eq!(app.solution_progress, 0);
+    }
+
+    #[test]
+    fn settings_and_history_are_stored() {
+        let mut app = app();
+        app.seed_input = "11".to_string();
+        app.settings.width = 43;
+        app.regenerate();
+        wait_for_maze(&mut app);
+        let mut storage = MemoryStorage::default();
+        eframe::App::save(&mut app, &mut storage);
+        assert!(storage.get_string(eframe::APP_KEY).is_some());
+
+        let mut loaded = MazeApp::new();
+        loaded.pending = None;
+        loaded.load(&storage).unwrap();
+        wait_for_maze(&mut loaded);
+        assert_eq!(loaded.seed_input, "11");
+        assert_eq!(loaded.settings.width, 43);
+        assert!(loaded.maze == app.maze);
+        assert!(loaded.history.iter().any(|item| item.entry.seed == 11));
+    }
+
+    /// Settings with colors other than the defaults
+    fn colorful_settings() -> AppSettings {
+        let mut settings = AppSettings {
+            wall_color: Color32::from_rgb(0x12, 0x34, 0x56),
+            pathway_color: Color32::from_rgb(0xff, 0xee, 0xdd),
+            solution_stroke: Stroke::new(3.5, Color32::from_rgb(0xab, 0xcd, 0xef)),
+            reward_color: Color32::GOLD,
+            danger_color: Color32::DARK_RED,
+            ..AppSettings::default()
+        };
+        settings
+            .cell_colors
+            .insert(CellType::Ghost, Color32::from_rgb(1, 2, 3));
+        settings
+    }
+
+    #[test]
+    fn settings_round_trip_through_json() {
+        let settings = colorful_settings();
+        let json = serde_json::to_value(&settings).unwrap();
+        assert_eq!(json["wall_color"], "#123456");
+        assert_eq!(json["danger_color"], "#8b0000");
+        assert_eq!(
+            json["solution_stroke"],
+            serde_json::json!({"width": 3.5, "color": "#abcdef"})
+        );
+        assert_eq!(json["cell_colors"]["Ghost"], "#010203");
+
+        let loaded: AppSettings = serde_json::from_value(json).unwrap();
+        assert_eq!(loaded.wall_color, settings.wall_color);
+        assert_eq!(loaded.pathway_color, settings.pathway_color);
+        assert_eq!(loaded.solution_stroke, settings.solution_stroke);
+        assert_eq!(loaded.reward_color, settings.reward_color);
+        assert_eq!(loaded.danger_color, settings.danger_color);
+        assert_eq!(loaded.cell_colors, settings.cell_colors);
+        assert_eq!(
+            (loaded.width, loaded.height, loaded.scale),
+            (settings.width, settings.height, settings.scale)
+        );
+    }
+
+    #[test]
+    fn colors_stored_by_earlier_versions_still_load() {
+        let mut json = serde_json::to_value(AppSettings::default()).unwrap();
+        json["wall_color"] = serde_json::json!([1, 2, 3, 255]);
+        // As RON keeps them, with the newtype around the array
+        json["pathway_color"] = serde_json::json!([[4, 5, 6, 255]]);
+        json["solution_stroke"] = serde_json::json!({"width": 2.0, "color": [7, 8, 9, 255]});
+        let fields = json.as_object_mut().unwrap();
+        fields.remove("cell_colors");
+        fields.remove("heatmap_near");
+
+        let loaded: AppSettings = serde_json::from_value(json).unwrap();
+        assert_eq!(loaded.wall_color, Color32::from_rgb(1, 2, 3));
+        assert_eq!(loaded.pathway_color, Color32::from_rgb(4, 5, 6));
+        assert_eq!(
+            loaded.solution_stroke,
+            Stroke::new(2.0, Color32::from_rgb(7, 8, 9))
+        );
+        assert_eq!(loaded.cell_colors, default_cell_colors());
+        assert_eq!(loaded.heatmap_near, default_heatmap_near());
+    }
+
+    #[test]
+    fn imported_settings_are_applied_or_the_error_shown() {
+        let mut app = app();
+        let path =
+            std::env::temp_dir().join(format!("mazeui-{}-settings.json", std::process::id()));
+        let settings = AppSettings {
+            width: 23,
+            seed: Some(5),
+            ..colorful_settings()
+        };
+        std::fs::write(&path, serde_json::to_vec_pretty(&settings).unwrap()).unwrap();
+        app.import_settings_from(&path);
+        assert!(app.error.is_none());
+        assert_eq!(app.seed_input, "5");
+        assert_eq!(app.settings.wall_color, settings.wall_color);
+        wait_for_maze(&mut app);
+        assert_eq!(app.maze.width(), 23);
+
+        let mut json = serde_json::to_value(&settings).unwrap();
+        json["width"] = 43.into();
+        json["wall_color"] = "#12".into();
+        std::fs::write(&path, json.to_string()).unwrap();
+        app.import_settings_from(&path);
+        let (title, message) = app.error.clone().unwrap();
+        assert_eq!(title, "Import failed");
+        assert!(
+            message.starts_with(&path.display().to_string()),
+            "{}",
+            message
+        );
+        assert!(message.contains("invalid color #12"), "{}", message);
+        // The settings stay as they were
+        assert_eq!(app.settings.width, 23);
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn the_history_brings_back_earlier_mazes() {
+        let mut app = app();
+        let mut generated = Vec::new();
+        for (seed, width) in [(1, 23), (2, 31), (3, 23), (4, 43)] {
+            app.settings.width = width;
+            app.seed_input = seed.to_string();
+            app.regenerate();
+            wait_for_maze(&mut app);
+            generated.push(app.maze.clone());
+        }
+        let seeds: Vec<u64> = app.history.iter().map(|item| item.entry.seed).collect();
+        assert_eq!(seeds, [4, 3, 2, 1]);
+        assert!(app.history.iter().all(|item| item.thumbnail.is_some()));
+
+        // Three generations ago
+        let entry = app.history[3].entry.clone();
+        assert_eq!(entry.width, 23);
+        app.restore(entry);
+        wait_for_maze(&mut app);
+        assert!(app.maze == generated[0]);
+        assert_eq!(app.settings.width, 23);
+        // It moves up rather than being there twice
+        let seeds: Vec<u64> = app.history.iter().map(|item| item.entry.seed).collect();
+        assert_eq!(seeds, [1, 4, 3, 2]);
+    }
+
+    #[test]
+    fn the_history_keeps_the_last_mazes_only() {
+        let mut app = app();
+        app.settings.width = 23;
+        app.settings.height = 15;
+        for seed in 0..HISTORY_LENGTH as u64 + 5 {
+            app.seed_input = seed.to_string();
+            app.regenerate();
+            wait_for_maze(&mut app);
+        }
+        assert_eq!(app.history.len(), HISTORY_LENGTH);
+        assert_eq!(
+            app.history.front().map(|item| item.entry.seed),
+            Some(HISTORY_LENGTH as u64 + 4)
+        );
+        assert_eq!(app.history.back().map(|item| item.entry.seed), Some(5));
+    }
+
+    #[test]
+    fn thumbnails_shade_pixels_by_their_open_cells() {
+        let app = app();
+        let (wall, path) = (Color32::BLACK, Color32::WHITE);
+        let image = thumbnail(&app.maze, wall, path);
+        // Small enough for a pixel per cell
+        assert_eq!(image.size, [31, 23]);
+        for (pos, cell) in app.maze.iter_cells() {
+            let expected = if cell == CellType::Wall { wall } else { path };
+            assert_eq!(image[(pos.x, pos.y)], expected, "{}", pos);
+        }
+
+        let maze = MazeBuilder::new()
+            .width(101)
+            .height(61)
+            .seed(1)
+            .build()
+            .unwrap();
+        // Three cells across a pixel, of the size the maze was rounded up to
+        let (width, height) = maze.get_size();
+        let image = thumbnail(&maze, wall, path);
+        assert_eq!(image.size, [width.div_ceil(3), height.div_ceil(3)]);
+        assert!(
+            image
+                .pixels
+                .iter()
+                .any(|&pixel| pixel != wall && pixel != path)
+        );
+    }
+
+    #[test]
+    fn history_times_are_rough() {
+        let now = unix_time();
+        assert_eq!(time_ago(now), "just now");
+        assert_eq!(time_ago(now - 150), "2 min ago");
+        assert_eq!(time_ago(now - 2 * 3600), "2 h ago");
+        assert_eq!(time_ago(now - 3 * 86400 - 10), "3 d ago");
+        // Clocks set back don't give negative times
+        assert_eq!(time_ago(now + 1000), "just now");
+    }
+
+    #[test]
+    fn edits_are_undone_and_redone_with_the_keyboard() {
+        let mut app = app();
+        let original = app.maze.clone();
+        let tip = dead_end_tip(&app.maze);
+        app.edit_cell(tip, true);
+        let other = dead_end_tip(&app.maze);
+        app.edit_cell(other, false);
+        let edited = app.maze.clone();
+        assert!(edited != original);
+
+        press_command(&mut app, egui::Key::Z);
+        assert_eq!(app.maze.get(other.x, other.y), CellType::Path);
+        press_command(&mut app, egui::Key::Z);
+        assert!(app.maze == original);
+        press_command(&mut app, egui::Key::Y);
+        press_command(&mut app, egui::Key::Y);
+        assert!(app.maze == edited);
+
+        // Not while playing
+        app.start_game();
+        press_command(&mut app, egui::Key::Z);
+        assert!(app.maze == edited);
+        app.game = None;
+
+        // A new maze can't be undone
+        app.regenerate();
+        wait_for_maze(&mut app);
+        assert!(!app.journal.can_undo());
+        assert!(!app.journal.can_redo());
+    }
+
+    #[test]
+    fn settings_with_some_cell_colors_load() {
+        let mut json = serde_json::to_value(AppSettings::default()).unwrap();
+        json["cell_colors"] = serde_json::json!({"Ghost": "#010203", "Candy": "#040506"});
+        let loaded: AppSettings = serde_json::from_value(json).unwrap();
+        assert_eq!(
+            loaded.cell_colors,
+            HashMap::from([
+                (CellType::Ghost, Color32::from_rgb(1, 2, 3)),
+                (CellType::Candy, Color32::from_rgb(4, 5, 6)),
+            ])
+        );
+        // The others get the reward or danger color
+        assert_eq!(
+            loaded.artifact_color(CellType::Ghost),
+            Color32::from_rgb(1, 2, 3)
+        );
+        assert_eq!(loaded.artifact_color(CellType::Zombie), loaded.danger_color);
+        assert_eq!(
+            loaded.artifact_color(CellType::Cookies),
+            loaded.reward_color
+        );
+
+        let json = serde_json::to_value(&loaded).unwrap();
+        assert_eq!(
+            json["cell_colors"],
+            serde_json::json!({"Ghost": "#010203", "Candy": "#040506"})
+        );
+    }
+
+    #[test]
+    fn palettes_set_all_colors_at_once() {
+        let mut settings = colorful_settings();
+        Palette::Light.apply(&mut settings);
+        assert_eq!(settings.wall_color, Color32::from_hex("#9aa5b1").unwrap());
+        assert_eq!(
+            settings.solution_stroke,
+            Stroke::new(3.5, Color32::from_hex("#1c7cd6").unwrap())
+        );
+        assert_eq!(settings.cell_colors, default_cell_colors());
+
+        Palette::Dark.apply(&mut settings);
+        assert_eq!(
+            settings.pathway_color,
+            Color32::from_hex("#3a3d46").unwrap()
+        );
+        let defaults = default_cell_colors();
+        assert_eq!(settings.cell_colors.len(), defaults.len());
+        for (cell, color) in &settings.cell_colors {
+            let default = defaults[cell];
+            // Lighter, so they show on the dark paths
+            assert!(
+                color.r() >= default.r() && color.g() >= default.g() && color.b() >= default.b()
+            );
+            assert_ne!(*color, default, "{:?}", cell);
+        }
+
+        settings.reset_colors();
+        let defaults = AppSettings::default();
+        assert_eq!(settings.wall_color, defaults.wall_color);
+        assert_eq!(
+            settings.solution_stroke.color,
+            defaults.solution_stroke.color
+        );
+        assert_eq!(settings.cell_colors, defaults.cell_colors);
+        // Only the colors
+        assert_eq!(settings.solution_stroke.width, 3.5);
+    }
+
+    #[test]
+    fn exports_use_the_cell_colors() {
+        let mut app = app();
+        app.settings.cell_colors = HashMap::from([(CellType::Zombie, Color32::from_rgb(1, 2, 3))]);
+        let options = app.svg_options();
+        assert_eq!(
+            options.cell_colors,
+            HashMap::from([(CellType::Zombie, [1, 2, 3])])
+        );
+    }
+
+    #[test]
+    fn stats_are_worked_out_once_per_maze() {
+        let mut app = app();
+        let stats = wait_for_stats(&mut app);
+        let expected = app.maze.stats();
+        assert_eq!(stats.fingerprint, expected.fingerprint);
+        assert_eq!(stats.solution_length, expected.solution_length);
+        assert_eq!(
+            (stats.dead_ends, stats.loops, stats.rewards, stats.dangers),
+            (
+                expected.dead_ends,
+                expected.loops,
+                expected.rewards,
+                expected.dangers
+            )
+        );
+        // Not again while the maze stays the same
+        app.update_stats();
+        assert!(app.stats_pending.is_none());
+        assert!(app.stats.is_some());
+    }
+
+    #[test]
+    fn stats_update_after_edits_and_new_mazes() {
+        let mut app = app();
+        let before = wait_for_stats(&mut app);
+        let tip = dead_end_tip(&app.maze);
+        app.edit_cell(tip, false);
+        app.update_stats();
+        // Computing until the worker is done
+        assert!(app.stats.is_none());
+        let after = wait_for_stats(&mut app);
+        assert_eq!(after.rewards, before.rewards + 1);
+
+        app.settings.width = 23;
+        app.regenerate();
+        // Not for the maze that is about to be replaced
+        app.stats_stale = true;
+        app.update_stats();
+        assert!(app.stats_pending.is_none());
+        wait_for_maze(&mut app);
+        let stats = wait_for_stats(&mut app);
+        assert_eq!(stats.width, 23);
+        assert_eq!(
+            stats.solution_length,
+            app.maze.shortest_path().map(|path| path.len())
+        );
+    }
+
+    #[test]
+    fn the_graph_overlay_tells_the_nodes_apart() {
+        let app = app();
+        let maze = &app.maze;
+        let graph = GraphOverlay::new(maze);
+        let (nodes, edges) = maze.build_graph();
+        assert_eq!(graph.nodes.len(), nodes.len());
+        assert_eq!(graph.edges.len(), edges.len());
+        let open = |pos: Pos| {
+            pos.neighbors(maze.get_size())
+                .filter(|next| TRAVERSABLE.contains(&maze.get(next.x, next.y)))
+                .count()
+        };
+        let dead_ends = maze.dead_ends();
+        for &(pos, kind) in &graph.nodes {
+            match kind {
+                NodeKind::Start => assert_eq!(pos, maze.start_pos()),
+                NodeKind::Exit => assert_eq!(Some(pos), maze.exit_pos()),
+                NodeKind::Junction => assert!(open(pos) > 2, "{}", pos),
+                NodeKind::DeadEnd => assert!(dead_ends.contains(&pos), "{}", pos),
+            }
+        }
+        let count = |wanted: fn(NodeKind) -> bool| {
+            graph
+                .nodes
+                .iter()
+                .filter(|&&(_, kind)| wanted(kind))
+                .count()
+        };
+        assert_eq!(count(|kind| matches!(kind, NodeKind::Start)), 1);
+        assert_eq!(count(|kind| matches!(kind, NodeKind::Exit)), 1);
+        for &(from, to, _) in &graph.edges {
+            assert!(nodes.contains_key(&from) && nodes.contains_key(&to));
+            assert_ne!(from, to);
+        }
+    }
+
+    #[test]
+    fn edges_are_hovered_by_their_distance() {
+        let (a, b) = (Pos2::new(0.0, 0.0), Pos2::new(10.0, 0.0));
+        assert_eq!(distance_to_segment(Pos2::new(5.0, 3.0), a, b), 3.0);
+        // Beyond the ends it's the distance to the closer one
+        assert_eq!(distance_to_segment(Pos2::new(13.0, 4.0), a, b), 5.0);
+        assert_eq!(distance_to_segment(Pos2::new(-3.0, -4.0), a, b), 5.0);
+        assert_eq!(distance_to_segment(Pos2::new(3.0, 4.0), a, a), 5.0);
+    }
+
+    #[test]
+    fn the_graph_is_built_once_per_maze() {
+        let mut app = app();
+        let ctx = egui::Context::default();
+        draw_frame(&mut app, &ctx);
+        assert!(app.graph.is_none());
+        app.settings.show_graph = true;
+        draw_frame(&mut app, &ctx);
+        let edges = app.graph.as_ref().unwrap().edges.len();
+        assert_eq!(edges, app.maze.build_graph().1.len());
+
+        let tip = dead_end_tip(&app.maze);
+        app.edit_cell(tip, true);
+        assert!(app.graph.is_none());
+        draw_frame(&mut app, &ctx);
+        // The dead end tip filled away, its corridor is shorter
+        let graph = app.graph.as_ref().unwrap();
+        assert_eq!(graph.edges.len(), app.maze.build_graph().1.len());
+        assert!(!graph.nodes.iter().any(|&(pos, _)| pos == tip));
+    }
+
+    #[test]
+    fn graphs_of_big_mazes_are_drawn_where_visible() {
+        let mut app = app();
+        let maze = MazeBuilder::new()
+            .width(201)
+            .height(201)
+            .seed(1)
+            .build()
+            .unwrap();
+        app.show_maze(maze);
+        let shapes = |app: &mut MazeApp| {
+            let input = egui::RawInput {
+                screen_rect: Some(Rect::from_min_size(Pos2::ZERO, Vec2::new(800.0, 600.0))),
+                ..Default::default()
+            };
+            let output = egui::Context::default().run(input, |ctx| {
+                egui::CentralPanel::default().show(ctx, |ui| app.draw(ui));
+            });
+            output.shapes.len()
+        };
+        let without = shapes(&mut app);
+        app.settings.show_graph = true;
+        let with = shapes(&mut app);
+        let graph = app.graph.as_ref().unwrap();
+        // A screenful is about an eighth of the 201x201 cells
+        assert!(with > without);
+        assert!(
+            with - without < (graph.nodes.len() + graph.edges.len()) / 4,
+            "{} shapes for {} nodes and {} edges",
+            with - without,
+            graph.nodes.len(),
+            graph.edges.len()
+        );
+    }
+
+    /// The shapes of a frame with all of a small maze on the screen
+    fn shapes_drawn(app: &mut MazeApp) -> Vec<egui::Shape> {
+        let input = egui::RawInput {
+            screen_rect: Some(Rect::from_min_size(Pos2::ZERO, Vec2::new(800.0, 600.0))),
+            ..Default::default()
+        };
+        let output = egui::Context::default().run(input, |ctx| {
+            egui::CentralPanel::default().show(ctx, |ui| app.draw(ui));
+        });
+        output
+            .shapes
+            .into_iter()
+            .map(|clipped| clipped.shape)
+            .collect()
+    }
+
+    #[test]
+    fn the_heatmap_tints_cells_by_their_distance() {
+        let mut app = app();
+        app.settings.overlay = SvgOverlay::DistanceHeatmap;
+        app.settings.heatmap_near = Color32::BLACK;
+        app.settings.heatmap_far = Color32::WHITE;
+        let shapes = shapes_drawn(&mut app);
+
+        let distances = app.maze.distance_map(app.maze.start_pos());
+        assert_eq!(app.distances.as_ref(), Some(&distances));
+        let farthest = distances.iter().flatten().max().copied().unwrap();
+        let at = |distance| distances.iter().filter(|&&d| d == Some(distance)).count();
+        let scale = app.settings.scale;
+        let cell_fills: Vec<Color32> = shapes
+            .iter()
+            .filter_map(|shape| match shape {
+                egui::Shape::Rect(rect) if rect.rect.size() == Vec2::splat(scale) => {
+                    Some(rect.fill)
+                }
+                _ => None,
+            })
+            .collect();
+        assert_eq!(cell_fills.len(), distances.iter().flatten().count());
+        let count = |color| cell_fills.iter().filter(|&&fill| fill == color).count();
+        assert_eq!(count(Color32::BLACK), at(0));
+        assert_eq!(count(Color32::WHITE), at(farthest));
+        assert_eq!(count(app.settings.pathway_color), 0);
+
+        // The legend goes up to the farthest distance
+        let texts: Vec<String> = shapes
+            .iter()
+            .filter_map(|shape| match shape {
+                egui::Shape::Text(text) => Some(text.galley.text().to_string()),
+                _ => None,
+            })
+            .collect();
+        assert!(texts.contains(&farthest.to_string()), "{:?}", texts);
+    }
+
+    #[test]
+    fn the_heatmap_follows_new_mazes() {
+        let mut app = app();
+        shapes_drawn(&mut app);
+        assert!(app.distances.is_none());
+        app.settings.overlay = SvgOverlay::DistanceHeatmap;
+        shapes_drawn(&mut app);
+        assert!(app.distances.is_some());
+
+        app.settings.width = 23;
+        app.regenerate();
+        wait_for_maze(&mut app);
+        assert!(app.distances.is_none());
+        shapes_drawn(&mut app);
+        assert_eq!(
+            app.distances,
+            Some(app.maze.distance_map(app.maze.start_pos()))
+        );
+    }
+
+    #[test]
+    fn clicking_the_border_moves_the_exit() {
+        let mut app = app();
+        app.settings.width = 31;
+        app.settings.height = 23;
+        let pos = Pos::new(0, 7);
+        app.edit_cell(pos, true);
+        assert!(app.edit_error.is_none());
+        assert_eq!(app.maze.exit_pos(), Some(pos));
+        assert_eq!(app.settings.exit_type, ExitLocation::Custom(pos));
+        assert_eq!(app.maze.shortest_path().unwrap().last(), Some(&pos));
+
+        // Corners are rejected and the exit stays
+        let corner = Pos::new(0, 0);
+        app.edit_cell(corner, true);
+        assert!(app.edit_error.is_some());
+        assert_eq!(app.rejected_exit.as_ref().map(|(at, _)| *at), Some(corner));
+        assert_eq!(app.maze.exit_pos(), Some(pos));
+
+        // The next maze has it there too
+        app.regenerate();
+        wait_for_maze(&mut app);
+        assert_eq!(app.maze.exit_pos(), Some(pos));
+        assert_eq!(app.maze.shortest_path().unwrap().last(), Some(&pos));
+    }
+
+    #[test]
+    fn shortcuts_cycle_the_solution_and_toggle_the_artifacts() {
+        let mut app = app();
+        let mut overlays = Vec::new();
+        for _ in 0..3 {
+            press_shortcut(&mut app, egui::Key::S, false);
+            overlays.push(app.settings.with_path.clone());
+        }
+        assert_eq!(
+            overlays,
+            [
+                SolutionType::ShortestPath,
+                SolutionType::MinimumSpanningTree,
+                SolutionType::None,
+            ]
+        );
+        assert!(app.settings.show_artifacts);
+        press_shortcut(&mut app, egui::Key::A, false);
+        assert!(!app.settings.show_artifacts);
+        press_shortcut(&mut app, egui::Key::A, false);
+        assert!(app.settings.show_artifacts);
+    }
+
+    #[test]
+    fn shortcuts_zoom_and_generate() {
+        let mut app = app();
+        press_shortcut(&mut app, egui::Key::Plus, false);
+        assert_eq!(app.settings.scale, 10.0 * ZOOM_STEP);
+        press_shortcut(&mut app, egui::Key::Equals, false);
+        press_shortcut(&mut app, egui::Key::Minus, false);
+        assert_eq!(app.settings.scale, 10.0 * ZOOM_STEP);
+
+        app.settings.width = 23;
+        press_shortcut(&mut app, egui::Key::G, false);
+        assert!(app.pending.is_some());
+        wait_for_maze(&mut app);
+        assert_eq!(app.maze.width(), 23);
+    }
+
+    #[test]
+    fn shortcuts_leave_text_fields_and_games_alone() {
+        let mut app = app();
+        for key in [egui::Key::S, egui::Key::A, egui::Key::G, egui::Key::F1] {
+            press_shortcut(&mut app, key, true);
+        }
+        assert_eq!(app.settings.with_path, SolutionType::None);
+        assert!(app.settings.show_artifacts);
+        assert!(app.pending.is_none());
+        assert!(!app.show_help);
+
+        // Only the help opens while playing, the letters move the player
+        app.start_game();
+        press_shortcut(&mut app, egui::Key::A, false);
+        press_shortcut(&mut app, egui::Key::G, false);
+        assert!(app.settings.show_artifacts);
+        assert!(app.pending.is_none());
+        press_shortcut(&mut app, egui::Key::F1, false);
+        assert!(app.show_help);
+        press_shortcut(&mut app, egui::Key::F1, false);
+        assert!(!app.show_help);
+    }
+}
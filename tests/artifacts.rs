@@ -0,0 +1,310 @@
+use mazegen::{CellType, DANGERS, GUARD_SAMPLES, Maze, MazeBuilder, Pos, REWARDS, TRAVERSABLE};
+use rand::SeedableRng;
+use rand::rngs::StdRng;
+
+fn maze(seed: u64) -> Maze {
+    MazeBuilder::new()
+        .width(31)
+        .height(23)
+        .artifacts(0.2)
+        .seed(seed)
+        .build()
+        .unwrap()
+}
+
+/// The dead ends that may be decorated: plain path, outside the room and
+/// not right in front of the exit
+fn decoratable(maze: &Maze) -> Vec<Pos> {
+    let exit = maze.exit_pos().unwrap();
+    maze.dead_ends()
+        .into_iter()
+        .filter(|&pos| maze.get(pos.x, pos.y) == CellType::Path)
+        .filter(|&pos| !maze.room().contains(pos) && pos.manhattan(exit) > 1)
+        .collect()
+}
+
+#[test]
+fn only_free_dead_ends_are_decorated() {
+    for seed in 0..10 {
+        let plain = maze(seed);
+        let mut maze = plain.clone();
+        let decorated = maze.decorate_dead_ends(0.5, 0.5, &mut StdRng::seed_from_u64(seed));
+        // Every one of them, as the chances add up to one
+        assert_eq!(decorated, decoratable(&plain));
+        for (pos, cell) in maze.iter_cells() {
+            if decorated.contains(&pos) {
+                assert!(REWARDS.contains(&cell) || DANGERS.contains(&cell));
+            } else {
+                // Artifacts placed before are left as they were
+                assert_eq!(cell, plain.get(pos.x, pos.y), "{}", pos);
+            }
+        }
+    }
+}
+
+#[test]
+fn dead_ends_get_rewards_and_dangers_as_likely_as_asked() {
+    let (mut candidates, mut rewards, mut dangers) = (0, 0, 0);
+    for seed in 0..40 {
+        let mut maze = maze(seed);
+        candidates += decoratable(&maze).len();
+        for pos in maze.decorate_dead_ends(0.5, 0.2, &mut StdRng::seed_from_u64(seed)) {
+            let cell = maze.get(pos.x, pos.y);
+            if REWARDS.contains(&cell) {
+                rewards += 1;
+            } else {
+                assert!(DANGERS.contains(&cell));
+                dangers += 1;
+            }
+        }
+    }
+    let share = |count: usize| count as f32 / candidates as f32;
+    assert!(candidates > 200);
+    assert!((share(rewards) - 0.5).abs() < 0.1, "{}", share(rewards));
+    assert!((share(dangers) - 0.2).abs() < 0.1, "{}", share(dangers));
+}
+
+#[test]
+fn no_chances_no_decorations() {
+    let plain = maze(1);
+    let mut maze = plain.clone();
+    assert!(
+        maze.decorate_dead_ends(0.0, 0.0, &mut StdRng::seed_from_u64(1))
+            .is_empty()
+    );
+    assert!(maze == plain);
+}
+
+#[test]
+fn the_builder_decorates_dead_ends() {
+    let maze = MazeBuilder::new()
+        .width(31)
+        .height(23)
+        .dead_end_artifacts(1.0, 0.0)
+        .seed(3)
+        .build()
+        .unwrap();
+    let exit = maze.exit_pos().unwrap();
+    for pos in maze.dead_ends() {
+        if !maze.room().contains(pos) && pos.manhattan(exit) > 1 {
+            let cell = maze.get(pos.x, pos.y);
+            assert!(REWARDS.contains(&cell), "{} {:?}", pos, cell);
+        }
+    }
+}
+
+fn braided(braid: f32, seed: u64) -> Maze {
+    MazeBuilder::new()
+        .width(31)
+        .height(23)
+        .braid(braid)
+        .seed(seed)
+        .build()
+        .unwrap()
+}
+
+/// Plain path cells outside the room that every way to the exit takes
+fn unavoidable(maze: &Maze) -> Vec<Pos> {
+    maze.shortest_path()
+        .unwrap()
+        .into_iter()
+        .filter(|&pos| maze.get(pos.x, pos.y) == CellType::Path && !maze.room().contains(pos))
+        .filter(|&pos| {
+            let mut blocked = maze.clone();
+            blocked.set(pos.x, pos.y, CellType::Wall);
+            blocked.shortest_path().is_none()
+        })
+        .collect()
+}
+
+fn assert_apart(guards: &[Pos]) {
+    for (i, a) in guards.iter().enumerate() {
+        for b in &guards[i + 1..] {
+            assert!(a.manhattan(*b) > 1, "{} next to {}", a, b);
+        }
+    }
+}
+
+#[test]
+fn guards_of_a_perfect_maze_are_on_the_solution() {
+    for seed in 0..10 {
+        let plain = braided(0.0, seed);
+        let solution = plain.shortest_path().unwrap();
+        let mut maze = plain.clone();
+        let guards = maze.place_guards(3, &mut StdRng::seed_from_u64(seed));
+        assert_eq!(guards.len(), 3);
+        assert_apart(&guards);
+        for pos in &guards {
+            assert!(solution.contains(pos), "seed {}: {}", seed, pos);
+            assert_eq!(plain.get(pos.x, pos.y), CellType::Path);
+            assert!(DANGERS.contains(&maze.get(pos.x, pos.y)));
+        }
+        // The solution stays the same, only more dangerous
+        assert_eq!(maze.shortest_path().unwrap(), solution);
+    }
+}
+
+#[test]
+fn guards_stop_when_the_unavoidable_cells_run_out() {
+    let plain = braided(0.0, 1);
+    let mut maze = plain.clone();
+    let guards = maze.place_guards(1000, &mut StdRng::seed_from_u64(1));
+    assert!(!guards.is_empty() && guards.len() < 1000);
+    assert_apart(&guards);
+    let unavoidable = unavoidable(&plain);
+    assert!(guards.iter().all(|pos| unavoidable.contains(pos)));
+    // Every unavoidable corridor cell is guarded or next to a guard
+    let open = |pos: Pos| TRAVERSABLE.contains(&plain.get(pos.x, pos.y));
+    for pos in unavoidable {
+        if pos
+            .neighbors(plain.get_size())
+            .filter(|&next| open(next))
+            .count()
+            != 2
+        {
+            continue;
+        }
+        assert!(
+            guards.iter().any(|guard| guard.manhattan(pos) <= 1),
+            "{}",
+            pos
+        );
+    }
+}
+
+#[test]
+fn guards_of_a_braided_maze_are_in_the_busiest_corridors() {
+    for seed in 0..5 {
+        // Taking the unavoidable cells leaves only the busy corridors
+        let mut maze = braided(0.5, seed);
+        for pos in unavoidable(&maze) {
+            maze.set(pos.x, pos.y, CellType::Candy);
+        }
+        let inside = |cells: &[Pos]| -> Vec<Pos> {
+            cells[1..cells.len() - 1]
+                .iter()
+                .copied()
+                .filter(|&pos| maze.get(pos.x, pos.y) == CellType::Path)
+                .filter(|&pos| !maze.room().contains(pos))
+                .collect()
+        };
+        // The same routes place_guards samples with the same rng
+        let busiest: Vec<Vec<Pos>> = maze
+            .corridor_importance_with_rng(GUARD_SAMPLES, &mut StdRng::seed_from_u64(seed))
+            .into_iter()
+            .filter(|&(_, importance)| importance > 0.0)
+            .map(|(cells, _)| inside(&cells))
+            .filter(|cells| !cells.is_empty())
+            .collect();
+        let guards = maze
+            .clone()
+            .place_guards(3, &mut StdRng::seed_from_u64(seed));
+        assert_eq!(guards.len(), 3);
+        assert_apart(&guards);
+        assert!(busiest[0].contains(&guards[0]), "seed {}", seed);
+        assert!(
+            guards
+                .iter()
+                .all(|pos| busiest.iter().any(|cells| cells.contains(pos)))
+        );
+    }
+}
+
+fn steps(maze: &Maze, from: Pos, to: Pos) -> Option<u32> {
+    maze.distance_map(from)[to.y * maze.width() + to.x].map(|steps| steps as u32)
+}
+
+#[test]
+fn artifact_distances_are_symmetric_and_match_a_search() {
+    for seed in 0..3 {
+        let mut maze = braided(0.4, seed);
+        maze.place_artifacts_with_rng(0.2, &mut StdRng::seed_from_u64(seed));
+        let points: Vec<Pos> = [maze.start_pos(), maze.exit_pos().unwrap()]
+            .into_iter()
+            .chain(maze.artifacts().map(|(pos, _)| pos))
+            .collect();
+        let matrix = maze.artifact_distance_matrix();
+        assert_eq!(matrix.len(), points.len());
+        for (i, row) in matrix.iter().enumerate() {
+            assert_eq!(row.len(), points.len());
+            assert_eq!(row[i], 0);
+            for (j, &distance) in row.iter().enumerate() {
+                assert_eq!(distance, matrix[j][i]);
+            }
+        }
+        assert_eq!(
+            matrix[0][1] as usize,
+            maze.shortest_path().unwrap().len() - 1
+        );
+        for (i, j) in [(0, 2), (1, 3), (2, points.len() - 1)] {
+            assert_eq!(Some(matrix[i][j]), steps(&maze, points[i], points[j]));
+        }
+    }
+}
+
+#[test]
+fn unreachable_artifacts_are_infinitely_far() {
+    let mut maze = maze(2);
+    // A candy walled in where the maze has no passages
+    maze.set(0, 0, CellType::Candy);
+    let matrix = maze.artifact_distance_matrix();
+    let walled_in = 2 + maze
+        .artifacts()
+        .position(|(pos, _)| pos == Pos::new(0, 0))
+        .unwrap();
+    assert!(matrix[walled_in].iter().enumerate().all(|(i, &distance)| {
+        if i == walled_in {
+            distance == 0
+        } else {
+            distance == u32::MAX
+        }
+    }));
+}
+
+#[test]
+fn the_nearest_artifact_is_the_closest_of_its_kind() {
+    let maze = maze(3);
+    for from in [maze.start_pos(), maze.shortest_path().unwrap()[10]] {
+        for (kind, nearest) in [
+            (&*REWARDS, maze.nearest_reward(from)),
+            (&*DANGERS, maze.nearest_danger(from)),
+        ] {
+            let closest = maze
+                .artifacts()
+                .filter(|(_, cell)| kind.contains(cell))
+                .filter_map(|(pos, _)| steps(&maze, from, pos))
+                .min();
+            let (pos, distance) = nearest.unwrap();
+            assert_eq!(Some(distance), closest);
+            assert!(kind.contains(&maze.get(pos.x, pos.y)));
+            assert_eq!(steps(&maze, from, pos), Some(distance));
+        }
+    }
+    // Nothing to find from inside a wall or in a maze without artifacts
+    assert_eq!(maze.nearest_reward(Pos::new(0, 0)), None);
+    let empty = braided(0.0, 3);
+    assert_eq!(empty.nearest_reward(empty.start_pos()), None);
+    assert_eq!(empty.nearest_danger(empty.start_pos()), None);
+    assert!(empty.stats().reward_distances.is_none());
+}
+
+#[test]
+fn stats_sum_up_how_far_rewards_are_off_the_path() {
+    let maze = maze(4);
+    let path = maze.shortest_path().unwrap();
+    let mut distances: Vec<u32> = maze
+        .artifacts()
+        .filter(|(_, cell)| REWARDS.contains(cell))
+        .map(|(reward, _)| {
+            path.iter()
+                .filter_map(|&pos| steps(&maze, pos, reward))
+                .min()
+                .unwrap()
+        })
+        .collect();
+    distances.sort();
+    let summary = maze.stats().reward_distances.unwrap();
+    assert_eq!(summary.min, distances[0]);
+    assert_eq!(summary.median, distances[(distances.len() - 1) / 2]);
+    assert_eq!(summary.max, *distances.last().unwrap());
+}
@@ -0,0 +1,383 @@
+use mazegen::{
+    CellType, ExitLocation, Maze, MazeBuilder, MazeError, Pos, Rect, Room, TRAVERSABLE, WeightTable,
+};
+
+fn invalid_option(builder: MazeBuilder) -> &'static str {
+    match builder.build() {
+        Err(MazeError::InvalidOption { name, .. }) => name,
+        other => panic!("expected an invalid option, got {:?}", other.map(|_| ())),
+    }
+}
+
+#[test]
+fn default_build() {
+    let maze = MazeBuilder::new().seed(1).build().unwrap();
+    assert_eq!(maze.get_size(), (63, 31));
+    assert_eq!(maze.room_size(), 3);
+    assert_eq!(maze.exit_side(), ExitLocation::Right);
+    assert_eq!(maze.artifacts().count(), 0);
+    assert!(maze.shortest_path().is_some());
+    assert!(maze.validate().is_empty());
+}
+
+#[test]
+fn same_seed_same_maze() {
+    let builder = MazeBuilder::new().width(31).height(23).seed(7);
+    assert!(builder.build().unwrap() == builder.build().unwrap());
+    assert!(builder.build().unwrap() != builder.clone().seed(8).build().unwrap());
+}
+
+#[test]
+fn size_setters_round_up() {
+    let maze = MazeBuilder::new()
+        .width(20)
+        .height(9)
+        .seed(1)
+        .build()
+        .unwrap();
+    assert_eq!(maze.get_size(), (23, 11));
+    let maze = MazeBuilder::new()
+        .width(27)
+        .height(15)
+        .seed(1)
+        .build()
+        .unwrap();
+    assert_eq!(maze.get_size(), (27, 15));
+}
+
+#[test]
+fn room_size_setter() {
+    let maze = MazeBuilder::new().room_size(5).seed(1).build().unwrap();
+    assert_eq!(maze.room_size(), 5);
+    assert!(maze.room().contains(maze.start_pos()));
+}
+
+fn open_cells(maze: &Maze, room: Room) -> usize {
+    room.rect()
+        .positions()
+        .filter(|pos| TRAVERSABLE.contains(&maze.get(pos.x, pos.y)))
+        .count()
+}
+
+#[test]
+fn even_rooms_are_as_big_as_asked() {
+    for seed in 0..5 {
+        let maze = MazeBuilder::new()
+            .width(31)
+            .height(23)
+            .room_size(4)
+            .artifacts(0.3)
+            .seed(seed)
+            .build()
+            .unwrap();
+        // One cell more left of and above the start at (15, 11)
+        let room = Room {
+            top_left: Pos::new(13, 9),
+            width: 4,
+            height: 4,
+        };
+        assert_eq!(maze.room(), room);
+        assert_eq!(open_cells(&maze, room), 16);
+        assert!(maze.artifacts().count() > 0);
+        assert!(maze.artifacts().all(|(pos, _)| !room.contains(pos)));
+        assert_eq!(maze.shortest_path().unwrap()[0], maze.start_pos());
+        assert!(maze.validate().is_empty());
+    }
+}
+
+#[test]
+fn odd_rooms_stay_around_the_start() {
+    for (room_size, top_left) in [(3, Pos::new(14, 10)), (5, Pos::new(13, 9))] {
+        let maze = MazeBuilder::new()
+            .width(31)
+            .height(23)
+            .room_size(room_size)
+            .seed(1)
+            .build()
+            .unwrap();
+        let room = Room {
+            top_left,
+            width: room_size,
+            height: room_size,
+        };
+        assert_eq!(maze.room(), room);
+        assert_eq!(open_cells(&maze, room), room_size * room_size);
+    }
+}
+
+#[test]
+fn rooms_can_be_off_center() {
+    let room = Room {
+        top_left: Pos::new(11, 9),
+        width: 5,
+        height: 3,
+    };
+    for seed in 0..5 {
+        let maze = MazeBuilder::new()
+            .width(31)
+            .height(23)
+            .room(room)
+            .artifacts(0.3)
+            .seed(seed)
+            .build()
+            .unwrap();
+        assert_eq!(maze.room(), room);
+        assert_eq!(open_cells(&maze, room), 15);
+        assert!(room.contains(maze.start_pos()));
+        assert!(maze.artifacts().all(|(pos, _)| !room.contains(pos)));
+        assert!(maze.shortest_path().is_some());
+    }
+    // It has to hold the start and leave the border free
+    for top_left in [Pos::new(1, 1), Pos::new(0, 10)] {
+        assert!(matches!(
+            MazeBuilder::new()
+                .width(31)
+                .height(23)
+                .room(Room { top_left, ..room })
+                .build(),
+            Err(MazeError::InvalidRoomSize { .. })
+        ));
+    }
+}
+
+#[test]
+fn exit_setter() {
+    for side in [
+        ExitLocation::Top,
+        ExitLocation::Bottom,
+        ExitLocation::Left,
+        ExitLocation::Right,
+    ] {
+        let maze = MazeBuilder::new()
+            .exit(side.clone())
+            .seed(3)
+            .build()
+            .unwrap();
+        let exit = maze.exit_pos().unwrap();
+        let (width, height) = maze.get_size();
+        let on_side = match side {
+            ExitLocation::Top => exit.y == 0,
+            ExitLocation::Bottom => exit.y == height - 1,
+            ExitLocation::Left => exit.x == 0,
+            _ => exit.x == width - 1,
+        };
+        assert!(on_side, "{:?} exit at {}", side, exit);
+        assert_eq!(maze.exit_side(), side);
+        assert!(maze.shortest_path().is_some());
+    }
+}
+
+#[test]
+fn artifacts_setter() {
+    let maze = MazeBuilder::new().artifacts(0.2).seed(1).build().unwrap();
+    assert!(maze.artifacts().count() > 0);
+}
+
+#[test]
+fn braid_setter() {
+    for seed in 0..5 {
+        let perfect = MazeBuilder::new().braid(0.0).seed(seed).build().unwrap();
+        assert!(perfect.is_perfect());
+        assert_eq!(perfect.count_loops(), 0);
+        let braided = MazeBuilder::new().braid(1.0).seed(seed).build().unwrap();
+        assert!(!braided.is_perfect());
+        assert!(braided.dead_ends().len() < perfect.dead_ends().len());
+    }
+}
+
+#[test]
+fn corridor_width_setter() {
+    let maze = MazeBuilder::new()
+        .corridor_width(3)
+        .seed(1)
+        .build()
+        .unwrap();
+    // 17 and 9 corridors of three cells with a wall between them
+    assert_eq!(maze.get_size(), (69, 37));
+    let start = maze.start_pos();
+    assert_eq!(maze.get(start.x, start.y), CellType::Start);
+    assert!(maze.shortest_path().is_some());
+    // The start is in the middle of a corridor, with open cells beside it
+    assert!(TRAVERSABLE.contains(&maze.get(start.x + 1, start.y)));
+    assert!(TRAVERSABLE.contains(&maze.get(start.x, start.y + 1)));
+}
+
+#[test]
+fn weave_setter() {
+    let maze = MazeBuilder::new()
+        .width(63)
+        .height(63)
+        .weave(1.0)
+        .seed(1)
+        .build()
+        .unwrap();
+    assert!(
+        maze.cells()
+            .iter()
+            .any(|cell| matches!(cell, CellType::BridgeNorthSouth | CellType::BridgeEastWest))
+    );
+    assert!(maze.shortest_path().is_some());
+}
+
+#[test]
+fn weights_setter() {
+    let mut weights = WeightTable::new();
+    weights.set(CellType::Witch, 100);
+    let maze = MazeBuilder::new()
+        .weights(weights.clone())
+        .seed(1)
+        .build()
+        .unwrap();
+    assert_eq!(maze.weight_table().get(CellType::Witch), 100);
+}
+
+#[test]
+fn min_solution_length_setter() {
+    let maze = MazeBuilder::new()
+        .min_solution_length(40)
+        .seed(1)
+        .build()
+        .unwrap();
+    assert!(maze.shortest_path().unwrap().len() >= 40);
+}
+
+#[test]
+fn min_solution_length_holds_for_every_seed() {
+    for exit in [ExitLocation::Random, ExitLocation::Left] {
+        for seed in 0..20 {
+            let maze = MazeBuilder::new()
+                .width(23)
+                .height(23)
+                .exit(exit.clone())
+                .braid(0.5)
+                .min_solution_length(30)
+                .seed(seed)
+                .build()
+                .unwrap();
+            assert!(maze.shortest_path().unwrap().len() >= 30, "seed {}", seed);
+        }
+    }
+}
+
+#[test]
+fn min_solution_length_gives_up() {
+    let built = MazeBuilder::new()
+        .width(11)
+        .height(11)
+        .min_solution_length(1000)
+        .seed(1)
+        .build();
+    assert!(matches!(
+        built,
+        Err(MazeError::InvalidOption {
+            name: "minimum solution length",
+            ..
+        })
+    ));
+}
+
+#[test]
+fn out_of_range_options() {
+    assert_eq!(
+        invalid_option(MazeBuilder::new().artifacts(1.5)),
+        "artifacts ratio"
+    );
+    assert_eq!(invalid_option(MazeBuilder::new().braid(-0.1)), "braid");
+    assert_eq!(invalid_option(MazeBuilder::new().weave(2.0)), "weave");
+    assert_eq!(
+        invalid_option(MazeBuilder::new().difficulty(101.0)),
+        "difficulty"
+    );
+    assert_eq!(
+        invalid_option(MazeBuilder::new().corridor_width(0)),
+        "corridor width"
+    );
+}
+
+#[test]
+fn invalid_combinations() {
+    assert_eq!(
+        invalid_option(MazeBuilder::new().weave(0.5).corridor_width(2)),
+        "weave"
+    );
+    assert_eq!(
+        invalid_option(MazeBuilder::new().dead_end_artifacts(0.6, 0.6)),
+        "dead end dangers"
+    );
+    assert!(matches!(
+        MazeBuilder::new().width(20).exact(true).build(),
+        Err(MazeError::InvalidDimensions { width: 20, .. })
+    ));
+    assert!(matches!(
+        MazeBuilder::new().room_size(99).build(),
+        Err(MazeError::InvalidRoomSize { room_size: 99, .. })
+    ));
+}
+
+#[test]
+fn errors_name_the_option() {
+    let err = MazeBuilder::new().braid(3.0).build().err().unwrap();
+    assert_eq!(err.to_string(), "Invalid braid: 3 is not between 0 and 1");
+}
+
+#[test]
+fn getters_agree_with_the_constrained_size() {
+    for (width, height) in [(1, 1), (7, 7), (20, 9), (60, 30), (63, 31)] {
+        let maze = Maze::new(width, height, 3, ExitLocation::Top);
+        let (width, height) = (
+            Maze::constrain_dimension(width),
+            Maze::constrain_dimension(height),
+        );
+        assert_eq!((maze.width(), maze.height()), (width, height));
+        assert_eq!(maze.get_size(), (width, height));
+        assert_eq!(maze.bounds(), Rect::new(0, 0, width, height));
+        assert_eq!(maze.room_size(), 3);
+        assert_eq!(maze.exit_location(), &ExitLocation::Top);
+        assert_eq!(maze.start_pos(), Pos::new(width / 2, height / 2));
+        assert_eq!(maze.exit_pos(), None);
+    }
+    let maze = MazeBuilder::new()
+        .width(60)
+        .height(30)
+        .exit(ExitLocation::Bottom)
+        .seed(1)
+        .build()
+        .unwrap();
+    assert_eq!(maze.get_size(), (63, 31));
+    assert_eq!(maze.exit_pos().unwrap().y, 30);
+    let start = maze.start_pos();
+    assert_eq!(maze.get(start.x, start.y), CellType::Start);
+}
+
+#[test]
+fn dimensions_round_up_to_the_next_size() {
+    let constrained: Vec<usize> = (1..=20).map(Maze::constrain_dimension).collect();
+    let expected = [
+        7, 7, 7, 7, 7, 7, 7, 11, 11, 11, 11, 15, 15, 15, 15, 19, 19, 19, 19, 23,
+    ];
+    assert_eq!(constrained, expected);
+    for dim in 1..=20 {
+        let maze = Maze::new(dim, dim, 3, ExitLocation::Right);
+        assert_eq!(maze.get_size(), (expected[dim - 1], expected[dim - 1]));
+    }
+}
+
+#[test]
+fn exact_sizes_arent_adjusted() {
+    for dim in 1..=20 {
+        let built = MazeBuilder::new()
+            .width(dim)
+            .height(23)
+            .exact(true)
+            .seed(1)
+            .build();
+        if Maze::constrain_dimension(dim) == dim {
+            assert_eq!(built.unwrap().get_size(), (dim, 23));
+        } else {
+            assert!(matches!(
+                built,
+                Err(MazeError::InvalidDimensions { width, .. }) if width == dim
+            ));
+        }
+    }
+}
@@ -0,0 +1,193 @@
+use mazegen::{
+    CellType, ExitLocation, Maze, MazeBuilder, MazeError, MazeEvent, Pos, Rect, TRAVERSABLE,
+};
+use rand::SeedableRng;
+use rand::rngs::StdRng;
+
+fn maze() -> Maze {
+    MazeBuilder::new()
+        .width(31)
+        .height(23)
+        .artifacts(0.1)
+        .seed(1)
+        .build()
+        .unwrap()
+}
+
+#[test]
+fn corners_are_in_bounds() {
+    let maze = maze();
+    for (x, y) in [(0, 0), (30, 0), (0, 22), (30, 22)] {
+        assert_eq!(maze.try_get(x, y), Some(maze.get(x, y)), "({}, {})", x, y);
+    }
+}
+
+#[test]
+fn out_of_range_access() {
+    let mut maze = maze();
+    for (x, y) in [(31, 0), (0, 23), (31, 23), (usize::MAX, 0)] {
+        assert_eq!(maze.try_get(x, y), None, "({}, {})", x, y);
+        assert!(matches!(
+            maze.try_set(x, y, CellType::Path),
+            Err(MazeError::OutOfBounds { pos, size: (31, 23) }) if pos == Pos::new(x, y)
+        ));
+    }
+    maze.try_set(1, 1, CellType::Candy).unwrap();
+    assert_eq!(maze.get(1, 1), CellType::Candy);
+}
+
+#[test]
+fn cells_are_row_major() {
+    let maze = maze();
+    let (width, height) = maze.get_size();
+    assert_eq!(maze.cells().len(), width * height);
+    for (pos, cell) in maze.iter_cells() {
+        assert_eq!(maze.cells()[pos.y * width + pos.x], cell);
+        assert_eq!(maze.get(pos.x, pos.y), cell);
+    }
+    let rows: Vec<&[CellType]> = maze.rows().collect();
+    assert_eq!(rows.len(), height);
+    for (y, row) in rows.into_iter().enumerate() {
+        assert_eq!(row.len(), width);
+        assert_eq!(row[width - 1], maze.get(width - 1, y));
+    }
+    let positions: Vec<Pos> = maze.iter_cells().map(|(pos, _)| pos).collect();
+    assert!(positions.is_sorted());
+}
+
+#[test]
+fn cells_in_a_rect_are_clipped_to_the_maze() {
+    let maze = Maze::new(11, 7, 3, ExitLocation::Right);
+    let cells: Vec<Pos> = maze
+        .cells_in(Rect::new(9, 5, 10, 10))
+        .map(|(pos, _)| pos)
+        .collect();
+    assert_eq!(
+        cells,
+        [
+            Pos::new(9, 5),
+            Pos::new(10, 5),
+            Pos::new(9, 6),
+            Pos::new(10, 6)
+        ]
+    );
+}
+
+#[test]
+fn equal_grids_have_equal_fingerprints() {
+    let maze = maze();
+    assert_eq!(maze.clone().fingerprint(), maze.fingerprint());
+    // However it was made
+    let mut copy = Maze::new(31, 23, 3, ExitLocation::Right);
+    for (pos, cell) in maze.iter_cells() {
+        copy.set(pos.x, pos.y, cell);
+    }
+    assert_eq!(copy.fingerprint(), maze.fingerprint());
+    let shared = Maze::from_share_string(&maze.to_share_string().unwrap()).unwrap();
+    assert_eq!(shared.fingerprint(), maze.fingerprint());
+}
+
+#[test]
+fn any_change_changes_the_fingerprint() {
+    let maze = maze();
+    for (pos, cell) in maze.iter_cells().step_by(37) {
+        let mut changed = maze.clone();
+        let other = if cell == CellType::Wall {
+            CellType::Path
+        } else {
+            CellType::Wall
+        };
+        changed.set(pos.x, pos.y, other);
+        assert_ne!(changed.fingerprint(), maze.fingerprint(), "{}", pos);
+    }
+}
+
+#[test]
+fn fingerprints_are_stable() {
+    // FNV-1a of the size and the cells, worked out by hand
+    let mut maze = Maze::new(7, 7, 3, ExitLocation::Right);
+    assert_eq!(maze.fingerprint(), 0x792d_2080_6590_55e5);
+    maze.set(3, 3, CellType::Start);
+    assert_eq!(maze.fingerprint(), 0xa94b_f5ce_6b88_a3ff);
+    // The size counts, not just the cells
+    assert_eq!(
+        Maze::new(7, 11, 3, ExitLocation::Right).fingerprint(),
+        0x35ae_83c1_7811_c971
+    );
+    assert_eq!(
+        Maze::new(11, 7, 3, ExitLocation::Right).fingerprint(),
+        0xea87_2bb0_a5bc_65f1
+    );
+}
+
+#[test]
+fn stats_have_the_fingerprint() {
+    let maze = maze();
+    assert_eq!(maze.stats().fingerprint, maze.fingerprint());
+}
+
+#[test]
+fn rect_intersections() {
+    let rect = Rect::new(2, 3, 4, 5);
+    assert_eq!(
+        rect.intersect(Rect::new(4, 1, 10, 4)),
+        Rect::new(4, 3, 2, 2)
+    );
+    assert_eq!(rect.intersect(Rect::new(0, 0, 20, 20)), rect);
+    assert_eq!(Rect::new(0, 0, 20, 20).intersect(rect), rect);
+    // Side by side and far apart
+    assert!(rect.intersect(Rect::new(6, 3, 4, 5)).is_empty());
+    assert!(rect.intersect(Rect::new(2, 8, 4, 5)).is_empty());
+    assert!(rect.intersect(Rect::new(10, 10, 1, 1)).is_empty());
+    assert!(Rect::new(3, 3, 0, 4).is_empty());
+    assert!(!rect.contains(Pos::new(6, 3)));
+    assert!(rect.contains(Pos::new(5, 7)));
+    assert_eq!(
+        Rect::new(25, 20, 10, 10).clamp_to(maze().bounds()),
+        Rect::new(25, 20, 6, 3)
+    );
+}
+
+#[test]
+fn cells_in_the_whole_maze_are_all_cells() {
+    let maze = maze();
+    assert!(maze.cells_in(maze.bounds()).eq(maze.iter_cells()));
+    assert!(
+        maze.cells_in(Rect::new(0, 0, 100, 100))
+            .eq(maze.iter_cells())
+    );
+    assert_eq!(maze.cells_in(Rect::new(40, 0, 5, 5)).count(), 0);
+    assert_eq!(maze.cells_in(Rect::new(3, 4, 5, 6)).count(), 30);
+}
+
+#[test]
+fn artifacts_are_the_ones_placed() {
+    for seed in 0..5 {
+        let mut maze = Maze::new(31, 23, 3, ExitLocation::Right);
+        let mut rng = StdRng::seed_from_u64(seed);
+        maze.generate_with_rng(&mut rng);
+        let mut placed = Vec::new();
+        maze.place_artifacts_observed(0.2, &mut rng, |event| {
+            if let MazeEvent::PlaceArtifact(pos, cell) = event {
+                placed.push((pos, cell));
+            }
+        });
+        placed.sort_by_key(|&(pos, _)| (pos.y, pos.x));
+        assert!(!placed.is_empty());
+        assert_eq!(maze.artifacts().collect::<Vec<_>>(), placed);
+    }
+}
+
+#[test]
+fn dead_ends_have_one_way_out() {
+    let maze = maze();
+    let open = |pos: Pos| TRAVERSABLE.contains(&maze.get(pos.x, pos.y));
+    let expected: Vec<Pos> = maze
+        .iter_cells()
+        .map(|(pos, _)| pos)
+        .filter(|&pos| open(pos))
+        .filter(|&pos| pos.neighbors(maze.get_size()).filter(|&n| open(n)).count() == 1)
+        .collect();
+    assert!(!expected.is_empty());
+    assert_eq!(maze.dead_ends(), expected);
+}
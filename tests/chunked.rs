@@ -0,0 +1,82 @@
+use mazegen::{CellType, ChunkedMaze, MazeError, TRAVERSABLE, WorldRect};
+
+/// The cells of `-20..40` square, looking at them in the given order
+fn cells(world: &mut ChunkedMaze, reversed: bool) -> Vec<CellType> {
+    let mut positions: Vec<(i64, i64)> = (-20..40)
+        .flat_map(|y| (-20..40).map(move |x| (x, y)))
+        .collect();
+    if reversed {
+        positions.reverse();
+    }
+    let mut cells: Vec<((i64, i64), CellType)> = positions
+        .into_iter()
+        .map(|(x, y)| ((x, y), world.get(x, y)))
+        .collect();
+    cells.sort_by_key(|&(pos, _)| (pos.1, pos.0));
+    cells.into_iter().map(|(_, cell)| cell).collect()
+}
+
+#[test]
+fn the_seed_makes_the_world_in_any_order() {
+    let forward = cells(&mut ChunkedMaze::new(10, 7, 64).unwrap(), false);
+    // With a single chunk cached most chunks are generated again and again
+    let backward = cells(&mut ChunkedMaze::new(10, 7, 1).unwrap(), true);
+    assert_eq!(forward, backward);
+    let other = cells(&mut ChunkedMaze::new(10, 8, 64).unwrap(), false);
+    assert_ne!(forward, other);
+}
+
+#[test]
+fn neighboring_chunks_meet_at_openings() {
+    let mut world = ChunkedMaze::new(10, 3, 4).unwrap();
+    let size = world.chunk_size() as i64;
+    for chunk in -2..2 {
+        let wall = chunk * size;
+        // The wall between chunks side by side, then the one between
+        // chunks on top of each other
+        let vertical: Vec<i64> = (1..size)
+            .step_by(2)
+            .filter(|&y| world.get(wall, y) != CellType::Wall)
+            .collect();
+        assert!(!vertical.is_empty());
+        for y in vertical {
+            assert!(TRAVERSABLE.contains(&world.get(wall - 1, y)));
+            assert!(TRAVERSABLE.contains(&world.get(wall + 1, y)));
+        }
+        let horizontal: Vec<i64> = (1..size)
+            .step_by(2)
+            .filter(|&x| world.get(x, wall) != CellType::Wall)
+            .collect();
+        assert!(!horizontal.is_empty());
+        for x in horizontal {
+            assert!(TRAVERSABLE.contains(&world.get(x, wall - 1)));
+            assert!(TRAVERSABLE.contains(&world.get(x, wall + 1)));
+        }
+        // Where walls cross it stays closed
+        assert_eq!(world.get(wall, wall), CellType::Wall);
+    }
+}
+
+#[test]
+fn a_region_of_whole_chunks_is_connected() {
+    let mut world = ChunkedMaze::new(10, 5, 9).unwrap();
+    for (x, y) in [(0, 0), (-30, 10), (20, -40)] {
+        let maze = world.region(WorldRect::new(x, y, 31, 31)).unwrap();
+        assert!(maze.validate().is_empty(), "({}, {})", x, y);
+        assert!(maze.shortest_path().is_some());
+    }
+}
+
+#[test]
+fn regions_have_to_lie_on_walls() {
+    let mut world = ChunkedMaze::new(10, 5, 9).unwrap();
+    assert!(matches!(
+        world.region(WorldRect::new(1, 0, 31, 31)),
+        Err(MazeError::InvalidOption { name: "region", .. })
+    ));
+    assert!(matches!(
+        world.region(WorldRect::new(0, 0, 30, 31)),
+        Err(MazeError::InvalidDimensions { width: 30, .. })
+    ));
+    assert!(ChunkedMaze::new(10, 5, 0).is_err());
+}
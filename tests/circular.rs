@@ -0,0 +1,101 @@
+use mazegen::{CellType, CircularMaze, MazeError, PolarPos, SvgOptions};
+use rand::SeedableRng;
+use rand::rngs::StdRng;
+
+fn generated(rings: usize, seed: u64) -> CircularMaze {
+    let mut maze = CircularMaze::new(rings).unwrap();
+    maze.generate_with_rng(&mut StdRng::seed_from_u64(seed));
+    maze
+}
+
+#[test]
+fn rings_split_their_cells_at_most_in_two() {
+    let maze = CircularMaze::new(12).unwrap();
+    assert_eq!(maze.rings(), 12);
+    assert_eq!(maze.ring_size(0), 1);
+    assert_eq!(maze.ring_size(1), 6);
+    for ring in 1..maze.rings() {
+        let (inner, outer) = (maze.ring_size(ring), maze.ring_size(ring + 1));
+        assert!(outer == inner || outer == 2 * inner, "ring {}", ring + 1);
+    }
+    assert!(maze.ring_size(12) > 6);
+}
+
+#[test]
+fn every_cell_has_the_right_neighbors() {
+    for rings in [2, 5, 10] {
+        let maze = CircularMaze::new(rings).unwrap();
+        for pos in maze.positions() {
+            let neighbors = maze.neighbors(pos);
+            // Along the ring both ways, one inwards and one or two outwards
+            let expected = match pos.ring {
+                0 => maze.ring_size(1),
+                ring if ring == rings => 3,
+                ring => 3 + maze.ring_size(ring + 1) / maze.ring_size(ring),
+            };
+            assert_eq!(neighbors.len(), expected, "{}", pos);
+            for next in neighbors {
+                assert!(maze.neighbors(next).contains(&pos), "{} and {}", pos, next);
+                assert!(pos.ring.abs_diff(next.ring) <= 1);
+            }
+        }
+    }
+}
+
+#[test]
+fn solvable_from_the_center_to_the_rim() {
+    for seed in 0..10 {
+        let maze = generated(8, seed);
+        let exit = maze.exit_pos().unwrap();
+        assert_eq!(exit.ring, maze.rings());
+        assert_eq!(maze.get(maze.start_pos()), CellType::Start);
+        let path = maze.solve().unwrap();
+        assert_eq!(path[0], maze.start_pos());
+        assert_eq!(path.last(), Some(&exit));
+        for pair in path.windows(2) {
+            assert!(maze.is_open(pair[0], pair[1]), "{} to {}", pair[0], pair[1]);
+        }
+    }
+}
+
+#[test]
+fn generated_mazes_are_perfect() {
+    let maze = generated(6, 3);
+    let cells = maze.positions().count();
+    let passages: usize = maze.positions().map(|pos| maze.passages(pos).len()).sum();
+    // A spanning tree, every passage counted from both ends
+    assert_eq!(passages / 2, cells - 1);
+}
+
+#[test]
+fn artifacts_dont_block_the_way() {
+    let mut maze = generated(8, 4);
+    let before = maze.solve().unwrap();
+    maze.place_artifacts_with_rng(0.3, &mut StdRng::seed_from_u64(4));
+    assert!(maze.positions().any(|pos| !matches!(
+        maze.get(pos),
+        CellType::Path | CellType::Start | CellType::Exit
+    )));
+    assert_eq!(maze.solve(), Some(before));
+}
+
+#[test]
+fn svg_titles_the_artifacts() {
+    let mut maze = generated(5, 5);
+    let pos = PolarPos { ring: 2, index: 1 };
+    maze.set(pos, CellType::Witch);
+    let mut out = Vec::new();
+    maze.write_svg(&mut out, &SvgOptions::default()).unwrap();
+    let svg = String::from_utf8(out).unwrap();
+    assert!(svg.contains("<title>Witch</title></circle>"));
+    assert!(!svg.contains(" title="));
+    assert!(svg.contains(" A "));
+}
+
+#[test]
+fn too_few_rings() {
+    assert!(matches!(
+        CircularMaze::new(1),
+        Err(MazeError::InvalidOption { name: "rings", .. })
+    ));
+}
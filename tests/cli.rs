@@ -0,0 +1,629 @@
+#![cfg(feature = "cli")]
+
+use std::path::PathBuf;
+use std::process::{Command, Output};
+
+use mazegen::{CellType, DANGERS, Maze, MazeBuilder, PlayerMove, Simulation, SimulationOptions};
+
+/// Runs the `maze` binary with `args`
+fn maze(args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_maze"))
+        .args(args)
+        .output()
+        .unwrap()
+}
+
+fn stdout(output: &Output) -> String {
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    String::from_utf8(output.stdout.clone()).unwrap()
+}
+
+/// A file name in the temporary directory no other test uses
+fn temp_file(name: &str) -> PathBuf {
+    std::env::temp_dir().join(format!("mazegen-cli-{}-{}", std::process::id(), name))
+}
+
+/// Generates a maze with `args`, saved to a file, and loads it
+fn generated(name: &str, args: &[&str]) -> Maze {
+    let path = temp_file(name);
+    let out = path.to_str().unwrap();
+    stdout(&maze(&[args, &["--out", out][..]].concat()));
+    let maze = Maze::load_json(out).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    maze
+}
+
+/// Saves a seeded maze to a file for the subcommands reading one
+fn saved_maze(name: &str, seed: u64) -> (PathBuf, Maze) {
+    let maze = MazeBuilder::new()
+        .width(31)
+        .height(23)
+        .artifacts(0.1)
+        .seed(seed)
+        .build()
+        .unwrap();
+    let path = temp_file(name);
+    maze.save_json(path.to_str().unwrap()).unwrap();
+    (path, maze)
+}
+
+#[test]
+fn the_seed_makes_the_maze() {
+    let args = ["--width", "31", "--height", "23", "--seed", "3"];
+    let maze = generated("seed-a.json", &args);
+    assert!(maze == generated("seed-b.json", &args));
+    let other = generated(
+        "seed-c.json",
+        &["--width", "31", "--height", "23", "--seed", "4"],
+    );
+    assert!(maze != other);
+    assert_eq!(maze.get_size(), (31, 23));
+}
+
+#[test]
+fn the_exit_goes_where_asked() {
+    for side in ["top", "left", "bottom"] {
+        let maze = generated(
+            &format!("exit-{}.json", side),
+            &["--exit", side, "--seed", "1"],
+        );
+        let exit = maze.exit_pos().unwrap();
+        let on_side = match side {
+            "top" => exit.y == 0,
+            "left" => exit.x == 0,
+            _ => exit.y == maze.height() - 1,
+        };
+        assert!(on_side, "{} exit at {}", side, exit);
+    }
+}
+
+#[test]
+fn verbose_prints_the_configuration() {
+    let output = stdout(&maze(&[
+        "--verbose",
+        "--seed",
+        "5",
+        "--width",
+        "20",
+        "--algorithm",
+        "dfs",
+    ]));
+    for line in ["Size: 23x31", "Algorithm: Dfs", "Seed: 5", "Exit: Right at"] {
+        assert!(output.contains(line), "{} not in {}", line, output);
+    }
+}
+
+#[test]
+fn a_random_seed_is_printed_when_verbose() {
+    let output = stdout(&maze(&["--verbose", "--width", "11", "--height", "11"]));
+    let seed = output
+        .lines()
+        .find_map(|line| line.strip_prefix("Seed: "))
+        .unwrap();
+    assert!(seed.parse::<u64>().is_ok());
+}
+
+#[test]
+fn invalid_options_fail() {
+    let output = maze(&["--exit", "nowhere"]);
+    assert!(!output.status.success());
+    let output = maze(&["--room-size", "99", "--seed", "1"]);
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).starts_with("maze: "));
+}
+
+#[test]
+fn generate_subcommand_takes_the_flat_flags() {
+    let flat = generated("flat.json", &["--seed", "8", "--width", "15"]);
+    let path = temp_file("subcommand.json");
+    let out = path.to_str().unwrap();
+    stdout(&maze(&[
+        "generate", "--seed", "8", "--width", "15", "--out", out,
+    ]));
+    let subcommand = Maze::load_json(out).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    assert!(flat == subcommand);
+}
+
+#[test]
+fn solve_prints_the_solution() {
+    let (path, saved) = saved_maze("solve.json", 1);
+    let file = path.to_str().unwrap();
+    let solution = saved.shortest_path().unwrap();
+
+    let positions = stdout(&maze(&["solve", file]));
+    let expected: Vec<String> = solution
+        .iter()
+        .map(|pos| format!("{},{}", pos.x, pos.y))
+        .collect();
+    assert_eq!(positions.lines().collect::<Vec<_>>(), expected);
+
+    let moves = stdout(&maze(&["solve", file, "--format", "moves"]));
+    assert_eq!(moves.trim(), Maze::path_to_moves(&solution).unwrap());
+
+    let walked = stdout(&maze(&["solve", file, "--solver", "wall-follower"]));
+    assert!(walked.lines().count() >= solution.len());
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn export_writes_the_files_asked_for() {
+    let (path, saved) = saved_maze("export.json", 2);
+    let svg_path = temp_file("export.svg");
+    let dot_path = temp_file("export.dot");
+    stdout(&maze(&[
+        "export",
+        path.to_str().unwrap(),
+        "--svg-file",
+        svg_path.to_str().unwrap(),
+        "--dot-file",
+        dot_path.to_str().unwrap(),
+    ]));
+    let svg = std::fs::read_to_string(&svg_path).unwrap();
+    assert!(svg.starts_with("<svg "));
+    let dot = std::fs::read_to_string(&dot_path).unwrap();
+    assert!(dot.starts_with("graph Maze {"));
+    assert_eq!(dot.matches(" -- ").count(), saved.build_graph().1.len());
+    for file in [path, svg_path, dot_path] {
+        std::fs::remove_file(file).unwrap();
+    }
+}
+
+#[cfg(feature = "image")]
+#[test]
+fn export_writes_png_files() {
+    let (path, saved) = saved_maze("export-png.json", 3);
+    let png_path = temp_file("export.png");
+    stdout(&maze(&[
+        "export",
+        path.to_str().unwrap(),
+        "--png-file",
+        png_path.to_str().unwrap(),
+        "--scale",
+        "4",
+    ]));
+    let png = std::fs::read(&png_path).unwrap();
+    assert!(png.starts_with(b"\x89PNG\r\n\x1a\n"));
+    // The size follows the signature and the IHDR chunk header
+    let width = u32::from_be_bytes(png[16..20].try_into().unwrap());
+    let height = u32::from_be_bytes(png[20..24].try_into().unwrap());
+    assert_eq!(
+        (width, height),
+        (4 * saved.width() as u32, 4 * saved.height() as u32)
+    );
+    for file in [path, png_path] {
+        std::fs::remove_file(file).unwrap();
+    }
+}
+
+#[test]
+fn stats_prints_json() {
+    let (path, saved) = saved_maze("stats.json", 4);
+    let stats: serde_json::Value =
+        serde_json::from_str(&stdout(&maze(&["stats", path.to_str().unwrap()]))).unwrap();
+    assert_eq!(stats["width"], 31);
+    assert_eq!(stats["height"], 23);
+    assert_eq!(
+        stats["solution_length"],
+        saved.shortest_path().unwrap().len()
+    );
+    assert_eq!(stats["fingerprint"], saved.fingerprint());
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn bench_compares_the_solvers() {
+    let (path, _) = saved_maze("bench.json", 5);
+    let results: serde_json::Value =
+        serde_json::from_str(&stdout(&maze(&["bench", path.to_str().unwrap(), "--json"]))).unwrap();
+    assert!(results.as_array().unwrap().len() >= 2);
+    let table = stdout(&maze(&["bench", path.to_str().unwrap()]));
+    assert!(table.starts_with("Solver"));
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn diff_fails_for_different_mazes() {
+    let (left, _) = saved_maze("diff-left.json", 6);
+    let (right, _) = saved_maze("diff-right.json", 7);
+    let (left, right) = (left.to_str().unwrap(), right.to_str().unwrap());
+    assert_eq!(
+        stdout(&maze(&["diff", left, left])).trim(),
+        "The mazes are equal"
+    );
+    let output = maze(&["diff", left, right]);
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("the mazes differ"));
+    for file in [left, right] {
+        std::fs::remove_file(file).unwrap();
+    }
+}
+
+#[test]
+fn replay_checks_a_recorded_run() {
+    let (path, saved) = saved_maze("replay-maze.json", 8);
+    std::fs::remove_file(&path).unwrap();
+    let mut game = Simulation::new(&saved, SimulationOptions::default(), 1);
+    game.step(PlayerMove::Wait);
+    let replay = temp_file("replay.json");
+    game.record().save(replay.to_str().unwrap()).unwrap();
+    let output = stdout(&maze(&["replay", replay.to_str().unwrap()]));
+    assert!(output.contains("Outcome: Continue"), "{}", output);
+    assert!(output.contains("Steps taken: 0"), "{}", output);
+    let start = saved.start_pos();
+    assert!(output.starts_with(&format!("Turn 1: Wait, player at {}", start)));
+    std::fs::remove_file(&replay).unwrap();
+}
+
+#[test]
+fn missing_files_fail() {
+    for subcommand in ["solve", "export", "stats", "bench"] {
+        let output = maze(&[subcommand, "/nonexistent/maze.json"]);
+        assert!(!output.status.success(), "{}", subcommand);
+        assert!(String::from_utf8_lossy(&output.stderr).starts_with("maze: "));
+    }
+}
+
+#[test]
+fn solutions_are_written_as_json() {
+    let (path, saved) = saved_maze("solution-maze.json", 9);
+    let file = path.to_str().unwrap();
+    let solution = saved.shortest_path().unwrap();
+    let out = temp_file("solution.json");
+    for format in ["positions", "moves"] {
+        stdout(&maze(&[
+            "solve",
+            file,
+            "--solution-out",
+            out.to_str().unwrap(),
+            "--solution-format",
+            format,
+        ]));
+        let json: serde_json::Value =
+            serde_json::from_str(&std::fs::read_to_string(&out).unwrap()).unwrap();
+        assert_eq!(json["format"], format);
+        assert_eq!(json["length"], solution.len());
+        assert_eq!(json["cost"], saved.path_cost(&solution));
+        match format {
+            "moves" => assert_eq!(json["moves"], Maze::path_to_moves(&solution).unwrap()),
+            _ => assert_eq!(
+                json["path"][0],
+                serde_json::json!([saved.start_pos().x, saved.start_pos().y])
+            ),
+        }
+    }
+    for file in [path, out] {
+        std::fs::remove_file(file).unwrap();
+    }
+}
+
+#[test]
+fn no_solution_file_for_unsolvable_mazes() {
+    let (path, mut saved) = saved_maze("unsolvable.json", 10);
+    let exit = saved.exit_pos().unwrap();
+    for neighbor in exit.neighbors(saved.get_size()) {
+        saved.set(neighbor.x, neighbor.y, mazegen::CellType::Wall);
+    }
+    saved.save_json(path.to_str().unwrap()).unwrap();
+    let out = temp_file("unsolvable-solution.json");
+    let output = maze(&[
+        "solve",
+        path.to_str().unwrap(),
+        "--solution-out",
+        out.to_str().unwrap(),
+    ]);
+    assert!(!output.status.success());
+    assert!(!out.exists());
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn print_color_is_plain_text_when_piped() {
+    let (path, saved) = saved_maze("print.json", 11);
+    let printed = stdout(&maze(&["export", path.to_str().unwrap(), "--print-color"]));
+    let mut plain = Vec::new();
+    saved.write_text(&mut plain, &[]).unwrap();
+    assert_eq!(printed.as_bytes(), plain);
+    assert!(!printed.contains('\x1b'));
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn circular_mazes_are_written_as_svg() {
+    let path = temp_file("circular.svg");
+    let svg_file = path.to_str().unwrap();
+    stdout(&maze(&[
+        "--topology",
+        "circular",
+        "--rings",
+        "4",
+        "--seed",
+        "1",
+        "--svg-file",
+        svg_file,
+    ]));
+    let svg = std::fs::read_to_string(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    assert!(svg.starts_with("<svg "));
+}
+
+#[test]
+fn circular_mazes_reject_grid_options() {
+    for (args, flag) in [
+        (&["--width", "31"][..], "--width"),
+        (&["--braid", "0.5"][..], "--braid"),
+        (&["--corridor-width", "2"][..], "--corridor-width"),
+        (&["--preset", "kids-easy"][..], "--preset"),
+        (&["--out", "circular.json"][..], "--out"),
+    ] {
+        for subcommand in [&[][..], &["generate"][..]] {
+            let output = maze(&[subcommand, &["--topology", "circular"][..], args].concat());
+            assert!(!output.status.success(), "{:?}", args);
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            assert!(
+                stderr.contains(&format!("{} isn't supported", flag)),
+                "{}",
+                stderr
+            );
+        }
+    }
+}
+
+#[test]
+fn the_word_is_hidden_on_the_way_out() {
+    let maze = generated(
+        "word.json",
+        &[
+            "--seed",
+            "4",
+            "--width",
+            "31",
+            "--height",
+            "23",
+            "--letters",
+            "0.3",
+            "--word",
+            "SPOOKY",
+        ],
+    );
+    let spelled: String = maze
+        .shortest_path()
+        .unwrap()
+        .iter()
+        .filter_map(|pos| maze.labels().get(pos))
+        .collect();
+    assert_eq!(spelled, "SPOOKY");
+    assert!(maze.labels().len() > 6);
+}
+
+#[test]
+fn adjusted_sizes_are_reported() {
+    let path = temp_file("adjusted.json");
+    let out = path.to_str().unwrap();
+    let output = maze(&[
+        "--width", "60", "--height", "30", "--seed", "1", "--out", out,
+    ]);
+    stdout(&output);
+    std::fs::remove_file(&path).unwrap();
+    assert!(String::from_utf8_lossy(&output.stderr).contains("Size: 63x31"));
+    let output = maze(&["--width", "60", "--height", "31", "--exact", "--out", out]);
+    assert!(!output.status.success());
+}
+
+#[test]
+fn the_spanning_tree_is_printed_when_asked() {
+    let args = ["--width", "31", "--height", "23", "--seed", "5"];
+    let plain = stdout(&maze(&args));
+    assert!(!plain.contains("Minimum Spanning Tree"));
+    let path = temp_file("mst.json");
+    let out = path.to_str().unwrap();
+    let printed = stdout(&maze(&[&args[..], &["--print-mst", "--out", out]].concat()));
+    let maze = Maze::load_json(out).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    assert!(printed.contains(&maze.mst_prim().to_string()));
+}
+
+#[test]
+fn the_spanning_tree_is_drawn_as_the_path() {
+    let path = temp_file("mst-drawn.json");
+    let svg_path = temp_file("mst.svg");
+    stdout(&maze(&[
+        "--width",
+        "31",
+        "--height",
+        "23",
+        "--seed",
+        "6",
+        "--with-path",
+        "minimum-spanning-tree",
+        "--out",
+        path.to_str().unwrap(),
+        "--svg-file",
+        svg_path.to_str().unwrap(),
+    ]));
+    let maze = Maze::load_json(path.to_str().unwrap()).unwrap();
+    let svg = std::fs::read_to_string(&svg_path).unwrap();
+    assert_eq!(
+        svg.matches("<polyline ").count(),
+        maze.mst_kruskal().edges.len()
+    );
+    for file in [path, svg_path] {
+        std::fs::remove_file(file).unwrap();
+    }
+}
+
+#[test]
+fn the_path_is_at_least_as_long_as_asked() {
+    for seed in ["1", "2", "3"] {
+        let maze = generated(
+            &format!("min-length-{}.json", seed),
+            &[
+                "--width",
+                "23",
+                "--height",
+                "23",
+                "--exit",
+                "random",
+                "--min-path-length",
+                "30",
+                "--seed",
+                seed,
+            ],
+        );
+        assert!(maze.shortest_path().unwrap().len() >= 30);
+    }
+}
+
+#[test]
+fn loaded_dead_ends_can_be_decorated() {
+    let (path, saved) = saved_maze("deadends.json", 4);
+    let decorated = generated(
+        "deadends-out.json",
+        &[
+            "--load",
+            path.to_str().unwrap(),
+            "--deadend-dangers",
+            "1",
+            "--seed",
+            "4",
+        ],
+    );
+    std::fs::remove_file(&path).unwrap();
+    let exit = saved.exit_pos().unwrap();
+    let mut dangers = 0;
+    for (pos, cell) in saved.iter_cells() {
+        let free_dead_end = cell == CellType::Path
+            && saved.dead_ends().contains(&pos)
+            && !saved.room().contains(pos)
+            && pos.manhattan(exit) > 1;
+        if free_dead_end {
+            assert!(DANGERS.contains(&decorated.get(pos.x, pos.y)));
+            dangers += 1;
+        } else {
+            assert_eq!(decorated.get(pos.x, pos.y), cell);
+        }
+    }
+    assert!(dangers > 0);
+}
+
+#[test]
+fn the_collection_route_is_printed_as_the_path() {
+    let (path, saved) = saved_maze("collection.json", 12);
+    let printed = stdout(&maze(&[
+        "export",
+        path.to_str().unwrap(),
+        "--print-color",
+        "--with-path",
+        "full-collection",
+    ]));
+    let route = saved.full_collection_route().unwrap();
+    let mut plain = Vec::new();
+    saved.write_text(&mut plain, &route.path).unwrap();
+    assert_eq!(printed.as_bytes(), plain);
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn a_danger_budget_limits_the_written_solution() {
+    let (path, saved) = saved_maze("danger-maze.json", 13);
+    let out = temp_file("danger-solution.json");
+    stdout(&maze(&[
+        "solve",
+        path.to_str().unwrap(),
+        "--solution-out",
+        out.to_str().unwrap(),
+        "--max-danger",
+        "1000",
+    ]));
+    let json: serde_json::Value =
+        serde_json::from_str(&std::fs::read_to_string(&out).unwrap()).unwrap();
+    let (expected, danger) = saved.path_with_danger_budget(1000).unwrap();
+    assert_eq!(json["length"], expected.len());
+    assert_eq!(json["danger"], danger);
+
+    // Without a way that cheap there is nothing to write
+    std::fs::remove_file(&out).unwrap();
+    let output = maze(&[
+        "solve",
+        path.to_str().unwrap(),
+        "--solution-out",
+        out.to_str().unwrap(),
+        "--max-danger=-1",
+    ]);
+    assert!(!output.status.success());
+    assert!(!out.exists());
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn presets_fill_in_the_options_not_given() {
+    let maze = generated("preset.json", &["--preset", "kids-easy", "--seed", "1"]);
+    assert_eq!(maze.get_size(), (23, 15));
+    assert!(maze.artifacts().count() > 0);
+    let maze = generated(
+        "preset-width.json",
+        &["--preset", "kids-easy", "--width", "31", "--seed", "1"],
+    );
+    assert_eq!(maze.get_size(), (31, 15));
+    let maze = generated(
+        "preset-generate.json",
+        &[
+            "generate",
+            "--preset",
+            "print-large",
+            "--height",
+            "23",
+            "--seed",
+            "1",
+        ],
+    );
+    assert_eq!(maze.get_size(), (119, 23));
+    assert_eq!(maze.artifacts().count(), 0);
+}
+
+#[test]
+fn presets_are_read_from_toml_files() {
+    let path = temp_file("preset.toml");
+    std::fs::write(
+        &path,
+        "width = 31\nheight = 11\ntheme = { wall_color = [0, 0, 128] }\n",
+    )
+    .unwrap();
+    let preset_file = path.to_str().unwrap();
+    let built = generated(
+        "preset-file.json",
+        &[
+            "--preset-file",
+            preset_file,
+            "--height",
+            "23",
+            "--seed",
+            "1",
+        ],
+    );
+    assert_eq!(built.get_size(), (31, 23));
+
+    let svg_path = temp_file("preset.svg");
+    stdout(&maze(&[
+        "--preset-file",
+        preset_file,
+        "--seed",
+        "1",
+        "--svg-file",
+        svg_path.to_str().unwrap(),
+    ]));
+    let svg = std::fs::read_to_string(&svg_path).unwrap();
+    assert!(svg.contains("#000080"));
+    assert!(!svg.contains("#222222"));
+    std::fs::remove_file(&svg_path).unwrap();
+
+    std::fs::write(&path, "width = \"wide\"\n").unwrap();
+    let output = maze(&["--preset-file", preset_file]);
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains(preset_file));
+    std::fs::remove_file(&path).unwrap();
+}
@@ -0,0 +1,93 @@
+use mazegen::{CellDiff, CellType, Maze, MazeBuilder, MazeError};
+
+fn maze(seed: u64) -> Maze {
+    MazeBuilder::new()
+        .width(31)
+        .height(23)
+        .artifacts(0.2)
+        .seed(seed)
+        .build()
+        .unwrap()
+}
+
+#[test]
+fn a_copy_has_no_differences() {
+    let maze = maze(1);
+    assert!(maze.clone() == maze);
+    assert!(maze.diff(&maze.clone()).unwrap().is_empty());
+}
+
+#[test]
+fn diff_finds_a_moved_artifact() {
+    let maze = maze(2);
+    let (from, artifact) = maze.artifacts().next().unwrap();
+    let to = maze
+        .iter_cells()
+        .find(|&(_, cell)| cell == CellType::Path)
+        .map(|(pos, _)| pos)
+        .unwrap();
+    let mut moved = maze.clone();
+    moved.set(from.x, from.y, CellType::Path);
+    moved.set(to.x, to.y, artifact);
+    assert!(moved != maze);
+
+    let mut expected = vec![
+        CellDiff {
+            pos: from,
+            left: artifact,
+            right: CellType::Path,
+        },
+        CellDiff {
+            pos: to,
+            left: CellType::Path,
+            right: artifact,
+        },
+    ];
+    expected.sort_by_key(|diff| (diff.pos.y, diff.pos.x));
+    assert!(maze.diff(&moved).unwrap() == expected);
+    let reversed: Vec<CellDiff> = expected
+        .iter()
+        .map(|diff| CellDiff {
+            pos: diff.pos,
+            left: diff.right,
+            right: diff.left,
+        })
+        .collect();
+    assert!(moved.diff(&maze).unwrap() == reversed);
+    assert_eq!(
+        expected[0].to_string(),
+        format!(
+            "{}: {} -> {}",
+            expected[0].pos, expected[0].left, expected[0].right
+        )
+    );
+}
+
+#[test]
+fn mazes_of_different_sizes_cant_be_diffed() {
+    let small = MazeBuilder::new()
+        .width(11)
+        .height(11)
+        .seed(1)
+        .build()
+        .unwrap();
+    let large = maze(1);
+    assert!(small != large);
+    assert!(matches!(
+        small.diff(&large),
+        Err(MazeError::InvalidDimensions {
+            width: 31,
+            height: 23,
+            ..
+        })
+    ));
+}
+
+#[test]
+fn equality_only_looks_at_the_cells() {
+    let maze = maze(3);
+    let mut labeled = maze.clone();
+    labeled.place_labels(&[('A', 1)], 1.0, &mut rand::rng());
+    assert!(!labeled.labels().is_empty());
+    assert!(labeled == maze);
+}
@@ -0,0 +1,701 @@
+use mazegen::{
+    CellChange, CellType, ExitLocation, Maze, MazeBuilder, MazeError, MazeJournal, MazeViolation,
+    Pos, Rect, Room, TRAVERSABLE, WeightTable,
+};
+use rand::SeedableRng;
+use rand::rngs::StdRng;
+
+fn perfect_maze(seed: u64) -> Maze {
+    MazeBuilder::new()
+        .width(31)
+        .height(23)
+        .braid(0.0)
+        .seed(seed)
+        .build()
+        .unwrap()
+}
+
+fn is_open(maze: &Maze, pos: Pos) -> bool {
+    TRAVERSABLE.contains(&maze.get(pos.x, pos.y))
+}
+
+fn assert_solvable(maze: &Maze) {
+    let path = maze.shortest_path().unwrap();
+    assert_eq!(path[0], maze.start_pos());
+    assert_eq!(path.last(), maze.exit_pos().as_ref());
+    for pair in path.windows(2) {
+        assert_eq!(pair[0].manhattan(pair[1]), 1);
+    }
+}
+
+#[test]
+fn generated_mazes_are_valid() {
+    for seed in 0..10 {
+        let maze = perfect_maze(seed);
+        assert!(maze.validate().is_empty());
+        let start = maze.start_pos();
+        assert_eq!(maze.get(start.x, start.y), CellType::Start);
+        assert_eq!(
+            maze.cells()
+                .iter()
+                .filter(|&&cell| cell == CellType::Start)
+                .count(),
+            1
+        );
+    }
+}
+
+#[test]
+fn repair_reconnects_a_cut_corridor() {
+    let mut maze = perfect_maze(1);
+    let room = maze.room();
+    let path = maze.shortest_path().unwrap();
+    let cut = path[room_exit(&path, &maze) + (path.len() - room_exit(&path, &maze)) / 2];
+    maze.set(cut.x, cut.y, CellType::Wall);
+
+    // The corridor was the only way to the exit
+    assert!(maze.shortest_path().is_none());
+    let violations = maze.validate();
+    assert!(
+        violations
+            .iter()
+            .any(|violation| matches!(violation, MazeViolation::Unreachable { .. })),
+        "{:?}",
+        violations
+    );
+
+    assert!(maze.repair_connectivity() > 0);
+    assert!(maze.validate().is_empty());
+    assert!(room.contains(maze.start_pos()));
+    assert_solvable(&maze);
+}
+
+/// Index of the first cell of `path` after the room
+fn room_exit(path: &[Pos], maze: &Maze) -> usize {
+    let room = maze.room();
+    path.iter().position(|&pos| !room.contains(pos)).unwrap()
+}
+
+#[test]
+fn repair_joins_a_pocket() {
+    let mut maze = perfect_maze(2);
+    let size = maze.get_size();
+    let room = maze.room();
+    // In a perfect maze the cell in front of a dead end only joins it to the
+    // rest, so walling that cell up leaves the dead end alone
+    let (pocket, entrance) = maze
+        .dead_ends()
+        .into_iter()
+        .find_map(|pos| {
+            let entrance = pos.neighbors(size).find(|&n| is_open(&maze, n))?;
+            (maze.get(pos.x, pos.y) == CellType::Path && !room.contains(entrance))
+                .then_some((pos, entrance))
+        })
+        .unwrap();
+    maze.set(entrance.x, entrance.y, CellType::Wall);
+    assert!(maze.validate().contains(&MazeViolation::Unreachable {
+        pos: pocket,
+        size: 1
+    }));
+
+    assert_eq!(maze.repair_connectivity(), 1);
+    assert!(maze.validate().is_empty());
+    assert_solvable(&maze);
+}
+
+#[test]
+fn validate_reports_broken_rules() {
+    let mut maze = perfect_maze(3);
+    maze.set(0, 1, CellType::Path);
+    let start = maze.start_pos();
+    maze.set(start.x, start.y, CellType::Path);
+    let exit = maze.exit_pos().unwrap();
+    maze.set(exit.x, exit.y, CellType::Wall);
+    let violations = maze.validate();
+    assert!(violations.contains(&MazeViolation::OpenBorder(Pos::new(0, 1))));
+    assert!(violations.contains(&MazeViolation::MissingStart));
+    assert!(violations.contains(&MazeViolation::MissingExit));
+
+    let mut maze = perfect_maze(3);
+    let beside = Pos::new(start.x + 1, start.y);
+    maze.set(beside.x, beside.y, CellType::Wall);
+    assert!(
+        maze.validate()
+            .contains(&MazeViolation::RoomBlocked(beside))
+    );
+}
+
+#[test]
+fn filling_a_bottleneck_fails() {
+    let mut maze = perfect_maze(4);
+    // Every cell of the solution of a perfect maze is on the only way out
+    assert!(maze.is_perfect());
+    let path = maze.shortest_path().unwrap();
+    let middle = path[room_exit(&path, &maze) + 1];
+    let before = maze.get(middle.x, middle.y);
+    assert!(matches!(
+        maze.fill(middle),
+        Err(MazeError::InvalidEdit { pos, .. }) if pos == middle
+    ));
+    assert_eq!(maze.get(middle.x, middle.y), before);
+    assert!(maze.validate().is_empty());
+}
+
+#[test]
+fn filling_a_dead_end_tip_succeeds() {
+    let mut maze = perfect_maze(4);
+    let tip = maze
+        .dead_ends()
+        .into_iter()
+        .find(|pos| maze.get(pos.x, pos.y) == CellType::Path)
+        .unwrap();
+    maze.fill(tip).unwrap();
+    assert_eq!(maze.get(tip.x, tip.y), CellType::Wall);
+    assert!(maze.validate().is_empty());
+    assert_solvable(&maze);
+}
+
+#[test]
+fn the_start_exit_and_room_cant_be_filled() {
+    let mut maze = perfect_maze(5);
+    let start = maze.start_pos();
+    for pos in [
+        start,
+        maze.exit_pos().unwrap(),
+        Pos::new(start.x + 1, start.y),
+    ] {
+        assert!(maze.fill(pos).is_err(), "{}", pos);
+    }
+}
+
+#[test]
+fn carving_the_border_makes_an_exit() {
+    let mut maze = perfect_maze(6);
+    let (width, height) = maze.get_size();
+    // A corner only touches other border cells
+    assert!(maze.carve(Pos::new(0, 0)).is_err());
+    let beside_corridor = (1..height - 1)
+        .map(|y| Pos::new(0, y))
+        .find(|pos| is_open(&maze, Pos::new(1, pos.y)))
+        .unwrap();
+    maze.carve(beside_corridor).unwrap();
+    assert_eq!(
+        maze.get(beside_corridor.x, beside_corridor.y),
+        CellType::Exit
+    );
+    assert!(maze.validate().is_empty());
+    assert!(maze.carve(Pos::new(width, 0)).is_err());
+}
+
+#[test]
+fn artifacts_only_go_on_paths() {
+    let mut maze = perfect_maze(7);
+    let path = maze.shortest_path().unwrap();
+    let on_path = path[room_exit(&path, &maze)];
+    maze.place(on_path, CellType::Witch).unwrap();
+    assert_eq!(maze.get(on_path.x, on_path.y), CellType::Witch);
+    assert!(maze.place(Pos::new(0, 0), CellType::Witch).is_err());
+    assert!(maze.place(on_path, CellType::Start).is_err());
+}
+
+/// Dead end tips outside the room, free to be filled or decorated
+fn tips(maze: &Maze) -> Vec<Pos> {
+    maze.dead_ends()
+        .into_iter()
+        .filter(|pos| maze.get(pos.x, pos.y) == CellType::Path)
+        .collect()
+}
+
+#[test]
+fn undoing_every_edit_gives_back_the_original() {
+    let mut maze = perfect_maze(9);
+    let original = maze.clone();
+    let tips = tips(&maze);
+    let pillar = Pos::new(2, 2);
+    let mut journal = MazeJournal::new(100);
+    journal
+        .edit(&mut maze, tips[0], |maze| maze.fill(tips[0]))
+        .unwrap();
+    journal
+        .edit(&mut maze, tips[1], |maze| {
+            maze.place(tips[1], CellType::Candy)
+        })
+        .unwrap();
+    journal
+        .edit(&mut maze, pillar, |maze| maze.carve(pillar))
+        .unwrap();
+    journal
+        .edit(&mut maze, tips[1], |maze| {
+            maze.place(tips[1], CellType::Zombie)
+        })
+        .unwrap();
+    let edited = maze.clone();
+
+    let mut undone = Vec::new();
+    while let Some(change) = journal.undo(&mut maze) {
+        undone.push(change);
+    }
+    assert!(maze == original);
+    assert_eq!(
+        undone,
+        [
+            CellChange {
+                pos: tips[1],
+                before: CellType::Candy,
+                after: CellType::Zombie,
+            },
+            CellChange {
+                pos: pillar,
+                before: CellType::Wall,
+                after: CellType::Path,
+            },
+            CellChange {
+                pos: tips[1],
+                before: CellType::Path,
+                after: CellType::Candy,
+            },
+            CellChange {
+                pos: tips[0],
+                before: CellType::Path,
+                after: CellType::Wall,
+            },
+        ]
+    );
+    assert!(!journal.can_undo());
+
+    while journal.redo(&mut maze).is_some() {}
+    assert!(maze == edited);
+    assert!(!journal.can_redo());
+    assert!(journal.can_undo());
+    assert_eq!(maze.shortest_path(), uncached(&maze).shortest_path());
+}
+
+#[test]
+fn failed_edits_and_edits_changing_nothing_arent_recorded() {
+    let mut maze = perfect_maze(10);
+    let original = maze.clone();
+    let mut journal = MazeJournal::default();
+    let start = maze.start_pos();
+    assert!(
+        journal
+            .edit(&mut maze, start, |maze| maze.fill(start))
+            .is_err()
+    );
+    // Filling a wall is no change
+    let corner = Pos::new(0, 0);
+    journal
+        .edit(&mut maze, corner, |maze| maze.fill(corner))
+        .unwrap();
+    let outside = Pos::new(31, 0);
+    assert!(matches!(
+        journal.edit(&mut maze, outside, |maze| maze.carve(outside)),
+        Err(MazeError::OutOfBounds { .. })
+    ));
+    assert!(!journal.can_undo());
+    assert_eq!(journal.undo(&mut maze), None);
+    assert!(maze == original);
+}
+
+#[test]
+fn a_new_edit_drops_what_could_be_redone() {
+    let mut maze = perfect_maze(11);
+    let tips = tips(&maze);
+    let mut journal = MazeJournal::default();
+    journal
+        .edit(&mut maze, tips[0], |maze| {
+            maze.place(tips[0], CellType::Candy)
+        })
+        .unwrap();
+    journal.undo(&mut maze).unwrap();
+    assert!(journal.can_redo());
+    journal
+        .edit(&mut maze, tips[1], |maze| {
+            maze.place(tips[1], CellType::Witch)
+        })
+        .unwrap();
+    assert!(!journal.can_redo());
+    assert_eq!(journal.redo(&mut maze), None);
+    assert_eq!(maze.get(tips[0].x, tips[0].y), CellType::Path);
+}
+
+#[test]
+fn only_the_last_edits_can_be_undone() {
+    let mut maze = perfect_maze(12);
+    let tips = tips(&maze);
+    let mut journal = MazeJournal::new(2);
+    for &tip in &tips[..3] {
+        journal
+            .edit(&mut maze, tip, |maze| maze.place(tip, CellType::Candy))
+            .unwrap();
+    }
+    while journal.undo(&mut maze).is_some() {}
+    // The first edit is kept
+    assert_eq!(maze.get(tips[0].x, tips[0].y), CellType::Candy);
+    assert_eq!(maze.get(tips[1].x, tips[1].y), CellType::Path);
+    assert_eq!(maze.get(tips[2].x, tips[2].y), CellType::Path);
+
+    while journal.redo(&mut maze).is_some() {}
+    journal.set_depth(1);
+    journal.undo(&mut maze).unwrap();
+    assert!(!journal.can_undo());
+    assert_eq!(maze.get(tips[1].x, tips[1].y), CellType::Candy);
+
+    let mut nothing = MazeJournal::new(0);
+    nothing
+        .edit(&mut maze, tips[3], |maze| {
+            maze.place(tips[3], CellType::Candy)
+        })
+        .unwrap();
+    assert!(!nothing.can_undo());
+    assert_eq!(maze.get(tips[3].x, tips[3].y), CellType::Candy);
+}
+
+/// The walled up 7x7 part of another maze, with its exit on its border
+fn piece() -> Maze {
+    perfect_maze(8).crop(Rect::new(0, 0, 7, 7)).unwrap()
+}
+
+#[test]
+fn crop_at_the_edges() {
+    let maze = perfect_maze(9);
+    let (width, height) = maze.get_size();
+    for rect in [
+        Rect::new(0, 0, 7, 7),
+        Rect::new(width - 7, height - 7, 7, 7),
+        Rect::new(0, height - 11, 11, 11),
+    ] {
+        let cropped = maze.crop(rect).unwrap();
+        assert_eq!(cropped.get_size(), (rect.width, rect.height));
+        let start = cropped.start_pos();
+        assert_eq!(cropped.get(start.x, start.y), CellType::Start);
+        assert!(cropped.shortest_path().is_some(), "{:?}", rect);
+        for y in 1..rect.height - 1 {
+            for x in 1..rect.width - 1 {
+                if Pos::new(x, y) != start {
+                    assert_eq!(cropped.get(x, y), maze.get(rect.x + x, rect.y + y));
+                }
+            }
+        }
+    }
+}
+
+#[test]
+fn crop_around_the_room_keeps_it() {
+    let maze = perfect_maze(9);
+    let start = maze.start_pos();
+    let cropped = maze
+        .crop(Rect::new(start.x - 3, start.y - 3, 7, 7))
+        .unwrap();
+    assert_eq!(cropped.room_size(), maze.room_size());
+    assert!(cropped.room().contains(cropped.start_pos()));
+}
+
+#[test]
+fn crop_rejects_bad_rects() {
+    let maze = perfect_maze(9);
+    let (width, _) = maze.get_size();
+    assert!(matches!(
+        maze.crop(Rect::new(width - 5, 0, 7, 7)),
+        Err(MazeError::OutOfBounds { pos, .. }) if pos == Pos::new(width + 1, 6)
+    ));
+    assert!(matches!(
+        maze.crop(Rect::new(0, 0, 8, 7)),
+        Err(MazeError::InvalidDimensions { width: 8, .. })
+    ));
+    assert!(matches!(
+        maze.crop(Rect::new(0, 0, 0, 7)),
+        Err(MazeError::InvalidDimensions { width: 0, .. })
+    ));
+    // The center would land on a wall between corridors
+    assert!(matches!(
+        maze.crop(Rect::new(1, 1, 7, 7)),
+        Err(MazeError::InvalidOption { name: "crop", .. })
+    ));
+}
+
+#[test]
+fn blit_copies_the_cells() {
+    let mut maze = perfect_maze(10);
+    let piece = piece();
+    let at = Pos::new(2, 2);
+    let edges = maze.blit(&piece, at).unwrap();
+    for (pos, cell) in piece.iter_cells() {
+        let expected = match cell {
+            CellType::Start | CellType::Exit => CellType::Path,
+            cell => cell,
+        };
+        assert_eq!(maze.get(at.x + pos.x, at.y + pos.y), expected, "{}", pos);
+    }
+    // The piece's border is walled up apart from its exit
+    let exit = piece.exit_pos().unwrap();
+    assert_eq!(edges, vec![Pos::new(at.x + exit.x, at.y + exit.y)]);
+}
+
+#[test]
+fn blit_over_the_center_room_fails() {
+    let mut maze = perfect_maze(10);
+    let before = maze.clone();
+    let start = maze.start_pos();
+    let result = maze.blit(&piece(), Pos::new(start.x - 3, start.y - 3));
+    assert!(matches!(
+        result,
+        Err(MazeError::InvalidEdit { pos, .. }) if maze.room().contains(pos)
+    ));
+    assert!(maze == before);
+}
+
+#[test]
+fn blit_out_of_bounds_fails() {
+    let mut maze = perfect_maze(10);
+    let before = maze.clone();
+    let (width, height) = maze.get_size();
+    assert!(matches!(
+        maze.blit(&piece(), Pos::new(width - 5, 2)),
+        Err(MazeError::OutOfBounds { pos, .. }) if pos == Pos::new(width + 1, 8)
+    ));
+    assert!(matches!(
+        maze.blit(&piece(), Pos::new(2, height)),
+        Err(MazeError::OutOfBounds { .. })
+    ));
+    assert!(maze == before);
+}
+
+#[test]
+fn regenerating_a_region_keeps_the_rest() {
+    let rect = Rect::new(2, 2, 9, 9);
+    let inner = Rect::new(3, 3, 7, 7);
+    let maze = perfect_maze(11);
+    let mut changed = false;
+    for seed in 0..5 {
+        let mut regenerated = maze.clone();
+        regenerated
+            .regenerate_region_with_rng(rect, &mut StdRng::seed_from_u64(seed))
+            .unwrap();
+        for (pos, cell) in maze.iter_cells() {
+            if !inner.contains(pos) {
+                assert_eq!(regenerated.get(pos.x, pos.y), cell, "{}", pos);
+            }
+        }
+        assert!(regenerated.validate().is_empty());
+        assert_solvable(&regenerated);
+        changed |= regenerated != maze;
+    }
+    assert!(changed);
+}
+
+#[test]
+fn regenerating_a_region_is_seeded() {
+    let regenerate = |seed| {
+        let mut maze = perfect_maze(12);
+        maze.regenerate_region_with_rng(Rect::new(16, 4, 11, 5), &mut StdRng::seed_from_u64(seed))
+            .unwrap();
+        maze
+    };
+    assert!(regenerate(1) == regenerate(1));
+}
+
+#[test]
+fn regions_have_to_fit() {
+    let mut maze = perfect_maze(13);
+    let before = maze.clone();
+    let (width, height) = maze.get_size();
+    let start = maze.start_pos();
+    // Around the room
+    assert!(matches!(
+        maze.regenerate_region(Rect::new(start.x - 3, start.y - 3, 7, 7)),
+        Err(MazeError::InvalidOption { name: "region", .. })
+    ));
+    // Off the wall lines
+    assert!(matches!(
+        maze.regenerate_region(Rect::new(1, 2, 7, 7)),
+        Err(MazeError::InvalidDimensions { .. })
+    ));
+    assert!(matches!(
+        maze.regenerate_region(Rect::new(2, 2, 6, 7)),
+        Err(MazeError::InvalidDimensions { width: 6, .. })
+    ));
+    assert!(matches!(
+        maze.regenerate_region(Rect::new(width - 5, 2, 7, 7)),
+        Err(MazeError::OutOfBounds { .. })
+    ));
+    assert!(matches!(
+        maze.regenerate_region(Rect::new(0, height - 1, 0, 0)),
+        Err(MazeError::InvalidDimensions { .. })
+    ));
+    assert!(maze == before);
+
+    let mut wide = MazeBuilder::new()
+        .corridor_width(2)
+        .seed(1)
+        .build()
+        .unwrap();
+    assert!(matches!(
+        wide.regenerate_region(Rect::new(2, 2, 7, 7)),
+        Err(MazeError::InvalidOption { name: "region", .. })
+    ));
+}
+
+/// A copy of `maze` that hasn't looked for its shortest path yet
+fn uncached(maze: &Maze) -> Maze {
+    let (width, height) = maze.get_size();
+    let mut copy = Maze::new(
+        width,
+        height,
+        maze.room_size(),
+        maze.exit_location().clone(),
+    );
+    copy.set_room(maze.room()).unwrap();
+    for (pos, cell) in maze.iter_cells() {
+        copy.set(pos.x, pos.y, cell);
+    }
+    copy
+}
+
+#[test]
+fn changes_forget_the_cached_shortest_path() {
+    let mut maze = perfect_maze(14);
+    let check = |maze: &Maze| assert_eq!(maze.shortest_path(), uncached(maze).shortest_path());
+    let cached = maze.shortest_path().unwrap();
+
+    let cut = cached[room_exit(&cached, &maze) + 1];
+    maze.set(cut.x, cut.y, CellType::Wall);
+    assert_eq!(maze.shortest_path(), None);
+    maze.try_set(cut.x, cut.y, CellType::Path).unwrap();
+    assert_eq!(maze.shortest_path(), Some(cached.clone()));
+
+    let (width, height) = maze.get_size();
+    let exit = (1..height - 1)
+        .map(|y| Pos::new(0, y))
+        .find(|&pos| maze.move_exit(pos).is_ok())
+        .unwrap();
+    assert_eq!(maze.shortest_path().unwrap().last(), Some(&exit));
+    check(&maze);
+
+    let path = maze.shortest_path().unwrap();
+    let on_path = path[room_exit(&path, &maze) + 2];
+    maze.place(on_path, CellType::Witch).unwrap();
+    check(&maze);
+
+    maze.regenerate_region_with_rng(Rect::new(2, 2, 9, 9), &mut StdRng::seed_from_u64(1))
+        .unwrap();
+    check(&maze);
+
+    maze.set_room(Room::centered(width, height, 5)).unwrap();
+    check(&maze);
+
+    maze.set_weight_table(WeightTable::new());
+    check(&maze);
+}
+
+#[test]
+fn the_exit_moves_along_the_border() {
+    let mut maze = perfect_maze(13);
+    let old = maze.exit_pos().unwrap();
+    let pos = Pos::new(0, 7);
+    maze.move_exit(pos).unwrap();
+    assert_eq!(maze.get(old.x, old.y), CellType::Wall);
+    assert_eq!(maze.exit_pos(), Some(pos));
+    assert_eq!(
+        maze.cells()
+            .iter()
+            .filter(|&&cell| cell == CellType::Exit)
+            .count(),
+        1
+    );
+    assert_eq!(maze.exit_location(), &ExitLocation::Custom(pos));
+    assert_eq!(maze.exit_side(), ExitLocation::Left);
+    assert!(maze.validate().is_empty());
+    assert_solvable(&maze);
+
+    // Moving it where it is already changes no cells
+    let moved = maze.clone();
+    maze.move_exit(pos).unwrap();
+    assert!(maze == moved);
+
+    for (pos, side) in [
+        (Pos::new(9, 0), ExitLocation::Top),
+        (Pos::new(30, 3), ExitLocation::Right),
+        (Pos::new(21, 22), ExitLocation::Bottom),
+    ] {
+        maze.move_exit(pos).unwrap();
+        assert_eq!(maze.exit_pos(), Some(pos));
+        assert_eq!(maze.exit_side(), side);
+        assert_solvable(&maze);
+    }
+}
+
+#[test]
+fn the_exit_only_moves_next_to_a_corridor() {
+    let mut maze = perfect_maze(14);
+    let original = maze.clone();
+    let (width, height) = maze.get_size();
+    for pos in [
+        Pos::new(0, 0),
+        Pos::new(width - 1, height - 1),
+        // Across from where two corridors may meet, not from a corridor
+        Pos::new(0, 8),
+        Pos::new(5, 5),
+        maze.start_pos(),
+    ] {
+        assert!(
+            matches!(maze.move_exit(pos), Err(MazeError::InvalidEdit { .. })),
+            "{}",
+            pos
+        );
+    }
+    assert!(matches!(
+        maze.move_exit(Pos::new(width, 1)),
+        Err(MazeError::OutOfBounds { .. })
+    ));
+    assert!(maze == original);
+    assert_eq!(maze.exit_location(), original.exit_location());
+
+    // Opening the wall inside doesn't make it a corridor
+    if maze.get(1, 8) == CellType::Wall {
+        maze.carve(Pos::new(1, 8)).unwrap();
+    }
+    assert!(maze.move_exit(Pos::new(0, 8)).is_err());
+}
+
+#[test]
+fn moved_exits_are_generated_again() {
+    let pos = Pos::new(0, 7);
+    let builder = MazeBuilder::new()
+        .width(31)
+        .height(23)
+        .exit(ExitLocation::Custom(pos));
+    for seed in 0..5 {
+        let maze = builder.clone().seed(seed).build().unwrap();
+        assert_eq!(maze.exit_pos(), Some(pos));
+        assert_eq!(maze.exit_location(), &ExitLocation::Custom(pos));
+        assert_solvable(&maze);
+    }
+    for pos in [Pos::new(0, 0), Pos::new(0, 8)] {
+        assert!(matches!(
+            builder.clone().exit(ExitLocation::Custom(pos)).build(),
+            Err(MazeError::InvalidOption { name: "exit", .. })
+        ));
+    }
+    assert!(matches!(
+        builder.clone().corridor_width(2).build(),
+        Err(MazeError::InvalidOption { name: "exit", .. })
+    ));
+}
+
+#[test]
+fn moved_exits_turn_with_the_maze() {
+    let mut maze = perfect_maze(16);
+    maze.move_exit(Pos::new(0, 7)).unwrap();
+    for turned in [
+        maze.rotated_90(),
+        maze.mirrored_horizontal(),
+        maze.transposed(),
+    ] {
+        let exit = turned.exit_pos().unwrap();
+        assert_eq!(turned.exit_location(), &ExitLocation::Custom(exit));
+    }
+    assert_eq!(
+        maze.mirrored_horizontal().exit_location(),
+        &ExitLocation::Custom(Pos::new(30, 7))
+    );
+}
@@ -0,0 +1,1193 @@
+use std::collections::{HashMap, HashSet};
+
+use mazegen::{
+    CellType, Direction, ExitLocation, LegendPosition, Maze, MazeBuilder, MazeError, Pos,
+    RevealRule, SolutionStyle, SolutionType, SvgAnimation, SvgOptions, SvgOverlay, TRAVERSABLE,
+    Theme,
+};
+
+fn maze(seed: u64) -> Maze {
+    MazeBuilder::new()
+        .width(31)
+        .height(23)
+        .artifacts(0.1)
+        .seed(seed)
+        .build()
+        .unwrap()
+}
+
+fn svg(maze: &Maze, options: &SvgOptions) -> String {
+    let mut out = Vec::new();
+    maze.write_svg(&mut out, options).unwrap();
+    String::from_utf8(out).unwrap()
+}
+
+/// The value of `name="..."` in `tag`
+fn attribute<'a>(tag: &'a str, name: &str) -> Option<&'a str> {
+    let start = tag.find(&format!(" {}=\"", name))? + name.len() + 3;
+    let end = start + tag[start..].find('"')?;
+    Some(&tag[start..end])
+}
+
+/// The fill of every cell-sized rectangle filled with an `rgb(...)` color
+fn cell_fills(svg: &str) -> HashMap<Pos, String> {
+    svg.lines()
+        .map(str::trim)
+        .filter(|line| line.starts_with("<rect ") && line.contains("fill=\"rgb("))
+        .filter(|line| attribute(line, "width") == Some("1"))
+        .map(|line| {
+            let coordinate = |name| attribute(line, name).unwrap().parse().unwrap();
+            (
+                Pos::new(coordinate("x"), coordinate("y")),
+                attribute(line, "fill").unwrap().to_string(),
+            )
+        })
+        .collect()
+}
+
+#[test]
+fn heatmap_fills_every_traversable_cell() {
+    let maze = maze(1);
+    let options = SvgOptions {
+        overlay: SvgOverlay::DistanceHeatmap,
+        ..SvgOptions::default()
+    };
+    let fills = cell_fills(&svg(&maze, &options));
+    for (pos, cell) in maze.iter_cells() {
+        assert_eq!(
+            fills.contains_key(&pos),
+            TRAVERSABLE.contains(&cell),
+            "{} {:?}",
+            pos,
+            cell
+        );
+    }
+}
+
+#[test]
+fn heatmap_runs_from_near_to_far_color() {
+    let maze = maze(2);
+    let options = SvgOptions {
+        overlay: SvgOverlay::DistanceHeatmap,
+        heatmap_colors: ([0, 0, 255], [255, 0, 0]),
+        ..SvgOptions::default()
+    };
+    let fills = cell_fills(&svg(&maze, &options));
+    let (farthest, _) = maze
+        .distance_map(maze.start_pos())
+        .into_iter()
+        .enumerate()
+        .filter_map(|(idx, distance)| Some((idx, distance?)))
+        .max_by_key(|&(_, distance)| distance)
+        .unwrap();
+    let farthest = Pos::new(farthest % maze.width(), farthest / maze.width());
+    assert_eq!(fills[&maze.start_pos()], "rgb(0, 0, 255)");
+    assert_eq!(fills[&farthest], "rgb(255, 0, 0)");
+}
+
+#[test]
+fn heatmap_titles_the_exit_with_its_distance() {
+    let maze = maze(3);
+    let options = SvgOptions {
+        overlay: SvgOverlay::DistanceHeatmap,
+        ..SvgOptions::default()
+    };
+    let distance = maze.shortest_path().unwrap().len() - 1;
+    assert!(svg(&maze, &options).contains(&format!("<title>Distance: {}</title>", distance)));
+}
+
+#[test]
+fn no_heatmap_without_the_overlay() {
+    let maze = maze(1);
+    assert!(cell_fills(&svg(&maze, &SvgOptions::default())).is_empty());
+}
+
+#[test]
+fn artifacts_can_be_left_out() {
+    let maze = maze(4);
+    assert!(maze.artifacts().count() > 0);
+    let shown = svg(&maze, &SvgOptions::default());
+    let hidden = svg(
+        &maze,
+        &SvgOptions {
+            show_artifacts: false,
+            ..SvgOptions::default()
+        },
+    );
+    for (_, cell) in maze.artifacts() {
+        let title = format!("<title>{}</title>", cell);
+        assert!(shown.contains(&title), "{}", title);
+        assert!(!hidden.contains(&title), "{}", title);
+    }
+}
+
+#[test]
+fn theme_colors_are_used() {
+    let maze = maze(5);
+    let options = SvgOptions {
+        theme: Theme {
+            wall_color: [1, 2, 3],
+            path_color: [4, 5, 6],
+            reward_color: [7, 8, 9],
+            danger_color: [10, 11, 12],
+            solution_color: [13, 14, 15],
+        },
+        with_solution: SolutionType::ShortestPath,
+        ..SvgOptions::default()
+    };
+    let svg = svg(&maze, &options);
+    for color in ["#010203", "#040506", "#0d0e0f"] {
+        assert!(svg.contains(color), "{}", color);
+    }
+}
+
+#[test]
+fn svg_files_are_written() {
+    let maze = maze(6);
+    let path = std::env::temp_dir().join(format!("mazegen-export-{}.svg", std::process::id()));
+    let filename = path.to_str().unwrap();
+    maze.export_to_svg(filename, &SvgOptions::default())
+        .unwrap();
+    let written = std::fs::read_to_string(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(written, svg(&maze, &SvgOptions::default()));
+    assert!(written.starts_with("<svg "));
+    assert!(written.trim_end().ends_with("</svg>"));
+}
+
+#[test]
+fn dot_lists_the_graph() {
+    let maze = maze(7);
+    let mut out = Vec::new();
+    maze.write_dot(&mut out).unwrap();
+    let dot = String::from_utf8(out).unwrap();
+    let (nodes, edges) = maze.build_graph();
+    assert!(dot.starts_with("graph Maze {"));
+    assert_eq!(dot.matches(" -- ").count(), edges.len());
+    assert!(nodes.len() > 1);
+}
+
+#[test]
+fn ansi_snapshot() {
+    let mut maze = Maze::new(7, 7, 3, ExitLocation::Right);
+    for x in 1..6 {
+        maze.set(x, 3, CellType::Path);
+    }
+    maze.set(2, 3, CellType::Candy);
+    maze.set(3, 3, CellType::Start);
+    maze.set(4, 3, CellType::Zombie);
+    maze.set(6, 3, CellType::Exit);
+    let theme = Theme {
+        wall_color: [1, 1, 1],
+        path_color: [2, 2, 2],
+        reward_color: [3, 3, 3],
+        danger_color: [4, 4, 4],
+        solution_color: [5, 5, 5],
+    };
+    let solution: Vec<Pos> = (3..7).map(|x| Pos::new(x, 3)).collect();
+    let mut out = Vec::new();
+    maze.write_ansi(&mut out, &theme, &solution).unwrap();
+
+    let wall = "\x1b[48;2;1;1;1m\x1b[38;2;1;1;1m  ";
+    let walls = format!("{}\x1b[0m\n", wall.repeat(7));
+    let corridor = [
+        wall,
+        "\x1b[48;2;2;2;2m\x1b[38;2;1;1;1m  ",
+        "\x1b[48;2;3;3;3m\x1b[38;2;1;1;1m  ",
+        "\x1b[48;2;5;5;5m\x1b[38;2;1;1;1mS ",
+        // Artifacts keep their color on the solution
+        "\x1b[48;2;4;4;4m\x1b[38;2;1;1;1m  ",
+        "\x1b[48;2;5;5;5m\x1b[38;2;1;1;1m  ",
+        "\x1b[48;2;5;5;5m\x1b[38;2;1;1;1mE ",
+    ]
+    .concat();
+    let expected = format!(
+        "{}{}\x1b[0m\n{}",
+        walls.repeat(3),
+        corridor,
+        walls.repeat(3)
+    );
+    assert_eq!(String::from_utf8(out).unwrap(), expected);
+}
+
+fn dot(maze: &Maze) -> String {
+    let mut out = Vec::new();
+    maze.write_dot(&mut out).unwrap();
+    String::from_utf8(out).unwrap()
+}
+
+#[test]
+fn dot_output_is_the_same_every_time() {
+    let maze = maze(8);
+    let first = dot(&maze);
+    assert_eq!(dot(&maze), first);
+    assert_eq!(dot(&maze.clone()), first);
+    let path = std::env::temp_dir().join(format!("mazegen-export-{}.dot", std::process::id()));
+    maze.export_to_dot(path.to_str().unwrap()).unwrap();
+    let written = std::fs::read_to_string(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(written, first);
+}
+
+#[test]
+fn dot_nodes_and_edges_are_sorted() {
+    let dot = dot(&maze(9));
+    let ids = |line: &str| -> Vec<usize> {
+        line.trim()
+            .split(|c: char| !c.is_ascii_digit())
+            .filter(|part| !part.is_empty())
+            .take(2)
+            .map(|id| id.parse().unwrap())
+            .collect()
+    };
+    let nodes: Vec<usize> = dot
+        .lines()
+        .filter(|line| !line.contains(" -- "))
+        .filter(|line| {
+            // Not the `node [...]` defaults
+            line.trim_start()
+                .strip_prefix('n')
+                .is_some_and(|rest| rest.starts_with(|c: char| c.is_ascii_digit()))
+        })
+        .map(|line| ids(line)[0])
+        .collect();
+    assert!(nodes.is_sorted());
+    let edges: Vec<Vec<usize>> = dot
+        .lines()
+        .filter(|line| line.contains(" -- "))
+        .map(ids)
+        .collect();
+    assert!(edges.is_sorted());
+    assert!(dot.contains("label=\"Start\""));
+    assert!(dot.contains("label=\"Exit\""));
+}
+
+#[test]
+fn solution_is_drawn_after_the_walls() {
+    let maze = maze(10);
+    for crisp in [false, true] {
+        let options = SvgOptions {
+            with_solution: SolutionType::ShortestPath,
+            crisp,
+            ..SvgOptions::default()
+        };
+        let svg = svg(&maze, &options);
+        let wall = "fill=\"#222222\"";
+        let lines: Vec<&str> = svg.lines().collect();
+        let last_wall = lines
+            .iter()
+            .rposition(|line| {
+                (line.contains("<rect ") || line.contains("<path ")) && line.contains(wall)
+            })
+            .unwrap();
+        let solution = lines
+            .iter()
+            .position(|line| line.contains("<polyline "))
+            .unwrap();
+        assert!(last_wall < solution, "crisp {}", crisp);
+    }
+}
+
+/// The kind of element on each line, like `rect` or `/g`
+fn elements(svg: &str) -> Vec<&str> {
+    svg.lines()
+        .filter_map(|line| line.trim().strip_prefix('<'))
+        .map(|line| line.split([' ', '>']).next().unwrap())
+        .collect()
+}
+
+#[test]
+fn overlays_come_after_the_maze() {
+    let mut maze = maze(11);
+    maze.place_labels(&[('A', 1)], 0.2, &mut rand::rng());
+    for with_solution in [
+        SolutionType::ShortestPath,
+        SolutionType::MinimumSpanningTree,
+    ] {
+        let options = SvgOptions {
+            with_solution,
+            ..SvgOptions::default()
+        };
+        let svg = svg(&maze, &options);
+        let elements = elements(&svg);
+        let overlay = svg
+            .lines()
+            .filter(|line| line.trim().starts_with('<'))
+            .position(|line| line.contains("class=\"overlay\""))
+            .unwrap();
+        // The walls, artifacts and labels all come before the overlay
+        assert!(elements[..overlay].contains(&"text"));
+        for kind in ["rect", "circle", "use", "text"] {
+            if let Some(last) = elements.iter().rposition(|&element| element == kind) {
+                assert!(last < overlay, "{} after the overlay", kind);
+            }
+        }
+        // Which holds nothing but lines and is the last group
+        let end = overlay + elements[overlay..].iter().position(|&e| e == "/g").unwrap();
+        assert!(elements[overlay + 1..end].iter().all(|&e| e == "polyline"));
+        assert!(!elements[end + 1..].contains(&"g"));
+        for line in svg.lines().filter(|line| line.contains("<polyline ")) {
+            assert!(line.contains("stroke-linejoin=\"round\""));
+            assert!(line.contains("stroke-linecap=\"round\""));
+        }
+    }
+}
+
+#[test]
+fn the_solution_draws_itself_over_its_length() {
+    let maze = maze(12);
+    let options = SvgOptions {
+        with_solution: SolutionType::ShortestPath,
+        animation: SvgAnimation::SolutionDraw { duration_secs: 5.0 },
+        ..SvgOptions::default()
+    };
+    let svg = svg(&maze, &options);
+    assert!(svg.contains("@keyframes draw-solution { to { stroke-dashoffset: 0; } }"));
+    // Every step of the solution goes one cell across
+    let length = (maze.shortest_path().unwrap().len() - 1).to_string();
+    let line = svg
+        .lines()
+        .find(|line| line.contains("<polyline "))
+        .unwrap();
+    assert_eq!(attribute(line, "stroke-dasharray"), Some(length.as_str()));
+    assert_eq!(attribute(line, "stroke-dashoffset"), Some(length.as_str()));
+    assert!(line.contains("animation: draw-solution 5s linear 0s forwards"));
+}
+
+#[test]
+fn svg_is_static_by_default() {
+    let maze = maze(12);
+    let options = SvgOptions {
+        with_solution: SolutionType::ShortestPath,
+        ..SvgOptions::default()
+    };
+    let svg = svg(&maze, &options);
+    assert!(!svg.contains("@keyframes"));
+    assert!(!svg.contains("stroke-dashoffset"));
+}
+
+#[cfg(feature = "image")]
+#[test]
+fn generation_gifs_have_a_frame_per_step() {
+    use image::AnimationDecoder;
+    use image::codecs::gif::GifDecoder;
+    use mazegen::MAX_GIF_FRAMES;
+    use rand::SeedableRng;
+    use rand::rngs::StdRng;
+
+    let blank = Maze::new(63, 31, 3, ExitLocation::Right);
+    let mut maze = blank.clone();
+    let mut events = Vec::new();
+    maze.generate_observed(&mut StdRng::seed_from_u64(1), |event| events.push(event));
+    let path = std::env::temp_dir().join(format!("mazegen-export-{}.gif", std::process::id()));
+    let frames = |frame_every| {
+        blank
+            .export_generation_gif(path.to_str().unwrap(), &events, frame_every, 2)
+            .unwrap();
+        let gif = std::io::Cursor::new(std::fs::read(&path).unwrap());
+        GifDecoder::new(gif)
+            .unwrap()
+            .into_frames()
+            .collect_frames()
+            .unwrap()
+    };
+    // The blank maze and one frame for every 25 events
+    let every_25 = frames(25);
+    assert_eq!(every_25.len(), 1 + events.len().div_ceil(25));
+    for frame in &every_25 {
+        assert_eq!(frame.buffer().dimensions(), (126, 62));
+    }
+    // Too many frames are merged
+    assert!(events.len() >= MAX_GIF_FRAMES);
+    let every_one = frames(1);
+    assert!(every_one.len() > every_25.len());
+    assert!(every_one.len() <= MAX_GIF_FRAMES);
+    std::fs::remove_file(&path).unwrap();
+}
+
+/// The cubes of the OpenSCAD script of `maze`, with their translation
+fn scad_cubes(maze: &Maze, wall_height_mm: f32, cell_mm: f32, base_mm: f32) -> Vec<String> {
+    let mut out = Vec::new();
+    maze.write_scad(&mut out, wall_height_mm, cell_mm, base_mm)
+        .unwrap();
+    String::from_utf8(out)
+        .unwrap()
+        .lines()
+        .filter(|line| line.contains("cube("))
+        .map(|line| line.trim().to_string())
+        .collect()
+}
+
+#[test]
+fn scad_has_a_cube_per_run_of_walls() {
+    let maze = maze(13);
+    let mut runs = 0;
+    for y in 0..maze.height() {
+        for x in 0..maze.width() {
+            if maze.get(x, y) == CellType::Wall && (x == 0 || maze.get(x - 1, y) != CellType::Wall)
+            {
+                runs += 1;
+            }
+        }
+    }
+    // And one for the base plate
+    assert_eq!(scad_cubes(&maze, 10.0, 2.0, 1.5).len(), runs + 1);
+}
+
+#[test]
+fn scad_is_sized_in_millimeters() {
+    let mut maze = Maze::new(7, 7, 3, ExitLocation::Right);
+    for x in 1..7 {
+        maze.set(x, 3, CellType::Path);
+    }
+    let cubes = scad_cubes(&maze, 10.0, 2.0, 1.5);
+    assert_eq!(cubes.len(), 8);
+    assert_eq!(cubes[0], "translate([0, 0, 0]) cube([14, 14, 1.5]);");
+    // The top row is at the far end of the y axis
+    assert_eq!(cubes[1], "translate([0, 12, 1.5]) cube([14, 2, 10]);");
+    // The row with the corridor is a single wall cell at its start
+    assert_eq!(cubes[4], "translate([0, 6, 1.5]) cube([2, 2, 10]);");
+    assert_eq!(cubes[7], "translate([0, 0, 1.5]) cube([14, 2, 10]);");
+    let doubled = scad_cubes(&maze, 20.0, 4.0, 3.0);
+    assert_eq!(doubled[0], "translate([0, 0, 0]) cube([28, 28, 3]);");
+    assert_eq!(doubled[1], "translate([0, 24, 3]) cube([28, 4, 20]);");
+}
+
+#[test]
+fn stl_has_twelve_triangles_per_cube() {
+    let maze = maze(13);
+    let path = std::env::temp_dir().join(format!("mazegen-export-{}.stl", std::process::id()));
+    maze.export_to_stl(path.to_str().unwrap(), 10.0, 2.0, 1.5)
+        .unwrap();
+    let stl = std::fs::read(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    let triangles = 12 * scad_cubes(&maze, 10.0, 2.0, 1.5).len();
+    assert_eq!(
+        u32::from_le_bytes(stl[80..84].try_into().unwrap()) as usize,
+        triangles
+    );
+    // A header, the count, and a normal, three corners and two spare bytes
+    // per triangle
+    assert_eq!(stl.len(), 84 + triangles * 50);
+}
+
+#[test]
+fn models_need_positive_sizes() {
+    let maze = maze(13);
+    for (wall_height_mm, cell_mm, base_mm) in
+        [(0.0, 2.0, 1.5), (10.0, -1.0, 1.5), (10.0, 2.0, f32::NAN)]
+    {
+        assert!(matches!(
+            maze.write_scad(&mut Vec::new(), wall_height_mm, cell_mm, base_mm),
+            Err(MazeError::InvalidOption { .. })
+        ));
+    }
+}
+
+/// Sides of wall cells that face an open cell or the outside
+fn wall_sides(maze: &Maze) -> usize {
+    let is_wall = |x: isize, y: isize| {
+        x >= 0
+            && y >= 0
+            && (x as usize) < maze.width()
+            && (y as usize) < maze.height()
+            && maze.get(x as usize, y as usize) == CellType::Wall
+    };
+    maze.iter_cells()
+        .filter(|&(_, cell)| cell == CellType::Wall)
+        .map(|(pos, _)| {
+            let (x, y) = (pos.x as isize, pos.y as isize);
+            [(x, y - 1), (x + 1, y), (x, y + 1), (x - 1, y)]
+                .into_iter()
+                .filter(|&(x, y)| !is_wall(x, y))
+                .count()
+        })
+        .sum()
+}
+
+#[test]
+fn wall_outlines_are_closed_and_cover_every_side() {
+    for seed in 14..17 {
+        let maze = maze(seed);
+        let outlines = maze.wall_outlines();
+        let mut length = 0;
+        for outline in &outlines {
+            assert!(outline.len() >= 4);
+            // Back to the first point along the grid
+            for (i, &(x, y)) in outline.iter().enumerate() {
+                let (next_x, next_y) = outline[(i + 1) % outline.len()];
+                assert!(x == next_x || y == next_y);
+                length += x.abs_diff(next_x) + y.abs_diff(next_y);
+            }
+        }
+        assert_eq!(length, wall_sides(&maze), "seed {}", seed);
+    }
+}
+
+#[test]
+fn wall_outline_of_a_corridor() {
+    let mut maze = Maze::new(7, 7, 3, ExitLocation::Right);
+    for x in 1..7 {
+        maze.set(x, 3, CellType::Path);
+    }
+    // The corridor leads outside, so the walls around it are one piece
+    let outlines = maze.wall_outlines();
+    assert_eq!(outlines.len(), 1);
+    let mut corners = outlines[0].clone();
+    corners.sort();
+    assert_eq!(
+        corners,
+        [
+            (0, 0),
+            (0, 7),
+            (1, 3),
+            (1, 4),
+            (7, 0),
+            (7, 3),
+            (7, 4),
+            (7, 7)
+        ]
+    );
+    assert_eq!(wall_sides(&maze), 40);
+}
+
+#[test]
+fn laser_svg_has_a_closed_path_per_outline() {
+    let maze = maze(14);
+    let mut out = Vec::new();
+    maze.write_laser_svg(&mut out, 2.5).unwrap();
+    let svg = String::from_utf8(out).unwrap();
+    assert!(svg.starts_with(&format!(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{}mm\" height=\"{}mm\"",
+        2.5 * maze.width() as f32,
+        2.5 * maze.height() as f32
+    )));
+    let paths: Vec<&str> = svg
+        .lines()
+        .filter_map(|line| attribute(line, "d"))
+        .collect();
+    assert_eq!(paths.len(), maze.wall_outlines().len());
+    for d in paths {
+        assert!(d.starts_with("M ") && d.ends_with(" Z"), "{}", d);
+    }
+    assert!(svg.contains("stroke=\"red\" stroke-width=\"0.01\""));
+}
+
+/// The lines of the last overlay group
+fn overlay_lines(svg: &str) -> Vec<&str> {
+    let lines: Vec<&str> = svg.lines().collect();
+    let start = lines
+        .iter()
+        .rposition(|line| line.contains("class=\"overlay\""))
+        .unwrap();
+    let end = start
+        + lines[start..]
+            .iter()
+            .position(|line| line.trim() == "</g>")
+            .unwrap();
+    lines[start + 1..end].to_vec()
+}
+
+#[test]
+fn dots_mark_every_nth_cell() {
+    let maze = maze(15);
+    let length = maze.shortest_path().unwrap().len();
+    for every in 1..=5 {
+        let options = SvgOptions {
+            with_solution: SolutionType::ShortestPath,
+            solution_style: SolutionStyle::Dots { every },
+            ..SvgOptions::default()
+        };
+        let svg = svg(&maze, &options);
+        let dots = overlay_lines(&svg);
+        assert!(dots.iter().all(|line| line.contains("<circle ")));
+        assert_eq!(dots.len(), length.div_ceil(every), "every {}", every);
+        assert!(!svg.contains("<polyline "));
+    }
+}
+
+/// A way from the start going east, then turning south to the exit
+fn corner() -> Maze {
+    let mut maze = Maze::new(7, 7, 1, ExitLocation::Bottom);
+    maze.set(3, 3, CellType::Start);
+    maze.set(4, 3, CellType::Path);
+    for y in 3..6 {
+        maze.set(5, y, CellType::Path);
+    }
+    maze.set(5, 6, CellType::Exit);
+    maze
+}
+
+#[test]
+fn arrows_point_the_way() {
+    let maze = corner();
+    let path = maze.shortest_path().unwrap();
+    assert_eq!(path.len(), 6);
+    let markers = SolutionStyle::Arrows { every: 1 }.markers(&path);
+    let directions: Vec<Direction> = markers.iter().map(|&(_, direction)| direction).collect();
+    // The last one keeps the way it was entered
+    assert_eq!(
+        directions,
+        [
+            Direction::East,
+            Direction::East,
+            Direction::South,
+            Direction::South,
+            Direction::South,
+            Direction::South
+        ]
+    );
+    assert_eq!(SolutionStyle::Arrows { every: 2 }.markers(&path).len(), 3);
+    assert!(SolutionStyle::Line.markers(&path).is_empty());
+
+    let options = SvgOptions {
+        with_solution: SolutionType::ShortestPath,
+        solution_style: SolutionStyle::Arrows { every: 1 },
+        ..SvgOptions::default()
+    };
+    let svg = svg(&maze, &options);
+    let arrows = overlay_lines(&svg);
+    assert_eq!(arrows.len(), 6);
+    // Drawn pointing east and turned clockwise
+    assert!(arrows[1].contains("transform=\"translate(4.5 3.5) rotate(0)\""));
+    assert!(arrows[2].contains("transform=\"translate(5.5 3.5) rotate(90)\""));
+}
+
+#[test]
+fn solution_styles_parse_what_they_print() {
+    for style in [
+        SolutionStyle::Line,
+        SolutionStyle::Dots { every: 3 },
+        SolutionStyle::Arrows { every: 1 },
+    ] {
+        assert_eq!(style.to_string().parse(), Ok(style));
+    }
+    assert_eq!("dots".parse(), Ok(SolutionStyle::Dots { every: 1 }));
+    assert!("dots:0".parse::<SolutionStyle>().is_err());
+    assert!("stars:2".parse::<SolutionStyle>().is_err());
+}
+
+/// A corridor across the middle row with two pumpkins, a bat, a candy and
+/// a black cat, which has no icon of its own
+fn artifact_corridor() -> Maze {
+    let mut maze = Maze::new(11, 7, 1, ExitLocation::Right);
+    for x in 1..10 {
+        maze.set(x, 3, CellType::Path);
+    }
+    maze.set(5, 3, CellType::Start);
+    maze.set(10, 3, CellType::Exit);
+    for (x, cell) in [
+        (1, CellType::Pumpkin),
+        (2, CellType::Bat),
+        (3, CellType::Pumpkin),
+        (7, CellType::Candy),
+        (8, CellType::BlackCat),
+    ] {
+        maze.set(x, 3, cell);
+    }
+    maze
+}
+
+/// The ids of the symbols in the defs block
+fn symbols(svg: &str) -> Vec<&str> {
+    svg.lines()
+        .filter(|line| line.trim().starts_with("<symbol "))
+        .map(|line| attribute(line, "id").unwrap())
+        .collect()
+}
+
+#[test]
+fn icons_are_defined_once_and_used_by_each_artifact() {
+    let maze = artifact_corridor();
+    let svg = svg(&maze, &SvgOptions::default());
+    assert_eq!(symbols(&svg), ["icon-candy", "icon-bat", "icon-pumpkin"]);
+    for (x, id) in [(1, "pumpkin"), (2, "bat"), (3, "pumpkin"), (7, "candy")] {
+        let usage = format!("<use href=\"#icon-{}\" x=\"{}\" y=\"3\"", id, x);
+        assert!(svg.contains(&usage), "{}", usage);
+    }
+    assert_eq!(svg.matches("<use href=").count(), 4);
+    // Without an icon it's a circle
+    assert!(svg.contains("<circle cx=\"8.5\" cy=\"3.5\" r=\"0.4\""));
+    assert!(svg.contains("<title>Black Cat</title>"));
+}
+
+#[test]
+fn icons_can_be_replaced() {
+    let maze = artifact_corridor();
+    let shapes = "<rect width=\"10\" height=\"10\" fill=\"black\" />";
+    let options = SvgOptions {
+        icons: HashMap::from([
+            (CellType::BlackCat, shapes.to_string()),
+            (CellType::Bat, shapes.to_string()),
+        ]),
+        ..SvgOptions::default()
+    };
+    let svg = svg(&maze, &options);
+    assert!(svg.contains(&format!(
+        "<symbol id=\"icon-black-cat\" viewBox=\"0 0 10 10\">{}</symbol>",
+        shapes
+    )));
+    assert!(svg.contains(&format!(
+        "<symbol id=\"icon-bat\" viewBox=\"0 0 10 10\">{}</symbol>",
+        shapes
+    )));
+    assert!(svg.contains("<use href=\"#icon-black-cat\" x=\"8\" y=\"3\""));
+    assert!(!svg.contains("<circle cx=\"8.5\""));
+}
+
+/// The fill of the circle drawn in the cell at `x` of the corridor
+fn circle_fill(svg: &str, x: usize) -> &str {
+    let circle = format!("<circle cx=\"{}.5\" cy=\"3.5\" r=\"0.4\"", x);
+    let line = svg.lines().find(|line| line.contains(&circle)).unwrap();
+    attribute(line, "fill").unwrap()
+}
+
+#[test]
+fn artifacts_without_icons_can_have_colors_of_their_own() {
+    let mut maze = artifact_corridor();
+    maze.set(4, 3, CellType::GummyBears);
+    maze.set(6, 3, CellType::Zombie);
+    let svg_default = svg(&maze, &SvgOptions::default());
+    assert_eq!(circle_fill(&svg_default, 4), "#22dd11");
+    assert_eq!(circle_fill(&svg_default, 6), "#ee4433");
+    assert_eq!(circle_fill(&svg_default, 8), "#ee4433");
+
+    let options = SvgOptions {
+        cell_colors: HashMap::from([
+            (CellType::GummyBears, [1, 2, 3]),
+            (CellType::BlackCat, [4, 5, 6]),
+            (CellType::Candy, [7, 8, 9]),
+        ]),
+        ..SvgOptions::default()
+    };
+    let svg = svg(&maze, &options);
+    assert_eq!(circle_fill(&svg, 4), "#010203");
+    // The others keep the color of the theme
+    assert_eq!(circle_fill(&svg, 6), "#ee4433");
+    assert_eq!(circle_fill(&svg, 8), "#040506");
+    // Icons have colors of their own
+    assert!(!svg.contains("#070809"));
+}
+
+#[test]
+fn no_icons_without_artifacts() {
+    let maze = artifact_corridor();
+    let options = SvgOptions {
+        show_artifacts: false,
+        ..SvgOptions::default()
+    };
+    let svg = svg(&maze, &options);
+    assert!(!svg.contains("<defs>"));
+    assert!(!svg.contains("<use "));
+}
+
+/// Width and height of the image
+fn svg_size(svg: &str) -> (f32, f32) {
+    let tag = svg.lines().next().unwrap();
+    let size = |name| attribute(tag, name).unwrap().parse().unwrap();
+    (size("width"), size("height"))
+}
+
+/// The text of each entry of the legend
+fn legend_entries(svg: &str) -> Vec<&str> {
+    let lines: Vec<&str> = svg.lines().collect();
+    let Some(start) = lines
+        .iter()
+        .position(|line| line.contains("class=\"legend\""))
+    else {
+        return Vec::new();
+    };
+    lines[start..]
+        .iter()
+        .take_while(|line| line.trim() != "</g>")
+        .filter_map(|line| line.split_once("\">")?.1.strip_suffix("</text>"))
+        .collect()
+}
+
+#[test]
+fn the_legend_lists_the_artifacts_present() {
+    let maze = artifact_corridor();
+    let plain = svg(&maze, &SvgOptions::default());
+    assert_eq!(svg_size(&plain), (110.0, 70.0));
+    assert!(legend_entries(&plain).is_empty());
+    let options = SvgOptions {
+        include_legend: true,
+        ..SvgOptions::default()
+    };
+    let svg = svg(&maze, &options);
+    let expected: Vec<String> = [
+        CellType::Candy,
+        CellType::BlackCat,
+        CellType::Bat,
+        CellType::Pumpkin,
+    ]
+    .into_iter()
+    .map(|cell| format!("{} ({:+})", cell, maze.weight_table().get(cell)))
+    .collect();
+    assert_eq!(legend_entries(&svg), expected);
+    // A row of one and a half cells for each, and a cell to spare, below
+    // the maze. The column is wider than the maze.
+    assert_eq!(svg_size(&svg), (120.0, 70.0 + 70.0));
+}
+
+#[test]
+fn the_legend_can_go_to_the_right() {
+    let maze = artifact_corridor();
+    let options = SvgOptions {
+        include_legend: true,
+        legend_position: LegendPosition::Right,
+        ..SvgOptions::default()
+    };
+    let svg = svg(&maze, &options);
+    assert_eq!(legend_entries(&svg).len(), 4);
+    assert_eq!(svg_size(&svg), (110.0 + 120.0, 70.0));
+}
+
+#[test]
+fn no_legend_without_artifacts() {
+    let mut maze = Maze::new(11, 7, 1, ExitLocation::Right);
+    for x in 1..11 {
+        maze.set(x, 3, CellType::Path);
+    }
+    let options = SvgOptions {
+        include_legend: true,
+        ..SvgOptions::default()
+    };
+    let svg = svg(&maze, &options);
+    assert!(legend_entries(&svg).is_empty());
+    assert_eq!(svg_size(&svg), (110.0, 70.0));
+}
+
+fn fogged(maze: &Maze, reveal: &RevealRule) -> String {
+    let mut out = Vec::new();
+    maze.write_svg_fogged(&mut out, 10.0, reveal).unwrap();
+    String::from_utf8(out).unwrap()
+}
+
+/// The fill of every cell covered by a rectangle a row high, like the runs
+/// of walls and open cells a fogged SVG is drawn with
+fn drawn_cells(svg: &str) -> HashMap<Pos, String> {
+    let mut cells = HashMap::new();
+    for line in svg.lines().map(str::trim) {
+        if !line.starts_with("<rect ") || attribute(line, "height") != Some("1") {
+            continue;
+        }
+        let coordinate = |name| attribute(line, name).unwrap().parse::<usize>().unwrap();
+        for x in coordinate("x")..coordinate("x") + coordinate("width") {
+            let fill = attribute(line, "fill").unwrap().to_string();
+            assert!(cells.insert(Pos::new(x, coordinate("y")), fill).is_none());
+        }
+    }
+    cells
+}
+
+/// The open cells drawn in the path color
+fn light_cells(svg: &str) -> HashSet<Pos> {
+    drawn_cells(svg)
+        .into_iter()
+        .filter(|(_, fill)| fill == "#eeeeee")
+        .map(|(pos, _)| pos)
+        .collect()
+}
+
+#[test]
+fn fog_hides_all_but_the_revealed_cells() {
+    let maze = maze(1);
+    for reveal in [
+        RevealRule::AroundStart { radius: 4 },
+        RevealRule::AroundPath { radius: 1 },
+    ] {
+        let svg = fogged(&maze, &reveal);
+        assert!(svg.contains("<rect width=\"100%\" height=\"100%\" fill=\"#999999\" />"));
+        let drawn = drawn_cells(&svg);
+        let revealed = maze.revealed_cells(&reveal);
+        assert_eq!(drawn.keys().copied().collect::<HashSet<_>>(), revealed);
+        let light = light_cells(&svg);
+        for (pos, cell) in maze.iter_cells() {
+            let open = TRAVERSABLE.contains(&cell);
+            assert_eq!(light.contains(&pos), open && revealed.contains(&pos));
+            // Walls around anything revealed are drawn with it
+            let next_to_revealed = (pos.x.saturating_sub(1)..=pos.x + 1).any(|x| {
+                (pos.y.saturating_sub(1)..=pos.y + 1)
+                    .any(|y| revealed.contains(&Pos::new(x, y)) && maze.get(x, y) != CellType::Wall)
+            });
+            if !open && next_to_revealed {
+                assert_eq!(drawn[&pos], "#222222", "{}", pos);
+            }
+        }
+    }
+}
+
+#[test]
+fn fog_lifts_along_the_passages() {
+    let maze = maze(2);
+    let from_start = maze.distance_map(maze.start_pos());
+    let light = light_cells(&fogged(&maze, &RevealRule::AroundStart { radius: 5 }));
+    for (pos, cell) in maze.iter_cells() {
+        if TRAVERSABLE.contains(&cell) {
+            let near = from_start[pos.y * maze.width() + pos.x].is_some_and(|steps| steps <= 5);
+            assert_eq!(light.contains(&pos), near, "{}", pos);
+        }
+    }
+
+    let solution: HashSet<Pos> = maze.shortest_path().unwrap().into_iter().collect();
+    let svg = fogged(&maze, &RevealRule::AroundPath { radius: 0 });
+    assert_eq!(light_cells(&svg), solution);
+}
+
+#[test]
+fn fog_reveals_exactly_the_cells_given() {
+    let maze = maze(3);
+    let start = maze.start_pos();
+    let cells = HashSet::from([start, Pos::new(1000, 1000)]);
+    let svg = fogged(&maze, &RevealRule::Explicit(cells));
+    assert_eq!(light_cells(&svg), HashSet::from([start]));
+}
+
+#[test]
+fn hidden_artifacts_arent_written() {
+    let maze = maze(4);
+    let reveal = RevealRule::AroundStart { radius: 3 };
+    let revealed = maze.revealed_cells(&reveal);
+    let (shown, hidden): (Vec<_>, Vec<_>) = maze
+        .artifacts()
+        .partition(|(pos, _)| revealed.contains(pos));
+    assert!(!hidden.is_empty());
+    let svg = fogged(&maze, &reveal);
+    let artifacts = svg.lines().filter(|line| line.contains("<title>")).count();
+    assert_eq!(artifacts, shown.len());
+}
+
+fn sized(width: usize, height: usize) -> Maze {
+    MazeBuilder::new()
+        .width(width)
+        .height(height)
+        .seed(1)
+        .build()
+        .unwrap()
+}
+
+/// The text of every coordinate label, column letters first
+fn labels(svg: &str) -> Vec<&str> {
+    svg.lines()
+        .map(str::trim)
+        .filter(|line| line.starts_with("<text "))
+        .map(|line| &line[line.find('>').unwrap() + 1..line.find("</text>").unwrap()])
+        .collect()
+}
+
+#[test]
+fn coordinates_label_each_column_and_row() {
+    let maze = sized(23, 15);
+    let options = SvgOptions {
+        scale: 20.0,
+        coordinates: true,
+        ..Default::default()
+    };
+    let plain = svg_size(&svg(
+        &maze,
+        &SvgOptions {
+            scale: 20.0,
+            ..Default::default()
+        },
+    ));
+    assert_eq!(plain, (460.0, 300.0));
+    let svg = svg(&maze, &options);
+    let columns: Vec<String> = "ABCDEFGHIJKLMNOPQRSTUVW"
+        .chars()
+        .map(String::from)
+        .collect();
+    let rows: Vec<String> = (1..=15).map(|row| row.to_string()).collect();
+    assert_eq!(labels(&svg), [columns, rows].concat());
+    assert!(svg.contains("font-size=\"0.8\""));
+    // The margin makes the image bigger
+    let (width, height) = svg_size(&svg);
+    assert!(width > plain.0 && height > plain.1);
+}
+
+#[test]
+fn coordinates_stay_readable_at_small_scales() {
+    let maze = sized(23, 15);
+    let options = SvgOptions {
+        scale: 2.0,
+        coordinates: true,
+        ..Default::default()
+    };
+    let svg = svg(&maze, &options);
+    // 9 pixels high, which takes five cells, so only every fifth is labeled
+    assert!(svg.contains("font-size=\"4.5\""));
+    assert_eq!(labels(&svg), ["A", "F", "K", "P", "U", "1", "6", "11"]);
+}
+
+#[test]
+fn no_coordinates_unless_asked() {
+    let svg = svg(&sized(23, 15), &SvgOptions::default());
+    assert!(labels(&svg).is_empty());
+    assert!(!svg.contains("class=\"coordinates\""));
+    assert!(!svg.contains("class=\"grid\""));
+}
+
+#[test]
+fn the_grid_has_a_line_every_few_cells() {
+    let maze = sized(23, 15);
+    for (every, lines) in [(1, 24 + 16), (5, 5 + 4)] {
+        let options = SvgOptions {
+            grid: Some(every),
+            ..Default::default()
+        };
+        let svg = svg(&maze, &options);
+        let grid = svg
+            .lines()
+            .find(|line| line.contains("class=\"grid\""))
+            .unwrap();
+        assert_eq!(attribute(grid, "d").unwrap().matches('M').count(), lines);
+        assert!(grid.contains("M0 15H23"));
+    }
+}
+
+#[test]
+fn text_with_coordinates_numbers_the_rows() {
+    let maze = sized(31, 11);
+    let solution = maze.shortest_path().unwrap();
+    let mut plain = Vec::new();
+    maze.write_text(&mut plain, &solution).unwrap();
+    let mut labeled = Vec::new();
+    maze.write_text_with_coordinates(&mut labeled, &solution)
+        .unwrap();
+    let (plain, labeled) = (
+        String::from_utf8(plain).unwrap(),
+        String::from_utf8(labeled).unwrap(),
+    );
+    let lines: Vec<&str> = labeled.lines().collect();
+    // Two lines of letters for the columns after Z
+    assert_eq!(lines[0], format!("   {}AAAAA", " ".repeat(26)));
+    assert_eq!(lines[1], "   ABCDEFGHIJKLMNOPQRSTUVWXYZABCDE");
+    for (y, (row, plain)) in lines[2..].iter().zip(plain.lines()).enumerate() {
+        assert_eq!(*row, format!("{:>2} {}", y + 1, plain));
+    }
+    assert_eq!(lines.len(), 2 + 11);
+}
+
+fn crisp(scale: f32) -> SvgOptions {
+    SvgOptions {
+        scale,
+        crisp: true,
+        ..Default::default()
+    }
+}
+
+/// The cells the runs `M{x} {y}h{n}v1h-{n}z` of a crisp wall path cover,
+/// failing on anything that doesn't start and end on whole cells
+fn crisp_wall_cells(d: &str) -> Vec<Pos> {
+    let mut cells = Vec::new();
+    for run in d.split('M').skip(1) {
+        let numbers: Vec<usize> = run
+            .split(|c: char| !c.is_ascii_digit() && c != '.')
+            .filter(|number| !number.is_empty())
+            .map(|number| number.parse().unwrap())
+            .collect();
+        let [x, y, length, 1, back] = numbers[..] else {
+            panic!("not a run of walls: M{}", run);
+        };
+        assert_eq!(length, back);
+        assert!(run.ends_with('z'));
+        cells.extend((x..x + length).map(|x| Pos::new(x, y)));
+    }
+    cells
+}
+
+#[test]
+fn crisp_svgs_scale_by_whole_pixels() {
+    let maze = maze(1);
+    let tiny = svg(&maze, &crisp(0.2));
+    let options = SvgOptions {
+        coordinates: true,
+        ..crisp(20.0)
+    };
+    let labeled = svg(&maze, &options);
+    let svg = svg(&maze, &crisp(10.4));
+    let tag = svg.lines().next().unwrap();
+    assert_eq!(svg_size(&svg), (310.0, 230.0));
+    assert_eq!(attribute(tag, "viewBox"), Some("0 0 31 23"));
+    assert!(svg.contains("  <g shape-rendering=\"crispEdges\" >"));
+    assert!(!svg.contains("transform=\"scale("));
+    // Never less than a pixel per cell
+    assert_eq!(svg_size(&tiny), (31.0, 23.0));
+
+    let group = labeled
+        .lines()
+        .find(|line| line.contains("crispEdges"))
+        .unwrap();
+    // The margin for the coordinates is whole cells too
+    assert_eq!(
+        group,
+        "  <g shape-rendering=\"crispEdges\" transform=\"translate(2 2)\" >"
+    );
+}
+
+#[test]
+fn crisp_walls_are_one_seamless_path() {
+    for seed in 0..3 {
+        let maze = maze(seed);
+        let svg = svg(&maze, &crisp(10.0));
+        let walls: Vec<&str> = svg
+            .lines()
+            .filter(|line| line.trim().starts_with("<path ") && line.contains("fill=\"#222222\""))
+            .collect();
+        assert_eq!(walls.len(), 1);
+        // Every wall cell covered exactly once, by whole cells that end up
+        // as whole pixels, so there is nothing to blend with the background
+        let mut covered = crisp_wall_cells(attribute(walls[0], "d").unwrap());
+        covered.sort();
+        let mut expected: Vec<Pos> = maze
+            .iter_cells()
+            .filter(|&(_, cell)| cell == CellType::Wall)
+            .map(|(pos, _)| pos)
+            .collect();
+        expected.sort();
+        assert_eq!(covered, expected);
+        assert!(
+            !svg.lines()
+                .any(|line| line.contains("<rect ") && line.contains("#222222"))
+        );
+    }
+}
+
+#[test]
+fn svgs_arent_crisp_by_default() {
+    let svg = svg(&maze(1), &SvgOptions::default());
+    assert!(!svg.contains("crispEdges"));
+    assert!(svg.contains("  <g transform=\"scale(10)\" >"));
+    assert_eq!(
+        attribute(svg.lines().next().unwrap(), "viewBox"),
+        Some("0 0 310 230")
+    );
+}
+
+#[test]
+fn the_collection_route_is_drawn_in_its_own_color() {
+    let maze = maze(5);
+    let route = maze.full_collection_route().unwrap();
+    let options = SvgOptions {
+        with_solution: SolutionType::FullCollection,
+        ..Default::default()
+    };
+    let svg = svg(&maze, &options);
+    let lines = overlay_lines(&svg);
+    assert_eq!(lines.len(), 1);
+    assert!(lines[0].contains("stroke=\"#8e44ad\""));
+    let points: Vec<String> = route
+        .path
+        .iter()
+        .map(|pos| format!("{},{}", pos.x as f32 + 0.5, pos.y as f32 + 0.5))
+        .collect();
+    assert_eq!(
+        attribute(lines[0], "points")
+            .unwrap()
+            .split_whitespace()
+            .collect::<Vec<_>>(),
+        points
+    );
+    // The shortest path isn't drawn along with it
+    assert!(!svg.contains("stroke=\"#1ca3a3\""));
+}
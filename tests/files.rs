@@ -0,0 +1,152 @@
+#![cfg(feature = "serde")]
+
+use mazegen::{CellType, Maze, MazeBuilder, MazeError, SvgOptions, WeightTable};
+
+fn maze(seed: u64) -> Maze {
+    MazeBuilder::new()
+        .width(31)
+        .height(23)
+        .artifacts(0.1)
+        .seed(seed)
+        .build()
+        .unwrap()
+}
+
+#[test]
+fn reloaded_mazes_have_the_same_solution() {
+    for seed in 0..5 {
+        let maze = maze(seed);
+        let path = std::env::temp_dir().join(format!(
+            "mazegen-files-{}-{}.json",
+            std::process::id(),
+            seed
+        ));
+        let filename = path.to_str().unwrap();
+        maze.save_json(filename).unwrap();
+        let loaded = Maze::load_json(filename).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert!(loaded == maze);
+        assert_eq!(loaded.room(), maze.room());
+        assert_eq!(loaded.exit_side(), maze.exit_side());
+        assert_eq!(loaded.shortest_path(), maze.shortest_path());
+    }
+}
+
+#[test]
+fn json_round_trips() {
+    let maze = maze(5);
+    let json = maze.to_json();
+    assert_eq!(Maze::from_json(&json).unwrap().to_json(), json);
+}
+
+#[test]
+fn newer_versions_are_reported() {
+    let json = maze(6)
+        .to_json()
+        .replacen("\"version\":1", "\"version\":2", 1);
+    assert!(matches!(
+        Maze::from_json(&json),
+        Err(MazeError::UnsupportedVersion {
+            version: 2,
+            supported: 1
+        })
+    ));
+    // Even if the rest can't be read by this release
+    assert!(matches!(
+        Maze::from_json("{\"version\": 99, \"shape\": \"hexagonal\"}"),
+        Err(MazeError::UnsupportedVersion { version: 99, .. })
+    ));
+}
+
+#[test]
+fn broken_files_fail() {
+    assert!(matches!(
+        Maze::from_json("{\"width\": 7}"),
+        Err(MazeError::Parse { .. })
+    ));
+    assert!(matches!(
+        Maze::from_json("not json"),
+        Err(MazeError::Parse { .. })
+    ));
+    assert!(matches!(
+        Maze::load_json("/nonexistent/maze.json"),
+        Err(MazeError::Io { path: Some(_), .. })
+    ));
+}
+
+#[test]
+fn impossible_sizes_fail() {
+    let json = maze(7).to_json();
+    let huge = json.replacen("\"width\":31", &format!("\"width\":{}", usize::MAX), 1);
+    assert!(matches!(
+        Maze::from_json(&huge),
+        Err(MazeError::InvalidDimensions { .. })
+    ));
+    let wide = json.replacen("\"corridor_width\":1", "\"corridor_width\":100", 1);
+    assert!(matches!(
+        Maze::from_json(&wide),
+        Err(MazeError::InvalidOption {
+            name: "corridor width",
+            ..
+        })
+    ));
+}
+
+#[test]
+fn weights_are_saved_with_the_maze() {
+    let mut maze = maze(8);
+    assert!(!maze.to_json().contains("\"weights\""));
+    let mut weights = WeightTable::new();
+    weights.set(CellType::Witch, 100);
+    weights.set(CellType::Candy, -20);
+    weights.set(CellType::Bat, 0);
+    maze.set_weight_table(weights.clone());
+    let json = maze.to_json();
+    assert!(json.contains("\"weights\":[[\"Candy\",-20],[\"Witch\",100],[\"Bat\",0]]"));
+    let loaded = Maze::from_json(&json).unwrap();
+    assert_eq!(loaded.weight_table(), &weights);
+    assert_eq!(loaded.to_json(), json);
+}
+
+#[test]
+fn svg_metadata_generates_the_same_maze_again() {
+    let params = MazeBuilder::new()
+        .width(31)
+        .height(23)
+        .braid(0.3)
+        .artifacts(0.1)
+        .seed(42);
+    let maze = params.build().unwrap();
+    let options = SvgOptions {
+        title: Some("Spooky <maze>".to_string()),
+        params: Some(params),
+        ..SvgOptions::default()
+    };
+    let mut out = Vec::new();
+    maze.write_svg(&mut out, &options).unwrap();
+    let svg = String::from_utf8(out).unwrap();
+    let description = format!("31×23 maze, seed 42, difficulty {:.0}", maze.difficulty());
+    assert!(svg.contains(&format!(" role=\"img\" aria-label=\"{}\">", description)));
+    assert!(svg.contains("<title>Spooky &lt;maze&gt;</title>"));
+    assert!(svg.contains(&format!("<desc>{}</desc>", description)));
+
+    let params = Maze::params_from_svg_metadata(svg.as_bytes()).unwrap();
+    let again = params.build().unwrap();
+    assert_eq!(again.fingerprint(), maze.fingerprint());
+    assert!(again == maze);
+}
+
+#[test]
+fn svg_without_metadata_has_no_params() {
+    let mut out = Vec::new();
+    maze(1).write_svg(&mut out, &SvgOptions::default()).unwrap();
+    assert!(matches!(
+        Maze::params_from_svg_metadata(out.as_slice()),
+        Err(MazeError::Parse { line: 1, .. })
+    ));
+    let unclosed = "<svg>\n<metadata><![CDATA[{\"seed\":1}\n</svg>";
+    assert!(matches!(
+        Maze::params_from_svg_metadata(unclosed.as_bytes()),
+        Err(MazeError::Parse { line: 2, .. })
+    ));
+}
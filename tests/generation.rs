@@ -0,0 +1,416 @@
+use mazegen::{CellType, ExitLocation, Hand, Maze, MazeBuilder, MazeEvent, Solver, TRAVERSABLE};
+use rand::SeedableRng;
+use rand::rngs::StdRng;
+
+const SEEDS: u64 = 20;
+
+fn average_difficulty(builder: MazeBuilder) -> f32 {
+    let total: f32 = (0..SEEDS)
+        .map(|seed| builder.clone().seed(seed).build().unwrap().difficulty())
+        .sum();
+    total / SEEDS as f32
+}
+
+#[test]
+fn difficulty_is_on_its_scale() {
+    for seed in 0..SEEDS {
+        let maze = MazeBuilder::new()
+            .artifacts(0.3)
+            .seed(seed)
+            .build()
+            .unwrap();
+        assert!((0.0..=100.0).contains(&maze.difficulty()));
+    }
+}
+
+#[test]
+fn difficulty_falls_with_braid() {
+    let builder = MazeBuilder::new().width(31).height(31);
+    let averages: Vec<f32> = [0.0, 0.5, 1.0]
+        .into_iter()
+        .map(|braid| average_difficulty(builder.clone().braid(braid)))
+        .collect();
+    assert!(
+        averages.windows(2).all(|pair| pair[0] > pair[1]),
+        "{:?}",
+        averages
+    );
+}
+
+#[test]
+fn difficulty_rises_with_size() {
+    let averages: Vec<f32> = [11, 23, 43]
+        .into_iter()
+        .map(|size| average_difficulty(MazeBuilder::new().width(size).height(size).braid(0.0)))
+        .collect();
+    assert!(
+        averages.windows(2).all(|pair| pair[0] < pair[1]),
+        "{:?}",
+        averages
+    );
+}
+
+#[test]
+fn generate_with_difficulty_is_seeded() {
+    let generate = || {
+        let mut maze = Maze::new(31, 31, 3, ExitLocation::Right);
+        let score = maze.generate_with_difficulty_with_rng(
+            40.0,
+            2.0,
+            20,
+            0.1,
+            &mut StdRng::seed_from_u64(5),
+        );
+        (maze, score)
+    };
+    let (maze, score) = generate();
+    let (again, again_score) = generate();
+    assert!(maze == again);
+    assert_eq!(score, again_score);
+    assert_eq!(score, maze.difficulty());
+}
+
+#[test]
+fn builder_difficulty_keeps_the_closest_maze() {
+    let target = 50.0;
+    let closest = |tolerance, attempts| {
+        let maze = MazeBuilder::new()
+            .difficulty(target)
+            .difficulty_tolerance(tolerance, attempts)
+            .seed(9)
+            .build()
+            .unwrap();
+        (maze.difficulty() - target).abs()
+    };
+    // Without any tolerance all attempts are used, and more of them can only
+    // get closer
+    assert!(closest(0.0, 30) <= closest(0.0, 1));
+    assert!(closest(100.0, 30) == closest(0.0, 1));
+}
+
+#[test]
+fn invalid_difficulty_tolerance() {
+    assert!(
+        MazeBuilder::new()
+            .difficulty(50.0)
+            .difficulty_tolerance(-1.0, 10)
+            .build()
+            .is_err()
+    );
+    assert!(
+        MazeBuilder::new()
+            .difficulty(50.0)
+            .difficulty_tolerance(5.0, 0)
+            .build()
+            .is_err()
+    );
+}
+
+#[test]
+fn replaying_generation_events_reproduces_the_maze() {
+    for seed in 0..5 {
+        let mut maze = Maze::new(31, 23, 3, ExitLocation::Right);
+        let mut rng = StdRng::seed_from_u64(seed);
+        let mut events = Vec::new();
+        maze.generate_observed(&mut rng, |event| events.push(event));
+        maze.place_artifacts_observed(0.2, &mut rng, |event| events.push(event));
+
+        let mut replayed = Maze::new(31, 23, 3, ExitLocation::Right);
+        assert!(replayed.cells().iter().all(|&cell| cell == CellType::Wall));
+        for &event in &events {
+            replayed.apply(event);
+        }
+        assert!(replayed == maze);
+        assert!(
+            events
+                .iter()
+                .any(|event| matches!(event, MazeEvent::PlaceArtifact(..)))
+        );
+    }
+}
+
+#[test]
+fn observing_doesnt_change_generation() {
+    let mut observed = Maze::new(31, 23, 3, ExitLocation::Right);
+    observed.generate_observed(&mut StdRng::seed_from_u64(4), |_| {});
+    let mut plain = Maze::new(31, 23, 3, ExitLocation::Right);
+    plain.generate_with_rng(&mut StdRng::seed_from_u64(4));
+    assert!(observed == plain);
+}
+
+#[test]
+fn solver_events_follow_the_search() {
+    let maze = MazeBuilder::new()
+        .width(31)
+        .height(23)
+        .seed(2)
+        .build()
+        .unwrap();
+    for solver in [Solver::Bfs, Solver::AStar] {
+        let mut events = Vec::new();
+        let trace = maze.solve_observed(solver, |event| events.push(event));
+        let visits: Vec<_> = events
+            .iter()
+            .filter_map(|&event| match event {
+                MazeEvent::Visit(pos) => Some(pos),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(visits, trace.order);
+        assert!(events.first() == Some(&MazeEvent::Enqueue(maze.start_pos())));
+        assert!(events.last() == Some(&MazeEvent::FoundExit(maze.exit_pos().unwrap())));
+        assert_eq!(
+            trace.path.map(|path| path.len()),
+            maze.shortest_path().map(|path| path.len())
+        );
+    }
+}
+
+fn seeded(seed: u64) -> Maze {
+    let mut rng = StdRng::seed_from_u64(seed);
+    let mut maze = Maze::new(31, 23, 3, ExitLocation::Random);
+    maze.generate_with_rng(&mut rng);
+    maze.place_artifacts_with_rng(0.2, &mut rng);
+    maze
+}
+
+#[test]
+fn the_same_rng_makes_the_same_maze() {
+    for seed in 0..5 {
+        let maze = seeded(seed);
+        assert!(maze.artifacts().count() > 0);
+        assert!(seeded(seed) == maze);
+        assert_eq!(seeded(seed).exit_pos(), maze.exit_pos());
+        assert!(seeded(seed + 100) != maze);
+    }
+}
+
+#[test]
+fn artifacts_follow_the_rng() {
+    let mut maze = Maze::new(31, 23, 3, ExitLocation::Right);
+    maze.generate_with_rng(&mut StdRng::seed_from_u64(1));
+    let place = |seed| {
+        let mut maze = maze.clone();
+        maze.place_artifacts_with_rng(0.3, &mut StdRng::seed_from_u64(seed));
+        maze.artifacts().collect::<Vec<_>>()
+    };
+    assert_eq!(place(2), place(2));
+    assert_ne!(place(2), place(3));
+}
+
+/// Whether every open cell is part of a square of `width` x `width` open
+/// cells
+fn corridors_are_wide(maze: &Maze, width: usize) -> bool {
+    let (columns, rows) = maze.get_size();
+    let open = |x: usize, y: usize| TRAVERSABLE.contains(&maze.get(x, y));
+    let in_square = |x: usize, y: usize| {
+        (x.saturating_sub(width - 1)..=x.min(columns - width)).any(|left| {
+            (y.saturating_sub(width - 1)..=y.min(rows - width))
+                .any(|top| (left..left + width).all(|x| (top..top + width).all(|y| open(x, y))))
+        })
+    };
+    (0..rows).all(|y| (0..columns).all(|x| !open(x, y) || in_square(x, y)))
+}
+
+#[test]
+fn wide_corridors_are_wide_everywhere() {
+    for (corridor_width, braid) in [(2, 0.0), (2, 0.5), (3, 1.0)] {
+        for seed in 0..5 {
+            let maze = MazeBuilder::new()
+                .width(31)
+                .height(23)
+                .corridor_width(corridor_width)
+                .braid(braid)
+                .seed(seed)
+                .build()
+                .unwrap();
+            let (width, height) = maze.get_size();
+            assert_eq!((width - 1) % (corridor_width + 1), 0);
+            assert_eq!((height - 1) % (corridor_width + 1), 0);
+            assert!(corridors_are_wide(&maze, corridor_width), "seed {}", seed);
+            assert!(maze.shortest_path().is_some());
+        }
+    }
+}
+
+#[test]
+fn narrow_corridors_arent_wide() {
+    let maze = MazeBuilder::new()
+        .width(31)
+        .height(23)
+        .seed(1)
+        .build()
+        .unwrap();
+    assert!(corridors_are_wide(&maze, 1));
+    assert!(!corridors_are_wide(&maze, 2));
+}
+
+/// The longest walk between any two open cells, searched from all of them
+fn diameter(maze: &Maze) -> usize {
+    maze.iter_cells()
+        .filter(|(_, cell)| TRAVERSABLE.contains(cell))
+        .filter_map(|(pos, _)| maze.distance_map(pos).into_iter().flatten().max())
+        .max()
+        .unwrap()
+}
+
+#[test]
+fn longest_shortest_path_finds_the_longest_walk() {
+    for (braid, seed) in [(0.0, 1), (0.0, 2), (0.0, 3), (0.3, 4), (1.0, 5)] {
+        let maze = MazeBuilder::new()
+            .width(31)
+            .height(23)
+            .braid(braid)
+            .seed(seed)
+            .build()
+            .unwrap();
+        let (from, to, steps) = maze.longest_shortest_path().unwrap();
+        assert_eq!(
+            maze.distance_map(from)[to.y * maze.width() + to.x],
+            Some(steps as usize)
+        );
+        if maze.is_perfect() {
+            assert_eq!(steps as usize, diameter(&maze), "seed {}", seed);
+        } else {
+            assert!(steps as usize <= diameter(&maze));
+        }
+    }
+}
+
+#[test]
+fn the_farthest_exit_is_at_least_as_far_as_any_side() {
+    for seed in 0..5 {
+        let solution = |exit: ExitLocation| {
+            MazeBuilder::new()
+                .width(31)
+                .height(23)
+                .exit(exit)
+                .seed(seed)
+                .build()
+                .unwrap()
+                .shortest_path()
+                .unwrap()
+                .len()
+        };
+        let farthest = solution(ExitLocation::Farthest);
+        for side in [
+            ExitLocation::Left,
+            ExitLocation::Right,
+            ExitLocation::Top,
+            ExitLocation::Bottom,
+        ] {
+            let length = solution(side.clone());
+            assert!(
+                farthest >= length,
+                "seed {}: {} < {:?} {}",
+                seed,
+                farthest,
+                side,
+                length
+            );
+        }
+    }
+}
+
+/// Generates with `generate_observed` and `place_artifacts_observed` in one
+/// go, returning the maze and the events
+fn generated_at_once(exit: ExitLocation, seed: u64) -> (Maze, Vec<MazeEvent>) {
+    let mut maze = Maze::new(31, 23, 3, exit);
+    let mut rng = StdRng::seed_from_u64(seed);
+    let mut events = Vec::new();
+    maze.generate_observed(&mut rng, |event| events.push(event));
+    maze.place_artifacts_observed(0.2, &mut rng, |event| events.push(event));
+    (maze, events)
+}
+
+#[test]
+fn generation_steps_end_where_generating_at_once_does() {
+    for exit in [ExitLocation::Right, ExitLocation::Farthest] {
+        for seed in 0..3 {
+            let (expected, expected_events) = generated_at_once(exit.clone(), seed);
+            let mut maze = Maze::new(31, 23, 3, exit.clone());
+            let mut rng = StdRng::seed_from_u64(seed);
+            let events: Vec<MazeEvent> = maze.generation_steps(0.2, &mut rng).collect();
+            assert!(maze == expected);
+            assert!(events == expected_events);
+
+            let mut replayed = Maze::new(31, 23, 3, exit.clone());
+            for &event in &events {
+                replayed.apply(event);
+            }
+            assert!(replayed == maze);
+        }
+    }
+}
+
+#[test]
+fn generation_steps_can_be_taken_a_few_at_a_time() {
+    let (expected, expected_events) = generated_at_once(ExitLocation::Right, 4);
+    let mut maze = Maze::new(31, 23, 3, ExitLocation::Right);
+    let mut rng = StdRng::seed_from_u64(4);
+    let mut steps = maze.generation_steps(0.2, &mut rng);
+    let mut events = Vec::new();
+    while let Some(event) = steps.next() {
+        events.push(event);
+        // A frame's worth, then the rest
+        events.extend(steps.by_ref().take(6));
+    }
+    assert!(events == expected_events);
+    assert!(maze == expected);
+}
+
+#[test]
+fn generation_dropped_early_leaves_a_connected_maze() {
+    for taken in [1, 10, 100, 200] {
+        let mut maze = Maze::new(31, 23, 3, ExitLocation::Right);
+        let mut rng = StdRng::seed_from_u64(5);
+        assert_eq!(
+            maze.generation_steps(0.2, &mut rng).take(taken).count(),
+            taken
+        );
+        // Carved so far, all reachable from the start but the exit, which
+        // may not be connected yet
+        let distances = maze.distance_map(maze.start_pos());
+        for (pos, cell) in maze.iter_cells() {
+            if TRAVERSABLE.contains(&cell) && cell != CellType::Exit {
+                assert!(
+                    distances[pos.y * maze.width() + pos.x].is_some(),
+                    "{} after {} events",
+                    pos,
+                    taken
+                );
+            }
+        }
+    }
+}
+
+#[test]
+fn solve_steps_report_what_solve_observed_does() {
+    let maze = MazeBuilder::new()
+        .width(31)
+        .height(23)
+        .braid(0.3)
+        .seed(6)
+        .build()
+        .unwrap();
+    for solver in [
+        Solver::Bfs,
+        Solver::AStar,
+        Solver::WallFollower(Hand::Left),
+        Solver::DeadEndFill,
+    ] {
+        let mut observed = Vec::new();
+        let expected = maze.solve_observed(solver, |event| observed.push(event));
+        let mut steps = maze.solve_steps(solver);
+        let mut events: Vec<MazeEvent> = steps.by_ref().take(3).collect();
+        // Only done once used up
+        assert!(steps.trace().is_none());
+        events.extend(steps.by_ref());
+        assert!(events == observed, "{:?}", solver);
+        let trace = steps.trace().unwrap();
+        assert_eq!(trace.path, expected.path);
+        assert_eq!(trace.visited, expected.visited);
+        assert_eq!(trace.order, expected.order);
+        assert_eq!(trace.expansions, expected.expansions);
+    }
+}
@@ -0,0 +1,140 @@
+use std::collections::HashSet;
+
+use mazegen::{Maze, MazeBuilder, MstResult, Pos, SvgOptions, SvgOverlay, TRAVERSABLE};
+use rand::SeedableRng;
+use rand::rngs::StdRng;
+
+fn maze(braid: f32, seed: u64) -> Maze {
+    MazeBuilder::new()
+        .width(31)
+        .height(23)
+        .braid(braid)
+        .artifacts(0.2)
+        .seed(seed)
+        .build()
+        .unwrap()
+}
+
+fn edge_weights(mst: &MstResult) -> i32 {
+    mst.edges.iter().map(|edge| edge.weight()).sum()
+}
+
+#[test]
+fn prim_and_kruskal_weigh_the_same() {
+    for braid in [0.0, 0.5, 1.0] {
+        for seed in 0..5 {
+            let maze = maze(braid, seed);
+            let (prim, kruskal) = (maze.mst_prim(), maze.mst_kruskal());
+            // The trees may differ where edges weigh the same, their weight
+            // doesn't
+            assert_eq!(prim.total_weight, kruskal.total_weight, "seed {}", seed);
+            assert_eq!(prim.total_weight, edge_weights(&prim));
+            assert_eq!(kruskal.total_weight, edge_weights(&kruskal));
+            for mst in [prim, kruskal] {
+                assert_eq!(mst.edges.len(), mst.nodes.len() - 1);
+            }
+        }
+    }
+}
+
+#[test]
+fn spanning_trees_leave_out_edges_of_loops() {
+    for seed in 0..5 {
+        let maze = maze(1.0, seed);
+        let (_, edges) = maze.build_graph();
+        for mst in [maze.mst_prim(), maze.mst_kruskal()] {
+            assert!(mst.edges.len() < edges.len());
+            assert!(mst.edges.is_subset(&edges));
+            assert!(mst.total_weight <= edges.iter().map(|edge| edge.weight()).sum());
+        }
+    }
+}
+
+/// Every corridor between junctions that isn't all inside the room
+fn corridors_outside_the_room(maze: &Maze) -> HashSet<Vec<Pos>> {
+    let room = maze.room();
+    maze.corridor_importance(0)
+        .into_iter()
+        .map(|(cells, _)| cells)
+        .filter(|cells| cells.iter().any(|&pos| !room.contains(pos)))
+        .collect()
+}
+
+#[test]
+fn every_corridor_of_a_perfect_maze_is_cut() {
+    for seed in 0..5 {
+        let maze = maze(0.0, seed);
+        let cut: HashSet<Vec<Pos>> = maze.cut_corridors().into_iter().collect();
+        // The room has loops of its own
+        assert!(cut == corridors_outside_the_room(&maze), "seed {}", seed);
+    }
+}
+
+#[test]
+fn braided_corridors_arent_all_cut() {
+    for seed in 0..5 {
+        let maze = maze(1.0, seed);
+        let cut: HashSet<Vec<Pos>> = maze.cut_corridors().into_iter().collect();
+        assert!(cut.is_subset(&corridors_outside_the_room(&maze)));
+        assert!(cut.len() < corridors_outside_the_room(&maze).len());
+    }
+}
+
+#[test]
+fn forks_of_a_perfect_maze_are_articulation_points() {
+    for seed in 0..5 {
+        let maze = maze(0.0, seed);
+        let room = maze.room();
+        let open = |pos: Pos| TRAVERSABLE.contains(&maze.get(pos.x, pos.y));
+        let forks: HashSet<Pos> = maze
+            .iter_cells()
+            .map(|(pos, _)| pos)
+            .filter(|&pos| open(pos) && !room.contains(pos))
+            .filter(|&pos| pos.neighbors(maze.get_size()).filter(|&n| open(n)).count() > 2)
+            .collect();
+        let articulation: HashSet<Pos> = maze
+            .articulation_points()
+            .into_iter()
+            .filter(|&pos| !room.contains(pos))
+            .collect();
+        assert_eq!(articulation, forks, "seed {}", seed);
+    }
+}
+
+#[test]
+fn stats_count_the_cuts() {
+    let maze = maze(0.5, 1);
+    let stats = maze.stats();
+    assert_eq!(stats.articulation_points, maze.articulation_points().len());
+    assert_eq!(stats.cut_corridors, maze.cut_corridors().len());
+}
+
+#[test]
+fn corridor_importance_comes_most_important_first() {
+    let maze = maze(0.5, 2);
+    let importance = maze.corridor_importance_with_rng(200, &mut StdRng::seed_from_u64(1));
+    assert!(importance.windows(2).all(|pair| pair[0].1 >= pair[1].1));
+    assert!(
+        importance
+            .iter()
+            .all(|&(_, share)| (0.0..=1.0).contains(&share))
+    );
+    assert!(importance[0].1 > 0.0);
+    let again = maze.corridor_importance_with_rng(200, &mut StdRng::seed_from_u64(1));
+    assert_eq!(importance, again);
+}
+
+#[test]
+fn cut_corridors_are_shaded() {
+    let maze = maze(0.5, 3);
+    let options = SvgOptions {
+        overlay: SvgOverlay::CutCorridors,
+        ..SvgOptions::default()
+    };
+    let mut out = Vec::new();
+    maze.write_svg(&mut out, &options).unwrap();
+    let svg = String::from_utf8(out).unwrap();
+    let cells: HashSet<Pos> = maze.cut_corridors().into_iter().flatten().collect();
+    assert!(!cells.is_empty());
+    assert_eq!(svg.matches("fill-opacity=\"0.5\"").count(), cells.len());
+}
@@ -0,0 +1,145 @@
+use std::collections::HashMap;
+
+use mazegen::{CellType, Maze, MazeBuilder, MazeError, Pos, SvgOptions, TRAVERSABLE};
+use rand::SeedableRng;
+use rand::rngs::StdRng;
+
+fn maze(seed: u64) -> Maze {
+    MazeBuilder::new()
+        .width(31)
+        .height(23)
+        .braid(0.5)
+        .artifacts(0.2)
+        .seed(seed)
+        .build()
+        .unwrap()
+}
+
+fn open_neighbors(maze: &Maze, pos: Pos) -> usize {
+    pos.neighbors(maze.get_size())
+        .filter(|n| TRAVERSABLE.contains(&maze.get(n.x, n.y)))
+        .count()
+}
+
+fn corridors(maze: &Maze) -> Vec<Pos> {
+    maze.iter_cells()
+        .filter(|&(pos, cell)| cell == CellType::Path && open_neighbors(maze, pos) == 2)
+        .map(|(pos, _)| pos)
+        .collect()
+}
+
+#[test]
+fn labels_only_go_on_corridors() {
+    for seed in 0..5 {
+        let mut maze = maze(seed);
+        let labels =
+            maze.place_labels(&[('A', 3), ('B', 1)], 0.5, &mut StdRng::seed_from_u64(seed));
+        assert_eq!(labels.len(), corridors(&maze).len() / 2);
+        assert_eq!(&labels, maze.labels());
+        for (&pos, &label) in &labels {
+            // Not on walls, artifacts, the start or the exit
+            assert_eq!(maze.get(pos.x, pos.y), CellType::Path, "{}", pos);
+            // Not on junctions or dead ends
+            assert_eq!(open_neighbors(&maze, pos), 2, "{}", pos);
+            assert!(label == 'A' || label == 'B');
+        }
+    }
+}
+
+#[test]
+fn labels_follow_their_weights() {
+    let mut maze = maze(1);
+    let labels = maze.place_labels(&[('A', 1), ('B', 0)], 1.0, &mut StdRng::seed_from_u64(1));
+    assert_eq!(labels.len(), corridors(&maze).len());
+    assert!(labels.values().all(|&label| label == 'A'));
+    // Without any weight nothing can be picked
+    let labels = maze.place_labels(&[('A', 0)], 1.0, &mut StdRng::seed_from_u64(1));
+    assert!(labels.is_empty());
+    assert!(maze.labels().is_empty());
+}
+
+#[test]
+fn labels_replace_earlier_ones() {
+    let mut maze = maze(2);
+    maze.place_labels(&[('A', 1)], 1.0, &mut StdRng::seed_from_u64(2));
+    let labels = maze.place_labels(&[('Z', 1)], 0.1, &mut StdRng::seed_from_u64(2));
+    assert!(!labels.is_empty());
+    assert_eq!(&labels, maze.labels());
+    assert!(labels.values().all(|&label| label == 'Z'));
+}
+
+#[test]
+fn labels_are_drawn() {
+    let mut maze = maze(3);
+    let labels: HashMap<Pos, char> =
+        maze.place_labels(&[('Q', 1), ('<', 1)], 0.3, &mut StdRng::seed_from_u64(3));
+    assert!(labels.values().any(|&label| label == '<'));
+
+    let mut out = Vec::new();
+    maze.write_text(&mut out, &[]).unwrap();
+    let text = String::from_utf8(out).unwrap();
+    let rows: Vec<Vec<char>> = text.lines().map(|row| row.chars().collect()).collect();
+    for (pos, &label) in &labels {
+        assert_eq!(rows[pos.y][pos.x], label, "{}", pos);
+    }
+
+    let svg = |options: &SvgOptions| {
+        let mut out = Vec::new();
+        maze.write_svg(&mut out, options).unwrap();
+        String::from_utf8(out).unwrap()
+    };
+    let shown = svg(&SvgOptions::default());
+    for (pos, &label) in &labels {
+        let label = if label == '<' {
+            "&lt;".to_string()
+        } else {
+            label.to_string()
+        };
+        let text = format!(
+            "<text x=\"{}\" y=\"{}\"",
+            pos.x as f32 + 0.5,
+            pos.y as f32 + 0.5
+        );
+        let line = shown.lines().find(|line| line.contains(&text)).unwrap();
+        assert!(line.ends_with(&format!(">{}</text>", label)), "{}", line);
+    }
+    let hidden = svg(&SvgOptions {
+        show_labels: false,
+        ..SvgOptions::default()
+    });
+    assert!(!hidden.contains("<text x="));
+}
+
+#[test]
+fn the_word_is_spelled_along_the_way_out() {
+    for seed in 0..5 {
+        let mut maze = maze(seed);
+        maze.place_labels(&[('X', 1)], 0.5, &mut StdRng::seed_from_u64(seed));
+        let placed = maze.embed_word_on_solution("SPOOKY").unwrap();
+        assert_eq!(placed.len(), 6);
+        let path = maze.shortest_path().unwrap();
+        let spelled: String = path
+            .iter()
+            .filter_map(|pos| maze.labels().get(pos))
+            .collect();
+        assert_eq!(spelled, "SPOOKY");
+        assert!(!placed.contains_key(&path[0]));
+        assert!(!placed.contains_key(path.last().unwrap()));
+        // The filler letters off the path are kept
+        assert!(maze.labels().values().any(|&label| label == 'X'));
+    }
+}
+
+#[test]
+fn the_word_must_fit_on_the_way_out() {
+    let mut maze = maze(1);
+    let inner = maze.shortest_path().unwrap().len() - 2;
+    let word = "A".repeat(inner + 1);
+    assert!(matches!(
+        maze.embed_word_on_solution(&word),
+        Err(MazeError::InvalidOption { name: "word", .. })
+    ));
+    assert!(maze.labels().is_empty());
+    let word = "A".repeat(inner);
+    assert_eq!(maze.embed_word_on_solution(&word).unwrap().len(), inner);
+}
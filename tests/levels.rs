@@ -0,0 +1,97 @@
+use mazegen::{CellType, Maze3D, MazeBuilder, MazeError, SvgOptions};
+use rand::SeedableRng;
+use rand::rngs::StdRng;
+
+fn levels(count: usize, seed: u64) -> Maze3D {
+    let builder = MazeBuilder::new().width(15).height(15);
+    Maze3D::generate_with_rng(&builder, count, &mut StdRng::seed_from_u64(seed)).unwrap()
+}
+
+fn count(maze: &Maze3D, wanted: CellType) -> usize {
+    maze.levels
+        .iter()
+        .flat_map(|level| level.cells().iter())
+        .filter(|&&cell| cell == wanted)
+        .count()
+}
+
+#[test]
+fn solvable_across_levels() {
+    for seed in 0..10 {
+        let maze = levels(3, seed);
+        let path = maze.solve().unwrap();
+        assert_eq!(path[0], maze.start_pos());
+        assert_eq!(path[0].0, 1);
+        assert_eq!(path.last(), maze.exit_pos().as_ref());
+        for pair in path.windows(2) {
+            let ((z, from), (next_z, to)) = (pair[0], pair[1]);
+            if z == next_z {
+                assert_eq!(from.manhattan(to), 1);
+            } else {
+                // Up or down a staircase, staying on the same cell
+                assert_eq!(from, to);
+                assert!(maze.stairs.contains(&(z.min(next_z), from)));
+            }
+        }
+    }
+}
+
+#[test]
+fn stairs_connect_paths_on_both_levels() {
+    for seed in 0..10 {
+        let maze = levels(4, seed);
+        for z in 0..3 {
+            let stairs = maze.stairs.iter().filter(|&&(level, _)| level == z).count();
+            assert!((1..=2).contains(&stairs), "{} stairs up from {}", stairs, z);
+        }
+        for &(z, pos) in &maze.stairs {
+            for level in [&maze.levels[z], &maze.levels[z + 1]] {
+                assert_eq!(level.get(pos.x, pos.y), CellType::Path, "{} on {}", pos, z);
+                assert!(!level.room().contains(pos));
+            }
+            assert!(maze.neighbors((z, pos)).contains(&(z + 1, pos)));
+            assert!(maze.neighbors((z + 1, pos)).contains(&(z, pos)));
+        }
+    }
+}
+
+#[test]
+fn one_start_and_one_exit() {
+    let maze = levels(3, 1);
+    assert_eq!(count(&maze, CellType::Start), 1);
+    assert_eq!(count(&maze, CellType::Exit), 1);
+    let (z, start) = maze.start_pos();
+    assert_eq!(maze.levels[z].get(start.x, start.y), CellType::Start);
+}
+
+#[test]
+fn the_rng_makes_the_levels() {
+    let (maze, again) = (levels(3, 2), levels(3, 2));
+    assert!(maze.levels == again.levels);
+    assert_eq!(maze.stairs, again.stairs);
+}
+
+#[test]
+fn svg_marks_the_stairs() {
+    let maze = levels(2, 3);
+    let mut out = Vec::new();
+    maze.write_svg(&mut out, &SvgOptions::default()).unwrap();
+    let svg = String::from_utf8(out).unwrap();
+    let stairs = maze.stairs.len();
+    assert_eq!(
+        svg.matches("<title>Stairs to level 2</title>").count(),
+        stairs
+    );
+    assert_eq!(
+        svg.matches("<title>Stairs to level 1</title>").count(),
+        stairs
+    );
+}
+
+#[test]
+fn at_least_one_level() {
+    assert!(matches!(
+        Maze3D::generate(&MazeBuilder::new(), 0),
+        Err(MazeError::InvalidOption { name: "levels", .. })
+    ));
+}
@@ -0,0 +1,89 @@
+use mazegen::{CellType, ExitLocation, Maze, MazeBuilder};
+
+fn braided(braid: f32, seed: u64) -> Maze {
+    MazeBuilder::new()
+        .width(31)
+        .height(23)
+        .braid(braid)
+        .seed(seed)
+        .build()
+        .unwrap()
+}
+
+/// A ring of 16 cells around the start, with the start joined to its top
+/// and the exit to its right side
+fn ring() -> Maze {
+    let mut maze = Maze::new(11, 11, 1, ExitLocation::Right);
+    for i in 3..8 {
+        maze.set(i, 3, CellType::Path);
+        maze.set(i, 7, CellType::Path);
+        maze.set(3, i, CellType::Path);
+        maze.set(7, i, CellType::Path);
+    }
+    maze.set(5, 4, CellType::Path);
+    maze.set(5, 5, CellType::Start);
+    maze.set(8, 5, CellType::Path);
+    maze.set(9, 5, CellType::Path);
+    maze.set(10, 5, CellType::Exit);
+    maze
+}
+
+#[test]
+fn a_ring_is_one_loop() {
+    let mut maze = ring();
+    assert_eq!(maze.count_loops(), 1);
+    assert!(!maze.is_perfect());
+    assert_eq!(maze.stats().loops, 1);
+    // Cutting the ring leaves a tree
+    maze.set(3, 5, CellType::Wall);
+    assert_eq!(maze.count_loops(), 0);
+    assert!(maze.is_perfect());
+}
+
+#[test]
+fn separate_parts_arent_perfect() {
+    let mut maze = ring();
+    maze.set(3, 5, CellType::Wall);
+    maze.set(1, 1, CellType::Path);
+    assert_eq!(maze.count_loops(), 0);
+    assert!(!maze.is_perfect());
+}
+
+#[test]
+fn unbraided_mazes_are_perfect() {
+    for seed in 0..10 {
+        let maze = braided(0.0, seed);
+        assert!(maze.is_perfect(), "seed {}", seed);
+        assert_eq!(maze.count_loops(), 0);
+    }
+    // The room and the cells across wide corridors don't make loops
+    for corridor_width in [2, 3] {
+        let maze = MazeBuilder::new()
+            .corridor_width(corridor_width)
+            .braid(0.0)
+            .seed(1)
+            .build()
+            .unwrap();
+        assert!(maze.is_perfect(), "corridors {} wide", corridor_width);
+    }
+}
+
+#[test]
+fn more_braid_makes_more_loops() {
+    for seed in 0..10 {
+        let loops: Vec<usize> = [0.0, 0.25, 0.5, 1.0]
+            .into_iter()
+            .map(|braid| {
+                let maze = braided(braid, seed);
+                assert_eq!(maze.stats().loops, maze.count_loops());
+                maze.count_loops()
+            })
+            .collect();
+        assert!(
+            loops.windows(2).all(|pair| pair[0] < pair[1]),
+            "seed {}: {:?}",
+            seed,
+            loops
+        );
+    }
+}
@@ -0,0 +1,75 @@
+use mazegen::{CellType, ExitLocation, Maze, MazeError, MazeMask, Pos, TRAVERSABLE};
+use rand::SeedableRng;
+use rand::rngs::StdRng;
+
+/// Inside a circle touching the middle of each side of a 31x31 maze
+fn in_circle(x: usize, y: usize) -> bool {
+    let (dx, dy) = (x as f32 - 15.0, y as f32 - 15.0);
+    dx * dx + dy * dy <= 15.5 * 15.5
+}
+
+fn masked(mask: &MazeMask, exit: ExitLocation, seed: u64) -> (Maze, Result<usize, MazeError>) {
+    let mut maze = Maze::new(31, 31, 3, exit);
+    let result = maze.generate_masked_with_rng(mask, &mut StdRng::seed_from_u64(seed));
+    (maze, result)
+}
+
+#[test]
+fn circular_mask_keeps_the_paths_inside() {
+    let mask = MazeMask::from_fn(31, 31, in_circle);
+    for seed in 0..5 {
+        let (maze, cut_off) = masked(&mask, ExitLocation::Right, seed);
+        // A disc has no parts cut off from its center
+        assert_eq!(cut_off.unwrap(), 0);
+        for (pos, cell) in maze.iter_cells() {
+            if TRAVERSABLE.contains(&cell) {
+                assert!(mask.contains(pos), "{:?} at {}", cell, pos);
+            }
+        }
+        assert_eq!(maze.exit_pos(), Some(Pos::new(30, 15)));
+        assert!(maze.shortest_path().is_some());
+    }
+}
+
+#[test]
+fn random_exits_stay_inside_the_mask() {
+    let mask = MazeMask::from_fn(31, 31, in_circle);
+    for seed in 0..5 {
+        let (maze, _) = masked(&mask, ExitLocation::Random, seed);
+        assert!(mask.contains(maze.exit_pos().unwrap()));
+        assert!(maze.shortest_path().is_some());
+    }
+}
+
+#[test]
+fn separate_parts_of_the_mask_stay_walls() {
+    // The corner block doesn't reach the circle
+    let mask = MazeMask::from_fn(31, 31, |x, y| in_circle(x, y) || (x <= 3 && y <= 3));
+    let (maze, cut_off) = masked(&mask, ExitLocation::Right, 1);
+    assert_eq!(cut_off.unwrap(), 4);
+    for y in 0..=3 {
+        for x in 0..=3 {
+            assert_eq!(maze.get(x, y), CellType::Wall);
+        }
+    }
+    assert!(maze.shortest_path().is_some());
+}
+
+#[test]
+fn the_room_and_exit_have_to_be_inside() {
+    let without_center = MazeMask::from_fn(31, 31, |x, y| in_circle(x, y) && (x, y) != (15, 15));
+    assert!(matches!(
+        masked(&without_center, ExitLocation::Right, 1).1,
+        Err(MazeError::InvalidOption { name: "mask", .. })
+    ));
+    let without_right = MazeMask::from_fn(31, 31, |x, y| in_circle(x, y) && x < 30);
+    assert!(matches!(
+        masked(&without_right, ExitLocation::Right, 1).1,
+        Err(MazeError::InvalidOption { name: "mask", .. })
+    ));
+    let smaller = MazeMask::from_fn(27, 31, in_circle);
+    assert!(matches!(
+        masked(&smaller, ExitLocation::Right, 1).1,
+        Err(MazeError::InvalidDimensions { width: 27, .. })
+    ));
+}
@@ -0,0 +1,69 @@
+#![cfg(feature = "parallel")]
+
+use std::time::Instant;
+
+use mazegen::{ExitLocation, Maze, MazeBuilder, MazeError};
+use rand::SeedableRng;
+use rand::rngs::StdRng;
+
+fn chunked(size: usize, chunk_size: usize, seed: u64) -> Maze {
+    MazeBuilder::new()
+        .width(size)
+        .height(size)
+        .chunk_size(chunk_size)
+        .seed(seed)
+        .build()
+        .unwrap()
+}
+
+#[test]
+fn huge_chunked_maze_is_connected_and_solvable() {
+    let started = Instant::now();
+    let maze = chunked(2001, 201, 1);
+    println!("Generated 2001x2001 in chunks in {:.2?}", started.elapsed());
+    let (width, height) = maze.get_size();
+    assert!(width >= 2001 && height >= 2001);
+    assert_eq!(width, height);
+    // No unreachable cells, an open room, one start and an exit
+    assert!(maze.validate().is_empty());
+    let path = maze.shortest_path().unwrap();
+    assert_eq!(path[0], maze.start_pos());
+    assert_eq!(path.last(), maze.exit_pos().as_ref());
+    println!("Solved in {:.2?}", started.elapsed());
+}
+
+#[test]
+fn chunks_dont_depend_on_the_threads() {
+    let maze = chunked(301, 63, 2);
+    let single = rayon::ThreadPoolBuilder::new()
+        .num_threads(1)
+        .build()
+        .unwrap()
+        .install(|| chunked(301, 63, 2));
+    assert!(maze == single);
+    assert!(maze != chunked(301, 63, 3));
+}
+
+#[test]
+fn chunks_need_narrow_corridors_and_room() {
+    let mut wide = MazeBuilder::new().corridor_width(2).blank().unwrap();
+    assert!(matches!(
+        wide.generate_parallel_with_rng(63, &mut StdRng::seed_from_u64(1)),
+        Err(MazeError::InvalidOption {
+            name: "chunk size",
+            ..
+        })
+    ));
+    let mut maze = Maze::new(101, 101, 9, ExitLocation::Right);
+    assert!(matches!(
+        maze.generate_parallel_with_rng(7, &mut StdRng::seed_from_u64(1)),
+        Err(MazeError::InvalidRoomSize { room_size: 9, .. })
+    ));
+    assert!(matches!(
+        MazeBuilder::new().chunk_size(63).difficulty(50.0).build(),
+        Err(MazeError::InvalidOption {
+            name: "chunk size",
+            ..
+        })
+    ));
+}
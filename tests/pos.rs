@@ -0,0 +1,148 @@
+use mazegen::{CellType, Direction, ExitLocation, Maze, MazeBuilder, Pos};
+
+const BOUNDS: (usize, usize) = (7, 5);
+
+fn neighbors(x: usize, y: usize) -> Vec<Pos> {
+    Pos::new(x, y).neighbors(BOUNDS).collect()
+}
+
+#[test]
+fn neighbors_inside() {
+    assert_eq!(
+        neighbors(3, 2),
+        [
+            Pos::new(4, 2),
+            Pos::new(3, 3),
+            Pos::new(2, 2),
+            Pos::new(3, 1)
+        ]
+    );
+}
+
+#[test]
+fn neighbors_at_the_top_left_corner() {
+    // Never the cell itself, which `saturating_sub` used to give
+    assert_eq!(neighbors(0, 0), [Pos::new(1, 0), Pos::new(0, 1)]);
+    assert_eq!(
+        neighbors(0, 2),
+        [Pos::new(1, 2), Pos::new(0, 3), Pos::new(0, 1)]
+    );
+    assert_eq!(
+        neighbors(3, 0),
+        [Pos::new(4, 0), Pos::new(3, 1), Pos::new(2, 0)]
+    );
+}
+
+#[test]
+fn neighbors_at_the_bottom_right_corner() {
+    assert_eq!(neighbors(6, 4), [Pos::new(5, 4), Pos::new(6, 3)]);
+    assert_eq!(
+        neighbors(6, 2),
+        [Pos::new(6, 3), Pos::new(5, 2), Pos::new(6, 1)]
+    );
+    assert_eq!(
+        neighbors(3, 4),
+        [Pos::new(4, 4), Pos::new(2, 4), Pos::new(3, 3)]
+    );
+}
+
+#[test]
+fn steps_off_the_grid() {
+    assert_eq!(Pos::new(0, 2).step(Direction::West, BOUNDS), None);
+    assert_eq!(Pos::new(2, 0).step(Direction::North, BOUNDS), None);
+    assert_eq!(Pos::new(6, 2).step(Direction::East, BOUNDS), None);
+    assert_eq!(Pos::new(2, 4).step(Direction::South, BOUNDS), None);
+    assert_eq!(
+        Pos::new(0, 0).step(Direction::East, BOUNDS),
+        Some(Pos::new(1, 0))
+    );
+    // Outside already
+    assert_eq!(Pos::new(9, 9).step(Direction::West, BOUNDS), None);
+}
+
+#[test]
+fn directions_turn_and_reverse() {
+    for direction in Direction::ALL {
+        let (dx, dy) = direction.offset();
+        let (rx, ry) = direction.reverse().offset();
+        assert_eq!((dx + rx, dy + ry), (0, 0));
+        assert_eq!(direction.turn_right().turn_left(), direction);
+        assert_eq!(direction.turn_right().turn_right(), direction.reverse());
+    }
+    assert_eq!(Direction::North.turn_right(), Direction::East);
+    assert_eq!(Direction::East.turn_left(), Direction::North);
+}
+
+#[test]
+fn manhattan_distance() {
+    assert_eq!(Pos::new(1, 2).manhattan(Pos::new(4, 0)), 5);
+    assert_eq!(Pos::new(4, 0).manhattan(Pos::new(1, 2)), 5);
+    assert_eq!(Pos::new(3, 3).manhattan(Pos::new(3, 3)), 0);
+}
+
+#[test]
+fn border_cells_arent_their_own_neighbor() {
+    // A corridor from an exit on the left border down to (1, 3)
+    let mut maze = Maze::new(7, 7, 3, ExitLocation::Left);
+    maze.set(0, 1, CellType::Exit);
+    for y in 1..4 {
+        maze.set(1, y, CellType::Path);
+    }
+    assert_eq!(maze.dead_ends(), [Pos::new(0, 1), Pos::new(1, 3)]);
+}
+
+#[test]
+fn positions_are_ordered_row_by_row() {
+    let mut positions = vec![
+        Pos::new(2, 1),
+        Pos::new(0, 2),
+        Pos::new(5, 0),
+        Pos::new(1, 1),
+    ];
+    positions.sort();
+    assert_eq!(
+        positions,
+        [
+            Pos::new(5, 0),
+            Pos::new(1, 1),
+            Pos::new(2, 1),
+            Pos::new(0, 2)
+        ]
+    );
+    assert!(Pos::new(9, 0) < Pos::new(0, 1));
+    assert_eq!(
+        Pos::new(3, 3).cmp(&Pos::new(3, 3)),
+        std::cmp::Ordering::Equal
+    );
+}
+
+#[test]
+fn positions_are_printed() {
+    assert_eq!(Pos::new(3, 4).to_string(), "(3, 4)");
+    assert_eq!(format!("{:?}", Pos::new(3, 4)), "Pos { x: 3, y: 4 }");
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn positions_round_trip_through_json() {
+    let json = serde_json::to_string(&Pos::new(3, 4)).unwrap();
+    assert_eq!(json, r#"{"x":3,"y":4}"#);
+    assert_eq!(serde_json::from_str::<Pos>(&json).unwrap(), Pos::new(3, 4));
+    let path = vec![Pos::new(0, 1), Pos::new(1, 1)];
+    let json = serde_json::to_string(&path).unwrap();
+    assert_eq!(serde_json::from_str::<Vec<Pos>>(&json).unwrap(), path);
+}
+
+#[test]
+fn spanning_trees_print_the_same_every_time() {
+    let maze = MazeBuilder::new()
+        .width(31)
+        .height(23)
+        .seed(1)
+        .build()
+        .unwrap();
+    let printed = maze.mst_prim().to_string();
+    assert_eq!(maze.clone().mst_prim().to_string(), printed);
+    assert!(printed.starts_with("Minimum Spanning Tree weight: "));
+    assert_eq!(printed.lines().count() - 1, maze.mst_prim().edges.len());
+}
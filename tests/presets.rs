@@ -0,0 +1,85 @@
+use mazegen::{ExitLocation, MazeBuilder, MazePreset, Preset, Theme};
+
+#[test]
+fn built_in_presets_build_solvable_mazes() {
+    for preset in Preset::ALL {
+        let options = preset.preset();
+        let maze = options.builder().seed(1).build().unwrap();
+        assert_eq!(
+            maze.get_size(),
+            (options.width, options.height),
+            "{}",
+            preset.name()
+        );
+        assert_eq!(maze.room_size(), options.room_size);
+        assert!(maze.shortest_path().is_some(), "{}", preset.name());
+        assert!(maze.validate().is_empty(), "{}", preset.name());
+        assert_eq!(
+            maze.artifacts().count() > 0,
+            options.artifacts > 0.0 || options.dead_end_rewards > 0.0,
+            "{}",
+            preset.name()
+        );
+    }
+}
+
+#[test]
+fn presets_are_found_by_name() {
+    for preset in Preset::ALL {
+        assert_eq!(MazePreset::by_name(preset.name()), Some(preset.preset()));
+    }
+    assert_eq!(
+        MazePreset::by_name("kids-easy").unwrap().theme,
+        Preset::KidsEasy.preset().theme
+    );
+    assert_ne!(Preset::KidsEasy.preset().theme, Theme::default());
+    assert_eq!(MazePreset::by_name("KidsEasy"), None);
+    assert_eq!(MazePreset::by_name(""), None);
+}
+
+#[test]
+fn builder_options_override_the_preset() {
+    let preset = Preset::HalloweenParty.preset();
+    let maze = preset
+        .builder()
+        .width(31)
+        .exit(ExitLocation::Left)
+        .artifacts(0.0)
+        .dead_end_artifacts(0.0, 0.0)
+        .seed(1)
+        .build()
+        .unwrap();
+    assert_eq!(maze.get_size(), (31, preset.height));
+    assert_eq!(maze.exit_pos().unwrap().x, 0);
+    assert_eq!(maze.artifacts().count(), 0);
+}
+
+#[test]
+fn the_default_preset_builds_the_default_maze() {
+    let preset = MazePreset::default();
+    let maze = preset.builder().seed(1).build().unwrap();
+    assert!(maze == MazeBuilder::new().seed(1).build().unwrap());
+    assert_eq!(preset.theme, Theme::default());
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn presets_keep_the_defaults_of_fields_left_out() {
+    let json = r#"{"width": 41, "exit": "Farthest", "theme": {"wall_color": [0, 0, 128]}}"#;
+    let preset: MazePreset = serde_json::from_str(json).unwrap();
+    assert_eq!(
+        preset,
+        MazePreset {
+            width: 41,
+            exit: ExitLocation::Farthest,
+            theme: Theme {
+                wall_color: [0, 0, 128],
+                ..Theme::default()
+            },
+            ..MazePreset::default()
+        }
+    );
+    let brutal = Preset::Brutal.preset();
+    let json = serde_json::to_string(&brutal).unwrap();
+    assert_eq!(serde_json::from_str::<MazePreset>(&json).unwrap(), brutal);
+}
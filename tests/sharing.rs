@@ -0,0 +1,108 @@
+use base64::Engine;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use mazegen::{ExitLocation, Maze, MazeBuilder, MazeError};
+
+fn assert_round_trip(maze: &Maze) {
+    let code = maze.to_share_string().unwrap();
+    let decoded = Maze::from_share_string(&code).unwrap();
+    assert!(decoded == *maze, "{}", code);
+    assert_eq!(decoded.room_size(), maze.room_size());
+    assert_eq!(decoded.exit_side(), maze.exit_side());
+    assert_eq!(decoded.to_share_string().unwrap(), code);
+}
+
+#[test]
+fn share_codes_round_trip() {
+    for seed in 0..10 {
+        let maze = MazeBuilder::new()
+            .width(43)
+            .height(27)
+            .room_size(5)
+            .artifacts(0.2)
+            .seed(seed)
+            .build()
+            .unwrap();
+        assert!(maze.artifacts().count() > 0);
+        assert_round_trip(&maze);
+    }
+}
+
+#[test]
+fn share_codes_keep_wide_corridors_and_bridges() {
+    for seed in 0..3 {
+        let wide = MazeBuilder::new()
+            .corridor_width(3)
+            .exit(ExitLocation::Bottom)
+            .artifacts(0.1)
+            .seed(seed)
+            .build()
+            .unwrap();
+        assert_round_trip(&wide);
+        let woven = MazeBuilder::new().weave(1.0).seed(seed).build().unwrap();
+        assert_round_trip(&woven);
+    }
+}
+
+#[test]
+fn long_runs_are_split() {
+    // The rows of walls around an empty maze are longer than a run can be
+    let maze = Maze::new(303, 7, 3, ExitLocation::Right);
+    assert_round_trip(&maze);
+}
+
+#[test]
+fn too_wide_for_a_share_code() {
+    let maze = Maze::new(65539, 7, 3, ExitLocation::Right);
+    assert!(matches!(
+        maze.to_share_string(),
+        Err(MazeError::InvalidDimensions { width: 65539, .. })
+    ));
+}
+
+#[test]
+fn broken_share_codes() {
+    let code = MazeBuilder::new()
+        .seed(1)
+        .build()
+        .unwrap()
+        .to_share_string()
+        .unwrap();
+    assert!(Maze::from_share_string("").is_err());
+    assert!(Maze::from_share_string("not base64!").is_err());
+    assert!(Maze::from_share_string(&code[..code.len() - 4]).is_err());
+}
+
+#[test]
+fn huge_sizes_fail_before_allocating() {
+    // Version 1, 65535x65535 cells, room size 3, exit on the right and a
+    // single wall
+    let mut bytes = vec![1, 0xff, 0xff, 0xff, 0xff, 3, 2];
+    bytes.extend([0, 1]);
+    let code = URL_SAFE_NO_PAD.encode(&bytes);
+    assert!(matches!(
+        Maze::from_share_string(&code),
+        Err(MazeError::InvalidDimensions {
+            width: 65535,
+            height: 65535,
+            ..
+        })
+    ));
+}
+
+#[test]
+fn headers_need_the_right_number_of_cells() {
+    let maze = Maze::new(11, 7, 3, ExitLocation::Right);
+    let code = maze.to_share_string().unwrap();
+    let mut bytes = URL_SAFE_NO_PAD.decode(&code).unwrap();
+    // One cell short
+    let last = bytes.len() - 1;
+    bytes[last] -= 1;
+    assert!(matches!(
+        Maze::from_share_string(&URL_SAFE_NO_PAD.encode(&bytes)),
+        Err(MazeError::InvalidDimensions {
+            width: 11,
+            height: 7,
+            ..
+        })
+    ));
+}
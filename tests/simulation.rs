@@ -0,0 +1,373 @@
+use mazegen::{
+    CellType, DANGERS, Direction, ExitLocation, Maze, MazeBuilder, MazeError, MonsterBehavior,
+    PlayerMove, Pos, Replay, Simulation, SimulationOptions, StepOutcome, TRAVERSABLE, WeightTable,
+};
+
+const EAST: PlayerMove = PlayerMove::Go(Direction::East);
+
+/// A corridor across the middle row from the start to the exit, with a
+/// candy worth 5 and a zombie costing 10 on the way
+fn corridor() -> Maze {
+    let mut maze = Maze::new(11, 7, 1, ExitLocation::Right);
+    for x in 1..10 {
+        maze.set(x, 3, CellType::Path);
+    }
+    maze.set(5, 3, CellType::Start);
+    maze.set(10, 3, CellType::Exit);
+    maze.set(7, 3, CellType::Candy);
+    maze.set(8, 3, CellType::Zombie);
+    let mut weights = WeightTable::new();
+    weights.set(CellType::Candy, -5);
+    weights.set(CellType::Zombie, 10);
+    maze.set_weight_table(weights);
+    maze
+}
+
+fn with_health(health: i32) -> SimulationOptions {
+    SimulationOptions {
+        health: Some(health),
+        ..Default::default()
+    }
+}
+
+#[test]
+fn walking_the_corridor_collects_everything_and_wins() {
+    let maze = corridor();
+    let mut game = Simulation::new(&maze, with_health(20), 1);
+    assert_eq!(game.player(), Pos::new(5, 3));
+    assert_eq!(game.remaining_artifacts(), 2);
+    assert_eq!(game.optimal_steps(), Some(5));
+
+    assert_eq!(game.step(EAST), StepOutcome::Continue);
+    assert_eq!((game.score(), game.health()), (0, Some(20)));
+    assert_eq!(game.step(EAST), StepOutcome::Continue);
+    assert_eq!((game.score(), game.health()), (5, Some(25)));
+    assert_eq!(game.remaining_artifacts(), 1);
+    assert_eq!(game.maze().get(7, 3), CellType::Path);
+    assert_eq!(game.step(EAST), StepOutcome::Continue);
+    assert_eq!((game.score(), game.health()), (-5, Some(15)));
+    assert_eq!(game.remaining_artifacts(), 0);
+    assert_eq!(game.step(EAST), StepOutcome::Continue);
+    assert_eq!(game.step(EAST), StepOutcome::Win);
+
+    assert_eq!(game.player(), Pos::new(10, 3));
+    assert_eq!((game.turn(), game.steps()), (5, 5));
+    assert!(game.is_over());
+    // The maze it was made from is left alone
+    assert_eq!(maze.get(7, 3), CellType::Candy);
+}
+
+#[test]
+fn walls_block_without_using_a_turn() {
+    let mut game = Simulation::new(&corridor(), SimulationOptions::default(), 1);
+    assert_eq!(
+        game.step(PlayerMove::Go(Direction::North)),
+        StepOutcome::Blocked
+    );
+    assert_eq!((game.player(), game.turn()), (Pos::new(5, 3), 0));
+    assert_eq!(game.step(PlayerMove::Wait), StepOutcome::Continue);
+    assert_eq!(
+        (game.player(), game.turn(), game.steps()),
+        (Pos::new(5, 3), 1, 0)
+    );
+    assert_eq!(game.outcome(), StepOutcome::Continue);
+    assert!(game.record().turns.len() == 1);
+}
+
+#[test]
+fn running_out_of_health_loses_for_good() {
+    let mut game = Simulation::new(&corridor(), with_health(5), 1);
+    for _ in 0..2 {
+        assert_eq!(game.step(EAST), StepOutcome::Continue);
+    }
+    assert_eq!(game.health(), Some(10));
+    assert_eq!(game.step(EAST), StepOutcome::Lose);
+    assert_eq!(game.health(), Some(0));
+    // Nothing changes once it's over
+    assert_eq!(game.step(EAST), StepOutcome::Lose);
+    assert_eq!((game.player(), game.turn()), (Pos::new(8, 3), 3));
+
+    // Without any health there is nothing to lose
+    let mut game = Simulation::new(&corridor(), SimulationOptions::default(), 1);
+    for _ in 0..4 {
+        assert_eq!(game.step(EAST), StepOutcome::Continue);
+    }
+    assert_eq!(game.health(), None);
+    assert_eq!(game.step(EAST), StepOutcome::Win);
+}
+
+fn haunted(seed: u64) -> Maze {
+    MazeBuilder::new()
+        .width(31)
+        .height(23)
+        .artifacts(0.2)
+        .braid(0.5)
+        .seed(seed)
+        .build()
+        .unwrap()
+}
+
+fn wanderers() -> SimulationOptions {
+    SimulationOptions {
+        behaviors: DANGERS
+            .iter()
+            .map(|&danger| (danger, MonsterBehavior::Wander))
+            .collect(),
+        ..Default::default()
+    }
+}
+
+#[test]
+fn monsters_wander_a_cell_at_a_time_outside_the_room() {
+    for seed in 0..5 {
+        let maze = haunted(seed);
+        let mut game = Simulation::new(&maze, wanderers(), seed);
+        let mut monsters: Vec<Pos> = game.monsters().collect();
+        assert_eq!(
+            monsters.len(),
+            maze.artifacts()
+                .filter(|(_, cell)| DANGERS.contains(cell))
+                .count()
+        );
+        let mut moved = false;
+        for _ in 0..30 {
+            assert_eq!(game.step(PlayerMove::Wait), StepOutcome::Continue);
+            let now: Vec<Pos> = game.monsters().collect();
+            // The player waits in the room, where no monster can get to
+            assert_eq!(now.len(), monsters.len());
+            for (before, after) in monsters.iter().zip(&now) {
+                assert!(before.manhattan(*after) <= 1, "{} to {}", before, after);
+                assert!(!maze.room().contains(*after));
+                assert!(DANGERS.contains(&game.maze().get(after.x, after.y)));
+                moved |= before != after;
+            }
+            monsters = now;
+        }
+        assert!(moved);
+        assert_eq!(game.remaining_artifacts(), maze.artifacts().count());
+    }
+}
+
+#[test]
+fn static_dangers_stay_put() {
+    let maze = haunted(1);
+    let mut game = Simulation::new(&maze, SimulationOptions::default(), 1);
+    assert_eq!(game.monsters().count(), 0);
+    for _ in 0..10 {
+        game.step(PlayerMove::Wait);
+    }
+    assert!(*game.maze() == maze);
+}
+
+#[test]
+fn the_same_seed_plays_out_the_same() {
+    let maze = haunted(2);
+    let play = |seed| {
+        let mut game = Simulation::new(&maze, wanderers(), seed);
+        let mut monsters = Vec::new();
+        for _ in 0..20 {
+            game.step(PlayerMove::Wait);
+            monsters.push(game.monsters().collect::<Vec<_>>());
+        }
+        monsters
+    };
+    assert_eq!(play(3), play(3));
+    assert_ne!(play(3), play(4));
+}
+
+/// A way from the start east and up, then back west along the top row, with
+/// a ghost at its far end
+fn ghost_corridor() -> Maze {
+    let mut maze = Maze::new(11, 7, 1, ExitLocation::Right);
+    for x in 1..10 {
+        maze.set(x, 1, CellType::Path);
+        maze.set(x.max(5), 3, CellType::Path);
+    }
+    maze.set(9, 2, CellType::Path);
+    maze.set(5, 3, CellType::Start);
+    maze.set(10, 3, CellType::Exit);
+    maze.set(1, 1, CellType::Ghost);
+    let mut weights = WeightTable::new();
+    weights.set(CellType::Ghost, 10);
+    maze.set_weight_table(weights);
+    maze
+}
+
+fn ghosts(behavior: MonsterBehavior, line_of_sight: bool) -> SimulationOptions {
+    SimulationOptions {
+        health: Some(100),
+        behaviors: [(CellType::Ghost, behavior)].into(),
+        line_of_sight,
+    }
+}
+
+#[test]
+fn a_chasing_ghost_closes_in_every_turn() {
+    let maze = ghost_corridor();
+    let options = ghosts(MonsterBehavior::Chase { recompute_every: 1 }, false);
+    let mut game = Simulation::new(&maze, options, 1);
+    let distance = |game: &Simulation, ghost: Pos| {
+        game.maze().distance_map(game.player())[ghost.y * 11 + ghost.x].unwrap()
+    };
+    let mut last = distance(&game, Pos::new(1, 1));
+    assert_eq!(last, 14);
+    let mut turns = 0;
+    loop {
+        let Some(ghost) = game.monsters().next() else {
+            break;
+        };
+        // Two steps towards it, then the player waits to be caught
+        let player_move = if turns < 2 { EAST } else { PlayerMove::Wait };
+        assert_eq!(game.step(player_move), StepOutcome::Continue);
+        turns += 1;
+        if let Some(ghost) = game.monsters().next() {
+            let now = distance(&game, ghost);
+            assert!(now < last, "turn {}: {} after {}", turns, now, last);
+            last = now;
+        } else {
+            assert_eq!(ghost.manhattan(game.player()), 1);
+        }
+    }
+    assert_eq!(turns, 12);
+    assert_eq!((game.player(), game.health()), (Pos::new(7, 3), Some(90)));
+    assert_eq!(game.remaining_artifacts(), 0);
+}
+
+#[test]
+fn ghosts_only_give_chase_once_they_see_the_player() {
+    let maze = ghost_corridor();
+    let chase = MonsterBehavior::Chase { recompute_every: 1 };
+    let mut sighted = Simulation::new(&maze, ghosts(chase, true), 7);
+    let mut wandering = Simulation::new(&maze, ghosts(MonsterBehavior::Wander, false), 7);
+    let mut seen = false;
+    for _ in 0..500 {
+        // Only along the row of the start can a ghost see the player
+        seen |= sighted.monsters().next().unwrap().y == 3;
+        sighted.step(PlayerMove::Wait);
+        if !seen {
+            // Until then it wanders just the same
+            wandering.step(PlayerMove::Wait);
+            assert!(sighted.monsters().eq(wandering.monsters()));
+        }
+    }
+    assert!(seen);
+    // As close as it gets to the player waiting in the room
+    assert!(sighted.monsters().eq([Pos::new(6, 3)]));
+}
+
+/// A way from `pos` straight into a wall, if there is one
+fn wall_beside(maze: &Maze, pos: Pos) -> Option<Direction> {
+    Direction::ALL.into_iter().find(|&direction| {
+        pos.step(direction, maze.get_size())
+            .is_none_or(|next| !TRAVERSABLE.contains(&maze.get(next.x, next.y)))
+    })
+}
+
+/// Plays `maze` along its shortest path with monsters about, bumping into
+/// a wall once on the way
+fn recorded_run(maze: &Maze) -> Simulation {
+    let options = SimulationOptions {
+        health: Some(1000),
+        ..Default::default()
+    }
+    .monsters();
+    let mut game = Simulation::new(maze, options, 5);
+    game.step(PlayerMove::Wait);
+    let mut bumped = false;
+    for step in maze.shortest_path().unwrap().windows(2) {
+        if let Some(wall) = wall_beside(maze, step[0]).filter(|_| !bumped) {
+            assert_eq!(game.step(PlayerMove::Go(wall)), StepOutcome::Blocked);
+            bumped = true;
+        }
+        let offset = (
+            step[1].x as isize - step[0].x as isize,
+            step[1].y as isize - step[0].y as isize,
+        );
+        let direction = Direction::ALL
+            .into_iter()
+            .find(|direction| direction.offset() == offset)
+            .unwrap();
+        game.step(PlayerMove::Go(direction));
+    }
+    assert!(bumped);
+    game
+}
+
+#[test]
+fn recorded_runs_replay_to_the_same_end() {
+    for seed in 0..5 {
+        let maze = haunted(seed);
+        let game = recorded_run(&maze);
+        assert_eq!(game.outcome(), StepOutcome::Win);
+        let replay = game.record();
+        assert_eq!(replay.seed, 5);
+        // Blocked moves aren't recorded
+        assert_eq!(replay.turns.len(), game.turn());
+        assert!(
+            replay
+                .turns
+                .iter()
+                .enumerate()
+                .all(|(i, turn)| turn.turn == i + 1)
+        );
+        assert_eq!(Simulation::replay(&replay).unwrap(), game.summary());
+    }
+}
+
+fn diverged_in(replay: &Replay) -> usize {
+    match Simulation::replay(replay) {
+        Err(MazeError::ReplayDiverged { turn, .. }) => turn,
+        other => panic!("expected the replay to diverge, got {:?}", other),
+    }
+}
+
+#[test]
+fn tampered_replays_diverge() {
+    let maze = haunted(1);
+    let replay = recorded_run(&maze).record();
+
+    // A step through a wall
+    let mut tampered = replay.clone();
+    let (i, wall) = (1..replay.turns.len())
+        .find_map(|i| Some((i, wall_beside(&maze, replay.turns[i - 1].player)?)))
+        .unwrap();
+    tampered.turns[i].player_move = PlayerMove::Go(wall);
+    assert_eq!(diverged_in(&tampered), replay.turns[i].turn);
+
+    // A monster said to be somewhere else
+    let mut tampered = replay.clone();
+    let turn = tampered
+        .turns
+        .iter_mut()
+        .find(|turn| !turn.monsters.is_empty())
+        .unwrap();
+    turn.monsters[0] = Pos::new(0, 0);
+    let tampered_turn = turn.turn;
+    assert_eq!(diverged_in(&tampered), tampered_turn);
+
+    // A better score than earned, by playing on after the exit
+    let mut tampered = replay.clone();
+    let mut extra = tampered.turns.last().unwrap().clone();
+    extra.turn += 1;
+    extra.player_move = PlayerMove::Wait;
+    tampered.turns.push(extra);
+    assert_eq!(diverged_in(&tampered), replay.turns.len() + 1);
+
+    // The same moves with another seed make the monsters go elsewhere
+    let mut tampered = replay.clone();
+    tampered.seed += 1;
+    assert!(Simulation::replay(&tampered).is_err());
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn replays_are_saved_and_loaded() {
+    let replay = recorded_run(&haunted(2)).record();
+    let path = std::env::temp_dir().join(format!("mazegen-replay-{}.json", std::process::id()));
+    let filename = path.to_str().unwrap();
+    replay.save(filename).unwrap();
+    let loaded = Replay::load(filename).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(loaded, replay);
+    assert!(Simulation::replay(&loaded).is_ok());
+}
@@ -0,0 +1,549 @@
+use std::collections::HashSet;
+
+use mazegen::{
+    CellType, EXACT_COLLECTION_REWARDS, ExitLocation, Hand, Maze, MazeBuilder, MazeError, Pos,
+    REWARDS, Solver, TRAVERSABLE, WeightTable, benchmark_solvers,
+};
+
+fn perfect_maze(seed: u64) -> Maze {
+    MazeBuilder::new()
+        .width(31)
+        .height(23)
+        .braid(0.0)
+        .seed(seed)
+        .build()
+        .unwrap()
+}
+
+fn braided_maze(seed: u64) -> Maze {
+    MazeBuilder::new()
+        .width(31)
+        .height(23)
+        .braid(0.5)
+        .seed(seed)
+        .build()
+        .unwrap()
+}
+
+/// Starts at the start, ends at the exit and only takes steps to an open
+/// neighbor
+fn assert_walk(maze: &Maze, path: &[Pos]) {
+    assert_eq!(path.first(), Some(&maze.start_pos()));
+    assert_eq!(path.last(), maze.exit_pos().as_ref());
+    for pair in path.windows(2) {
+        assert_eq!(pair[0].manhattan(pair[1]), 1, "{} to {}", pair[0], pair[1]);
+    }
+    for pos in path {
+        assert!(TRAVERSABLE.contains(&maze.get(pos.x, pos.y)), "{}", pos);
+    }
+}
+
+#[test]
+fn wall_follower_solves_perfect_mazes() {
+    for seed in 0..10 {
+        let maze = perfect_maze(seed);
+        let shortest = maze.shortest_path().unwrap();
+        for hand in [Hand::Left, Hand::Right] {
+            let walked = maze.solve_wall_follower(hand).unwrap();
+            assert_walk(&maze, &walked);
+            // The walk includes every backtrack, so it is never shorter
+            assert!(walked.len() >= shortest.len());
+            assert_eq!(maze.solve(Solver::WallFollower(hand)), Some(walked));
+        }
+    }
+}
+
+#[test]
+fn wall_follower_walks_past_every_cell_of_the_shortest_path() {
+    for seed in 0..10 {
+        let maze = perfect_maze(seed);
+        let walked: HashSet<Pos> = maze
+            .solve_wall_follower(Hand::Left)
+            .unwrap()
+            .into_iter()
+            .collect();
+        let room = maze.room();
+        for pos in maze.shortest_path().unwrap() {
+            assert!(room.contains(pos) || walked.contains(&pos), "{}", pos);
+        }
+    }
+}
+
+#[test]
+fn wall_follower_on_braided_mazes_is_valid_when_it_succeeds() {
+    for seed in 0..10 {
+        let maze = braided_maze(seed);
+        for hand in [Hand::Left, Hand::Right] {
+            if let Some(walked) = maze.solve_wall_follower(hand) {
+                assert_walk(&maze, &walked);
+            }
+        }
+    }
+}
+
+#[test]
+fn dead_end_fill_leaves_the_solution_in_perfect_mazes() {
+    for seed in 0..10 {
+        let maze = perfect_maze(seed);
+        let room = maze.room();
+        let outside_room = |cells: Vec<Pos>| -> HashSet<Pos> {
+            cells
+                .into_iter()
+                .filter(|&pos| !room.contains(pos))
+                .collect()
+        };
+        // Without loops only the way from the room to the exit is left
+        assert_eq!(
+            outside_room(maze.solve_dead_end_fill()),
+            outside_room(maze.shortest_path().unwrap())
+        );
+    }
+}
+
+#[test]
+fn dead_end_fill_keeps_the_solution_in_braided_mazes() {
+    for seed in 0..10 {
+        let maze = braided_maze(seed);
+        let open: HashSet<Pos> = maze.solve_dead_end_fill().into_iter().collect();
+        for pos in maze.shortest_path().unwrap() {
+            assert!(open.contains(&pos), "{}", pos);
+        }
+        for pos in &open {
+            assert_ne!(maze.get(pos.x, pos.y), CellType::Wall);
+        }
+    }
+}
+
+#[test]
+fn dead_end_fill_path_goes_from_start_to_exit() {
+    let maze = perfect_maze(3);
+    let path = maze.solve(Solver::DeadEndFill).unwrap();
+    assert_walk(&maze, &path);
+    assert_eq!(path.len(), maze.shortest_path().unwrap().len());
+}
+
+#[test]
+fn a_star_visits_no_more_cells_than_bfs() {
+    for seed in 0..10 {
+        let maze = braided_maze(seed);
+        let bfs = maze.solve_instrumented(Solver::Bfs);
+        let a_star = maze.solve_instrumented(Solver::AStar);
+        assert!(bfs.visited.len() >= a_star.visited.len());
+        assert!(bfs.expansions >= a_star.expansions);
+        assert_eq!(
+            bfs.path.map(|path| path.len()),
+            a_star.path.map(|path| path.len())
+        );
+    }
+}
+
+#[test]
+fn solution_cells_were_visited() {
+    for seed in 0..10 {
+        let maze = braided_maze(seed);
+        for solver in [Solver::Bfs, Solver::AStar] {
+            let trace = maze.solve_instrumented(solver);
+            let visited: HashSet<Pos> = trace.visited.iter().copied().collect();
+            let expanded: HashSet<Pos> = trace.order.iter().copied().collect();
+            assert_eq!(visited.len(), trace.visited.len(), "visited twice");
+            assert_eq!(trace.order.len(), trace.expansions);
+            assert!(expanded.is_subset(&visited));
+            for pos in trace.path.unwrap() {
+                assert!(visited.contains(&pos), "{:?} didn't visit {}", solver, pos);
+            }
+        }
+    }
+}
+
+fn positions(coordinates: &[(usize, usize)]) -> Vec<Pos> {
+    coordinates.iter().map(|&(x, y)| Pos::new(x, y)).collect()
+}
+
+#[test]
+fn moves_of_straight_runs() {
+    let right = positions(&[(1, 1), (2, 1), (3, 1), (4, 1)]);
+    assert_eq!(Maze::path_to_moves(&right).unwrap(), "RRR");
+    let reversed: Vec<Pos> = right.into_iter().rev().collect();
+    assert_eq!(Maze::path_to_moves(&reversed).unwrap(), "LLL");
+    let down = positions(&[(3, 0), (3, 1), (3, 2)]);
+    assert_eq!(Maze::path_to_moves(&down).unwrap(), "DD");
+    // No steps, no moves
+    assert_eq!(Maze::path_to_moves(&down[..1]).unwrap(), "");
+    assert_eq!(Maze::path_to_moves(&[]).unwrap(), "");
+}
+
+#[test]
+fn moves_with_turns() {
+    let path = positions(&[(1, 1), (2, 1), (2, 2), (1, 2), (1, 1), (1, 0)]);
+    assert_eq!(Maze::path_to_moves(&path).unwrap(), "RDLUU");
+}
+
+#[test]
+fn moves_of_a_solution_lead_to_the_exit() {
+    let maze = perfect_maze(1);
+    let path = maze.shortest_path().unwrap();
+    let moves = Maze::path_to_moves(&path).unwrap();
+    assert_eq!(moves.len(), path.len() - 1);
+    let (mut x, mut y) = (path[0].x as isize, path[0].y as isize);
+    for step in moves.chars() {
+        match step {
+            'R' => x += 1,
+            'D' => y += 1,
+            'L' => x -= 1,
+            _ => y -= 1,
+        }
+    }
+    let exit = maze.exit_pos().unwrap();
+    assert_eq!((x, y), (exit.x as isize, exit.y as isize));
+}
+
+#[test]
+fn moves_need_adjacent_positions() {
+    for (from, to) in [((1, 1), (2, 2)), ((1, 1), (3, 1)), ((1, 1), (1, 1))] {
+        let path = positions(&[(0, 1), from, to, (to.0, to.1 + 1)]);
+        assert!(matches!(
+            Maze::path_to_moves(&path),
+            Err(MazeError::NotAdjacent { from: f, to: t })
+                if f == Pos::new(from.0, from.1) && t == Pos::new(to.0, to.1)
+        ));
+    }
+}
+
+#[test]
+fn shortest_paths_begin_at_the_start() {
+    for room_size in [3, 5, 7] {
+        for (seed, exit) in [
+            (1, ExitLocation::Right),
+            (2, ExitLocation::Top),
+            (3, ExitLocation::Random),
+        ] {
+            let maze = MazeBuilder::new()
+                .width(31)
+                .height(23)
+                .room_size(room_size)
+                .exit(exit)
+                .braid(0.3)
+                .seed(seed)
+                .build()
+                .unwrap();
+            let path = maze.shortest_path().unwrap();
+            assert_eq!(path[0], maze.start_pos());
+            assert_walk(&maze, &path);
+            // Including the steps inside the room
+            let exit = maze.exit_pos().unwrap();
+            assert_eq!(
+                maze.distance_map(maze.start_pos())[exit.y * maze.width() + exit.x],
+                Some(path.len() - 1)
+            );
+            assert_eq!(maze.stats().solution_length, Some(path.len()));
+        }
+    }
+}
+
+/// Two ways of 13 steps from the start to the exit, leaving the room at
+/// its top and bottom right corners, which can each be reached two ways.
+/// Columns 6 to 14 of rows 2 to 8:
+///
+/// ```text
+/// ##......#
+/// ##.####.#
+/// ...####.#
+/// .S.####.E
+/// ...####.#
+/// ##.####.#
+/// ##......#
+/// ```
+fn two_ways() -> Maze {
+    let mut maze = Maze::new(15, 11, 3, ExitLocation::Right);
+    for y in 4..7 {
+        for x in 6..9 {
+            maze.set(x, y, CellType::Path);
+        }
+    }
+    maze.set(7, 5, CellType::Start);
+    for (x, y) in [(8, 3), (8, 7)] {
+        maze.set(x, y, CellType::Path);
+    }
+    for x in 8..14 {
+        maze.set(x, 2, CellType::Path);
+        maze.set(x, 8, CellType::Path);
+    }
+    for y in 2..9 {
+        maze.set(13, y, CellType::Path);
+    }
+    maze.set(14, 5, CellType::Exit);
+    maze
+}
+
+#[test]
+fn all_shortest_paths_finds_both_ways() {
+    let maze = two_ways();
+    assert!(maze.room().contains(Pos::new(8, 4)));
+    let paths = maze.all_shortest_paths(10);
+    // The ways through the room to its corners don't count twice
+    assert_eq!(paths.len(), 2);
+    assert_eq!(paths[0].len(), 14);
+    assert_eq!(paths[1].len(), 14);
+    assert_ne!(paths[0], paths[1]);
+    for path in &paths {
+        assert_walk(&maze, path);
+    }
+    assert!(paths.iter().any(|path| path.contains(&Pos::new(8, 2))));
+    assert!(paths.iter().any(|path| path.contains(&Pos::new(8, 8))));
+    assert_eq!(maze.all_shortest_paths(1).len(), 1);
+}
+
+#[test]
+fn all_shortest_paths_are_shortest_and_distinct() {
+    for seed in 0..5 {
+        let maze = braided_maze(seed);
+        let shortest = maze.shortest_path().unwrap().len();
+        let paths = maze.all_shortest_paths(10);
+        assert!(!paths.is_empty());
+        for (i, path) in paths.iter().enumerate() {
+            assert_walk(&maze, path);
+            assert_eq!(path.len(), shortest);
+            assert!(!paths[..i].contains(path));
+        }
+    }
+}
+
+#[test]
+fn k_shortest_paths_come_shortest_first() {
+    for seed in 0..5 {
+        let maze = braided_maze(seed);
+        let paths = maze.k_shortest_paths(4);
+        assert!(paths.len() > 1);
+        assert_eq!(paths[0].len(), maze.shortest_path().unwrap().len());
+        assert!(paths.windows(2).all(|pair| pair[0].len() <= pair[1].len()));
+        for (i, path) in paths.iter().enumerate() {
+            assert_walk(&maze, path);
+            assert!(!paths[..i].contains(path));
+        }
+    }
+}
+
+#[test]
+fn the_junction_graph_finds_paths_as_short() {
+    for seed in 0..10 {
+        for maze in [perfect_maze(seed), braided_maze(seed)] {
+            let path = maze.shortest_path_via_graph().unwrap();
+            assert_walk(&maze, &path);
+            assert_eq!(
+                path.len(),
+                maze.shortest_path().unwrap().len(),
+                "seed {}",
+                seed
+            );
+        }
+    }
+}
+
+#[test]
+fn the_junction_graph_finds_no_path_without_an_exit() {
+    let mut maze = perfect_maze(1);
+    let exit = maze.exit_pos().unwrap();
+    maze.set(exit.x, exit.y, CellType::Wall);
+    assert_eq!(maze.shortest_path_via_graph(), None);
+}
+
+#[test]
+fn benchmarked_solvers_agree_on_the_shortest_path() {
+    for seed in 0..5 {
+        for maze in [perfect_maze(seed), braided_maze(seed)] {
+            let shortest = maze.shortest_path().unwrap();
+            let results = benchmark_solvers(&maze).unwrap();
+            assert_eq!(results.len(), 6);
+            for result in &results {
+                let length = result.path_length.unwrap_or(usize::MAX);
+                if result.optimal {
+                    assert_eq!(length, shortest.len(), "{}", result.solver);
+                } else {
+                    assert!(length >= shortest.len(), "{}", result.solver);
+                }
+                assert!(result.time_ms >= 0.0);
+            }
+            let bfs = &results[0];
+            assert_eq!(bfs.solver, "BFS");
+            assert_eq!(bfs.path_cost, Some(maze.path_cost(&shortest)));
+            assert_eq!(
+                bfs.expansions,
+                Some(maze.solve_instrumented(Solver::Bfs).expansions)
+            );
+        }
+    }
+}
+
+/// `maze` with a candy, cookies and chocolate on plain path cells spread
+/// over it
+fn three_rewards(mut maze: Maze) -> (Maze, Vec<Pos>) {
+    let paths: Vec<Pos> = maze
+        .iter_cells()
+        .filter(|&(_, cell)| cell == CellType::Path)
+        .map(|(pos, _)| pos)
+        .collect();
+    let rewards: Vec<Pos> = [1, 2, 3].map(|i| paths[i * paths.len() / 4]).to_vec();
+    for (&pos, reward) in
+        rewards
+            .iter()
+            .zip([CellType::Candy, CellType::Cookies, CellType::Chocolate])
+    {
+        maze.set(pos.x, pos.y, reward);
+    }
+    (maze, rewards)
+}
+
+fn steps(maze: &Maze, from: Pos, to: Pos) -> u32 {
+    maze.distance_map(from)[to.y * maze.width() + to.x].unwrap() as u32
+}
+
+#[test]
+fn the_collection_route_is_the_shortest_over_all_rewards() {
+    for seed in 0..5 {
+        let (maze, rewards) = three_rewards(braided_maze(seed));
+        let (start, exit) = (maze.start_pos(), maze.exit_pos().unwrap());
+        let brute_force = [
+            [0, 1, 2],
+            [0, 2, 1],
+            [1, 0, 2],
+            [1, 2, 0],
+            [2, 0, 1],
+            [2, 1, 0],
+        ]
+        .iter()
+        .map(|order| {
+            let stops: Vec<Pos> = [start]
+                .into_iter()
+                .chain(order.iter().map(|&i| rewards[i]))
+                .chain([exit])
+                .collect();
+            stops
+                .windows(2)
+                .map(|leg| steps(&maze, leg[0], leg[1]))
+                .sum::<u32>()
+        })
+        .min()
+        .unwrap();
+
+        let route = maze.full_collection_route().unwrap();
+        assert!(route.optimal);
+        assert_eq!(route.steps, brute_force, "seed {}", seed);
+        assert_eq!(route.path.len() - 1, route.steps as usize);
+        assert_walk(&maze, &route.path);
+        assert!(rewards.iter().all(|reward| route.path.contains(reward)));
+    }
+}
+
+#[test]
+fn many_rewards_make_a_good_route_only() {
+    let maze = MazeBuilder::new()
+        .width(31)
+        .height(23)
+        .artifacts(0.3)
+        .seed(2)
+        .build()
+        .unwrap();
+    let rewards: Vec<Pos> = maze
+        .artifacts()
+        .filter(|(_, cell)| REWARDS.contains(cell))
+        .map(|(pos, _)| pos)
+        .collect();
+    assert!(rewards.len() > EXACT_COLLECTION_REWARDS);
+    let route = maze.full_collection_route().unwrap();
+    assert!(!route.optimal);
+    assert_walk(&maze, &route.path);
+    assert_eq!(route.path.len() - 1, route.steps as usize);
+    assert!(rewards.iter().all(|reward| route.path.contains(reward)));
+}
+
+#[test]
+fn collection_routes_need_every_reward_in_reach() {
+    // Without rewards it is just the way out
+    let maze = braided_maze(1);
+    let route = maze.full_collection_route().unwrap();
+    assert_eq!(
+        route.steps as usize,
+        maze.shortest_path().unwrap().len() - 1
+    );
+
+    let (mut maze, _) = three_rewards(braided_maze(1));
+    maze.set(0, 0, CellType::Candy);
+    assert!(maze.full_collection_route().is_none());
+}
+
+/// The danger weights along `path`, rewards counting as nothing
+fn danger_on(maze: &Maze, path: &[Pos]) -> i32 {
+    path.iter()
+        .map(|pos| maze.weight_table().get(maze.get(pos.x, pos.y)).max(0))
+        .sum()
+}
+
+#[test]
+fn a_danger_budget_picks_the_way_it_can_afford() {
+    let mut maze = two_ways();
+    maze.set(10, 2, CellType::Zombie);
+    maze.set(10, 8, CellType::Ghost);
+    let mut weights = WeightTable::new();
+    weights.set(CellType::Zombie, 5);
+    weights.set(CellType::Ghost, 8);
+    maze.set_weight_table(weights);
+
+    assert_eq!(maze.path_with_danger_budget(4), None);
+    let (path, danger) = maze.path_with_danger_budget(5).unwrap();
+    assert_eq!(danger, 5);
+    assert_walk(&maze, &path);
+    assert!(path.contains(&Pos::new(10, 2)));
+    let (path, danger) = maze.path_with_danger_budget(100).unwrap();
+    assert_eq!(path.len(), 14);
+    assert_eq!(danger, danger_on(&maze, &path));
+}
+
+#[test]
+fn no_danger_budget_avoids_all_dangers() {
+    for seed in 0..5 {
+        let maze = MazeBuilder::new()
+            .width(31)
+            .height(23)
+            .braid(0.5)
+            .artifacts(0.1)
+            .seed(seed)
+            .build()
+            .unwrap();
+        // The same maze with every danger walled off
+        let mut avoiding = maze.clone();
+        for (pos, cell) in maze.artifacts() {
+            if maze.weight_table().get(cell) > 0 {
+                avoiding.set(pos.x, pos.y, CellType::Wall);
+            }
+        }
+        match (maze.path_with_danger_budget(0), avoiding.shortest_path()) {
+            (Some((path, danger)), Some(expected)) => {
+                assert_eq!(danger, 0);
+                assert_eq!(path.len(), expected.len(), "seed {}", seed);
+                assert_walk(&avoiding, &path);
+            }
+            (None, None) => {}
+            (found, expected) => panic!(
+                "seed {}: {:?} but {:?} avoiding the dangers",
+                seed,
+                found.map(|(path, _)| path.len()),
+                expected.map(|path| path.len())
+            ),
+        }
+
+        // Enough for any way at all is just the shortest
+        let (path, danger) = maze.path_with_danger_budget(i32::MAX / 2).unwrap();
+        assert_eq!(path.len(), maze.shortest_path().unwrap().len());
+        assert_eq!(danger, danger_on(&maze, &path));
+
+        // More budget never makes the way longer
+        let mut last = usize::MAX;
+        for budget in [0, 5, 10, 20, 50] {
+            if let Some((path, danger)) = maze.path_with_danger_budget(budget) {
+                assert!(danger <= budget && danger == danger_on(&maze, &path));
+                assert!(path.len() <= last);
+                last = path.len();
+            }
+        }
+    }
+}
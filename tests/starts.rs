@@ -0,0 +1,98 @@
+use mazegen::{CellType, ExitLocation, Maze, MazeBuilder, MazeError, Pos, TRAVERSABLE};
+
+fn maze(braid: f32, seed: u64) -> Maze {
+    MazeBuilder::new()
+        .width(31)
+        .height(23)
+        .braid(braid)
+        .seed(seed)
+        .build()
+        .unwrap()
+}
+
+fn steps(maze: &Maze, from: Pos, to: Pos) -> usize {
+    maze.distance_map(from)[to.y * maze.width() + to.x].unwrap()
+}
+
+#[test]
+fn fair_starts_are_as_far_from_the_exit() {
+    for (braid, seed) in [(0.0, 1), (0.0, 2), (0.5, 3), (1.0, 4)] {
+        for (n, tolerance) in [(2, 0), (3, 2), (4, 5)] {
+            let plain = maze(braid, seed);
+            let mut maze = plain.clone();
+            let starts = maze.fair_starts(n, tolerance).unwrap();
+            assert_eq!(starts.len(), n);
+            let exit = maze.exit_pos().unwrap();
+            let distances: Vec<usize> =
+                starts.iter().map(|&pos| steps(&plain, pos, exit)).collect();
+            let (nearest, farthest) = (distances.iter().min(), distances.iter().max());
+            assert!(
+                farthest.unwrap() - nearest.unwrap() <= tolerance,
+                "{:?}",
+                distances
+            );
+            for (i, &pos) in starts.iter().enumerate() {
+                assert!(!starts[i + 1..].contains(&pos));
+                assert_eq!(plain.get(pos.x, pos.y), CellType::Path);
+                assert!(!maze.room().contains(pos));
+                assert_eq!(maze.get(pos.x, pos.y), CellType::Start);
+            }
+            // The start in the room is still the one to solve from
+            assert!(maze.validate().is_empty());
+            assert_eq!(maze.shortest_path(), plain.shortest_path());
+        }
+    }
+}
+
+#[test]
+fn two_fair_starts_are_far_apart() {
+    for seed in 0..5 {
+        let plain = maze(0.0, seed);
+        let exit = plain.exit_pos().unwrap();
+        let open = |pos: Pos| TRAVERSABLE.contains(&plain.get(pos.x, pos.y));
+        let candidates: Vec<Pos> = plain
+            .iter_cells()
+            .filter(|&(pos, cell)| cell == CellType::Path && !plain.room().contains(pos))
+            .filter(|&(pos, _)| pos.neighbors(plain.get_size()).filter(|&n| open(n)).count() <= 2)
+            .map(|(pos, _)| pos)
+            .collect();
+        // With any distance from the exit allowed, the starts are at least
+        // as far apart as the cell farthest from the exit is from any other
+        let farthest = *candidates
+            .iter()
+            .max_by_key(|&&pos| (steps(&plain, pos, exit), pos))
+            .unwrap();
+        let apart = candidates
+            .iter()
+            .map(|&pos| steps(&plain, farthest, pos))
+            .max()
+            .unwrap();
+        let starts = plain.clone().fair_starts(2, 1000).unwrap();
+        assert!(
+            steps(&plain, starts[0], starts[1]) >= apart,
+            "seed {}",
+            seed
+        );
+    }
+}
+
+#[test]
+fn fair_starts_need_enough_cells() {
+    let plain = maze(0.0, 1);
+    let mut maze = plain.clone();
+    assert!(maze.fair_starts(0, 0).unwrap().is_empty());
+    assert!(matches!(
+        maze.fair_starts(1000, 2),
+        Err(MazeError::InvalidOption {
+            name: "number of starts",
+            ..
+        })
+    ));
+    assert!(maze == plain);
+
+    let mut no_exit = Maze::new(11, 11, 3, ExitLocation::Right);
+    assert!(matches!(
+        no_exit.fair_starts(2, 0),
+        Err(MazeError::Unsolvable)
+    ));
+}
@@ -0,0 +1,80 @@
+use mazegen::{CellType, ConnectStrategy, Maze, MazeBuilder, MazeError};
+use rand::SeedableRng;
+use rand::rngs::StdRng;
+
+fn grid(rows: usize, columns: usize, size: usize) -> Vec<Vec<Maze>> {
+    (0..rows)
+        .map(|row| {
+            (0..columns)
+                .map(|column| {
+                    MazeBuilder::new()
+                        .width(size)
+                        .height(size)
+                        .seed((row * columns + column) as u64)
+                        .build()
+                        .unwrap()
+                })
+                .collect()
+        })
+        .collect()
+}
+
+fn count(maze: &Maze, wanted: CellType) -> usize {
+    maze.cells().iter().filter(|&&cell| cell == wanted).count()
+}
+
+#[test]
+fn two_by_two_tiling_is_solvable() {
+    for connect in [ConnectStrategy::Random(1), ConnectStrategy::Evenly(2)] {
+        let maze =
+            Maze::tile_with_rng(&grid(2, 2, 31), connect, &mut StdRng::seed_from_u64(1)).unwrap();
+        // Two tiles sharing a wall make 61 cells, rounded up to the next size
+        assert_eq!(maze.get_size(), (63, 63));
+        assert_eq!(count(&maze, CellType::Start), 1);
+        assert_eq!(count(&maze, CellType::Exit), 1);
+        let path = maze.shortest_path().unwrap();
+        assert_eq!(path[0], maze.start_pos());
+        assert_eq!(path.last(), maze.exit_pos().as_ref());
+        assert!(maze.validate().is_empty(), "{:?}", connect);
+    }
+}
+
+#[test]
+fn odd_tilings_need_no_extra_wall() {
+    let maze = Maze::tile(&grid(3, 3, 11), ConnectStrategy::Evenly(1)).unwrap();
+    assert_eq!(maze.get_size(), (31, 31));
+    assert!(maze.shortest_path().is_some());
+}
+
+#[test]
+fn tiling_is_seeded() {
+    let tile = |seed| {
+        Maze::tile_with_rng(
+            &grid(2, 3, 15),
+            ConnectStrategy::Random(2),
+            &mut StdRng::seed_from_u64(seed),
+        )
+        .unwrap()
+    };
+    assert!(tile(4) == tile(4));
+}
+
+#[test]
+fn tiles_must_match() {
+    assert!(matches!(
+        Maze::tile(&[], ConnectStrategy::Random(1)),
+        Err(MazeError::InvalidDimensions { .. })
+    ));
+    let mut uneven = grid(2, 2, 15);
+    uneven[1].pop();
+    assert!(matches!(
+        Maze::tile(&uneven, ConnectStrategy::Random(1)),
+        Err(MazeError::InvalidDimensions { .. })
+    ));
+    let mut mixed = grid(2, 2, 15);
+    mixed[0][1] = grid(1, 1, 11).remove(0).remove(0);
+    assert!(matches!(
+        Maze::tile(&mixed, ConnectStrategy::Random(1)),
+        Err(MazeError::InvalidDimensions { .. })
+    ));
+}
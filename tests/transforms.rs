@@ -0,0 +1,97 @@
+use mazegen::{CellType, Maze, MazeBuilder, Pos};
+
+fn maze(seed: u64) -> Maze {
+    MazeBuilder::new()
+        .width(31)
+        .height(23)
+        .artifacts(0.1)
+        .seed(seed)
+        .build()
+        .unwrap()
+}
+
+type Transform = fn(&Maze) -> Maze;
+
+fn transforms() -> [(&'static str, Transform); 5] {
+    [
+        ("rotated_90", Maze::rotated_90),
+        ("rotated_180", Maze::rotated_180),
+        ("mirrored_horizontal", Maze::mirrored_horizontal),
+        ("mirrored_vertical", Maze::mirrored_vertical),
+        ("transposed", Maze::transposed),
+    ]
+}
+
+#[test]
+fn rotating_four_times_is_the_identity() {
+    for seed in 0..3 {
+        let maze = maze(seed);
+        let rotated = maze.rotated_90();
+        assert_eq!(rotated.get_size(), (23, 31));
+        assert!(rotated != maze);
+        let back = rotated.rotated_90().rotated_90().rotated_90();
+        assert!(back == maze);
+        assert_eq!(back.exit_pos(), maze.exit_pos());
+        assert!(maze.rotated_90().rotated_90() == maze.rotated_180());
+        assert!(maze.rotated_180().rotated_180() == maze);
+    }
+}
+
+#[test]
+fn mirroring_twice_is_the_identity() {
+    let maze = maze(1);
+    assert!(maze.mirrored_horizontal().mirrored_horizontal() == maze);
+    assert!(maze.mirrored_vertical().mirrored_vertical() == maze);
+    assert!(maze.transposed().transposed() == maze);
+    // Both mirrors make half a turn
+    assert!(maze.mirrored_horizontal().mirrored_vertical() == maze.rotated_180());
+}
+
+#[test]
+fn cells_move_with_the_transform() {
+    let maze = maze(2);
+    let (width, height) = maze.get_size();
+    let rotated = maze.rotated_90();
+    let mirrored = maze.mirrored_horizontal();
+    let transposed = maze.transposed();
+    for (pos, cell) in maze.iter_cells() {
+        assert_eq!(rotated.get(height - 1 - pos.y, pos.x), cell);
+        assert_eq!(mirrored.get(width - 1 - pos.x, pos.y), cell);
+        assert_eq!(transposed.get(pos.y, pos.x), cell);
+    }
+}
+
+#[test]
+fn the_solution_length_stays_the_same() {
+    for seed in 0..3 {
+        let maze = maze(seed);
+        let length = maze.shortest_path().unwrap().len();
+        for (name, transform) in transforms() {
+            let transformed = transform(&maze);
+            let start = transformed.start_pos();
+            assert_eq!(
+                transformed.get(start.x, start.y),
+                CellType::Start,
+                "{}",
+                name
+            );
+            assert!(transformed.room().contains(start), "{}", name);
+            assert!(transformed.validate().is_empty(), "{}", name);
+            let path = transformed.shortest_path().unwrap();
+            assert_eq!(path.len(), length, "{}", name);
+            assert_eq!(path.last(), transformed.exit_pos().as_ref(), "{}", name);
+        }
+    }
+}
+
+#[test]
+fn the_exit_is_remapped() {
+    let maze = maze(4);
+    let exit = maze.exit_pos().unwrap();
+    let (width, height) = maze.get_size();
+    assert_eq!(
+        maze.rotated_180().exit_pos(),
+        Some(Pos::new(width - 1 - exit.x, height - 1 - exit.y))
+    );
+    assert_eq!(maze.transposed().exit_pos(), Some(Pos::new(exit.y, exit.x)));
+}
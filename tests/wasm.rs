@@ -0,0 +1,38 @@
+#![cfg(all(feature = "wasm", target_arch = "wasm32"))]
+
+use mazegen::wasm::WasmMaze;
+use wasm_bindgen_test::wasm_bindgen_test;
+
+fn generated(seed: u64) -> WasmMaze {
+    let Ok(mut maze) = WasmMaze::new(31, 23, 3, "right", seed) else {
+        panic!("a valid maze");
+    };
+    maze.generate();
+    maze.place_artifacts(0.1);
+    maze
+}
+
+#[wasm_bindgen_test]
+fn a_seeded_maze_solves() {
+    let maze = generated(7);
+    let solution = maze.solve();
+    // Pairs of x and y, ending at the exit on the right
+    assert!(solution.len() >= 4);
+    assert_eq!(solution.len() % 2, 0);
+    assert_eq!(solution[solution.len() - 2], 30);
+    assert_eq!(generated(7).solve(), solution);
+}
+
+#[wasm_bindgen_test]
+fn a_maze_has_cells_and_an_svg() {
+    let maze = generated(8);
+    assert_eq!(maze.cells().length() as usize, maze.width() * maze.height());
+    let svg = maze.to_svg(10.0);
+    assert!(svg.starts_with("<svg "));
+    assert!(svg.trim_end().ends_with("</svg>"));
+}
+
+#[wasm_bindgen_test]
+fn unknown_exits_are_rejected() {
+    assert!(WasmMaze::new(31, 23, 3, "nowhere", 1).is_err());
+}
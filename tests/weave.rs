@@ -0,0 +1,106 @@
+use mazegen::{CellType, Direction, ExitLocation, Maze, MazeBuilder, Rect, TRAVERSABLE};
+
+fn weave_maze(seed: u64) -> Maze {
+    weave_builder(seed).build().unwrap()
+}
+
+fn weave_builder(seed: u64) -> MazeBuilder {
+    MazeBuilder::new()
+        .width(63)
+        .height(63)
+        .weave(1.0)
+        .seed(seed)
+}
+
+fn is_bridge(cell: CellType) -> bool {
+    matches!(cell, CellType::BridgeNorthSouth | CellType::BridgeEastWest)
+}
+
+#[test]
+fn graph_paths_go_straight_over_bridges() {
+    for seed in 0..5 {
+        let maze = weave_maze(seed);
+        assert!(maze.cells().iter().any(|&cell| is_bridge(cell)));
+        for mst in [maze.mst_prim(), maze.mst_kruskal()] {
+            let paths = maze.mst_paths(&mst);
+            assert_eq!(paths.len(), mst.edges.len());
+            for path in &paths {
+                assert!(mst.nodes.contains_key(&path[0]));
+                assert!(mst.nodes.contains_key(path.last().unwrap()));
+                for pair in path.windows(2) {
+                    assert_eq!(pair[0].manhattan(pair[1]), 1, "{} to {}", pair[0], pair[1]);
+                }
+                for cells in path.windows(3) {
+                    let (before, on, after) = (cells[0], cells[1], cells[2]);
+                    if is_bridge(maze.get(on.x, on.y)) {
+                        assert_eq!(before.x + after.x, 2 * on.x, "turned on {}", on);
+                        assert_eq!(before.y + after.y, 2 * on.y, "turned on {}", on);
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[test]
+fn bridges_are_never_graph_nodes() {
+    let maze = weave_maze(1);
+    let (nodes, _) = maze.build_graph();
+    for pos in nodes.keys() {
+        assert!(!is_bridge(maze.get(pos.x, pos.y)), "{}", pos);
+    }
+}
+
+#[test]
+fn repair_reopens_the_passage_under_a_bridge() {
+    for seed in 0..5 {
+        let mut maze = weave_builder(seed).braid(0.0).build().unwrap();
+        let (bridge, under) = maze
+            .iter_cells()
+            .find_map(|(pos, cell)| {
+                let direction = Direction::ALL
+                    .into_iter()
+                    .find(|&direction| cell.is_under(direction))?;
+                Some((pos, direction))
+            })
+            .unwrap();
+        // Without loops, closing the way under the bridge cuts the maze in two
+        let cut = bridge.step(under, maze.get_size()).unwrap();
+        maze.set(cut.x, cut.y, CellType::Wall);
+        assert!(!maze.validate().is_empty());
+        assert!(maze.repair_connectivity() > 0);
+        assert!(maze.validate().is_empty(), "seed {}", seed);
+        assert!(maze.shortest_path().is_some());
+    }
+}
+
+#[test]
+fn farthest_exit_in_weave_mazes() {
+    for seed in 0..5 {
+        let maze = weave_builder(seed)
+            .exit(ExitLocation::Farthest)
+            .build()
+            .unwrap();
+        let exit = maze.exit_pos().unwrap();
+        let (width, height) = maze.get_size();
+        assert!(exit.x == 0 || exit.y == 0 || exit.x == width - 1 || exit.y == height - 1);
+        assert!(maze.validate().is_empty());
+        let path = maze.shortest_path().unwrap();
+        assert_eq!(path.last(), Some(&exit));
+    }
+}
+
+#[test]
+fn crops_of_weave_mazes_are_solvable() {
+    for seed in 0..5 {
+        let maze = weave_maze(seed);
+        for rect in [Rect::new(0, 0, 31, 31), Rect::new(16, 16, 31, 31)] {
+            let cropped = maze.crop(rect).unwrap();
+            let path = cropped.shortest_path().unwrap();
+            for pos in &path {
+                assert!(TRAVERSABLE.contains(&cropped.get(pos.x, pos.y)));
+            }
+            assert_eq!(path.last(), cropped.exit_pos().as_ref());
+        }
+    }
+}
@@ -0,0 +1,83 @@
+use mazegen::{CellType, ExitLocation, Maze, MazeBuilder, Pos, WeightTable};
+
+/// A straight way from the start to the exit past a witch, and a detour of
+/// 31 steps around it:
+///
+/// ```text
+/// row 5:  ....S....W.........E
+/// row 9:  ....+--------------+
+/// ```
+fn witch_or_detour() -> Maze {
+    let mut maze = Maze::new(31, 11, 1, ExitLocation::Right);
+    for x in 11..30 {
+        maze.set(x, 5, CellType::Path);
+        maze.set(x, 9, CellType::Path);
+    }
+    for y in 6..9 {
+        maze.set(11, y, CellType::Path);
+        maze.set(29, y, CellType::Path);
+    }
+    maze.set(15, 5, CellType::Start);
+    maze.set(20, 5, CellType::Witch);
+    maze.set(30, 5, CellType::Exit);
+    maze
+}
+
+#[test]
+fn the_least_cost_path_goes_past_a_light_danger() {
+    let maze = witch_or_detour();
+    assert_eq!(maze.start_pos(), Pos::new(15, 5));
+    let path = maze.least_cost_path().unwrap();
+    // 15 steps and a witch weighing 9 cost less than the detour
+    assert!(path.contains(&Pos::new(20, 5)));
+    assert_eq!(path.len(), 16);
+    assert_eq!(maze.path_cost(&path), 9);
+    assert_eq!(maze.shortest_path().unwrap().len(), 16);
+}
+
+#[test]
+fn a_heavier_witch_changes_the_least_cost_path() {
+    let mut maze = witch_or_detour();
+    let mut weights = WeightTable::new();
+    weights.set(CellType::Witch, 100);
+    maze.set_weight_table(weights);
+    let path = maze.least_cost_path().unwrap();
+    assert!(!path.contains(&Pos::new(20, 5)));
+    assert!(path.contains(&Pos::new(11, 9)));
+    assert_eq!(path.len(), 32);
+    assert_eq!(maze.path_cost(&path), 0);
+    assert_eq!(path[0], maze.start_pos());
+    assert_eq!(path.last(), maze.exit_pos().as_ref());
+    for pair in path.windows(2) {
+        assert_eq!(pair[0].manhattan(pair[1]), 1);
+    }
+    // The shortest path doesn't care about weights
+    assert!(maze.shortest_path().unwrap().contains(&Pos::new(20, 5)));
+}
+
+#[test]
+fn no_path_costs_less_than_the_least_cost_path() {
+    for seed in 0..5 {
+        let maze = MazeBuilder::new()
+            .width(31)
+            .height(23)
+            .braid(0.5)
+            .artifacts(0.3)
+            .seed(seed)
+            .build()
+            .unwrap();
+        // Steps plus the dangers stepped on
+        let cost = |path: &[Pos]| -> i32 {
+            path[1..]
+                .iter()
+                .map(|pos| 1 + maze.weight_table().get(maze.get(pos.x, pos.y)).max(0))
+                .sum()
+        };
+        let cheapest = maze.least_cost_path().unwrap();
+        let shortest = maze.shortest_path().unwrap();
+        assert_eq!(cheapest[0], maze.start_pos());
+        assert_eq!(cheapest.last(), maze.exit_pos().as_ref());
+        assert!(cheapest.len() >= shortest.len());
+        assert!(cost(&cheapest) <= cost(&shortest));
+    }
+}